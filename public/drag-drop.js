@@ -24,21 +24,16 @@ function renderFilledSlot(zone, slotNumber, device, isAvailable = true) {
 
     const removeBtn = zone.querySelector('.priority-btn.remove');
     removeBtn?.addEventListener('click', () => {
-        try {
-            const chainType = zone.getAttribute('data-chain-type');
-            const key = chainType === 'playback' ? 'playback_priorities' : 'recording_priorities';
-            const raw = localStorage.getItem(key);
-            let arr;
-            try {
-                arr = JSON.parse(raw ?? '[]');
-            } catch (_) {
-                arr = [];
-            }
-            if (!Array.isArray(arr)) arr = [];
-            arr = arr.filter(e => e && e.priority !== slotNumber);
-            localStorage.setItem(key, JSON.stringify(arr));
-        } catch (e) {
-            console.warn('Failed to update localStorage on remove:', e);
+        if (window.__TAURI__ && window.__TAURI__.core && window.__TAURI__.core.invoke) {
+            window.__TAURI__.core.invoke('clear_priority_slot', {
+                args: {
+                    device_type: device.device_type,
+                    priority_slot: slotNumber,
+                }
+            }).catch(err => {
+                console.error('Failed to clear priority slot:', err);
+                showToast('Failed to remove device from priority slot', 'error', 2500);
+            });
         }
 
         // Render empty slot again
@@ -150,30 +145,6 @@ if (window.interact) {
                     return;
                 }
 
-                    // Update localStorage immediately (robust parse)
-                        try {
-                            const key = chainType === 'playback' ? 'playback_priorities' : 'recording_priorities';
-                            const raw = localStorage.getItem(key);
-                            let arr;
-                            try {
-                                arr = JSON.parse(raw ?? '[]');
-                            } catch (_) {
-                                arr = [];
-                            }
-                            if (!Array.isArray(arr)) arr = [];
-                            // remove existing entry for this device or slot
-                            arr = arr.filter(e => e && e.device_id !== device.device_id && e.priority !== slotNumber);
-                            arr.push({
-                                device_id: device.device_id,
-                                device_name: device.device_name,
-                                device_type: device.device_type,
-                                priority: slotNumber
-                            });
-                            localStorage.setItem(key, JSON.stringify(arr));
-                        } catch (e) {
-                            console.warn('Failed to update localStorage priorities:', e);
-                        }
-
                     // Update the UI immediately (no page reload)
                     renderFilledSlot(zone, slotNumber, device, true);
 
@@ -196,7 +167,7 @@ if (window.interact) {
                                 });
                             }
 
-                            // Invoke backend to log/persist server-side if implemented
+                            // Persist the assignment in the backend priority store
                 if (window.__TAURI__ && window.__TAURI__.core && window.__TAURI__.core.invoke) {
                     window.__TAURI__.core.invoke('add_device_to_priority_slot', {
                         // wrap under the parameter name expected by the Rust command (args)
@@ -228,6 +199,14 @@ document.addEventListener('DOMContentLoaded', () => {
     });
 });
 
+// Announce switches made by the backend's auto-switch
+if (window.__TAURI__ && window.__TAURI__.event && window.__TAURI__.event.listen) {
+    window.__TAURI__.event.listen('auto-switched', (event) => {
+        const kind = event.payload.device_type === 'Recording' ? 'recording' : 'playback';
+        showToast(`Auto-switched ${kind} to ${event.payload.name}`, 'success', 2500);
+    });
+}
+
 // Lightweight toast notifications
 function ensureToastContainer() {
     let c = document.querySelector('.toast-container');
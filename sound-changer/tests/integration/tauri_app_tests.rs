@@ -1,23 +0,0 @@
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::process::Command;
-
-    #[test]
-    fn test_audio_manager_integration() {
-        let output = Command::new("powershell")
-            .arg("-Command")
-            .arg("Your-PowerShell-Command-Here")
-            .output()
-            .expect("Failed to execute PowerShell command");
-
-        assert!(output.status.success());
-    }
-
-    #[test]
-    fn test_audio_functionality() {
-        // Call the audio manager function and verify its behavior
-        let result = audio_manager_function(); // Replace with actual function
-        assert_eq!(result, expected_value); // Replace with actual expected value
-    }
-}
\ No newline at end of file
@@ -1,20 +1,381 @@
+use crate::backend::{self, AudioBackend};
+use crate::clock::{Clock, SystemClock};
+use crate::command_runner::{CommandRunner, PowerShellRunner};
+use crate::config::{
+    self, AudioManagerConfig, ConfigBackup, DeviceGroup, HotkeyAction, Profile, QuietHours,
+};
+use crate::csv_export;
+use crate::diagnostics::{DiagnosticsBundle, Redactor};
 use crate::error::{AudioError, AudioResult};
+use crate::matching::{hardware_name, MatchMode};
+use crate::powershell_host::PowerShellHost;
+use crate::priority_store::{
+    self, DevicePriority, PriorityChains, PriorityOrderedDevice, PriorityStore, PriorityValidation,
+};
 use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fs::{File, OpenOptions};
+use std::future::Future;
 use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
 use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::process::Command;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+/// PowerShell helpers that read and write HKLM through the 64-bit registry view, so a
+/// 32-bit PowerShell on 64-bit Windows doesn't see the redirected WOW6432Node keys.
+/// Every registry-accessing script starts with these (see `with_registry64`).
+macro_rules! registry64_functions {
+    () => {
+        r#"
+    function Open-Registry64Key([string]$Path, [bool]$Writable = $false) {
+        $base = [Microsoft.Win32.RegistryKey]::OpenBaseKey([Microsoft.Win32.RegistryHive]::LocalMachine, [Microsoft.Win32.RegistryView]::Registry64)
+        try { $base.OpenSubKey($Path, $Writable) } finally { $base.Close() }
+    }
+    function Get-Registry64Values([string]$Path) {
+        $key = Open-Registry64Key $Path
+        if (-not $key) { return $null }
+        try {
+            $values = [ordered]@{}
+            foreach ($name in $key.GetValueNames()) {
+                if ($name) { $values[$name] = $key.GetValue($name) }
+            }
+            [pscustomobject]$values
+        } finally { $key.Close() }
+    }
+    function Get-Registry64SubKeyNames([string]$Path) {
+        $key = Open-Registry64Key $Path
+        if (-not $key) { throw "Registry key not found: HKLM\$Path" }
+        try { $key.GetSubKeyNames() } finally { $key.Close() }
+    }
+    function Set-Registry64Value([string]$Path, [string]$Name, $Value, [Microsoft.Win32.RegistryValueKind]$Kind) {
+        try {
+            $key = Open-Registry64Key $Path $true
+            if (-not $key) { throw "Registry key not found: HKLM\$Path" }
+            try { $key.SetValue($Name, $Value, $Kind) } finally { $key.Close() }
+        }
+        catch {
+            # Writing HKLM unelevated is denied; say so in the words structured_error knows
+            $cause = $_.Exception
+            while ($cause.InnerException) { $cause = $cause.InnerException }
+            if ($cause -is [System.Security.SecurityException] -or $cause -is [System.UnauthorizedAccessException]) {
+                throw "Administrator rights are required to change HKLM\$Path"
+            }
+            throw
+        }
+    }
+"#
+    };
+}
+
 // Performance thresholds from Step 17
 const DEVICE_LISTING_TIMEOUT: Duration = Duration::from_secs(2);
 const DEVICE_SWITCHING_TIMEOUT: Duration = Duration::from_secs(1);
+/// Attempts per PowerShell operation when `max_retry_attempts` isn't configured
 const MAX_RETRY_ATTEMPTS: u32 = 3;
+/// Delay before the first retry when `retry_base_delay_ms` isn't configured; each later
+/// retry waits one more multiple of it
 const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Longest wait between two attempts, however many attempts came before
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+const MAX_BENCHMARK_ITERATIONS: u32 = 20;
+// Hard limits after which a PowerShell process is killed
+/// Per-attempt limit for ordinary scripts when `command_timeout_ms` isn't configured
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+const MODULE_INSTALL_TIMEOUT: Duration = Duration::from_secs(120);
+/// Time limit for querying PSGallery, which can stall when offline
+const MODULE_UPDATE_TIMEOUT: Duration = Duration::from_secs(20);
+/// PowerShell executables probed by `redetect_backend`, in order of preference
+const POWERSHELL_EXECUTABLES: [&str; 2] = ["powershell", "pwsh"];
+/// PowerShell helpers for the 64-bit HKLM registry view
+const REGISTRY64_FUNCTIONS: &str = registry64_functions!();
+/// Registry key (under HKLM) listing the installed Spatial Sound providers
+const SPATIAL_PROVIDERS_KEY: &str =
+    r"SOFTWARE\Microsoft\Windows\CurrentVersion\MMDevices\SpatialAudio\Providers";
+/// ID reported for Windows Sonic for Headphones, which is present on every Windows 10+ system
+const WINDOWS_SONIC_ID: &str = "WindowsSonic";
+/// Endpoint property naming the PnP enumerator the device hangs off (`HDAUDIO`, `USB`, ...)
+const ENUMERATOR_NAME_KEY: &str = "{a45c254e-df1c-4efd-8020-67d146a850e0},24";
+/// FX property holding the Loudness Equalization switch, a serialized VT_BOOL PROPVARIANT
+const LOUDNESS_EQUALIZATION_KEY: &str = "{fc52a749-4be9-4510-896e-966ba6525980},3";
+/// PROPVARIANT type tag of a VARIANT_BOOL
+const VT_BOOL: u16 = 0x000b;
+/// How long a switch waits for a newer switch of the same device type to supersede it
+const DEFAULT_SWITCH_COALESCE_WINDOW: Duration = Duration::from_millis(200);
+/// Longest coalescing window `switch_coalesce_window_ms` may set
+const MAX_SWITCH_COALESCE_WINDOW: Duration = Duration::from_secs(2);
+/// How often `wait_for_device` re-enumerates devices
+const DEVICE_WAIT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest `wait_for_device` will wait, whatever timeout it's given
+const MAX_DEVICE_WAIT: Duration = Duration::from_secs(600);
+/// Longest a `temporary_override` may pin a device
+const MAX_OVERRIDE_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+/// How long an enumerated device list is served from cache when `cache_ttl_ms` isn't
+/// configured
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(30);
+/// How often the background watcher refreshes the device list by default
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(30);
+/// Shortest interval `start_monitoring` accepts
+const MIN_WATCH_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the focus watcher checks the foreground window while following focus
+const FOCUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Volume (0-100) `panic_restore` leaves the restored device at
+const PANIC_RESTORE_VOLUME: u8 = 30;
+/// Characters of raw output quoted in a JSON parse error
+const PARSE_ERROR_SNIPPET_CHARS: usize = 200;
+/// Number of `evaluate_and_switch` runs kept in the auto-switch decision history
+const AUTOSWITCH_DECISION_CAPACITY: usize = 100;
+/// Entries a log command returns when the caller doesn't give a limit
+const DEFAULT_LOG_PAGE_SIZE: usize = 50;
+/// Number of PowerShell operations kept in the in-memory operation log
+const OPERATION_LOG_CAPACITY: usize = 200;
+
+/// Enumerates every endpoint plus the default for each role as JSON
+const DEVICE_LIST_SCRIPT: &str = concat!(
+    registry64_functions!(),
+    r#"
+    try {
+        # Check and import AudioDeviceCmdlets module
+        if (-not (Get-Module -ListAvailable -Name AudioDeviceCmdlets)) {
+            @{
+                error = "AudioDeviceCmdlets module not installed. Run: Install-Module AudioDeviceCmdlets -Force -Scope CurrentUser"
+                type = "ModuleNotInstalled"
+            } | ConvertTo-Json -Compress
+            exit 1
+        }
+        
+        Import-Module AudioDeviceCmdlets -ErrorAction Stop
+        
+        # Get all devices with detailed information
+        $allAudioDevices = Get-AudioDevice -List
+        $defaultPlayback = Get-AudioDevice -Playback -ErrorAction SilentlyContinue
+        $defaultRecording = Get-AudioDevice -Recording -ErrorAction SilentlyContinue
+        # Module versions without the communication switches throw a binding error
+        # that -ErrorAction can't suppress; treat those roles as unknown instead
+        $defaultCommunicationPlayback = try { Get-AudioDevice -PlaybackCommunication -ErrorAction Stop } catch { $null }
+        $defaultCommunicationRecording = try { Get-AudioDevice -RecordingCommunication -ErrorAction Stop } catch { $null }
+        
+        $allDevices = @()
+        # The warm host defines Send-Progress; one-shot runs report everything at the end
+        $reportProgress = [bool](Get-Command Send-Progress -ErrorAction SilentlyContinue)
+        # Endpoint property stores, opened once for the whole listing
+        $endpointsKey = Open-Registry64Key "SOFTWARE\Microsoft\Windows\CurrentVersion\MMDevices\Audio"
+        
+        foreach ($device in $allAudioDevices) {
+            $isDefaultPlayback = $defaultPlayback -and ($device.ID -eq $defaultPlayback.ID)
+            $isDefaultRecording = $defaultRecording -and ($device.ID -eq $defaultRecording.ID)
+            $isDefaultCommunicationPlayback = $defaultCommunicationPlayback -and ($device.ID -eq $defaultCommunicationPlayback.ID)
+            $isDefaultCommunicationRecording = $defaultCommunicationRecording -and ($device.ID -eq $defaultCommunicationRecording.ID)
+            
+            $isDefault = $isDefaultPlayback -or $isDefaultRecording
+            $isCommunicationDefault = $isDefaultCommunicationPlayback -or $isDefaultCommunicationRecording
+            
+            # DeviceIcon property, e.g. "%windir%\system32\mmres.dll,-3004"
+            $iconPath = $null
+            if ($endpointsKey) {
+                try {
+                    $flow = if ($device.Type -eq "Playback") { "Render" } else { "Capture" }
+                    $guid = $device.ID.Substring($device.ID.IndexOf("}.") + 2)
+                    $props = $endpointsKey.OpenSubKey("$flow\$guid\Properties")
+                    if ($props) {
+                        try { $iconPath = $props.GetValue("{259abffc-50a7-47ce-af08-68c9a7d73366},12") }
+                        finally { $props.Close() }
+                    }
+                }
+                catch { }
+            }
+            
+            $entry = @{
+                id = $device.ID
+                index = $device.Index
+                name = $device.Name
+                device_type = $device.Type
+                state = $device.State
+                is_default = $isDefault
+                is_communication_default = $isCommunicationDefault
+                last_seen = (Get-Date -Format "yyyy-MM-ddTHH:mm:ss.fffZ")
+                icon_path = $iconPath
+            }
+            $allDevices += $entry
+            if ($reportProgress) { Send-Progress ($entry | ConvertTo-Json -Compress) }
+        }
+        if ($endpointsKey) { $endpointsKey.Close() }
+        
+        # Output structured JSON
+        @{
+            devices = $allDevices
+            timestamp = (Get-Date -Format "yyyy-MM-ddTHH:mm:ss.fffZ")
+            session = $env:COMPUTERNAME
+        } | ConvertTo-Json -Depth 4 -Compress
+    }
+    catch {
+        $errorInfo = @{
+            error = $_.Exception.Message
+            type = "PowerShellExecutionError"
+            timestamp = (Get-Date -Format "yyyy-MM-ddTHH:mm:ss.fffZ")
+        }
+        $errorInfo | ConvertTo-Json -Compress
+        exit 1
+    }
+"#
+);
+
+/// Reports whether AudioDeviceCmdlets is installed, and its version, as JSON
+const MODULE_CHECK_SCRIPT: &str = r#"
+    try {
+        $module = Get-Module -ListAvailable -Name AudioDeviceCmdlets
+        if ($module) {
+            @{
+                available = $true
+                version = $module.Version.ToString()
+                path = $module.ModuleBase
+            } | ConvertTo-Json -Compress
+        } else {
+            @{
+                available = $false
+                message = "AudioDeviceCmdlets module not found"
+                install_command = "Install-Module AudioDeviceCmdlets -Force -Scope CurrentUser"
+            } | ConvertTo-Json -Compress
+        }
+    }
+    catch {
+        @{
+            available = $false
+            error = $_.Exception.Message
+        } | ConvertTo-Json -Compress
+    }
+"#;
+
+/// Built-in scripts that may be run for debugging; nothing outside this list can be executed
+#[derive(Debug, Clone, Copy)]
+pub enum KnownScript {
+    List,
+    ModuleCheck,
+}
+
+impl KnownScript {
+    const NAMES: [&'static str; 2] = ["list", "module-check"];
+
+    pub fn from_name(name: &str) -> AudioResult<Self> {
+        match name {
+            "list" => Ok(Self::List),
+            "module-check" => Ok(Self::ModuleCheck),
+            _ => Err(AudioError::InvalidArgument(format!(
+                "Unknown script '{}', expected one of: {}",
+                name,
+                Self::NAMES.join(", ")
+            ))),
+        }
+    }
+
+    fn script(self) -> &'static str {
+        match self {
+            Self::List => DEVICE_LIST_SCRIPT,
+            Self::ModuleCheck => MODULE_CHECK_SCRIPT,
+        }
+    }
+}
+
+/// Lists the PnP container ID of every present audio endpoint in one round trip
+const CONTAINER_MAP_SCRIPT: &str = r#"
+    try {
+        $prefix = 'SWD\MMDEVAPI\'
+        $endpoints = Get-PnpDevice -Class AudioEndpoint -PresentOnly -ErrorAction Stop |
+            Get-PnpDeviceProperty -KeyName DEVPKEY_Device_ContainerId -ErrorAction SilentlyContinue |
+            Where-Object { $_.InstanceId.StartsWith($prefix, [StringComparison]::OrdinalIgnoreCase) -and $_.Data } |
+            ForEach-Object {
+                @{
+                    id = $_.InstanceId.Substring($prefix.Length).ToLowerInvariant()
+                    container_id = "$($_.Data)"
+                }
+            }
+        @{
+            endpoints = @($endpoints)
+        } | ConvertTo-Json -Depth 3 -Compress
+    }
+    catch {
+        @{
+            error = $_.Exception.Message
+        } | ConvertTo-Json -Compress
+        exit 1
+    }
+"#;
+
+/// Everything switching depends on, gathered in one round trip. Each probe has its own
+/// try/catch and leaves its field out on failure. The app runs PowerShell with
+/// `-ExecutionPolicy Bypass`, so the policy reported is the first one set outside that
+/// process scope, which is what blocks the module in a normal console.
+const DIAGNOSTICS_SCRIPT: &str = r#"
+    $result = @{}
+    try { $result.powershell_version = $PSVersionTable.PSVersion.ToString() } catch {}
+    try {
+        $module = Get-Module -ListAvailable -Name AudioDeviceCmdlets |
+            Sort-Object Version -Descending | Select-Object -First 1
+        $result.module_installed = [bool]$module
+        if ($module) { $result.module_version = $module.Version.ToString() }
+    } catch {}
+    try {
+        $policy = Get-ExecutionPolicy -List |
+            Where-Object { $_.Scope -ne 'Process' -and $_.ExecutionPolicy -ne 'Undefined' } |
+            Select-Object -First 1
+        $result.execution_policy = if ($policy) { $policy.ExecutionPolicy.ToString() } else { 'Undefined' }
+    } catch {}
+    try {
+        $principal = New-Object Security.Principal.WindowsPrincipal([Security.Principal.WindowsIdentity]::GetCurrent())
+        $result.is_admin = $principal.IsInRole([Security.Principal.WindowsBuiltInRole]::Administrator)
+    } catch {}
+    try {
+        Import-Module AudioDeviceCmdlets -ErrorAction Stop
+        $null = Get-AudioDevice -List -ErrorAction Stop
+        $result.can_list_devices = $true
+    } catch {
+        $result.can_list_devices = $false
+    }
+    $result | ConvertTo-Json -Compress
+"#;
+
+/// Reports the installed AudioDeviceCmdlets version and the latest one on PSGallery
+const MODULE_UPDATE_SCRIPT: &str = r#"
+    try {
+        $installed = Get-Module -ListAvailable -Name AudioDeviceCmdlets |
+            Sort-Object Version -Descending |
+            Select-Object -First 1
+        try {
+            $latest = Find-Module -Name AudioDeviceCmdlets -Repository PSGallery -ErrorAction Stop
+        }
+        catch {
+            @{
+                error = "Could not reach PSGallery: $($_.Exception.Message)"
+            } | ConvertTo-Json -Compress
+            exit 1
+        }
+        @{
+            installed = if ($installed) { $installed.Version.ToString() } else { $null }
+            latest = $latest.Version.ToString()
+        } | ConvertTo-Json -Compress
+    }
+    catch {
+        @{
+            error = $_.Exception.Message
+        } | ConvertTo-Json -Compress
+        exit 1
+    }
+"#;
+
+/// Unparsed result of running a built-in script once
+#[derive(Debug, Clone, Serialize)]
+pub struct RawScriptOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    pub elapsed_ms: u64,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioDevice {
@@ -25,15 +386,32 @@ pub struct AudioDevice {
     pub is_default: bool,
     pub is_communication_default: bool,
     pub last_seen: Option<String>, // ISO timestamp
+    /// Short index reported by AudioDeviceCmdlets, accepted anywhere a device ID is
+    #[serde(default)]
+    pub index: Option<u32>,
+    /// `DeviceIcon` resource reference such as `%windir%\system32\mmres.dll,-3004`
+    #[serde(default)]
+    pub icon_path: Option<String>,
+    /// Nickname set with `set_device_alias`, shown instead of `name` when present
+    #[serde(default)]
+    pub display_name: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl AudioDevice {
+    /// Identity that survives endpoint ID changes (type plus case-insensitive name)
+    pub fn stable_key(&self) -> String {
+        format!("{:?}:{}", self.device_type, self.name.to_lowercase())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DeviceType {
     Playback,
     Recording,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Endpoint state, serialized as one of the strings returned by [`DeviceState::as_str`]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DeviceState {
     Active,
     Disabled,
@@ -42,673 +420,10774 @@ pub enum DeviceState {
     Unknown,
 }
 
-#[derive(Debug, Clone)]
-pub struct AudioManagerState {
-    cached_devices: HashMap<String, AudioDevice>,
-    last_refresh: Option<Instant>,
-    cache_ttl: Duration,
-    previous_default_playback: Option<String>,
-    previous_default_recording: Option<String>,
-}
+impl DeviceState {
+    pub const ALL: [DeviceState; 5] = [
+        DeviceState::Active,
+        DeviceState::Disabled,
+        DeviceState::NotPresent,
+        DeviceState::Unplugged,
+        DeviceState::Unknown,
+    ];
 
-impl Default for AudioManagerState {
-    fn default() -> Self {
-        Self {
-            cached_devices: HashMap::new(),
-            last_refresh: None,
-            cache_ttl: Duration::from_secs(30), // Cache for 30 seconds
-            previous_default_playback: None,
-            previous_default_recording: None,
+    /// Serialized form: "active", "disabled", "not_present", "unplugged" or "unknown"
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceState::Active => "active",
+            DeviceState::Disabled => "disabled",
+            DeviceState::NotPresent => "not_present",
+            DeviceState::Unplugged => "unplugged",
+            DeviceState::Unknown => "unknown",
         }
     }
-}
-
-pub struct AudioManager {
-    state: std::sync::Arc<tokio::sync::RwLock<AudioManagerState>>,
-    session_id: String,
-    /// Optional path to simple JSONL "mini DB" file used to persist device snapshots
-    db_path: Option<PathBuf>,
-}
-
-impl AudioManager {
-    /// Initialize audio manager with session tracking (Step 6)
-    pub fn new() -> AudioResult<Self> {
-        let session_id = Uuid::new_v4().to_string();
-        info!("Initializing AudioManager with session ID: {}", session_id);
 
-        // Default DB path: user's local app data directory under sound-changer/devices.jsonl
-        let db_path = dirs::data_local_dir().map(|mut p| {
-            p.push("sound-changer");
-            p.push("devices.jsonl");
-            p
-        });
+    /// Parse the serialized form or the Windows/PowerShell spelling ("NotPresent"),
+    /// ignoring case and underscores; anything unrecognized is `Unknown`
+    pub fn parse(value: &str) -> Self {
+        Self::try_parse(value).unwrap_or(DeviceState::Unknown)
+    }
 
-        Ok(Self {
-            state: std::sync::Arc::new(tokio::sync::RwLock::new(AudioManagerState::default())),
-            session_id,
-            db_path,
-        })
+    /// Like [`DeviceState::parse`], but `None` for an unrecognized value
+    pub fn try_parse(value: &str) -> Option<Self> {
+        let value = value.replace('_', "");
+        Self::ALL
+            .into_iter()
+            .find(|state| state.as_str().replace('_', "").eq_ignore_ascii_case(&value))
     }
+}
 
-    /// Compatibility method - calls get_audio_devices
-    pub async fn get_devices(&self) -> AudioResult<Vec<AudioDevice>> {
-        self.get_audio_devices().await
+impl Serialize for DeviceState {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
     }
+}
 
-    /// Get all audio devices with caching and performance monitoring (Steps 6, 17)
-    pub async fn get_audio_devices(&self) -> AudioResult<Vec<AudioDevice>> {
-        let start_time = Instant::now();
-        info!("Fetching audio devices (session: {})", self.session_id);
+impl<'de> Deserialize<'de> for DeviceState {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Ok(DeviceState::parse(&value))
+    }
+}
 
-        // Check cache validity
-        {
-            let state = self.state.read().await;
-            if let Some(last_refresh) = state.last_refresh {
-                if start_time.duration_since(last_refresh) < state.cache_ttl {
-                    debug!("Returning cached devices");
-                    return Ok(state.cached_devices.values().cloned().collect());
-                }
-            }
-        }
+/// Output of [`DEVICE_LIST_SCRIPT`], field for field
+#[derive(Debug, Deserialize)]
+struct PowerShellDeviceResponse {
+    /// Checked one by one against [`RawDevice`], so a malformed entry skips only itself
+    devices: Vec<serde_json::Value>,
+    /// When and where the list was taken; informational, so fixtures may leave them out
+    #[serde(default)]
+    timestamp: String,
+    #[serde(default)]
+    session: String,
+}
 
-        let devices = self.fetch_devices_from_powershell().await?;
+/// One device as [`DEVICE_LIST_SCRIPT`] emits it, before its strings are checked
+#[derive(Debug, Deserialize)]
+struct RawDevice {
+    id: String,
+    index: Option<u32>,
+    name: String,
+    device_type: String,
+    state: String,
+    is_default: bool,
+    /// Absent from older scripts, where the role is unknown rather than held
+    #[serde(default)]
+    is_communication_default: bool,
+    last_seen: Option<String>,
+    icon_path: Option<String>,
+}
 
-        // Update cache
-        {
-            let mut state = self.state.write().await;
-            state.cached_devices.clear();
-            for device in &devices {
-                state
-                    .cached_devices
-                    .insert(device.id.clone(), device.clone());
-            }
-            state.last_refresh = Some(start_time);
-        }
+impl TryFrom<RawDevice> for AudioDevice {
+    type Error = AudioError;
 
-        // Persist a snapshot to the simple JSONL DB (best-effort)
-        if let Some(db) = &self.db_path {
-            if let Err(e) = Self::save_devices_snapshot(db, &devices, &self.session_id).await {
-                warn!("Failed to persist device snapshot: {}", e);
+    fn try_from(raw: RawDevice) -> AudioResult<Self> {
+        let device_type = match raw.device_type.as_str() {
+            "Playback" => DeviceType::Playback,
+            "Recording" => DeviceType::Recording,
+            other => {
+                return Err(AudioError::ParseError(format!(
+                    "device_type of {}: unexpected value {:?}",
+                    raw.id, other
+                )))
             }
-        }
-
-        let elapsed = start_time.elapsed();
-        if elapsed > DEVICE_LISTING_TIMEOUT {
-            warn!(
-                "Device listing took {}ms, exceeds target of {}ms",
-                elapsed.as_millis(),
-                DEVICE_LISTING_TIMEOUT.as_millis()
-            );
-        }
+        };
+        let state = DeviceState::try_parse(&raw.state).ok_or_else(|| {
+            AudioError::ParseError(format!(
+                "state of {}: unexpected value {:?}",
+                raw.id, raw.state
+            ))
+        })?;
 
-        info!(
-            "Found {} audio devices in {}ms",
-            devices.len(),
-            elapsed.as_millis()
-        );
-        Ok(devices)
+        Ok(AudioDevice {
+            id: raw.id,
+            name: raw.name,
+            device_type,
+            state,
+            is_default: raw.is_default,
+            is_communication_default: raw.is_communication_default,
+            last_seen: raw.last_seen,
+            index: raw.index,
+            icon_path: raw.icon_path.filter(|path| !path.is_empty()),
+            display_name: None,
+        })
     }
+}
 
-    /// Fetch devices from PowerShell with enhanced error handling (Steps 9, 18)
-    async fn fetch_devices_from_powershell(&self) -> AudioResult<Vec<AudioDevice>> {
-        let powershell_script = r#"
-            try {
-                # Check and import AudioDeviceCmdlets module
-                if (-not (Get-Module -ListAvailable -Name AudioDeviceCmdlets)) {
-                    throw "AudioDeviceCmdlets module not installed. Run: Install-Module AudioDeviceCmdlets -Force -Scope CurrentUser"
-                }
-                
-                Import-Module AudioDeviceCmdlets -ErrorAction Stop
-                
-                # Get all devices with detailed information
-                $allAudioDevices = Get-AudioDevice -List
-                $defaultPlayback = Get-AudioDevice -Playback -ErrorAction SilentlyContinue
-                $defaultRecording = Get-AudioDevice -Recording -ErrorAction SilentlyContinue
-                $defaultCommunicationPlayback = Get-AudioDevice -PlaybackCommunication -ErrorAction SilentlyContinue
-                $defaultCommunicationRecording = Get-AudioDevice -RecordingCommunication -ErrorAction SilentlyContinue
-                
-                $allDevices = @()
-                
-                foreach ($device in $allAudioDevices) {
-                    $isDefaultPlayback = $defaultPlayback -and ($device.ID -eq $defaultPlayback.ID)
-                    $isDefaultRecording = $defaultRecording -and ($device.ID -eq $defaultRecording.ID)
-                    $isDefaultCommunicationPlayback = $defaultCommunicationPlayback -and ($device.ID -eq $defaultCommunicationPlayback.ID)
-                    $isDefaultCommunicationRecording = $defaultCommunicationRecording -and ($device.ID -eq $defaultCommunicationRecording.ID)
-                    
-                    $isDefault = $isDefaultPlayback -or $isDefaultRecording
-                    $isCommunicationDefault = $isDefaultCommunicationPlayback -or $isDefaultCommunicationRecording
-                    
-                    $allDevices += @{
-                        id = $device.ID
-                        name = $device.Name
-                        device_type = $device.Type
-                        state = $device.State
-                        is_default = $isDefault
-                        is_communication_default = $isCommunicationDefault
-                        last_seen = (Get-Date -Format "yyyy-MM-ddTHH:mm:ss.fffZ")
-                    }
-                }
-                
-                # Output structured JSON
-                @{
-                    devices = $allDevices
-                    timestamp = (Get-Date -Format "yyyy-MM-ddTHH:mm:ss.fffZ")
-                    session = $env:COMPUTERNAME
-                } | ConvertTo-Json -Depth 4 -Compress
-            }
-            catch {
-                $errorInfo = @{
-                    error = $_.Exception.Message
-                    type = "PowerShellExecutionError"
-                    timestamp = (Get-Date -Format "yyyy-MM-ddTHH:mm:ss.fffZ")
-                }
-                $errorInfo | ConvertTo-Json -Compress
-                exit 1
-            }
-        "#;
+/// Payload of `device-state-changed`: a device that stayed present but changed state,
+/// e.g. a Bluetooth headset going from active to unplugged when it sleeps
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceStateChange {
+    pub device_id: String,
+    pub device_name: String,
+    pub old_state: DeviceState,
+    pub new_state: DeviceState,
+}
 
-        self.execute_powershell_with_retry(powershell_script, "device enumeration")
-            .await
-            .and_then(|output| self.parse_device_list_response(&output))
-    }
+/// Payload of `device-changed`: what changed in the device list since the last poll
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeviceChange {
+    /// Devices that weren't enumerated last poll
+    pub added: Vec<AudioDevice>,
+    /// IDs of devices no longer enumerated
+    pub removed: Vec<String>,
+    /// Devices still present whose state changed, e.g. a USB headset going from
+    /// Unplugged back to Active
+    pub state_changed: Vec<AudioDevice>,
+}
 
-    /// Set default audio device with validation and fallback (Steps 6, 7, 19)
-    pub async fn set_default_device(
-        &self,
-        device_id: &str,
-        _device_type: &DeviceType,
-    ) -> AudioResult<()> {
-        self.set_default_audio_device(device_id).await
+impl DeviceChange {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.state_changed.is_empty()
     }
+}
 
-    /// Set default audio device with validation and fallback (Steps 6, 7, 19)
-    pub async fn set_default_audio_device(&self, device_id: &str) -> AudioResult<()> {
-        let start_time = Instant::now();
-        info!(
-            "Setting default audio device: {} (session: {})",
-            device_id, self.session_id
-        );
+/// Which default-device role(s) a switch applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DefaultRole {
+    Multimedia,
+    Communications,
+    Both,
+}
 
-        // Validate device exists first (Step 12)
-        self.validate_device_id(device_id).await?;
+/// Default device IDs for every role, captured before a switch so it can be rolled back
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DefaultDevices {
+    pub playback: Option<String>,
+    pub recording: Option<String>,
+    pub communication_playback: Option<String>,
+    pub communication_recording: Option<String>,
+}
 
-        // Store current default for fallback (Step 19)
-        let current_defaults = self.get_current_defaults().await?;
+/// A PCM stream format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    pub channels: u16,
+}
 
-        let result = self.change_default_device(device_id).await;
+/// One app's audio stream on an endpoint, as shown in the volume mixer
+#[derive(Debug, Clone, Serialize)]
+pub struct AudioSession {
+    /// Session instance identifier, unique per stream
+    pub session_id: String,
+    pub process_id: u32,
+    /// Name the app set for the session (often empty)
+    pub display_name: String,
+    /// Whether the session is currently playing
+    pub active: bool,
+}
 
-        match result {
-            Ok(_) => {
-                let elapsed = start_time.elapsed();
-                if elapsed > DEVICE_SWITCHING_TIMEOUT {
-                    warn!(
-                        "Device switching took {}ms, exceeds target of {}ms",
-                        elapsed.as_millis(),
-                        DEVICE_SWITCHING_TIMEOUT.as_millis()
-                    );
-                }
-                info!(
-                    "Successfully set default device: {} in {}ms",
-                    device_id,
-                    elapsed.as_millis()
-                );
+/// Result of asking a device whether it accepts a format
+#[derive(Debug, Clone, Serialize)]
+pub struct FormatSupport {
+    pub supported: bool,
+    /// Nearest format the device does accept, when the requested one isn't
+    pub closest_match: Option<AudioFormat>,
+}
 
-                // Update cache to reflect changes
-                self.invalidate_cache().await;
-                Ok(())
-            }
-            Err(e) => {
-                error!("Failed to set default device, attempting fallback");
-                self.fallback_to_previous_device(&current_defaults).await?;
-                Err(e)
-            }
+/// Result of asking a device whether it can play a channel count
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChannelSupport {
+    pub supported: bool,
+    /// Nearest channel count the device does play, when the requested one isn't
+    pub closest_channels: Option<u16>,
+}
+
+impl ChannelSupport {
+    /// Check `requested` against the channel counts a device reported. Ties go to the
+    /// smaller count, since downmixing loses less than upmixing invents.
+    fn from_counts(requested: u16, available: &[u16]) -> Self {
+        if available.contains(&requested) {
+            return Self {
+                supported: true,
+                closest_channels: None,
+            };
+        }
+        Self {
+            supported: false,
+            closest_channels: available
+                .iter()
+                .copied()
+                .min_by_key(|&channels| (channels.abs_diff(requested), channels)),
         }
     }
+}
 
-    /// Change audio output with device validation (Step 7)
-    pub async fn change_audio_output(
-        &self,
-        from_device_id: &str,
-        to_device_id: &str,
-    ) -> AudioResult<()> {
-        info!(
-            "Changing audio output from {} to {} (session: {})",
-            from_device_id, to_device_id, self.session_id
-        );
+/// Outcome of an operation applied to one device out of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceOperationResult {
+    pub device_id: String,
+    pub success: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+}
 
-        // Validate both devices exist
-        self.validate_device_id(from_device_id).await?;
-        self.validate_device_id(to_device_id).await?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FindingSeverity {
+    Info,
+    Warning,
+    Error,
+}
 
-        // Verify the from_device is currently the default
-        let devices = self.get_audio_devices().await?;
-        let from_device = devices
-            .iter()
-            .find(|d| d.id == from_device_id)
-            .ok_or_else(|| AudioError::DeviceNotFound(from_device_id.to_string()))?;
+/// One human-readable troubleshooting observation about a device
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosisFinding {
+    pub severity: FindingSeverity,
+    pub message: String,
+}
 
-        if !from_device.is_default {
-            return Err(AudioError::CommandFailed(format!(
-                "Device {} is not currently the default",
-                from_device_id
-            )));
-        }
+/// Troubleshooting report for a single device
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceDiagnosis {
+    pub device_id: String,
+    pub device_name: String,
+    pub default_format: Option<AudioFormat>,
+    pub exclusive_mode_allowed: Option<bool>,
+    pub findings: Vec<DiagnosisFinding>,
+}
 
-        self.set_default_audio_device(to_device_id).await
-    }
+/// A Spatial Sound format that can be enabled on an output device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SpatialProvider {
+    /// Registry key of the provider, or `WindowsSonic` for the built-in one
+    pub id: String,
+    pub name: String,
+    /// Ships with Windows 10 and later rather than being installed by an app
+    pub built_in: bool,
+}
 
-    /// Quick switch to device by name (Step 7)
-    pub async fn quick_switch_to_device(&self, device_name: &str) -> AudioResult<()> {
-        info!(
-            "Quick switching to device: {} (session: {})",
-            device_name, self.session_id
-        );
+/// Two active playback devices whose shared-mode formats don't play well together
+#[derive(Debug, Clone, Serialize)]
+pub struct FormatConflict {
+    pub device_a: String,
+    pub device_b: String,
+    pub reason: String,
+}
 
-        let devices = self.get_audio_devices().await?;
-        let target_device = devices
-            .iter()
-            .find(|d| d.name.to_lowercase().contains(&device_name.to_lowercase()))
-            .ok_or_else(|| AudioError::DeviceNotFound(device_name.to_string()))?;
+/// Shared-mode format and exclusive-mode settings read from the endpoint's registry properties
+#[derive(Debug, Clone, Default, Deserialize)]
+struct EndpointFormatSettings {
+    #[serde(default)]
+    sample_rate: Option<u32>,
+    #[serde(default)]
+    bits_per_sample: Option<u16>,
+    #[serde(default)]
+    channels: Option<u16>,
+    #[serde(default)]
+    exclusive_allowed: Option<u32>,
+    #[serde(default)]
+    exclusive_priority: Option<u32>,
+}
 
-        self.set_default_audio_device(&target_device.id).await
+impl EndpointFormatSettings {
+    fn default_format(&self) -> Option<AudioFormat> {
+        Some(AudioFormat {
+            sample_rate: self.sample_rate?,
+            bits_per_sample: self.bits_per_sample?,
+            channels: self.channels?,
+        })
     }
+}
 
-    /// Validate device ID exists (Step 12)
-    pub async fn validate_device_id(&self, device_id: &str) -> AudioResult<bool> {
-        debug!("Validating device ID: {}", device_id);
+/// Devices split by type with the default of each type already resolved
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GroupedDevices {
+    pub playback: Vec<AudioDevice>,
+    pub recording: Vec<AudioDevice>,
+    pub default_playback: Option<String>,
+    pub default_recording: Option<String>,
+}
+
+impl GroupedDevices {
+    fn from_devices(devices: Vec<AudioDevice>) -> Self {
+        let mut grouped = Self::default();
+        for device in devices {
+            let (list, default) = match device.device_type {
+                DeviceType::Playback => (&mut grouped.playback, &mut grouped.default_playback),
+                DeviceType::Recording => (&mut grouped.recording, &mut grouped.default_recording),
+            };
+            if device.is_default {
+                *default = Some(device.id.clone());
+            }
+            list.push(device);
+        }
+
+        grouped.playback.sort_by(|a, b| a.name.cmp(&b.name));
+        grouped.recording.sort_by(|a, b| a.name.cmp(&b.name));
+        grouped
+    }
+}
+
+/// The device holding each of the four default roles, if any
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RoleDefaults {
+    pub playback_multimedia: Option<AudioDevice>,
+    pub playback_communication: Option<AudioDevice>,
+    pub recording_multimedia: Option<AudioDevice>,
+    pub recording_communication: Option<AudioDevice>,
+}
+
+impl RoleDefaults {
+    fn from_devices(devices: &[AudioDevice]) -> Self {
+        let find = |device_type: DeviceType, communication: bool| {
+            devices
+                .iter()
+                .find(|d| {
+                    d.device_type == device_type
+                        && if communication {
+                            d.is_communication_default
+                        } else {
+                            d.is_default
+                        }
+                })
+                .cloned()
+        };
+
+        Self {
+            playback_multimedia: find(DeviceType::Playback, false),
+            playback_communication: find(DeviceType::Playback, true),
+            recording_multimedia: find(DeviceType::Recording, false),
+            recording_communication: find(DeviceType::Recording, true),
+        }
+    }
+}
+
+/// A default role held by a device that isn't Active, which plays or records nothing
+#[derive(Debug, Clone, Serialize)]
+pub struct DefaultAnomaly {
+    pub device_type: DeviceType,
+    /// `Both` when the device holds the multimedia and communication roles
+    pub role: DefaultRole,
+    pub device_id: String,
+    pub device_name: String,
+    pub state: DeviceState,
+    /// Highest-priority Active device to switch to instead, if there is one
+    pub suggested: Option<AudioDevice>,
+}
+
+/// IDs of the devices holding the communication role, so the UI can badge the chat
+/// device separately from the media one
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CommunicationDefaults {
+    pub playback: Option<String>,
+    pub recording: Option<String>,
+}
+
+impl From<RoleDefaults> for CommunicationDefaults {
+    fn from(defaults: RoleDefaults) -> Self {
+        Self {
+            playback: defaults.playback_communication.map(|d| d.id),
+            recording: defaults.recording_communication.map(|d| d.id),
+        }
+    }
+}
+
+/// Headline facts about the audio setup, plus the same as shareable text
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupSummary {
+    pub default_playback: Option<String>,
+    pub default_recording: Option<String>,
+    pub active_devices: usize,
+    /// Installed AudioDeviceCmdlets version, `None` when it isn't installed
+    pub module_version: Option<String>,
+    pub backend: String,
+    /// Auto-switch runs unless a device has been pinned by an override
+    pub auto_switch_on: bool,
+    pub pinned_devices: Vec<String>,
+    /// Multi-line plain-text rendering of the fields above
+    pub text: String,
+}
+
+impl SetupSummary {
+    fn new(
+        defaults: &RoleDefaults,
+        devices: &[AudioDevice],
+        module_version: Option<String>,
+        backend: String,
+        pinned_devices: Vec<String>,
+    ) -> Self {
+        let name = |device: &Option<AudioDevice>| device.as_ref().map(|d| d.name.clone());
+        let mut summary = Self {
+            default_playback: name(&defaults.playback_multimedia),
+            default_recording: name(&defaults.recording_multimedia),
+            active_devices: devices
+                .iter()
+                .filter(|d| d.state == DeviceState::Active)
+                .count(),
+            module_version,
+            backend,
+            auto_switch_on: pinned_devices.is_empty(),
+            pinned_devices,
+            text: String::new(),
+        };
+        summary.text = summary.render();
+        summary
+    }
+
+    fn render(&self) -> String {
+        let or_none = |value: &Option<String>| value.clone().unwrap_or_else(|| "none".to_string());
+        let auto_switch = if self.auto_switch_on {
+            "on".to_string()
+        } else {
+            format!("paused (pinned: {})", self.pinned_devices.join(", "))
+        };
+        [
+            format!("Default playback: {}", or_none(&self.default_playback)),
+            format!("Default recording: {}", or_none(&self.default_recording)),
+            format!("Active devices: {}", self.active_devices),
+            format!(
+                "AudioDeviceCmdlets: {}",
+                self.module_version.as_deref().unwrap_or("not installed")
+            ),
+            format!("Backend: {}", self.backend),
+            format!("Auto-switch: {}", auto_switch),
+        ]
+        .join("\n")
+    }
+}
+
+/// The default playback device with its current volume and mute state
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveDeviceState {
+    pub device: AudioDevice,
+    /// Master volume, 0-100
+    pub volume: u8,
+    pub muted: bool,
+}
+
+impl ActiveDeviceState {
+    /// Combine a device with the `{"volume": <scalar 0-1>, "muted": bool}` reading of it
+    fn from_reading(device: AudioDevice, output: &str) -> AudioResult<Self> {
+        let response: serde_json::Value = parse_json_or_error(output, "volume read")?;
+        if let Some(error) = response.get("error") {
+            return Err(AudioError::CommandFailed(
+                error.as_str().unwrap_or("Unknown error").to_string(),
+            ));
+        }
+
+        let scalar = response["volume"]
+            .as_f64()
+            .ok_or_else(|| AudioError::ParseError("Missing volume".to_string()))?;
+        Ok(Self {
+            device,
+            volume: (scalar.clamp(0.0, 1.0) * 100.0).round() as u8,
+            muted: response["muted"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+/// Driver health of the PnP device behind an endpoint
+#[derive(Debug, Clone, Serialize)]
+pub struct DriverStatus {
+    pub device_id: String,
+    /// PnP instance ID of the hardware device (or the endpoint if it has no parent)
+    pub pnp_instance_id: Option<String>,
+    pub pnp_name: Option<String>,
+    /// `DEVPKEY_Device_ProblemCode`; 0 when the driver reports no problem
+    pub problem_code: u32,
+    /// Human-readable meaning of `problem_code`, when there is a problem
+    pub problem: Option<String>,
+    pub ok: bool,
+}
+
+impl DriverStatus {
+    /// Parse the `{"instance_id", "name", "status", "problem_code"}` reading of a device
+    fn from_reading(device_id: &str, output: &str) -> AudioResult<Self> {
+        let response: serde_json::Value = parse_json_or_error(output, "driver status read")?;
+        if let Some(error) = response.get("error") {
+            return Err(AudioError::CommandFailed(
+                error.as_str().unwrap_or("Unknown error").to_string(),
+            ));
+        }
+
+        let problem_code = response["problem_code"].as_u64().unwrap_or(0) as u32;
+        let problem = (problem_code != 0).then(|| {
+            problem_description(problem_code)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("Device Manager problem code {}", problem_code))
+        });
+        Ok(Self {
+            device_id: device_id.to_string(),
+            pnp_instance_id: response["instance_id"].as_str().map(str::to_string),
+            pnp_name: response["name"].as_str().map(str::to_string),
+            problem_code,
+            problem,
+            ok: problem_code == 0,
+        })
+    }
+}
+
+/// Meaning of the common Device Manager (CM_PROB_*) problem codes
+fn problem_description(code: u32) -> Option<&'static str> {
+    Some(match code {
+        1 => "The device is not configured correctly",
+        3 => "The driver may be corrupted or the system is low on memory",
+        10 => "The device cannot start",
+        12 => "Not enough free resources for the device",
+        14 => "The device needs a restart to work properly",
+        18 => "The drivers need to be reinstalled",
+        19 => "The device's registry configuration is corrupted",
+        21 => "Windows is removing the device",
+        22 => "The device is disabled",
+        24 => "The device is not present or not working properly",
+        28 => "The drivers are not installed",
+        31 => "The device is not working properly, Windows cannot load its drivers",
+        39 => "The driver is corrupted or missing",
+        43 => "Windows stopped the device because it reported problems",
+        45 => "The device is not connected",
+        52 => "The driver's digital signature cannot be verified",
+        _ => return None,
+    })
+}
+
+/// The name pattern that selected a device in `switch_to_best_available`
+#[derive(Debug, Clone, Serialize)]
+pub struct BestAvailableMatch {
+    pub pattern: String,
+    pub device: AudioDevice,
+}
+
+/// An alias together with the device it currently points at, if present
+#[derive(Debug, Clone, Serialize)]
+pub struct AliasResolution {
+    pub alias: String,
+    pub stable_key: String,
+    pub device: Option<AudioDevice>,
+}
+
+/// An application's preferred output device, if that device is currently present
+#[derive(Debug, Clone, Serialize)]
+pub struct AppPreference {
+    pub app_name: String,
+    pub stable_key: String,
+    pub device: Option<AudioDevice>,
+}
+
+/// A saved profile annotated with whether it can be fully applied right now
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileStatus {
+    pub profile: Profile,
+    pub all_devices_present: bool,
+    pub missing_devices: Vec<String>,
+}
+
+/// Outcome of applying a profile whose devices may not all be connected
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileApplication {
+    /// Device IDs from the profile that weren't present, whose roles were left as they were
+    pub unavailable_devices: Vec<String>,
+}
+
+/// Timing statistics for repeated uncached device enumerations
+#[derive(Debug, Clone, Serialize)]
+pub struct EnumerationBenchmark {
+    pub iterations: u32,
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub mean_ms: u64,
+    pub p95_ms: u64,
+    pub first_call_ms: u64,
+    /// Whether the first (cold module load) call took over 1.5x the median of the rest
+    pub cold_start_outlier: bool,
+}
+
+impl EnumerationBenchmark {
+    fn from_durations(durations: &[u64]) -> Self {
+        let mut sorted = durations.to_vec();
+        sorted.sort_unstable();
+
+        let count = sorted.len().max(1);
+        let p95_index = ((count as f64 * 0.95).ceil() as usize).saturating_sub(1);
+        let first_call_ms = durations.first().copied().unwrap_or(0);
+
+        let mut warm = durations.get(1..).unwrap_or(&[]).to_vec();
+        warm.sort_unstable();
+        let cold_start_outlier = match warm.get(warm.len() / 2) {
+            Some(&median) => first_call_ms as f64 > median as f64 * 1.5,
+            None => false,
+        };
+
+        Self {
+            iterations: durations.len() as u32,
+            min_ms: sorted.first().copied().unwrap_or(0),
+            max_ms: sorted.last().copied().unwrap_or(0),
+            mean_ms: sorted.iter().sum::<u64>() / count as u64,
+            p95_ms: sorted.get(p95_index).copied().unwrap_or(0),
+            first_call_ms,
+            cold_start_outlier,
+        }
+    }
+}
+
+/// Round-trip time of switching to a device and back to the original default
+#[derive(Debug, Clone, Serialize)]
+pub struct SwitchLatency {
+    pub round_trip_ms: u64,
+    /// The device was already the default, so nothing was switched or measured
+    pub already_default: bool,
+}
+
+/// Payload of the `module-install-progress` event emitted during automatic installs
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum ModuleInstallProgress {
+    Started,
+    Succeeded,
+    Failed { error: String },
+}
+
+/// One PowerShell operation (including its retries) as recorded in the operation log
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationLogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub operation: String,
+    pub attempts: u32,
+    pub duration_ms: u64,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// A slice of an in-memory log: the `limit` newest entries older than `before`, so the UI
+/// can fetch recent entries and page backwards by passing the oldest timestamp it has
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct LogPage {
+    /// Defaults to `DEFAULT_LOG_PAGE_SIZE`
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub before: Option<DateTime<Utc>>,
+}
+
+impl LogPage {
+    /// The selected entries of an oldest-first log, still oldest first
+    fn apply<T: Clone>(
+        &self,
+        entries: &VecDeque<T>,
+        timestamp: impl Fn(&T) -> DateTime<Utc>,
+    ) -> Vec<T> {
+        let end = match self.before {
+            Some(before) => entries.partition_point(|entry| timestamp(entry) < before),
+            None => entries.len(),
+        };
+        let start = end.saturating_sub(self.limit.unwrap_or(DEFAULT_LOG_PAGE_SIZE));
+        entries.range(start..end).cloned().collect()
+    }
+}
+
+/// One run of `evaluate_and_switch`: what it chose and why
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoSwitchDecision {
+    pub timestamp: DateTime<Utc>,
+    pub device_type: DeviceType,
+    /// ID of the device switched to or kept, `None` when nothing was selected
+    pub chosen: Option<String>,
+    pub reason: String,
+}
+
+/// Outcome of evaluating a priority chain, before any switch happens
+enum AutoSwitchSelection {
+    /// An override pins the device type to this device ID
+    Pinned(String),
+    EmptyChain,
+    NoneAvailable,
+    Target {
+        device: AudioDevice,
+        priority: usize,
+    },
+}
+
+impl AutoSwitchSelection {
+    /// Decision-history reason for this outcome
+    fn reason(&self) -> String {
+        match self {
+            AutoSwitchSelection::Pinned(device_id) => {
+                format!("no switch: pinned to {} by an override", device_id)
+            }
+            AutoSwitchSelection::EmptyChain => "no switch: the priority chain is empty".to_string(),
+            AutoSwitchSelection::NoneAvailable => {
+                "no switch: no device from the priority chain is active".to_string()
+            }
+            AutoSwitchSelection::Target { device, priority } => switched_reason(device, *priority),
+        }
+    }
+}
+
+/// Aggregate timings of one kind of operation, derived from the operation log
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationStats {
+    pub operation: String,
+    pub count: usize,
+    pub failures: usize,
+    pub mean_ms: u64,
+    pub max_ms: u64,
+}
+
+impl OperationStats {
+    fn from_log(log: &[OperationLogEntry]) -> Vec<Self> {
+        let mut by_operation: BTreeMap<&str, Vec<&OperationLogEntry>> = BTreeMap::new();
+        for entry in log {
+            by_operation
+                .entry(&entry.operation)
+                .or_default()
+                .push(entry);
+        }
+
+        by_operation
+            .into_iter()
+            .map(|(operation, entries)| Self {
+                operation: operation.to_string(),
+                count: entries.len(),
+                failures: entries.iter().filter(|e| !e.success).count(),
+                mean_ms: entries.iter().map(|e| e.duration_ms).sum::<u64>() / entries.len() as u64,
+                max_ms: entries.iter().map(|e| e.duration_ms).max().unwrap_or(0),
+            })
+            .collect()
+    }
+}
+
+/// Whether the prerequisites for switching devices are in place
+#[derive(Debug, Clone, Serialize)]
+pub struct SetupStatus {
+    /// PowerShell executable in use, or `None` if none could be started
+    pub powershell_executable: Option<String>,
+    pub module_available: bool,
+    pub ready: bool,
+}
+
+/// A mechanism the manager uses to talk to the audio system
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BackendKind {
+    /// PowerShell scripts using AudioDeviceCmdlets and the MMDevices registry
+    PowerShell,
+    /// Direct Windows Core Audio (WASAPI) calls, only available on Windows
+    CoreAudio,
+    /// PulseAudio or PipeWire through `pactl`, the backend on Linux
+    Pulse,
+    /// macOS Core Audio through the `SwitchAudioSource` CLI
+    SwitchAudioSource,
+}
+
+impl BackendKind {
+    /// Backends available in this build, in the order they're preferred
+    pub fn available() -> Vec<BackendKind> {
+        if cfg!(target_os = "linux") {
+            return vec![BackendKind::Pulse];
+        }
+        if cfg!(target_os = "macos") {
+            return vec![BackendKind::SwitchAudioSource];
+        }
+        let mut backends = vec![BackendKind::PowerShell];
+        if cfg!(windows) {
+            backends.push(BackendKind::CoreAudio);
+        }
+        backends
+    }
+}
+
+/// Actions the available backends can perform, so the UI can hide the rest
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BackendCapabilities {
+    pub backends: Vec<BackendKind>,
+    pub can_enumerate: bool,
+    pub can_switch_default: bool,
+    pub can_set_volume: bool,
+    pub can_mute: bool,
+    pub can_set_format: bool,
+    pub can_exclusive_mode: bool,
+    pub can_enumerate_sessions: bool,
+    pub can_rename: bool,
+    pub can_read_properties: bool,
+    pub can_set_enhancements: bool,
+    /// Route system sounds through the communication default (`set_system_sounds_device`)
+    pub can_route_system_sounds: bool,
+}
+
+impl BackendCapabilities {
+    /// What a single backend supports
+    pub fn of(backend: BackendKind) -> Self {
+        match backend {
+            BackendKind::PowerShell => Self {
+                backends: vec![backend],
+                can_enumerate: true,
+                can_switch_default: true,
+                can_set_volume: true,
+                can_mute: true,
+                can_read_properties: true,
+                can_set_enhancements: true,
+                can_route_system_sounds: true,
+                ..Self::default()
+            },
+            BackendKind::CoreAudio => Self {
+                backends: vec![backend],
+                can_enumerate: true,
+                can_switch_default: true,
+                can_exclusive_mode: true,
+                can_enumerate_sessions: true,
+                ..Self::default()
+            },
+            BackendKind::Pulse => Self {
+                backends: vec![backend],
+                can_enumerate: true,
+                can_switch_default: true,
+                can_set_volume: true,
+                can_mute: true,
+                ..Self::default()
+            },
+            BackendKind::SwitchAudioSource => Self {
+                backends: vec![backend],
+                can_enumerate: true,
+                can_switch_default: true,
+                ..Self::default()
+            },
+        }
+    }
+
+    /// Everything at least one of `backends` supports
+    pub fn combined(backends: &[BackendKind]) -> Self {
+        backends
+            .iter()
+            .map(|backend| Self::of(*backend))
+            .fold(Self::default(), |all, caps| Self {
+                backends: [all.backends, caps.backends].concat(),
+                can_enumerate: all.can_enumerate || caps.can_enumerate,
+                can_switch_default: all.can_switch_default || caps.can_switch_default,
+                can_set_volume: all.can_set_volume || caps.can_set_volume,
+                can_mute: all.can_mute || caps.can_mute,
+                can_set_format: all.can_set_format || caps.can_set_format,
+                can_exclusive_mode: all.can_exclusive_mode || caps.can_exclusive_mode,
+                can_enumerate_sessions: all.can_enumerate_sessions || caps.can_enumerate_sessions,
+                can_rename: all.can_rename || caps.can_rename,
+                can_read_properties: all.can_read_properties || caps.can_read_properties,
+                can_set_enhancements: all.can_set_enhancements || caps.can_set_enhancements,
+                can_route_system_sounds: all.can_route_system_sounds
+                    || caps.can_route_system_sounds,
+            })
+    }
+}
+
+/// Where two backends' device lists disagree, from `cross_check_backends`
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendComparison {
+    pub primary: BackendKind,
+    pub secondary: BackendKind,
+    /// Number of devices both backends enumerated
+    pub matched: usize,
+    /// IDs of devices only the primary backend enumerated
+    pub only_in_primary: Vec<String>,
+    /// IDs of devices only the secondary backend enumerated
+    pub only_in_secondary: Vec<String>,
+    /// Devices both enumerated whose default or communication default flags differ
+    pub default_mismatches: Vec<DefaultMismatch>,
+}
+
+/// A device the two backends disagree is (or isn't) a default
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DefaultMismatch {
+    pub device_id: String,
+    pub primary_is_default: bool,
+    pub secondary_is_default: bool,
+    pub primary_is_communication_default: bool,
+    pub secondary_is_communication_default: bool,
+}
+
+impl BackendComparison {
+    /// Match devices by endpoint ID (ignoring case) and collect the differences
+    pub fn between(
+        (primary, primary_devices): (BackendKind, &[AudioDevice]),
+        (secondary, secondary_devices): (BackendKind, &[AudioDevice]),
+    ) -> Self {
+        let find = |devices: &[AudioDevice], id: &str| {
+            devices
+                .iter()
+                .find(|d| d.id.eq_ignore_ascii_case(id))
+                .cloned()
+        };
+
+        let mut comparison = Self {
+            primary,
+            secondary,
+            matched: 0,
+            only_in_primary: Vec::new(),
+            only_in_secondary: Vec::new(),
+            default_mismatches: Vec::new(),
+        };
+        for device in primary_devices {
+            let Some(other) = find(secondary_devices, &device.id) else {
+                comparison.only_in_primary.push(device.id.clone());
+                continue;
+            };
+            comparison.matched += 1;
+            if device.is_default != other.is_default
+                || device.is_communication_default != other.is_communication_default
+            {
+                comparison.default_mismatches.push(DefaultMismatch {
+                    device_id: device.id.clone(),
+                    primary_is_default: device.is_default,
+                    secondary_is_default: other.is_default,
+                    primary_is_communication_default: device.is_communication_default,
+                    secondary_is_communication_default: other.is_communication_default,
+                });
+            }
+        }
+        comparison.only_in_secondary = secondary_devices
+            .iter()
+            .filter(|d| find(primary_devices, &d.id).is_none())
+            .map(|d| d.id.clone())
+            .collect();
+        comparison
+    }
+
+    /// Whether the backends agree on every device and default
+    pub fn is_consistent(&self) -> bool {
+        self.only_in_primary.is_empty()
+            && self.only_in_secondary.is_empty()
+            && self.default_mismatches.is_empty()
+    }
+}
+
+/// Installed and latest published AudioDeviceCmdlets versions
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleUpdateInfo {
+    /// `None` when the module isn't installed
+    pub installed: Option<String>,
+    pub latest: String,
+    pub update_available: bool,
+}
+
+/// Result of the PowerShell health check. A field is `None` when its probe failed, so a
+/// partly broken setup still reports what could be gathered.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Diagnostics {
+    pub powershell_version: Option<String>,
+    pub module_installed: Option<bool>,
+    pub module_version: Option<String>,
+    /// Effective policy outside the app's own `Bypass` process scope
+    pub execution_policy: Option<String>,
+    pub is_admin: Option<bool>,
+    pub can_list_devices: Option<bool>,
+}
+
+/// Payload of the `quiet-hours-enforced` event: a device lowered to the quiet-hours cap
+#[derive(Debug, Clone, Serialize)]
+pub struct QuietHoursEnforced {
+    pub device_id: String,
+    pub device_name: String,
+    pub previous_volume: u8,
+    pub max_volume: u8,
+}
+
+/// Enumerated devices plus the entries that couldn't be parsed, as (name, reason), so a
+/// device Windows reports oddly shows up as skipped instead of vanishing
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeviceListResult {
+    pub devices: Vec<AudioDevice>,
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Payload of the `enumeration-complete` event that ends a streamed enumeration
+#[derive(Debug, Clone, Serialize)]
+pub struct EnumerationComplete {
+    pub count: usize,
+}
+
+/// Payload of the `auto-switched` event emitted after auto-switch changes a default
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoSwitched {
+    pub device_type: DeviceType,
+    pub device_id: String,
+    /// The device's nickname when it has one, otherwise its Windows name
+    pub name: String,
+}
+
+/// Age and freshness of the cached device list
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheInfo {
+    /// Time since the last refresh, or `None` if the cache is empty
+    pub age_ms: Option<u64>,
+    pub ttl_ms: u64,
+    pub device_count: usize,
+    pub is_stale: bool,
+}
+
+/// The device cache as it stands, read without refreshing it
+#[derive(Debug, Clone, Serialize)]
+pub struct CachedDevices {
+    pub devices: Vec<AudioDevice>,
+    /// Whether `get_audio_devices` would refresh instead of returning these
+    pub is_stale: bool,
+}
+
+/// A device pinned as default, during which auto-switch leaves its device type alone.
+/// Temporary overrides end at `expires_at`; manual ones last until cleared.
+#[derive(Debug, Clone)]
+struct DeviceOverride {
+    device_id: String,
+    device_type: DeviceType,
+    expires_at: Option<Instant>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioManagerState {
+    cached_devices: HashMap<String, AudioDevice>,
+    last_refresh: Option<Instant>,
+    /// Defaults each device type had before its last successful switch, one per role that
+    /// switch changed, for `undo_last_switch`
+    previous_defaults_playback: Vec<(String, DefaultRole)>,
+    previous_defaults_recording: Vec<(String, DefaultRole)>,
+    /// Device type of the most recent switch, the one `undo_last_switch` undoes by default
+    last_switched_type: Option<DeviceType>,
+    operation_log: VecDeque<OperationLogEntry>,
+    /// At most one override per device type
+    device_overrides: Vec<DeviceOverride>,
+    autoswitch_decisions: VecDeque<AutoSwitchDecision>,
+    /// ID, state and default flag of every device seen on the watcher's last poll
+    watched_devices: Option<Vec<(String, &'static str, bool)>>,
+    /// Endpoint IDs per PnP container, computed at warm-up and when devices come or go
+    device_containers: Option<HashMap<String, Vec<String>>>,
+    /// Entries the last enumeration couldn't parse, as (name, reason)
+    skipped_devices: Vec<(String, String)>,
+    /// Result of the last `benchmark_enumeration`, kept for the diagnostics bundle
+    last_benchmark: Option<EnumerationBenchmark>,
+}
+
+impl AudioManagerState {
+    /// Whether the cached device list was refreshed less than `ttl` before `now`
+    fn is_cache_fresh(&self, now: Instant, ttl: Duration) -> bool {
+        self.last_refresh
+            .is_some_and(|last_refresh| now.saturating_duration_since(last_refresh) < ttl)
+    }
+
+    /// The override pinning `device_type`, unless it has expired by `now`
+    fn active_override(&self, device_type: &DeviceType, now: Instant) -> Option<&DeviceOverride> {
+        self.device_overrides.iter().find(|o| {
+            o.device_type == *device_type
+                && !o.expires_at.is_some_and(|expires_at| now >= expires_at)
+        })
+    }
+
+    /// Pin a device, replacing any override of the same device type
+    fn set_override(&mut self, device_override: DeviceOverride) {
+        self.device_overrides
+            .retain(|o| o.device_type != device_override.device_type);
+        self.device_overrides.push(device_override);
+    }
+
+    /// Remove and return the overrides matching `predicate`
+    fn take_overrides(
+        &mut self,
+        predicate: impl Fn(&DeviceOverride) -> bool,
+    ) -> Vec<DeviceOverride> {
+        let (taken, kept) = std::mem::take(&mut self.device_overrides)
+            .into_iter()
+            .partition(predicate);
+        self.device_overrides = kept;
+        taken
+    }
+}
+
+impl Default for AudioManagerState {
+    fn default() -> Self {
+        Self {
+            cached_devices: HashMap::new(),
+            last_refresh: None,
+            previous_defaults_playback: Vec::new(),
+            previous_defaults_recording: Vec::new(),
+            last_switched_type: None,
+            operation_log: VecDeque::with_capacity(OPERATION_LOG_CAPACITY),
+            device_overrides: Vec::new(),
+            autoswitch_decisions: VecDeque::with_capacity(AUTOSWITCH_DECISION_CAPACITY),
+            watched_devices: None,
+            device_containers: None,
+            skipped_devices: Vec::new(),
+            last_benchmark: None,
+        }
+    }
+}
+
+pub struct AudioManager {
+    state: std::sync::Arc<tokio::sync::RwLock<AudioManagerState>>,
+    session_id: String,
+    /// Random salt for redacted diagnostics, drawn once per session and never exported
+    redaction_salt: String,
+    /// Optional path to simple JSONL "mini DB" file used to persist device snapshots
+    db_path: Option<PathBuf>,
+    /// User configuration (profiles and settings) loaded from `config.json`
+    config: tokio::sync::RwLock<AudioManagerConfig>,
+    config_path: Option<PathBuf>,
+    /// Persisted playback/recording priority chains
+    priorities: PriorityStore,
+    /// Serializes multi-step operations that change system state
+    operation_lock: tokio::sync::Mutex<()>,
+    /// Set once an automatic module install has been attempted this session
+    auto_install_attempted: AtomicBool,
+    /// Handle used to emit events to the frontend, attached once the app is set up
+    app_handle: OnceLock<AppHandle>,
+    /// When set, every method that changes system state fails with `AudioError::ReadOnly`
+    read_only: AtomicBool,
+    /// When set, default-device switches only update the cache instead of the system
+    dry_run: AtomicBool,
+    /// Log every PowerShell invocation in full (script, executable, outcome, duration)
+    trace_powershell: AtomicBool,
+    /// PowerShell executable used for every script, updated by `redetect_backend`
+    powershell_exe: Arc<RwLock<&'static str>>,
+    /// Runs every script; outside tests, on long-lived PowerShell sessions with the
+    /// module already imported
+    runner: Box<dyn CommandRunner>,
+    /// Platform backend used instead of PowerShell for listing, switching, volume and
+    /// mute, or `None` on Windows
+    native_backend: Option<Arc<dyn AudioBackend>>,
+    /// Time source for cache expiry and other time-based decisions
+    clock: Arc<dyn Clock>,
+    /// Latest requested switch per device type (playback, recording); a switch still
+    /// waiting out the coalescing window gives up once a newer one is requested
+    switch_generations: [AtomicU64; 2],
+    /// Background device monitor started by `start_monitoring`
+    monitor: std::sync::Mutex<Option<tauri::async_runtime::JoinHandle<()>>>,
+}
+
+tokio::task_local! {
+    /// Set while a bulk operation runs (see `AudioManager::in_bulk_operation`); records
+    /// whether that operation asked for a cache invalidation
+    static BULK_INVALIDATION: std::cell::Cell<bool>;
+}
+
+impl AudioManager {
+    /// Initialize audio manager with session tracking (Step 6)
+    pub fn new() -> AudioResult<Self> {
+        let session_id = Uuid::new_v4().to_string();
+        info!("Initializing AudioManager with session ID: {}", session_id);
+
+        // Default DB path: user's local app data directory under sound-changer/devices.jsonl
+        let db_path = config::app_data_dir().map(|p| p.join("devices.jsonl"));
+        let config_path = config::app_data_dir().map(|p| p.join("config.json"));
+        let priorities_path = config::app_data_dir().map(|p| p.join("priorities.json"));
+
+        let mut config = match &config_path {
+            Some(path) => config::load_json(path).unwrap_or_else(|e| {
+                warn!("Failed to load config, using defaults: {}", e);
+                AudioManagerConfig::default()
+            }),
+            None => AudioManagerConfig::default(),
+        };
+        if let Err(e) = config.validate_timing() {
+            warn!("Ignoring timing settings, using defaults: {}", e);
+            config.reset_timing();
+        }
+        let powershell_exe = Arc::new(RwLock::new(POWERSHELL_EXECUTABLES[0]));
+
+        Ok(Self {
+            state: std::sync::Arc::new(tokio::sync::RwLock::new(AudioManagerState::default())),
+            session_id,
+            redaction_salt: Uuid::new_v4().to_string(),
+            db_path,
+            config: tokio::sync::RwLock::new(config),
+            config_path,
+            priorities: PriorityStore::load(priorities_path),
+            operation_lock: tokio::sync::Mutex::new(()),
+            auto_install_attempted: AtomicBool::new(false),
+            app_handle: OnceLock::new(),
+            read_only: AtomicBool::new(false),
+            dry_run: AtomicBool::new(false),
+            trace_powershell: AtomicBool::new(false),
+            powershell_exe: powershell_exe.clone(),
+            runner: Box::new(PowerShellRunner::new(
+                PowerShellHost::default(),
+                powershell_exe,
+            )),
+            native_backend: backend::platform_backend(),
+            clock: Arc::new(SystemClock),
+            switch_generations: [AtomicU64::new(0), AtomicU64::new(0)],
+            monitor: std::sync::Mutex::new(None),
+        })
+    }
+
+    /// A manager that persists nothing and runs every script on `runner`, for driving it
+    /// without PowerShell
+    pub fn with_runner(runner: Box<dyn CommandRunner>) -> Self {
+        Self {
+            state: std::sync::Arc::new(tokio::sync::RwLock::new(AudioManagerState::default())),
+            session_id: Uuid::new_v4().to_string(),
+            redaction_salt: Uuid::new_v4().to_string(),
+            db_path: None,
+            config: tokio::sync::RwLock::new(AudioManagerConfig::default()),
+            config_path: None,
+            priorities: PriorityStore::load(None),
+            operation_lock: tokio::sync::Mutex::new(()),
+            auto_install_attempted: AtomicBool::new(false),
+            app_handle: OnceLock::new(),
+            read_only: AtomicBool::new(false),
+            dry_run: AtomicBool::new(false),
+            trace_powershell: AtomicBool::new(false),
+            powershell_exe: Arc::new(RwLock::new(POWERSHELL_EXECUTABLES[0])),
+            runner,
+            native_backend: None,
+            clock: Arc::new(SystemClock),
+            switch_generations: [AtomicU64::new(0), AtomicU64::new(0)],
+            monitor: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// A manager that persists nothing, with time driven by `clock`
+    #[cfg(test)]
+    fn in_memory(clock: Arc<dyn Clock>) -> Self {
+        let powershell_exe = Arc::new(RwLock::new(POWERSHELL_EXECUTABLES[0]));
+        let runner = PowerShellRunner::new(PowerShellHost::default(), powershell_exe.clone());
+        Self {
+            powershell_exe,
+            clock,
+            ..Self::with_runner(Box::new(runner))
+        }
+    }
+
+    /// Compatibility method - calls get_audio_devices
+    pub async fn get_devices(&self) -> AudioResult<Vec<AudioDevice>> {
+        self.get_audio_devices().await
+    }
+
+    /// Get all audio devices with caching and performance monitoring (Steps 6, 17)
+    pub async fn get_audio_devices(&self) -> AudioResult<Vec<AudioDevice>> {
+        info!("Fetching audio devices (session: {})", self.session_id);
+
+        // Check cache validity
+        let cache_ttl = self.cache_ttl().await;
+        {
+            let state = self.state.read().await;
+            if state.is_cache_fresh(self.clock.now(), cache_ttl) {
+                debug!("Returning cached devices");
+                return Ok(state.cached_devices.values().cloned().collect());
+            }
+        }
+
+        self.refresh_devices().await
+    }
 
+    /// [`Self::get_audio_devices`] plus the entries the enumeration had to skip, so the
+    /// UI can say how many devices couldn't be parsed
+    pub async fn get_audio_devices_detailed(&self) -> AudioResult<DeviceListResult> {
         let devices = self.get_audio_devices().await?;
-        let exists = devices.iter().any(|d| d.id == device_id);
+        let skipped = self.state.read().await.skipped_devices.clone();
+        Ok(DeviceListResult { devices, skipped })
+    }
+
+    /// Devices of one type, served from the same cache as [`Self::get_audio_devices`] so
+    /// asking for both types back to back enumerates once
+    pub async fn get_devices_by_type(
+        &self,
+        device_type: &DeviceType,
+    ) -> AudioResult<Vec<AudioDevice>> {
+        let mut devices = self.get_audio_devices().await?;
+        devices.retain(|d| d.device_type == *device_type);
+        Ok(devices)
+    }
+
+    /// Get all audio devices straight from PowerShell, ignoring the cache TTL, and refresh
+    /// the cache with the result. For user-initiated refreshes after changes made outside
+    /// the app; polling loops should stay on [`Self::get_audio_devices`]
+    pub async fn get_audio_devices_force(&self) -> AudioResult<Vec<AudioDevice>> {
+        info!(
+            "Force-refreshing audio devices (session: {})",
+            self.session_id
+        );
+        self.refresh_devices().await
+    }
+
+    /// Enumerate devices, replace the cache and persist a snapshot
+    async fn refresh_devices(&self) -> AudioResult<Vec<AudioDevice>> {
+        let start_time = Instant::now();
+        let now = self.clock.now();
+
+        let list = match self.fetch_devices().await {
+            Err(error @ AudioError::ModuleNotInstalled(_)) => {
+                if !self.try_auto_install_module().await {
+                    return Err(error);
+                }
+                self.fetch_devices().await?
+            }
+            result => result?,
+        };
+
+        self.cache_device_list(&list, now).await;
+        let devices = list.devices;
+
+        // Persist a snapshot to the simple JSONL DB (best-effort)
+        if let Some(db) = &self.db_path {
+            if let Err(e) = Self::save_devices_snapshot(db, &devices, &self.session_id).await {
+                warn!("Failed to persist device snapshot: {}", e);
+            }
+        }
+
+        let elapsed = start_time.elapsed();
+        if elapsed > DEVICE_LISTING_TIMEOUT {
+            warn!(
+                "Device listing took {}ms, exceeds target of {}ms",
+                elapsed.as_millis(),
+                DEVICE_LISTING_TIMEOUT.as_millis()
+            );
+        }
+
+        info!(
+            "Found {} audio devices in {}ms",
+            devices.len(),
+            elapsed.as_millis()
+        );
+        Ok(devices)
+    }
+
+    /// Enumerate devices bypassing the cache, emitting a `device-enumerated` event per
+    /// device as the script reports it and `enumeration-complete` with the total at the end
+    pub async fn stream_audio_devices(&self) -> AudioResult<usize> {
+        let count = self
+            .stream_devices_with(&mut |device| self.emit("device-enumerated", device.clone()))
+            .await?;
+        self.emit("enumeration-complete", EnumerationComplete { count });
+        Ok(count)
+    }
+
+    /// Enumerate devices bypassing the cache, handing each one to `on_device` as soon as
+    /// it's known, and return how many were found
+    async fn stream_devices_with(
+        &self,
+        on_device: &mut (dyn FnMut(&AudioDevice) + Send),
+    ) -> AudioResult<usize> {
+        let now = self.clock.now();
+        info!("Streaming audio devices (session: {})", self.session_id);
+
+        let list = match self.fetch_devices_with_sink(&mut *on_device).await {
+            Err(error @ AudioError::ModuleNotInstalled(_)) => {
+                if !self.try_auto_install_module().await {
+                    return Err(error);
+                }
+                self.fetch_devices_with_sink(on_device).await?
+            }
+            result => result?,
+        };
+
+        self.cache_device_list(&list, now).await;
+        Ok(list.devices.len())
+    }
+
+    /// Cache a fresh enumeration along with the entries it had to skip
+    async fn cache_device_list(&self, list: &DeviceListResult, now: Instant) {
+        self.cache_devices(&list.devices, now).await;
+        self.state.write().await.skipped_devices = list.skipped.clone();
+    }
+
+    /// Replace the cached device list, marking it refreshed at `now`
+    async fn cache_devices(&self, devices: &[AudioDevice], now: Instant) {
+        let mut state = self.state.write().await;
+        state.cached_devices.clear();
+        for device in devices {
+            state
+                .cached_devices
+                .insert(device.id.clone(), device.clone());
+        }
+        state.last_refresh = Some(now);
+    }
+
+    /// Fetch devices from the platform backend with enhanced error handling (Steps 9, 18)
+    async fn fetch_devices(&self) -> AudioResult<DeviceListResult> {
+        self.fetch_devices_with_sink(&mut |_| {}).await
+    }
+
+    /// Fetch devices from the platform backend, handing each one to `on_device` once:
+    /// as the listing script reports it on the warm host, otherwise as it's parsed
+    async fn fetch_devices_with_sink(
+        &self,
+        on_device: &mut (dyn FnMut(&AudioDevice) + Send),
+    ) -> AudioResult<DeviceListResult> {
+        let display_names = self.config.read().await.display_names.clone();
+        if let Some(result) = self
+            .with_native_backend(|backend| backend.list_devices())
+            .await
+        {
+            let list = result?;
+            return Ok(DeviceListResult {
+                devices: label_devices(list.devices, &display_names, on_device),
+                skipped: list.skipped,
+            });
+        }
+
+        let mut reported = HashSet::new();
+        let output = {
+            let mut on_progress = |line: &str| {
+                let Some(mut device) = serde_json::from_str::<RawDevice>(line)
+                    .ok()
+                    .and_then(|raw| AudioDevice::try_from(raw).ok())
+                else {
+                    // Skipped devices are recorded when the full listing is parsed
+                    return;
+                };
+                if reported.insert(device.id.clone()) {
+                    device.display_name = display_names.get(&device.id).cloned();
+                    on_device(&device);
+                }
+            };
+            self.execute_powershell_streaming(
+                DEVICE_LIST_SCRIPT,
+                "device enumeration",
+                self.command_timeout().await,
+                &mut on_progress,
+            )
+            .await
+        };
+        let result = output.and_then(|output| {
+            self.parse_device_list_response(&output, &display_names, &mut |device| {
+                if !reported.contains(&device.id) {
+                    on_device(device);
+                }
+            })
+        });
+
+        // Core Audio can still list devices without the module; switching needs it
+        #[cfg(all(windows, feature = "native-wasapi"))]
+        if let Err(AudioError::ModuleNotInstalled(missing)) = &result {
+            warn!("{} missing, listing devices through Core Audio", missing);
+            let devices = tokio::task::spawn_blocking(crate::wasapi::list_devices)
+                .await
+                .map_err(|e| AudioError::Unknown(e.to_string()))??;
+            return Ok(DeviceListResult {
+                devices: label_devices(devices, &display_names, on_device),
+                skipped: Vec::new(),
+            });
+        }
+        result
+    }
+
+    /// Run `operation` on the native backend on the blocking pool, or `None` when this
+    /// platform goes through PowerShell
+    async fn with_native_backend<T: Send + 'static>(
+        &self,
+        operation: impl FnOnce(&dyn AudioBackend) -> AudioResult<T> + Send + 'static,
+    ) -> Option<AudioResult<T>> {
+        let backend = self.native_backend.clone()?;
+        let result = tokio::task::spawn_blocking(move || operation(backend.as_ref()))
+            .await
+            .map_err(|e| AudioError::Unknown(e.to_string()));
+        Some(result.and_then(|result| result))
+    }
+
+    /// Devices split into playback and recording with defaults pre-resolved
+    pub async fn get_devices_grouped(&self) -> AudioResult<GroupedDevices> {
+        let devices = self.get_audio_devices().await?;
+        Ok(GroupedDevices::from_devices(devices))
+    }
+
+    /// The default device of every role, for the defaults overview
+    pub async fn get_all_defaults(&self) -> AudioResult<RoleDefaults> {
+        let devices = self.get_audio_devices().await?;
+        Ok(RoleDefaults::from_devices(&devices))
+    }
+
+    /// The communication playback and recording device IDs, without the multimedia roles
+    pub async fn get_communication_defaults(&self) -> AudioResult<CommunicationDefaults> {
+        Ok(self.get_all_defaults().await?.into())
+    }
+
+    /// A short, shareable description of the current audio setup for support questions
+    pub async fn get_setup_summary(&self) -> AudioResult<SetupSummary> {
+        let devices = self.get_audio_devices().await?;
+        let defaults = RoleDefaults::from_devices(&devices);
+        let module_version = self.module_version().await.unwrap_or_else(|e| {
+            warn!("Setup summary without a module version: {}", e);
+            None
+        });
+
+        let now = self.clock.now();
+        let pinned: Vec<String> = {
+            let state = self.state.read().await;
+            [DeviceType::Playback, DeviceType::Recording]
+                .iter()
+                .filter_map(|device_type| state.active_override(device_type, now))
+                .map(|o| {
+                    devices
+                        .iter()
+                        .find(|d| d.id == o.device_id)
+                        .map_or_else(|| o.device_id.clone(), |d| d.name.clone())
+                })
+                .collect()
+        };
+
+        let backends = BackendKind::available()
+            .iter()
+            .map(|backend| match backend {
+                BackendKind::PowerShell => self.powershell_executable().to_string(),
+                BackendKind::CoreAudio => "Core Audio".to_string(),
+                BackendKind::Pulse => "pactl".to_string(),
+                BackendKind::SwitchAudioSource => "SwitchAudioSource".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" + ");
+
+        Ok(SetupSummary::new(
+            &defaults,
+            &devices,
+            module_version,
+            backends,
+            pinned,
+        ))
+    }
+
+    /// The default playback device with its volume and mute state, or `None` when no
+    /// playback device is the default
+    pub async fn get_active_device_state(&self) -> AudioResult<Option<ActiveDeviceState>> {
+        let devices = self.get_audio_devices().await?;
+        let Some(device) = default_device(devices, &DeviceType::Playback) else {
+            return Ok(None);
+        };
+
+        let powershell_script = format!(
+            r#"
+                try {{
+                    Import-Module AudioDeviceCmdlets -ErrorAction Stop
+                    
+                    $endpoint = (Get-AudioDevice -ID {}).Device.AudioEndpointVolume
+                    @{{
+                        volume = $endpoint.MasterVolumeLevelScalar
+                        muted = $endpoint.Mute
+                    }} | ConvertTo-Json -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            powershell_string(&device.id)
+        );
+
+        let output = self
+            .execute_powershell_with_retry(
+                &powershell_script,
+                "volume read",
+                self.command_timeout().await,
+            )
+            .await?;
+        ActiveDeviceState::from_reading(device, &output).map(Some)
+    }
+
+    /// PowerShell operations run this session, oldest first
+    pub async fn get_operation_log(&self, page: LogPage) -> Vec<OperationLogEntry> {
+        page.apply(&self.state.read().await.operation_log, |entry| {
+            entry.timestamp
+        })
+    }
+
+    /// Write a single JSON file with everything needed to triage a support issue.
+    /// With `redact` set, device names and IDs are replaced by tokens salted for this
+    /// session, everywhere in the bundle, including devices the config refers to that
+    /// aren't connected.
+    pub async fn export_diagnostics_bundle(&self, path: &Path, redact: bool) -> AudioResult<()> {
+        info!(
+            "Exporting diagnostics bundle to {} (redact: {})",
+            path.display(),
+            redact
+        );
+
+        let module_available = self.check_module_availability().await.ok();
+        let devices = self.get_audio_devices().await.unwrap_or_else(|e| {
+            warn!("Exporting diagnostics without a device list: {}", e);
+            Vec::new()
+        });
+        let operation_log = self
+            .get_operation_log(LogPage {
+                limit: Some(OPERATION_LOG_CAPACITY),
+                before: None,
+            })
+            .await;
+
+        let bundle = DiagnosticsBundle {
+            generated_at: Utc::now(),
+            app_version: env!("CARGO_PKG_VERSION"),
+            session_id: self.session_id.clone(),
+            redacted: redact,
+            module_available,
+            read_only: self.is_read_only(),
+            config: self.config.read().await.clone(),
+            last_error: operation_log
+                .iter()
+                .rev()
+                .find_map(|entry| entry.error.clone()),
+            perf_stats: OperationStats::from_log(&operation_log),
+            enumeration_benchmark: self.state.read().await.last_benchmark.clone(),
+            operation_log,
+            devices,
+        };
+
+        let mut value = serde_json::to_value(&bundle)?;
+        if redact {
+            Redactor::new(&self.redaction_salt, &bundle.devices, &bundle.config).redact(&mut value);
+        }
+        config::save_json_atomic(path, &value)
+    }
+
+    /// Write the device list to `path` as CSV, with each device's connection type taken
+    /// from its PnP enumerator (left blank when the property store can't be read)
+    pub async fn export_devices_csv(&self, path: &Path) -> AudioResult<usize> {
+        info!("Exporting device list as CSV to {}", path.display());
+        let devices = self.get_audio_devices().await?;
+
+        let mut rows = Vec::with_capacity(devices.len());
+        for device in devices {
+            let connection_type = match self.get_device_properties(&device.id).await {
+                Ok(properties) => properties
+                    .get("EnumeratorName")
+                    .map(|name| csv_export::connection_type(name)),
+                Err(e) => {
+                    debug!("No connection type for {}: {}", device.id, e);
+                    None
+                }
+            };
+            rows.push((device, connection_type));
+        }
+
+        tokio::fs::write(path, csv_export::devices_to_csv(&rows)).await?;
+        Ok(rows.len())
+    }
+
+    /// How old the cached device list is and whether the next read will refresh it
+    pub async fn get_cache_info(&self) -> CacheInfo {
+        let cache_ttl = self.cache_ttl().await;
+        let state = self.state.read().await;
+        let age = state
+            .last_refresh
+            .map(|last_refresh| self.clock.now().saturating_duration_since(last_refresh));
+
+        CacheInfo {
+            age_ms: age.map(|age| age.as_millis() as u64),
+            ttl_ms: cache_ttl.as_millis() as u64,
+            device_count: state.cached_devices.len(),
+            is_stale: age.map_or(true, |age| age >= cache_ttl),
+        }
+    }
+
+    /// The cached devices without ever enumerating, unlike `get_audio_devices`, which
+    /// refreshes a stale cache. Empty when nothing has been cached.
+    pub async fn get_cached_devices(&self) -> CachedDevices {
+        let cache_ttl = self.cache_ttl().await;
+        let state = self.state.read().await;
+        CachedDevices {
+            devices: state.cached_devices.values().cloned().collect(),
+            is_stale: !state.is_cache_fresh(self.clock.now(), cache_ttl),
+        }
+    }
+
+    /// Drop the cached devices so the next read enumerates again
+    pub async fn clear_device_cache(&self) {
+        info!("Clearing device cache (session: {})", self.session_id);
+        self.invalidate_cache().await;
+    }
+
+    /// How long an enumerated device list is served from cache
+    async fn cache_ttl(&self) -> Duration {
+        self.config
+            .read()
+            .await
+            .cache_ttl_ms
+            .map_or(DEFAULT_CACHE_TTL, Duration::from_millis)
+    }
+
+    /// Per-attempt time limit for ordinary PowerShell scripts
+    async fn command_timeout(&self) -> Duration {
+        self.config
+            .read()
+            .await
+            .command_timeout_ms
+            .map_or(COMMAND_TIMEOUT, Duration::from_millis)
+    }
+
+    /// Configure how long enumerated devices are served from cache; 0 disables caching
+    pub async fn set_cache_ttl(&self, ttl_ms: u64) -> AudioResult<()> {
+        info!("Setting device cache TTL: {}ms", ttl_ms);
+        self.update_config(|config| {
+            config.cache_ttl_ms = Some(ttl_ms);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Re-read `config.json`, picking up settings edited by hand (timeouts, retries, cache
+    /// TTL and everything else) without restarting
+    pub async fn reload_config(&self) -> AudioResult<()> {
+        let path = self
+            .config_path
+            .as_ref()
+            .ok_or_else(|| AudioError::InvalidConfig("No config file to reload".to_string()))?;
+        let config: AudioManagerConfig = config::load_json(path)?;
+        config.validate_timing()?;
+        info!("Reloaded config from {}", path.display());
+        *self.config.write().await = config;
+        self.invalidate_cache().await;
+        Ok(())
+    }
+
+    /// Time repeated enumerations with the cache bypassed to measure PowerShell start-up cost
+    pub async fn benchmark_enumeration(
+        &self,
+        iterations: u32,
+    ) -> AudioResult<EnumerationBenchmark> {
+        let iterations = iterations.clamp(1, MAX_BENCHMARK_ITERATIONS);
+        info!(
+            "Benchmarking device enumeration over {} iterations (session: {})",
+            iterations, self.session_id
+        );
+
+        let mut durations = Vec::with_capacity(iterations as usize);
+        for _ in 0..iterations {
+            let start = Instant::now();
+            self.fetch_devices().await?;
+            durations.push(start.elapsed().as_millis() as u64);
+        }
+
+        let benchmark = EnumerationBenchmark::from_durations(&durations);
+        info!(
+            "Enumeration benchmark: min {}ms, mean {}ms, p95 {}ms, max {}ms",
+            benchmark.min_ms, benchmark.mean_ms, benchmark.p95_ms, benchmark.max_ms
+        );
+        self.state.write().await.last_benchmark = Some(benchmark.clone());
+        Ok(benchmark)
+    }
+
+    /// Set default audio device for `role` with validation and fallback (Steps 6, 7, 19).
+    /// A `manual_override` pins the device until `clear_manual_override`; other switches
+    /// fail with `AudioError::Pinned` while the device type is pinned to a different
+    /// device. Auto-switch only manages the multimedia role, so a communications-only
+    /// switch never pins.
+    pub async fn set_default_device(
+        &self,
+        device_id: &str,
+        device_type: &DeviceType,
+        role: DefaultRole,
+        manual_override: bool,
+    ) -> AudioResult<()> {
+        let manual_override = manual_override && role != DefaultRole::Communications;
+        if !manual_override {
+            if let Some(pinned) = self
+                .state
+                .read()
+                .await
+                .active_override(device_type, self.clock.now())
+                .filter(|o| o.device_id != device_id)
+            {
+                info!(
+                    "{:?} is pinned to {}, not switching to {}",
+                    device_type, pinned.device_id, device_id
+                );
+                return Err(AudioError::Pinned(pinned.device_id.clone()));
+            }
+        }
+
+        self.set_default_audio_device_for_role(device_id, role)
+            .await?;
+
+        if manual_override {
+            let device_id = self.resolve_device_id(device_id).await?;
+            info!("Pinning {:?} to {} until cleared", device_type, device_id);
+            self.state.write().await.set_override(DeviceOverride {
+                device_id,
+                device_type: device_type.clone(),
+                expires_at: None,
+            });
+        }
+        Ok(())
+    }
+
+    /// Drop the manual override of `device_type` and let auto-switch pick again,
+    /// returning whether there was one
+    pub async fn clear_manual_override(&self, device_type: &DeviceType) -> AudioResult<bool> {
+        let cleared = self
+            .state
+            .write()
+            .await
+            .take_overrides(|o| o.device_type == *device_type && o.expires_at.is_none());
+        if cleared.is_empty() {
+            return Ok(false);
+        }
+
+        info!("Cleared manual override of {:?}", device_type);
+        self.evaluate_and_switch(device_type).await?;
+        Ok(true)
+    }
+
+    /// Set default audio device with validation and fallback (Steps 6, 7, 19).
+    /// `device_id` may be the full endpoint ID or the AudioDeviceCmdlets index.
+    /// Switches of the same device type requested within the coalescing window
+    /// supersede each other, so only the last one is applied; the others fail with
+    /// `AudioError::Superseded`.
+    pub async fn set_default_audio_device(&self, device_id: &str) -> AudioResult<()> {
+        self.set_default_audio_device_for_role(device_id, DefaultRole::Both)
+            .await
+    }
+
+    /// Make `device_id` the default for `role` only, leaving the other role where it is
+    /// (e.g. music on the speakers while calls stay on the headset)
+    pub async fn set_default_audio_device_for_role(
+        &self,
+        device_id: &str,
+        role: DefaultRole,
+    ) -> AudioResult<()> {
+        self.ensure_writable("change the default device")?;
+        info!(
+            "Setting default audio device: {} ({:?}, session: {})",
+            device_id, role, self.session_id
+        );
+
+        // Validate device exists first (Step 12)
+        let device_id = self.resolve_device_id(device_id).await?;
+        let device_id = device_id.as_str();
+
+        // Only switches of both roles are coalesced, so picking the communication
+        // device right after the multimedia one doesn't cancel it
+        let device_type = endpoint_device_type(device_id).filter(|_| role == DefaultRole::Both);
+        let generation = match &device_type {
+            Some(device_type) => match self.coalesce_switch(device_type).await {
+                Some(generation) => Some(generation),
+                None => {
+                    info!("Switch to {} superseded by a newer switch", device_id);
+                    return Err(AudioError::Superseded(device_id.to_string()));
+                }
+            },
+            None => None,
+        };
+        let start_time = Instant::now();
+
+        // Store current default for fallback (Step 19)
+        let current_defaults = self.get_current_defaults().await?;
+
+        if let (Some(device_type), Some(generation)) = (&device_type, generation) {
+            if self.is_switch_superseded(device_type, generation) {
+                info!("Switch to {} superseded by a newer switch", device_id);
+                return Err(AudioError::Superseded(device_id.to_string()));
+            }
+        }
+
+        let result = self.change_default_device(device_id, role).await;
+
+        match result {
+            Ok(_) => {
+                let elapsed = start_time.elapsed();
+                if elapsed > DEVICE_SWITCHING_TIMEOUT {
+                    warn!(
+                        "Device switching took {}ms, exceeds target of {}ms",
+                        elapsed.as_millis(),
+                        DEVICE_SWITCHING_TIMEOUT.as_millis()
+                    );
+                }
+                info!(
+                    "Successfully set default device: {} in {}ms",
+                    device_id,
+                    elapsed.as_millis()
+                );
+
+                self.remember_previous_default(device_id, role, &current_defaults)
+                    .await;
+                // Update cache to reflect changes
+                self.invalidate_cache().await;
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed to set default device, attempting fallback");
+                self.fallback_to_previous_device(&current_defaults).await?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Record the defaults a switch to `device_id` replaced. Only the switched device type's
+    /// entry changes, so undoing a playback switch survives a later recording switch.
+    async fn remember_previous_default(
+        &self,
+        device_id: &str,
+        role: DefaultRole,
+        outgoing: &DefaultDevices,
+    ) {
+        let mut state = self.state.write().await;
+        let Some(device_type) = state
+            .cached_devices
+            .get(device_id)
+            .map(|d| d.device_type.clone())
+            .or_else(|| endpoint_device_type(device_id))
+        else {
+            return;
+        };
+        // Switching to the current default leaves nothing new to undo
+        let previous = displaced_defaults(outgoing, &device_type, role, device_id);
+        if previous.is_empty() {
+            return;
+        }
+
+        debug!("Previous {:?} defaults: {:?}", device_type, previous);
+        match device_type {
+            DeviceType::Playback => state.previous_defaults_playback = previous,
+            DeviceType::Recording => state.previous_defaults_recording = previous,
+        }
+        state.last_switched_type = Some(device_type);
+    }
+
+    /// Switch `device_type` (by default the type switched most recently) back to the
+    /// defaults its last switch replaced, each restored to the role it held. Undoing again
+    /// switches forward.
+    pub async fn undo_last_switch(&self, device_type: Option<DeviceType>) -> AudioResult<()> {
+        let (device_type, previous) = {
+            let state = self.state.read().await;
+            let Some(device_type) = device_type.or_else(|| state.last_switched_type.clone()) else {
+                return Err(AudioError::InvalidArgument("No switch to undo".to_string()));
+            };
+            let previous = match device_type {
+                DeviceType::Playback => state.previous_defaults_playback.clone(),
+                DeviceType::Recording => state.previous_defaults_recording.clone(),
+            };
+            (device_type, previous)
+        };
+        if previous.is_empty() {
+            return Err(AudioError::InvalidArgument(format!(
+                "No {:?} switch to undo",
+                device_type
+            )));
+        }
+
+        let outgoing = self.get_current_defaults().await?;
+        let mut forward = Vec::new();
+        for (device_id, role) in previous {
+            info!("Undoing switch: restoring {} ({:?})", device_id, role);
+            self.set_default_audio_device_for_role(&device_id, role)
+                .await?;
+            forward.extend(displaced_defaults(
+                &outgoing,
+                &device_type,
+                role,
+                &device_id,
+            ));
+        }
+
+        // Each restore only recorded its own role; undoing again redoes all of them
+        let mut state = self.state.write().await;
+        match device_type {
+            DeviceType::Playback => state.previous_defaults_playback = forward,
+            DeviceType::Recording => state.previous_defaults_recording = forward,
+        }
+        state.last_switched_type = Some(device_type);
+        Ok(())
+    }
+
+    /// Change audio output with device validation (Step 7)
+    pub async fn change_audio_output(
+        &self,
+        from_device_id: &str,
+        to_device_id: &str,
+    ) -> AudioResult<()> {
+        info!(
+            "Changing audio output from {} to {} (session: {})",
+            from_device_id, to_device_id, self.session_id
+        );
+
+        // Validate both devices exist
+        self.validate_device_id(from_device_id).await?;
+        self.validate_device_id(to_device_id).await?;
+
+        // Verify the from_device is currently the default
+        let devices = self.get_audio_devices().await?;
+        let from_device = devices
+            .iter()
+            .find(|d| d.id == from_device_id)
+            .ok_or_else(|| AudioError::DeviceNotFound(from_device_id.to_string()))?;
+
+        if !from_device.is_default {
+            return Err(AudioError::CommandFailed(format!(
+                "Device {} is not currently the default",
+                from_device_id
+            )));
+        }
+
+        self.set_default_audio_device(to_device_id).await
+    }
+
+    /// Quick switch to device by alias or name (Step 7). A user-defined alias always
+    /// wins over a substring match on the device name.
+    pub async fn quick_switch_to_device(&self, device_name: &str) -> AudioResult<()> {
+        info!(
+            "Quick switching to device: {} (session: {})",
+            device_name, self.session_id
+        );
+
+        if let Some(resolution) = self.lookup_alias(device_name).await? {
+            let device = resolution.device.ok_or_else(|| {
+                AudioError::DeviceNotFound(format!(
+                    "{} (alias for {})",
+                    resolution.alias, resolution.stable_key
+                ))
+            })?;
+            return self.set_default_audio_device(&device.id).await;
+        }
+
+        let match_mode = self.config.read().await.match_mode;
+        let devices = self.get_audio_devices().await?;
+        let target_device = devices
+            .iter()
+            .find(|d| match_mode.matches(&d.name, device_name))
+            .ok_or_else(|| AudioError::DeviceNotFound(device_name.to_string()))?;
+
+        self.set_default_audio_device(&target_device.id).await
+    }
+
+    /// Switch to the first Active device of `device_type` whose name contains one of
+    /// `ranked_names` (per the configured match mode), trying the patterns in order.
+    /// Nothing is persisted.
+    pub async fn switch_to_best_available(
+        &self,
+        device_type: &DeviceType,
+        ranked_names: Vec<String>,
+    ) -> AudioResult<BestAvailableMatch> {
+        if ranked_names.is_empty() {
+            return Err(AudioError::InvalidArgument(
+                "At least one name pattern is required".to_string(),
+            ));
+        }
+
+        let match_mode = self.config.read().await.match_mode;
+        let devices = self.get_audio_devices().await?;
+        let found = ranked_names.iter().find_map(|pattern| {
+            devices
+                .iter()
+                .find(|d| {
+                    d.device_type == *device_type
+                        && matches!(d.state, DeviceState::Active)
+                        && match_mode.matches(&d.name, pattern)
+                })
+                .map(|device| BestAvailableMatch {
+                    pattern: pattern.clone(),
+                    device: device.clone(),
+                })
+        });
+
+        let Some(best) = found else {
+            return Err(AudioError::DeviceNotFound(format!(
+                "no active {:?} device matches any of: {}",
+                device_type,
+                ranked_names.join(", ")
+            )));
+        };
+
+        info!(
+            "Best available {:?} device: {} (matched '{}')",
+            device_type, best.device.name, best.pattern
+        );
+        if !best.device.is_default {
+            self.set_default_audio_device(&best.device.id).await?;
+        }
+        Ok(best)
+    }
+
+    /// Poll until an Active device whose name matches `name_pattern` (per the configured
+    /// match mode) appears, failing with `AudioError::Timeout` once `timeout` (capped at
+    /// `MAX_DEVICE_WAIT`) elapses
+    pub async fn wait_for_device(
+        &self,
+        name_pattern: &str,
+        timeout: Duration,
+    ) -> AudioResult<AudioDevice> {
+        if name_pattern.trim().is_empty() {
+            return Err(AudioError::InvalidArgument(
+                "A device name pattern is required".to_string(),
+            ));
+        }
+
+        let timeout = timeout.min(MAX_DEVICE_WAIT);
+        let match_mode = self.config.read().await.match_mode;
+        info!(
+            "Waiting up to {}s for a device matching '{}'",
+            timeout.as_secs(),
+            name_pattern
+        );
+
+        poll_for_device(
+            || async move {
+                self.invalidate_cache().await;
+                self.get_audio_devices().await
+            },
+            name_pattern,
+            match_mode,
+            DEVICE_WAIT_POLL_INTERVAL,
+            timeout,
+        )
+        .await
+    }
+
+    /// Wait for a device matching `name_pattern` to appear, then make it the default.
+    /// Fails with `AudioError::Superseded` if another switch replaced that one.
+    pub async fn connect_and_switch(
+        &self,
+        name_pattern: &str,
+        timeout: Duration,
+    ) -> AudioResult<AudioDevice> {
+        let device = self.wait_for_device(name_pattern, timeout).await?;
+        if !device.is_default {
+            self.set_default_audio_device(&device.id).await?;
+        }
+        Ok(device)
+    }
+
+    /// Emergency reset: make a known-good playback device the default, unmuted at a safe
+    /// volume. Prefers the onboard (HD Audio) output, otherwise the first active one.
+    pub async fn panic_restore(&self) -> AudioResult<AudioDevice> {
+        warn!("Panic restore requested (session: {})", self.session_id);
+
+        let mut candidates: Vec<AudioDevice> = self
+            .get_audio_devices()
+            .await?
+            .into_iter()
+            .filter(|d| {
+                d.device_type == DeviceType::Playback && matches!(d.state, DeviceState::Active)
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // Without enumerator names the first active device still makes a usable target
+        let ids: Vec<&str> = candidates.iter().map(|d| d.id.as_str()).collect();
+        let enumerators = self.read_enumerator_names(&ids).await.unwrap_or_else(|e| {
+            warn!("Panic restore could not identify the onboard output: {}", e);
+            HashMap::new()
+        });
+        let internal = candidates
+            .iter()
+            .find(|d| {
+                enumerators
+                    .get(&d.id)
+                    .is_some_and(|name| name.eq_ignore_ascii_case("HDAUDIO"))
+            })
+            .cloned();
+
+        let target = match internal {
+            Some(device) => device,
+            None => candidates.into_iter().next().ok_or_else(|| {
+                AudioError::DeviceNotFound("no active playback device to restore to".to_string())
+            })?,
+        };
+
+        info!("Panic restore to {}", target.name);
+        self.set_default_audio_device(&target.id).await?;
+        self.ensure_audible(&target.id, None).await?;
+        for result in self
+            .set_devices_volume(&[&target.id], PANIC_RESTORE_VOLUME)
+            .await?
+        {
+            if let Some(error) = result.error {
+                warn!("Panic restore could not set a safe volume: {}", error);
+            }
+        }
+        Ok(target)
+    }
+
+    /// The PnP enumerator (e.g. `HDAUDIO`, `USB`) of each of `device_ids`, read in one
+    /// script run. Devices whose property store can't be read are left out.
+    async fn read_enumerator_names(
+        &self,
+        device_ids: &[&str],
+    ) -> AudioResult<HashMap<String, String>> {
+        let endpoints = device_ids
+            .iter()
+            .filter_map(|id| {
+                let path = endpoint_registry_path(id).ok()?;
+                Some(format!(
+                    "@{{ id = {}; path = {} }}",
+                    powershell_string(id),
+                    powershell_string(&path)
+                ))
+            })
+            .collect::<Vec<_>>();
+        if endpoints.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let powershell_script = with_registry64(format!(
+            r#"
+                try {{
+                    $result = @{{}}
+                    foreach ($endpoint in @({})) {{
+                        $key = Open-Registry64Key "$($endpoint.path)\Properties"
+                        if (-not $key) {{ continue }}
+                        try {{
+                            $name = $key.GetValue('{}')
+                            if ($name) {{ $result[$endpoint.id] = "$name" }}
+                        }}
+                        finally {{ $key.Close() }}
+                    }}
+                    $result | ConvertTo-Json -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            endpoints.join(", "),
+            ENUMERATOR_NAME_KEY
+        ));
+
+        let output = self
+            .execute_powershell_with_retry(
+                &powershell_script,
+                "enumerator name read",
+                self.command_timeout().await,
+            )
+            .await?;
+        parse_json_or_error(&output, "enumerator name read")
+    }
+
+    /// Point an alias at a device; the alias follows the device's stable key
+    pub async fn set_alias(&self, alias: &str, device_id: &str) -> AudioResult<()> {
+        let alias = normalize_alias(alias)?;
+        let devices = self.get_audio_devices().await?;
+        let device = devices
+            .iter()
+            .find(|d| d.id == device_id)
+            .ok_or_else(|| AudioError::DeviceNotFound(device_id.to_string()))?;
+
+        let stable_key = device.stable_key();
+        info!("Setting alias '{}' -> {}", alias, stable_key);
+        self.update_config(move |config| {
+            config.aliases.insert(alias, stable_key);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Show `alias` instead of the Windows name of `device_id`
+    pub async fn set_device_alias(&self, device_id: &str, alias: &str) -> AudioResult<()> {
+        let alias = alias.trim().to_string();
+        if alias.is_empty() {
+            return Err(AudioError::InvalidArgument(
+                "A device alias cannot be empty".to_string(),
+            ));
+        }
+        let device_id = self.resolve_device_id(device_id).await?;
+
+        info!("Naming device {} '{}'", device_id, alias);
+        let display_name = alias.clone();
+        self.update_config(|config| {
+            config.display_names.insert(device_id.clone(), display_name);
+            Ok(())
+        })
+        .await?;
+        self.set_cached_display_name(&device_id, Some(alias)).await;
+        Ok(())
+    }
+
+    /// Go back to the Windows name of `device_id`, returning whether it had an alias
+    pub async fn clear_device_alias(&self, device_id: &str) -> AudioResult<bool> {
+        let cleared = self
+            .update_config(|config| Ok(config.display_names.remove(device_id).is_some()))
+            .await?;
+        if cleared {
+            info!("Cleared the alias of device {}", device_id);
+            self.set_cached_display_name(device_id, None).await;
+        }
+        Ok(cleared)
+    }
+
+    /// Rename a cached device in place so the change shows without re-enumerating
+    async fn set_cached_display_name(&self, device_id: &str, display_name: Option<String>) {
+        if let Some(device) = self.state.write().await.cached_devices.get_mut(device_id) {
+            device.display_name = display_name;
+        }
+    }
+
+    /// Remove an alias, returning whether it existed
+    pub async fn remove_alias(&self, alias: &str) -> AudioResult<bool> {
+        let alias = normalize_alias(alias)?;
+        self.update_config(move |config| Ok(config.aliases.remove(&alias).is_some()))
+            .await
+    }
+
+    /// Resolve an alias to its device; the device is `None` when it's currently absent
+    pub async fn resolve_alias(&self, alias: &str) -> AudioResult<AliasResolution> {
+        self.lookup_alias(alias)
+            .await?
+            .ok_or_else(|| AudioError::InvalidArgument(format!("Unknown alias '{}'", alias)))
+    }
+
+    /// Remember the output device an application should be routed to
+    pub async fn set_app_preference(&self, app_name: &str, device_id: &str) -> AudioResult<()> {
+        let app_name = normalize_app_name(app_name)?;
+        let devices = self.get_audio_devices().await?;
+        let device = devices
+            .iter()
+            .find(|d| d.id == device_id)
+            .ok_or_else(|| AudioError::DeviceNotFound(device_id.to_string()))?;
+        if device.device_type != DeviceType::Playback {
+            return Err(AudioError::InvalidArgument(format!(
+                "'{}' is not an output device",
+                device.name
+            )));
+        }
+
+        let stable_key = device.stable_key();
+        info!("Setting preferred device for {}: {}", app_name, stable_key);
+        self.update_config(move |config| {
+            config.app_preferences.insert(app_name, stable_key);
+            Ok(())
+        })
+        .await
+    }
+
+    /// All stored application preferences, resolved against the current device list
+    pub async fn list_app_preferences(&self) -> AudioResult<Vec<AppPreference>> {
+        let preferences = self.config.read().await.app_preferences.clone();
+        if preferences.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let devices = self.get_audio_devices().await?;
+        Ok(preferences
+            .into_iter()
+            .map(|(app_name, stable_key)| {
+                let device = devices
+                    .iter()
+                    .find(|d| d.stable_key() == stable_key)
+                    .cloned();
+                AppPreference {
+                    app_name,
+                    stable_key,
+                    device,
+                }
+            })
+            .collect())
+    }
+
+    /// Report, slot by slot, whether the devices in a priority chain are present right now
+    pub async fn validate_priorities(
+        &self,
+        device_type: &DeviceType,
+    ) -> AudioResult<Vec<PriorityValidation>> {
+        let chain = self.priorities.chain(device_type).await;
+        let devices = self.get_audio_devices().await?;
+        Ok(priority_store::check_presence(&chain, &devices))
+    }
+
+    /// Find default roles held by Disabled, NotPresent or Unplugged devices, each with the
+    /// device to switch to instead
+    pub async fn detect_default_anomalies(&self) -> AudioResult<Vec<DefaultAnomaly>> {
+        let devices = self.get_audio_devices().await?;
+        let mut anomalies = Vec::new();
+        for device_type in [DeviceType::Playback, DeviceType::Recording] {
+            let chain = self.priorities.chain(&device_type).await;
+            anomalies.extend(default_anomalies(&devices, &device_type, &chain));
+        }
+        for anomaly in &anomalies {
+            warn!(
+                "{:?} default ({:?}) is {}: {}",
+                anomaly.device_type,
+                anomaly.role,
+                anomaly.state.as_str(),
+                anomaly.device_name
+            );
+        }
+        Ok(anomalies)
+    }
+
+    /// Devices of `device_type` ordered by their priority-chain slot, ready to render
+    pub async fn get_priority_ordered_devices(
+        &self,
+        device_type: &DeviceType,
+    ) -> AudioResult<Vec<PriorityOrderedDevice>> {
+        let chain = self.priorities.chain(device_type).await;
+        let devices = self.get_audio_devices().await?;
+        Ok(priority_store::order_by_priority(
+            &chain,
+            &devices,
+            device_type,
+        ))
+    }
+
+    /// Run the priority evaluation for `device_type` without switching
+    async fn select_autoswitch_target(
+        &self,
+        device_type: &DeviceType,
+    ) -> AudioResult<AutoSwitchSelection> {
+        if let Some(pinned) = self
+            .state
+            .read()
+            .await
+            .active_override(device_type, self.clock.now())
+        {
+            return Ok(AutoSwitchSelection::Pinned(pinned.device_id.clone()));
+        }
+
+        let chain = self.priorities.chain(device_type).await;
+        if chain.is_empty() {
+            return Ok(AutoSwitchSelection::EmptyChain);
+        }
+
+        let devices = self.get_audio_devices().await?;
+        Ok(chain
+            .iter()
+            .find_map(|entry| {
+                devices
+                    .iter()
+                    .find(|d| d.id == entry.device_id && matches!(d.state, DeviceState::Active))
+                    .map(|device| AutoSwitchSelection::Target {
+                        device: device.clone(),
+                        priority: entry.priority,
+                    })
+            })
+            .unwrap_or(AutoSwitchSelection::NoneAvailable))
+    }
+
+    /// The device auto-switch would select for `device_type` right now, without switching:
+    /// the highest-priority Active device in the stored chain, even if it's already the
+    /// default. `None` when an override suppresses switching or nothing is available.
+    pub async fn preview_autoswitch_target(
+        &self,
+        device_type: &DeviceType,
+    ) -> AudioResult<Option<AudioDevice>> {
+        match self.select_autoswitch_target(device_type).await? {
+            AutoSwitchSelection::Target { device, .. } => Ok(Some(device)),
+            selection => {
+                debug!("{:?}: {}", device_type, selection.reason());
+                Ok(None)
+            }
+        }
+    }
+
+    /// Switch to the highest-priority Active device in the stored chain for
+    /// `device_type`. Returns the device switched to, or `None` when the best
+    /// available device is already the default or nothing in the chain is available.
+    /// Every run is recorded in the auto-switch decision history, and a switch is
+    /// announced with an `auto-switched` event.
+    pub async fn evaluate_and_switch(
+        &self,
+        device_type: &DeviceType,
+    ) -> AudioResult<Option<AudioDevice>> {
+        let (target, priority) = match self.select_autoswitch_target(device_type).await {
+            Ok(AutoSwitchSelection::Target { device, priority }) => (device, priority),
+            Ok(selection) => {
+                self.record_decision(device_type, None, selection.reason())
+                    .await;
+                return Ok(None);
+            }
+            Err(e) => {
+                self.record_decision(device_type, None, format!("no switch: {}", e))
+                    .await;
+                return Err(e);
+            }
+        };
+
+        if target.is_default {
+            self.record_decision(
+                device_type,
+                Some(&target),
+                format!(
+                    "no switch: {} (priority {}) is already the default",
+                    target.name, priority
+                ),
+            )
+            .await;
+            return Ok(None);
+        }
+
+        info!("Auto-switching {:?} to {}", device_type, target.name);
+        if let Err(e) = self.set_default_audio_device(&target.id).await {
+            if let AudioError::Superseded(_) = e {
+                // A newer switch decides the default; this evaluation isn't a failure
+                self.record_decision(
+                    device_type,
+                    None,
+                    format!(
+                        "no switch: the switch to {} was superseded by a newer one",
+                        target.name
+                    ),
+                )
+                .await;
+                return Ok(None);
+            }
+            self.record_decision(
+                device_type,
+                None,
+                format!("switch to {} failed: {}", target.name, e),
+            )
+            .await;
+            return Err(e);
+        }
+        self.record_decision(
+            device_type,
+            Some(&target),
+            switched_reason(&target, priority),
+        )
+        .await;
+        self.emit(
+            "auto-switched",
+            AutoSwitched {
+                device_type: device_type.clone(),
+                device_id: target.id.clone(),
+                name: target
+                    .display_name
+                    .clone()
+                    .unwrap_or_else(|| target.name.clone()),
+            },
+        );
+
+        let (unmute, min_volume) = {
+            let config = self.config.read().await;
+            (config.unmute_on_autoswitch, config.autoswitch_min_volume)
+        };
+        if unmute {
+            if let Err(e) = self.ensure_audible(&target.id, min_volume).await {
+                warn!("Switched to {} but could not unmute it: {}", target.name, e);
+            }
+        }
+
+        Ok(Some(target))
+    }
+
+    /// Auto-switch decisions made this session, oldest first
+    pub async fn get_autoswitch_decisions(&self, page: LogPage) -> Vec<AutoSwitchDecision> {
+        page.apply(&self.state.read().await.autoswitch_decisions, |decision| {
+            decision.timestamp
+        })
+    }
+
+    /// Append to the auto-switch decision history, dropping the oldest entry when full
+    async fn record_decision(
+        &self,
+        device_type: &DeviceType,
+        chosen: Option<&AudioDevice>,
+        reason: String,
+    ) {
+        debug!("Auto-switch {:?}: {}", device_type, reason);
+        let mut state = self.state.write().await;
+        if state.autoswitch_decisions.len() == AUTOSWITCH_DECISION_CAPACITY {
+            state.autoswitch_decisions.pop_front();
+        }
+        state.autoswitch_decisions.push_back(AutoSwitchDecision {
+            timestamp: self.clock.utc_now(),
+            device_type: device_type.clone(),
+            chosen: chosen.map(|device| device.id.clone()),
+            reason,
+        });
+    }
+
+    /// Set how device names given by the user are matched against endpoint names
+    pub async fn set_match_mode(&self, match_mode: MatchMode) -> AudioResult<()> {
+        info!("Setting name match mode: {:?}", match_mode);
+        self.update_config(|config| {
+            config.match_mode = match_mode;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Make a device the default and pin it there for `duration`, suppressing auto-switch
+    /// for its device type. Once the override expires the priority chain is re-evaluated.
+    /// Durations longer than `MAX_OVERRIDE_DURATION` are rejected.
+    pub async fn temporary_override(
+        self: &Arc<Self>,
+        device_id: &str,
+        duration: Duration,
+    ) -> AudioResult<()> {
+        if duration.is_zero() {
+            return Err(AudioError::InvalidArgument(
+                "Override duration must be greater than zero".to_string(),
+            ));
+        }
+        let expires_at = if duration <= MAX_OVERRIDE_DURATION {
+            self.clock.now().checked_add(duration)
+        } else {
+            None
+        }
+        .ok_or_else(|| {
+            AudioError::InvalidArgument(format!(
+                "Override duration can't exceed {} hours",
+                MAX_OVERRIDE_DURATION.as_secs() / 3600
+            ))
+        })?;
+
+        let device_id = self.resolve_device_id(device_id).await?;
+        let devices = self.get_audio_devices().await?;
+        let device_type = devices
+            .iter()
+            .find(|d| d.id == device_id)
+            .map(|d| d.device_type.clone())
+            .ok_or_else(|| AudioError::DeviceNotFound(device_id.clone()))?;
+
+        self.set_default_audio_device(&device_id).await?;
+
+        info!(
+            "Pinning {:?} to {} for {}s",
+            device_type,
+            device_id,
+            duration.as_secs()
+        );
+        self.state.write().await.set_override(DeviceOverride {
+            device_id,
+            device_type,
+            expires_at: Some(expires_at),
+        });
+
+        let manager = Arc::clone(self);
+        tokio::spawn(async move {
+            tokio::time::sleep(duration).await;
+            manager.expire_override(expires_at).await;
+        });
+        Ok(())
+    }
+
+    /// Drop temporary overrides early, returning whether there were any
+    pub async fn cancel_override(&self) -> AudioResult<bool> {
+        let cancelled = self
+            .state
+            .write()
+            .await
+            .take_overrides(|o| o.expires_at.is_some());
+
+        for device_override in &cancelled {
+            info!("Cancelled override of {}", device_override.device_id);
+            self.evaluate_and_switch(&device_override.device_type)
+                .await?;
+        }
+        Ok(!cancelled.is_empty())
+    }
+
+    /// Hand the choice of default back to Windows: drop any override for
+    /// `device_type` and settle on the endpoint Windows reports as the default. If that
+    /// endpoint is gone, the first active one in Windows' enumeration order is used.
+    pub async fn reset_to_windows_default(
+        &self,
+        device_type: &DeviceType,
+    ) -> AudioResult<AudioDevice> {
+        self.ensure_writable("reset to the Windows default")?;
+        self.state
+            .write()
+            .await
+            .take_overrides(|o| o.device_type == *device_type);
+
+        self.invalidate_cache().await;
+        let devices = self.get_audio_devices().await?;
+        let target = windows_preferred_device(devices, device_type).ok_or_else(|| {
+            AudioError::DeviceNotFound(format!("no active {:?} device", device_type))
+        })?;
+
+        info!("Reset {:?} to Windows default {}", device_type, target.name);
+        if !target.is_default {
+            self.set_default_audio_device(&target.id).await?;
+        }
+        Ok(target)
+    }
+
+    /// Clear the override scheduled to end at `expires_at` and resume normal evaluation,
+    /// unless it has since been cancelled or replaced
+    async fn expire_override(&self, expires_at: Instant) {
+        let expired = self
+            .state
+            .write()
+            .await
+            .take_overrides(|o| o.expires_at == Some(expires_at));
+
+        for device_override in expired {
+            info!("Override of {} expired", device_override.device_id);
+            if let Err(e) = self.evaluate_and_switch(&device_override.device_type).await {
+                warn!("Re-evaluation after override expiry failed: {}", e);
+            }
+        }
+    }
+
+    /// Configure whether a launch at login starts minimized
+    pub async fn set_start_minimized(&self, enabled: bool) -> AudioResult<()> {
+        info!("Setting start minimized: {}", enabled);
+        self.update_config(|config| {
+            config.start_minimized = enabled;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn start_minimized(&self) -> bool {
+        self.config.read().await.start_minimized
+    }
+
+    /// Configure whether the background watcher stops polling while on battery
+    pub async fn set_pause_watcher_on_battery(&self, enabled: bool) -> AudioResult<()> {
+        info!("Setting pause watcher on battery: {}", enabled);
+        self.update_config(|config| {
+            config.pause_watcher_on_battery = enabled;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Configure whether focusing an application switches to its preferred device
+    pub async fn set_follow_focus(&self, enabled: bool) -> AudioResult<()> {
+        info!("Setting follow focus: {}", enabled);
+        self.update_config(|config| {
+            config.follow_focus = enabled;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Configure whether the watcher emits `device-state-changed` for individual devices
+    pub async fn set_state_change_events(&self, enabled: bool) -> AudioResult<()> {
+        info!("Setting per-device state change events: {}", enabled);
+        self.update_config(|config| {
+            config.state_change_events = enabled;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Configure how long a switch waits for a newer one of the same device type to
+    /// supersede it (`None` restores the default, 0 disables coalescing), up to
+    /// `MAX_SWITCH_COALESCE_WINDOW`
+    pub async fn set_switch_coalesce_window(&self, window_ms: Option<u64>) -> AudioResult<()> {
+        if window_ms.is_some_and(|ms| ms > MAX_SWITCH_COALESCE_WINDOW.as_millis() as u64) {
+            return Err(AudioError::InvalidArgument(format!(
+                "Switch coalesce window can't exceed {}ms",
+                MAX_SWITCH_COALESCE_WINDOW.as_millis()
+            )));
+        }
+        info!("Setting switch coalesce window: {:?}ms", window_ms);
+        self.update_config(|config| {
+            config.switch_coalesce_window_ms = window_ms;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Register a switch of `device_type` and wait out the coalescing window, returning
+    /// the switch's generation if no newer switch of the same type arrived meanwhile
+    async fn coalesce_switch(&self, device_type: &DeviceType) -> Option<u64> {
+        let generation = self
+            .switch_generation(device_type)
+            .fetch_add(1, Ordering::SeqCst)
+            + 1;
+        let window = self
+            .config
+            .read()
+            .await
+            .switch_coalesce_window_ms
+            .map_or(DEFAULT_SWITCH_COALESCE_WINDOW, Duration::from_millis)
+            .min(MAX_SWITCH_COALESCE_WINDOW);
+        if !window.is_zero() {
+            tokio::time::sleep(window).await;
+        }
+        (!self.is_switch_superseded(device_type, generation)).then_some(generation)
+    }
+
+    /// Whether a switch newer than `generation` has been requested for `device_type`
+    fn is_switch_superseded(&self, device_type: &DeviceType, generation: u64) -> bool {
+        self.switch_generation(device_type).load(Ordering::SeqCst) != generation
+    }
+
+    fn switch_generation(&self, device_type: &DeviceType) -> &AtomicU64 {
+        match device_type {
+            DeviceType::Playback => &self.switch_generations[0],
+            DeviceType::Recording => &self.switch_generations[1],
+        }
+    }
+
+    /// Start the background device monitor, polling every `interval` and emitting events
+    /// through `app_handle`. A monitor that is already running is replaced.
+    pub fn start_monitoring(
+        self: &Arc<Self>,
+        app_handle: AppHandle,
+        interval: Duration,
+    ) -> AudioResult<()> {
+        self.attach_app_handle(app_handle);
+        self.spawn_monitor(interval)
+    }
+
+    fn spawn_monitor(self: &Arc<Self>, interval: Duration) -> AudioResult<()> {
+        if interval < MIN_WATCH_INTERVAL {
+            return Err(AudioError::InvalidArgument(format!(
+                "Monitoring interval must be at least {}s",
+                MIN_WATCH_INTERVAL.as_secs()
+            )));
+        }
+
+        let task = tauri::async_runtime::spawn(Arc::clone(self).watch_devices(interval));
+        if let Some(previous) = self.monitor.lock().unwrap().replace(task) {
+            previous.abort();
+        }
+        Ok(())
+    }
+
+    /// Stop the background device monitor, returning whether one was running
+    pub fn stop_monitoring(&self) -> bool {
+        match self.monitor.lock().unwrap().take() {
+            Some(task) => {
+                info!("Stopping device watcher");
+                task.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Background loop refreshing the device list every `interval` and emitting
+    /// `devices-changed` when devices appear, disappear or change state or default
+    async fn watch_devices(self: Arc<Self>, interval: Duration) {
+        info!(
+            "Starting device watcher every {}s (session: {})",
+            interval.as_secs(),
+            self.session_id
+        );
+        loop {
+            tokio::time::sleep(interval).await;
+            self.watcher_tick(on_battery()).await;
+        }
+    }
+
+    /// Background loop polling the foreground window every `FOCUS_POLL_INTERVAL` and,
+    /// while follow focus is on, switching to the preferred device of each newly
+    /// focused application
+    pub async fn watch_focus(self: Arc<Self>) {
+        let mut focused: Option<String> = None;
+        loop {
+            tokio::time::sleep(FOCUS_POLL_INTERVAL).await;
+            if !self.config.read().await.follow_focus {
+                focused = None;
+                continue;
+            }
+
+            self.focus_tick(&mut focused, foreground_process_name())
+                .await;
+        }
+    }
+
+    /// One focus watcher tick: follow focus to `process_name` if it differs from the
+    /// application last followed. `focused` only moves on once the switch succeeded, so
+    /// a failed switch is retried on the next tick. Read-only mode won't clear between
+    /// ticks, so a switch it refused is not retried.
+    async fn focus_tick(&self, focused: &mut Option<String>, process_name: Option<String>) {
+        if process_name == *focused {
+            return;
+        }
+        let Some(name) = &process_name else {
+            *focused = None;
+            return;
+        };
+        match self.on_focus_changed(name).await {
+            Ok(_) => *focused = process_name,
+            Err(AudioError::ReadOnly(_)) => {
+                debug!("Read-only mode is enabled, not following focus to {}", name);
+                *focused = process_name;
+            }
+            Err(e) => warn!("Failed to follow focus to {}: {}", name, e),
+        }
+    }
+
+    /// Switch to the preferred device of the application that just took focus.
+    /// Returns the device switched to, if any.
+    pub async fn on_focus_changed(&self, process_name: &str) -> AudioResult<Option<AudioDevice>> {
+        let Some(target) = self.focus_target(process_name).await? else {
+            return Ok(None);
+        };
+        info!("{} took focus, switching to {}", process_name, target.name);
+        self.set_default_audio_device(&target.id).await?;
+        Ok(Some(target))
+    }
+
+    /// The device focusing `process_name` should switch to: its preferred device when
+    /// follow focus is on, the device is present and Active, it isn't already the
+    /// default, and no override pins its device type
+    async fn focus_target(&self, process_name: &str) -> AudioResult<Option<AudioDevice>> {
+        let stable_key = {
+            let config = self.config.read().await;
+            if !config.follow_focus {
+                return Ok(None);
+            }
+            let app_name = normalize_app_name(process_name)?;
+            match config.app_preferences.get(&app_name) {
+                Some(stable_key) => stable_key.clone(),
+                None => return Ok(None),
+            }
+        };
+
+        let devices = self.get_audio_devices().await?;
+        let target = devices.into_iter().find(|d| d.stable_key() == stable_key);
+        if let Some(device) = &target {
+            if let Some(pinned) = self
+                .state
+                .read()
+                .await
+                .active_override(&device.device_type, self.clock.now())
+            {
+                debug!(
+                    "{:?} is pinned to {}, not following focus to {}",
+                    device.device_type, pinned.device_id, process_name
+                );
+                return Ok(None);
+            }
+        }
+        Ok(match target {
+            Some(device) if device.state == DeviceState::Active && !device.is_default => {
+                Some(device)
+            }
+            Some(_) => None,
+            None => {
+                debug!(
+                    "Preferred device of {} ({}) is not present",
+                    process_name, stable_key
+                );
+                None
+            }
+        })
+    }
+
+    /// One watcher tick: poll the device list, then enforce quiet hours. Both are
+    /// skipped while on battery if the user asked for that. Returns whether the tick ran.
+    async fn watcher_tick(&self, on_battery: bool) -> bool {
+        if on_battery && self.config.read().await.pause_watcher_on_battery {
+            debug!("On battery, skipping device watcher tick");
+            return false;
+        }
+
+        if let Err(e) = self.poll_devices().await {
+            debug!("Device watcher poll failed: {}", e);
+        }
+        if let Err(e) = self.enforce_quiet_hours().await {
+            warn!("Failed to enforce quiet hours: {}", e);
+        }
+        true
+    }
+
+    /// Refresh the device list and emit events for whatever changed since the last poll
+    async fn poll_devices(&self) -> AudioResult<()> {
+        let devices = self.get_audio_devices().await?;
+        let snapshot = watch_snapshot(&devices);
+        let state_change_events = self.config.read().await.state_change_events;
+
+        let (changed, added_or_removed, state_changes, device_change) = {
+            let mut state = self.state.write().await;
+            let changes = match &state.watched_devices {
+                Some(previous) => (
+                    *previous != snapshot,
+                    !previous
+                        .iter()
+                        .map(|d| &d.0)
+                        .eq(snapshot.iter().map(|d| &d.0)),
+                    if state_change_events {
+                        device_state_changes(previous, &devices)
+                    } else {
+                        Vec::new()
+                    },
+                    device_change(previous, &devices),
+                ),
+                None => (false, false, Vec::new(), DeviceChange::default()),
+            };
+            state.watched_devices = Some(snapshot);
+            changes
+        };
+        if added_or_removed {
+            if let Err(e) = self.refresh_device_containers().await {
+                warn!("Failed to refresh device containers: {}", e);
+            }
+        }
+        for change in state_changes {
+            info!(
+                "{} changed from {} to {}",
+                change.device_name,
+                change.old_state.as_str(),
+                change.new_state.as_str()
+            );
+            self.emit("device-state-changed", change);
+        }
+        if !device_change.is_empty() {
+            info!(
+                "Devices added: {}, removed: {}, changed state: {}",
+                device_change.added.len(),
+                device_change.removed.len(),
+                device_change.state_changed.len()
+            );
+            self.emit("device-changed", device_change);
+        }
+        if changed {
+            info!("Device list changed, notifying frontend");
+            self.emit("devices-changed", devices);
+        }
+        Ok(())
+    }
+
+    /// Cap playback volume at `max_volume` between `start` and `end` every day
+    pub async fn set_quiet_hours(&self, quiet_hours: QuietHours) -> AudioResult<()> {
+        if quiet_hours.max_volume > 100 {
+            return Err(AudioError::InvalidArgument(format!(
+                "Volume must be between 0 and 100, got {}",
+                quiet_hours.max_volume
+            )));
+        }
+        if quiet_hours.start == quiet_hours.end {
+            return Err(AudioError::InvalidArgument(
+                "Quiet hours must start and end at different times".to_string(),
+            ));
+        }
+
+        info!(
+            "Setting quiet hours {} to {} (max volume {})",
+            quiet_hours.start, quiet_hours.end, quiet_hours.max_volume
+        );
+        self.update_config(|config| {
+            config.quiet_hours = Some(quiet_hours);
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn clear_quiet_hours(&self) -> AudioResult<()> {
+        info!("Clearing quiet hours");
+        self.update_config(|config| {
+            config.quiet_hours = None;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Lower the default playback device to the quiet-hours cap if it's inside the
+    /// window and above it, emitting `quiet-hours-enforced`
+    pub async fn enforce_quiet_hours(&self) -> AudioResult<Option<QuietHoursEnforced>> {
+        if self.is_read_only() || self.config.read().await.quiet_hours.is_none() {
+            return Ok(None);
+        }
+        let Some(active) = self.get_active_device_state().await? else {
+            return Ok(None);
+        };
+        let Some(max_volume) = self.quiet_hours_cap(active.volume).await else {
+            return Ok(None);
+        };
+
+        info!(
+            "Quiet hours: lowering {} from {} to {}",
+            active.device.name, active.volume, max_volume
+        );
+        let device_id = active.device.id.clone();
+        let result = self
+            .set_devices_volume(&[&device_id], max_volume)
+            .await?
+            .into_iter()
+            .next();
+        if let Some(error) = result.and_then(|r| r.error) {
+            return Err(AudioError::CommandFailed(error));
+        }
+
+        let enforced = QuietHoursEnforced {
+            device_id,
+            device_name: active.device.name,
+            previous_volume: active.volume,
+            max_volume,
+        };
+        self.emit("quiet-hours-enforced", enforced.clone());
+        Ok(Some(enforced))
+    }
+
+    /// The volume to lower `volume` to, if quiet hours are on right now and it exceeds
+    /// their cap
+    async fn quiet_hours_cap(&self, volume: u8) -> Option<u8> {
+        let quiet_hours = self.config.read().await.quiet_hours?;
+        (quiet_hours.contains(self.clock.local_time()) && volume > quiet_hours.max_volume)
+            .then_some(quiet_hours.max_volume)
+    }
+
+    /// Configure whether auto-switch unmutes its target and the minimum volume it restores
+    pub async fn set_autoswitch_unmute(
+        &self,
+        enabled: bool,
+        min_volume: Option<u8>,
+    ) -> AudioResult<()> {
+        if let Some(volume) = min_volume.filter(|v| *v > 100) {
+            return Err(AudioError::InvalidArgument(format!(
+                "Volume must be between 0 and 100, got {}",
+                volume
+            )));
+        }
+
+        self.update_config(|config| {
+            config.unmute_on_autoswitch = enabled;
+            config.autoswitch_min_volume = min_volume;
+            Ok(())
+        })
+        .await
+    }
+
+    /// List saved profiles along with which of their devices are currently missing
+    pub async fn list_profiles_with_status(&self) -> AudioResult<Vec<ProfileStatus>> {
+        let profiles: Vec<Profile> = self
+            .config
+            .read()
+            .await
+            .profiles
+            .values()
+            .cloned()
+            .collect();
+        let devices = self.get_audio_devices().await?;
+
+        Ok(profiles
+            .into_iter()
+            .map(|profile| {
+                let missing_devices: Vec<String> = profile
+                    .device_ids()
+                    .into_iter()
+                    .filter(|id| !devices.iter().any(|d| d.id == *id))
+                    .map(|id| id.to_string())
+                    .collect();
+                ProfileStatus {
+                    all_devices_present: missing_devices.is_empty(),
+                    missing_devices,
+                    profile,
+                }
+            })
+            .collect())
+    }
+
+    /// Saved profiles, sorted by name
+    pub async fn list_profiles(&self) -> Vec<Profile> {
+        self.config
+            .read()
+            .await
+            .profiles
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Snapshot the current default of every role and both priority chains as profile
+    /// `name`, replacing any profile already saved under that name
+    pub async fn save_profile(&self, name: &str) -> AudioResult<Profile> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AudioError::InvalidConfig(
+                "Profile name cannot be empty".to_string(),
+            ));
+        }
+
+        let defaults = self.get_current_defaults().await?;
+        let profile = Profile {
+            name: name.to_string(),
+            default_playback: defaults.playback,
+            default_recording: defaults.recording,
+            communication_playback: defaults.communication_playback,
+            communication_recording: defaults.communication_recording,
+            priorities: Some(self.priorities.chains().await),
+        };
+        info!("Saving profile '{}'", name);
+        self.update_config(|config| {
+            config
+                .profiles
+                .insert(profile.name.clone(), profile.clone());
+            Ok(())
+        })
+        .await?;
+        Ok(profile)
+    }
+
+    /// Delete a saved profile and any hotkeys bound to it
+    pub async fn delete_profile(&self, name: &str) -> AudioResult<()> {
+        info!("Deleting profile '{}'", name);
+        self.update_config(|config| config.delete_profile(name))
+            .await
+    }
+
+    /// Rename a saved profile
+    pub async fn rename_profile(&self, old_name: &str, new_name: &str) -> AudioResult<()> {
+        info!("Renaming profile '{}' to '{}'", old_name, new_name);
+        self.update_config(|config| config.rename_profile(old_name, new_name))
+            .await
+    }
+
+    /// Make every role of a saved profile default, rolling back if any switch fails, then
+    /// restore its priority chains. Roles whose device isn't connected are skipped and
+    /// reported rather than failing the whole profile.
+    pub async fn apply_profile(&self, name: &str) -> AudioResult<ProfileApplication> {
+        self.ensure_writable("apply a profile")?;
+        let profile = self
+            .config
+            .read()
+            .await
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| AudioError::InvalidConfig(format!("Unknown profile '{}'", name)))?;
+        let devices = self.get_audio_devices().await?;
+        let unavailable_devices: Vec<String> = profile
+            .device_ids()
+            .into_iter()
+            .filter(|id| !devices.iter().any(|d| d.id == *id))
+            .map(str::to_string)
+            .collect();
+        if !unavailable_devices.is_empty() {
+            warn!(
+                "Profile '{}' skips devices that aren't connected: {:?}",
+                name, unavailable_devices
+            );
+        }
+        let available = |id: &Option<String>| {
+            id.as_deref()
+                .filter(|id| !unavailable_devices.iter().any(|u| u == id))
+        };
+
+        info!("Applying profile '{}' (session: {})", name, self.session_id);
+        let _guard = self.operation_lock.lock().await;
+        self.in_bulk_operation(async {
+            let current_defaults = self.get_current_defaults().await?;
+            let targets: Vec<(&str, DefaultRole)> = role_targets(
+                available(&profile.default_playback),
+                available(&profile.communication_playback),
+            )
+            .into_iter()
+            .chain(role_targets(
+                available(&profile.default_recording),
+                available(&profile.communication_recording),
+            ))
+            .collect();
+
+            let result = apply_or_roll_back(&targets, &current_defaults, |id, role| async move {
+                self.change_default_device(&id, role).await
+            })
+            .await;
+            self.invalidate_cache().await;
+            result
+        })
+        .await?;
+
+        if let Some(chains) = profile.priorities {
+            self.priorities
+                .save(chains.playback, chains.recording)
+                .await?;
+        }
+        Ok(ProfileApplication {
+            unavailable_devices,
+        })
+    }
+
+    /// Move the default of `device_type` to the next active device, returning it, or
+    /// `None` when there's nothing else to switch to
+    pub async fn cycle_default(
+        &self,
+        device_type: &DeviceType,
+    ) -> AudioResult<Option<AudioDevice>> {
+        let devices = self.get_audio_devices().await?;
+        let Some(next) = next_default(&devices, device_type).cloned() else {
+            return Ok(None);
+        };
+        info!("Cycling {:?} default to {}", device_type, next.name);
+        self.set_default_device(&next.id, device_type, DefaultRole::Both, true)
+            .await?;
+        Ok(Some(next))
+    }
+
+    /// Move `role` of `device_type` to the next Active device below the current default in
+    /// the priority chain, wrapping to the top, and return it. `None` when fewer than two
+    /// chain members are available; `AudioError::Superseded` when a newer switch of the
+    /// same type (e.g. a quicker repeat of the hotkey) replaced this one.
+    pub async fn cycle_to_next_priority(
+        &self,
+        device_type: &DeviceType,
+        role: DefaultRole,
+    ) -> AudioResult<Option<AudioDevice>> {
+        let chain = self.priorities.chain(device_type).await;
+        let devices = self.get_audio_devices().await?;
+        let Some(next) = next_in_chain(&chain, &devices, role).cloned() else {
+            return Ok(None);
+        };
+        info!(
+            "Cycling {:?} priority chain ({:?}) to {}",
+            device_type, role, next.name
+        );
+        self.set_default_device(&next.id, device_type, role, true)
+            .await?;
+        Ok(Some(next))
+    }
+
+    /// Bind a global hotkey to an action, replacing any existing binding of `accelerator`
+    pub async fn set_hotkey(&self, accelerator: &str, action: HotkeyAction) -> AudioResult<()> {
+        if accelerator.trim().is_empty() {
+            return Err(AudioError::InvalidArgument(
+                "A hotkey accelerator is required".to_string(),
+            ));
+        }
+        info!("Binding hotkey {} to {:?}", accelerator, action);
+        self.update_config(|config| {
+            config.hotkeys.insert(accelerator.to_string(), action);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Remove a hotkey binding, returning whether there was one
+    pub async fn remove_hotkey(&self, accelerator: &str) -> AudioResult<bool> {
+        self.update_config(|config| Ok(config.hotkeys.remove(accelerator).is_some()))
+            .await
+    }
+
+    pub async fn list_hotkeys(&self) -> BTreeMap<String, HotkeyAction> {
+        self.config.read().await.hotkeys.clone()
+    }
+
+    /// Run the action currently bound to `accelerator`; a binding removed since the
+    /// hotkey was registered does nothing
+    pub async fn run_hotkey(&self, accelerator: &str) -> AudioResult<()> {
+        let Some(action) = self.config.read().await.hotkeys.get(accelerator).cloned() else {
+            debug!("Hotkey {} is no longer bound", accelerator);
+            return Ok(());
+        };
+        info!("Hotkey {} pressed: {:?}", accelerator, action);
+        match self.run_hotkey_action(&action).await {
+            // Pressing again quickly replaces the earlier switch, as intended
+            Err(AudioError::Superseded(device_id)) => {
+                info!("Hotkey {} switch to {} superseded", accelerator, device_id);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Dispatch a hotkey action to the matching manager operation
+    pub async fn run_hotkey_action(&self, action: &HotkeyAction) -> AudioResult<()> {
+        let devices = self.get_audio_devices().await?;
+        match hotkey_step(action, &devices) {
+            HotkeyStep::Switch {
+                id,
+                device_type,
+                role,
+            } => self.set_default_device(&id, &device_type, role, true).await,
+            HotkeyStep::PanicRestore => self.panic_restore().await.map(|_| ()),
+            HotkeyStep::ApplyProfile(name) => self.apply_profile(&name).await.map(|_| ()),
+            HotkeyStep::CyclePriority(device_type) => self
+                .cycle_to_next_priority(&device_type, DefaultRole::Both)
+                .await
+                .map(|_| ()),
+            HotkeyStep::SwitchToSlot(device_type, slot) => {
+                let chain = self.priorities.chain(&device_type).await;
+                match slot_device(&chain, &devices, slot) {
+                    Some(device) => {
+                        self.set_default_device(&device.id, &device_type, DefaultRole::Both, true)
+                            .await
+                    }
+                    None => {
+                        debug!("No Active device in {:?} slot {}", device_type, slot);
+                        Ok(())
+                    }
+                }
+            }
+            HotkeyStep::Nothing => Ok(()),
+        }
+    }
+
+    /// Seed the backend priority store from the chains the frontend kept in localStorage
+    pub async fn import_priorities_from_frontend(
+        &self,
+        playback: Vec<DevicePriority>,
+        recording: Vec<DevicePriority>,
+    ) -> AudioResult<bool> {
+        self.priorities
+            .import_from_frontend(playback, recording)
+            .await
+    }
+
+    /// Both priority chains as stored by the backend
+    pub async fn load_priorities(&self) -> PriorityChains {
+        self.priorities.chains().await
+    }
+
+    /// Replace both priority chains
+    pub async fn save_priorities(
+        &self,
+        playback: Vec<DevicePriority>,
+        recording: Vec<DevicePriority>,
+    ) -> AudioResult<()> {
+        self.ensure_writable("change priorities")?;
+        self.priorities.save(playback, recording).await
+    }
+
+    /// Put a device into one slot of its type's priority chain
+    pub async fn assign_priority_slot(&self, entry: DevicePriority) -> AudioResult<()> {
+        self.ensure_writable("change priorities")?;
+        self.priorities.assign_slot(entry).await
+    }
+
+    /// Empty one slot of a priority chain, returning whether it held a device
+    pub async fn clear_priority_slot(
+        &self,
+        device_type: &DeviceType,
+        slot: usize,
+    ) -> AudioResult<bool> {
+        self.ensure_writable("change priorities")?;
+        self.priorities.clear_slot(device_type, slot).await
+    }
+
+    /// Create a named group of playback and recording endpoints that switch together
+    pub async fn create_group(
+        &self,
+        name: &str,
+        playback_id: &str,
+        recording_id: &str,
+    ) -> AudioResult<DeviceGroup> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AudioError::InvalidConfig(
+                "Group name cannot be empty".to_string(),
+            ));
+        }
+
+        let devices = self.get_audio_devices().await?;
+        for (id, expected_type) in [
+            (playback_id, DeviceType::Playback),
+            (recording_id, DeviceType::Recording),
+        ] {
+            let device = devices
+                .iter()
+                .find(|d| d.id == id)
+                .ok_or_else(|| AudioError::DeviceNotFound(id.to_string()))?;
+            if device.device_type != expected_type {
+                return Err(AudioError::InvalidConfig(format!(
+                    "Device '{}' is not a {:?} device",
+                    device.name, expected_type
+                )));
+            }
+        }
+
+        let group = DeviceGroup {
+            name: name.to_string(),
+            playback_id: playback_id.to_string(),
+            recording_id: recording_id.to_string(),
+        };
+
+        let created = group.clone();
+        self.update_config(move |config| {
+            if config.groups.contains_key(&group.name) {
+                return Err(AudioError::InvalidConfig(format!(
+                    "Group '{}' already exists",
+                    group.name
+                )));
+            }
+            config.groups.insert(group.name.clone(), group);
+            Ok(())
+        })
+        .await?;
+
+        info!("Created device group '{}'", created.name);
+        Ok(created)
+    }
+
+    /// List saved device groups
+    pub async fn list_groups(&self) -> Vec<DeviceGroup> {
+        self.config.read().await.groups.values().cloned().collect()
+    }
+
+    /// Switch both defaults to the endpoints of a group, rolling back on partial failure
+    pub async fn apply_group(&self, name: &str) -> AudioResult<()> {
+        let group = self
+            .config
+            .read()
+            .await
+            .groups
+            .get(name)
+            .cloned()
+            .ok_or_else(|| AudioError::InvalidConfig(format!("Unknown group '{}'", name)))?;
+
+        info!(
+            "Applying device group '{}' (session: {})",
+            group.name, self.session_id
+        );
+        self.in_bulk_operation(
+            self.apply_defaults_atomic(Some(&group.playback_id), Some(&group.recording_id)),
+        )
+        .await
+    }
+
+    /// Make a playback and/or recording endpoint the default together under the
+    /// operation lock, rolling both back if either switch fails. Returns the defaults
+    /// afterwards.
+    pub async fn set_default_pair(
+        &self,
+        playback_id: Option<&str>,
+        recording_id: Option<&str>,
+    ) -> AudioResult<RoleDefaults> {
+        if playback_id.is_none() && recording_id.is_none() {
+            return Err(AudioError::InvalidArgument(
+                "A playback or recording device is required".to_string(),
+            ));
+        }
+
+        let devices = self.get_audio_devices().await?;
+        for (id, device_type) in [
+            (playback_id, DeviceType::Playback),
+            (recording_id, DeviceType::Recording),
+        ] {
+            let Some(id) = id else { continue };
+            let device = devices
+                .iter()
+                .find(|d| d.id == id)
+                .ok_or_else(|| AudioError::DeviceNotFound(id.to_string()))?;
+            if device.device_type != device_type {
+                return Err(AudioError::InvalidArgument(format!(
+                    "{} is not a {:?} device",
+                    device.name, device_type
+                )));
+            }
+        }
+
+        info!(
+            "Setting default pair: playback {:?}, recording {:?} (session: {})",
+            playback_id, recording_id, self.session_id
+        );
+        {
+            let _guard = self.operation_lock.lock().await;
+            self.in_bulk_operation(self.apply_defaults_atomic(playback_id, recording_id))
+                .await?;
+        }
+        self.get_all_defaults().await
+    }
+
+    /// Suggest groups by pairing playback and recording endpoints of the same physical
+    /// device: by PnP container where the container map knows the playback endpoint,
+    /// otherwise by hardware name
+    pub async fn suggest_groups(&self) -> AudioResult<Vec<DeviceGroup>> {
+        let devices = self.get_audio_devices().await?;
+        let containers = self.get_device_containers().await.unwrap_or_else(|e| {
+            warn!("Container grouping unavailable, pairing by name: {}", e);
+            HashMap::new()
+        });
+
+        let mut suggestions = Vec::new();
+        for playback in devices
+            .iter()
+            .filter(|d| d.device_type == DeviceType::Playback)
+        {
+            let hardware = hardware_name(&playback.name);
+            let recording = match endpoint_container(&containers, &playback.id) {
+                Some(container) => devices.iter().find(|d| {
+                    d.device_type == DeviceType::Recording
+                        && endpoint_container(&containers, &d.id) == Some(container)
+                }),
+                None => hardware.and_then(|hardware| {
+                    devices.iter().find(|d| {
+                        d.device_type == DeviceType::Recording
+                            && hardware_name(&d.name)
+                                .is_some_and(|h| h.eq_ignore_ascii_case(hardware))
+                    })
+                }),
+            };
+            if let Some(recording) = recording {
+                suggestions.push(DeviceGroup {
+                    name: hardware.unwrap_or(&playback.name).to_string(),
+                    playback_id: playback.id.clone(),
+                    recording_id: recording.id.clone(),
+                });
+            }
+        }
+
+        Ok(suggestions)
+    }
+
+    /// Check whether a device accepts a format in shared or exclusive mode via
+    /// Core Audio `IsFormatSupported`, returning the closest match when it doesn't
+    pub async fn test_format(
+        &self,
+        device_id: &str,
+        format: AudioFormat,
+        exclusive: bool,
+    ) -> AudioResult<FormatSupport> {
+        self.validate_device_id(device_id).await?;
+        info!(
+            "Testing format {:?} (exclusive: {}) on {}",
+            format, exclusive, device_id
+        );
+
+        #[cfg(windows)]
+        {
+            let device_id = device_id.to_string();
+            tokio::task::spawn_blocking(move || {
+                crate::wasapi::is_format_supported(&device_id, &format, exclusive)
+            })
+            .await
+            .map_err(|e| AudioError::Unknown(e.to_string()))?
+        }
+
+        #[cfg(not(windows))]
+        {
+            let _ = (format, exclusive);
+            Err(AudioError::WindowsApiError(
+                "Format checks require Windows Core Audio".to_string(),
+            ))
+        }
+    }
+
+    /// Check whether a device (the default playback device when `device_id` is `None`)
+    /// can play `channels` channels, e.g. 8 for 7.1, returning the closest channel count
+    /// it does play when it can't
+    pub async fn supports_channels(
+        &self,
+        device_id: Option<&str>,
+        channels: u16,
+    ) -> AudioResult<ChannelSupport> {
+        if channels == 0 {
+            return Err(AudioError::InvalidArgument(
+                "Channel count must be at least 1".to_string(),
+            ));
+        }
+        let device_id = match device_id {
+            Some(id) => self.resolve_device_id(id).await?,
+            None => default_device(self.get_audio_devices().await?, &DeviceType::Playback)
+                .map(|device| device.id)
+                .ok_or_else(|| {
+                    AudioError::DeviceNotFound("no default playback device".to_string())
+                })?,
+        };
+        info!("Testing {} channel support on {}", channels, device_id);
+
+        #[cfg(windows)]
+        {
+            let counts = tokio::task::spawn_blocking(move || {
+                crate::wasapi::supported_channel_counts(&device_id)
+            })
+            .await
+            .map_err(|e| AudioError::Unknown(e.to_string()))??;
+            Ok(ChannelSupport::from_counts(channels, &counts))
+        }
+
+        #[cfg(not(windows))]
+        {
+            let _ = device_id;
+            Err(AudioError::WindowsApiError(
+                "Channel checks require Windows Core Audio".to_string(),
+            ))
+        }
+    }
+
+    /// Audio sessions on a device (the default playback device when `device_id` is
+    /// `None`). The app's own sessions, e.g. from a test tone, are left out unless
+    /// `include_self` is set.
+    pub async fn get_audio_sessions(
+        &self,
+        device_id: Option<&str>,
+        include_self: bool,
+    ) -> AudioResult<Vec<AudioSession>> {
+        let device_id = match device_id {
+            Some(id) => self.resolve_device_id(id).await?,
+            None => default_device(self.get_audio_devices().await?, &DeviceType::Playback)
+                .map(|device| device.id)
+                .ok_or_else(|| {
+                    AudioError::DeviceNotFound("no default playback device".to_string())
+                })?,
+        };
+
+        #[cfg(windows)]
+        {
+            let sessions =
+                tokio::task::spawn_blocking(move || crate::wasapi::list_sessions(&device_id))
+                    .await
+                    .map_err(|e| AudioError::Unknown(e.to_string()))??;
+            Ok(self.filter_sessions(sessions, include_self))
+        }
+
+        #[cfg(not(windows))]
+        {
+            let _ = (device_id, include_self);
+            Err(AudioError::WindowsApiError(
+                "Audio sessions require Windows Core Audio".to_string(),
+            ))
+        }
+    }
+
+    /// Whether a session instance identifier belongs to this process. Identifiers end
+    /// in `%b<pid>`, e.g. `...|\Device\HarddiskVolume3\app.exe%b{...}|1%b4242`.
+    pub fn is_self_session(&self, session_id: &str) -> bool {
+        session_id
+            .rsplit_once("%b")
+            .and_then(|(_, pid)| pid.parse::<u32>().ok())
+            .is_some_and(|pid| pid == std::process::id())
+    }
+
+    /// Drop sessions owned by this process unless `include_self` is set
+    fn filter_sessions(
+        &self,
+        sessions: Vec<AudioSession>,
+        include_self: bool,
+    ) -> Vec<AudioSession> {
+        let own_pid = std::process::id();
+        sessions
+            .into_iter()
+            .filter(|session| {
+                include_self
+                    || (session.process_id != own_pid && !self.is_self_session(&session.session_id))
+            })
+            .collect()
+    }
+
+    /// Set the same volume on several devices at once. Every device is attempted and
+    /// reported individually instead of aborting on the first failure.
+    pub async fn set_group_volume(
+        &self,
+        device_ids: &[String],
+        volume: u8,
+    ) -> AudioResult<Vec<DeviceOperationResult>> {
+        if volume > 100 {
+            return Err(AudioError::InvalidArgument(format!(
+                "Volume must be between 0 and 100, got {}",
+                volume
+            )));
+        }
+
+        self.ensure_writable("change volume")?;
+        let _guard = self.operation_lock.lock().await;
+        info!(
+            "Setting volume {} on {} devices (session: {})",
+            volume,
+            device_ids.len(),
+            self.session_id
+        );
+
+        self.in_bulk_operation(async {
+            // Validate all IDs up front; missing devices are reported rather than fatal
+            let devices = self.get_audio_devices().await?;
+            let (present, missing): (Vec<&String>, Vec<&String>) = device_ids
+                .iter()
+                .partition(|id| devices.iter().any(|d| &d.id == *id));
+
+            let mut results: Vec<DeviceOperationResult> = missing
+                .into_iter()
+                .map(|id| DeviceOperationResult {
+                    device_id: id.clone(),
+                    success: false,
+                    error: Some(AudioError::DeviceNotFound(id.clone()).to_string()),
+                })
+                .collect();
+
+            if !present.is_empty() {
+                results.extend(self.set_devices_volume(&present, volume).await?);
+            }
+
+            self.invalidate_cache().await;
+            Ok(results)
+        })
+        .await
+    }
+
+    /// Mute or unmute one device
+    pub async fn set_device_mute(&self, device_id: &str, muted: bool) -> AudioResult<()> {
+        self.change_device_mute(device_id, Some(muted)).await?;
+        Ok(())
+    }
+
+    /// Flip one device's mute state, returning whether it is muted afterwards
+    pub async fn toggle_device_mute(&self, device_id: &str) -> AudioResult<bool> {
+        self.change_device_mute(device_id, None).await
+    }
+
+    /// Set a device's mute flag, or flip it when `muted` is `None`, and return the
+    /// resulting state
+    async fn change_device_mute(&self, device_id: &str, muted: Option<bool>) -> AudioResult<bool> {
+        self.ensure_writable("change mute")?;
+        let device_id = self.resolve_device_id(device_id).await?;
+
+        let id = device_id.clone();
+        if let Some(result) = self
+            .with_native_backend(move |backend| backend.set_mute(&id, muted))
+            .await
+        {
+            let muted = result?;
+            info!("{} {}", if muted { "Muted" } else { "Unmuted" }, device_id);
+            self.invalidate_cache().await;
+            return Ok(muted);
+        }
+
+        let value = match muted {
+            Some(true) => "$true",
+            Some(false) => "$false",
+            None => "(-not $endpoint.Mute)",
+        };
+        let powershell_script = format!(
+            r#"
+                try {{
+                    Import-Module AudioDeviceCmdlets -ErrorAction Stop
+                    
+                    $endpoint = (Get-AudioDevice -ID {}).Device.AudioEndpointVolume
+                    $endpoint.Mute = {}
+                    
+                    @{{
+                        muted = $endpoint.Mute
+                    }} | ConvertTo-Json -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            powershell_string(device_id),
+            value
+        );
+
+        let output = self
+            .execute_powershell_with_retry(
+                &powershell_script,
+                "set device mute",
+                self.command_timeout().await,
+            )
+            .await?;
+        let response: serde_json::Value = parse_json_or_error(&output, "mute update")?;
+        let muted = response["muted"]
+            .as_bool()
+            .ok_or_else(|| AudioError::ParseError("Missing mute state".to_string()))?;
+        info!("{} {}", if muted { "Muted" } else { "Unmuted" }, device_id);
+
+        self.invalidate_cache().await;
+        Ok(muted)
+    }
+
+    /// Inspect a device's default format, exclusive-mode settings and state and turn
+    /// them into a list of troubleshooting findings
+    pub async fn diagnose_device(&self, device_id: &str) -> AudioResult<DeviceDiagnosis> {
+        let devices = self.get_audio_devices().await?;
+        let device = devices
+            .iter()
+            .find(|d| d.id == device_id)
+            .ok_or_else(|| AudioError::DeviceNotFound(device_id.to_string()))?;
+        info!("Diagnosing device {} ({})", device.name, device.id);
+
+        let settings = self
+            .read_endpoint_format_settings(device_id)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Could not read format settings for {}: {}", device_id, e);
+                EndpointFormatSettings::default()
+            });
+        let default_format = settings.default_format();
+
+        // Only the native backend can ask the audio engine about the format itself
+        let engine_support = match default_format {
+            #[cfg(windows)]
+            Some(format) => {
+                let id = device_id.to_string();
+                tokio::task::spawn_blocking(move || {
+                    crate::wasapi::is_format_supported(&id, &format, false)
+                })
+                .await
+                .ok()
+                .and_then(|r| r.ok())
+            }
+            _ => None,
+        };
+
+        let findings = diagnosis_findings(device, &settings, engine_support.as_ref());
+        Ok(DeviceDiagnosis {
+            device_id: device.id.clone(),
+            device_name: device.name.clone(),
+            default_format,
+            exclusive_mode_allowed: settings.exclusive_allowed.map(|v| v != 0),
+            findings,
+        })
+    }
+
+    /// Compare the default formats of every active playback device and flag pairs whose
+    /// sample rates differ, which makes apps playing to several outputs at once (e.g.
+    /// through a virtual aggregate device) resample or drift
+    pub async fn check_format_conflicts(&self) -> AudioResult<Vec<FormatConflict>> {
+        let devices = self.get_audio_devices().await?;
+
+        let mut formats = Vec::new();
+        for device in devices
+            .into_iter()
+            .filter(|d| d.device_type == DeviceType::Playback && d.state == DeviceState::Active)
+        {
+            match self.read_endpoint_format_settings(&device.id).await {
+                Ok(settings) => {
+                    if let Some(format) = settings.default_format() {
+                        formats.push((device, format));
+                    }
+                }
+                Err(e) => warn!("Could not read format settings for {}: {}", device.id, e),
+            }
+        }
+
+        let conflicts = format_conflicts(&formats);
+        info!(
+            "Found {} format conflicts across {} active playback devices",
+            conflicts.len(),
+            formats.len()
+        );
+        Ok(conflicts)
+    }
+
+    /// Spatial Sound providers installed on this machine. Windows Sonic is always listed;
+    /// third-party providers (Dolby Atmos, DTS Sound Unbound) come from the registry.
+    pub async fn list_spatial_providers(&self) -> AudioResult<Vec<SpatialProvider>> {
+        let powershell_script = with_registry64(format!(
+            r#"
+                try {{
+                    $providers = @(Get-Registry64SubKeyNames "{0}" | ForEach-Object {{
+                        $props = Get-Registry64Values "{0}\$_"
+                        @{{
+                            id = $_
+                            name = "$($props.FriendlyName)"
+                        }}
+                    }})
+                    ConvertTo-Json -InputObject $providers -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            SPATIAL_PROVIDERS_KEY
+        ));
+
+        let registered = match self
+            .execute_powershell_with_retry(
+                &powershell_script,
+                "spatial provider enumeration",
+                self.command_timeout().await,
+            )
+            .await
+            .and_then(|output| {
+                parse_json_or_error::<Vec<HashMap<String, String>>>(&output, "spatial providers")
+            }) {
+            Ok(registered) => registered,
+            Err(e) => {
+                warn!("Could not read installed spatial providers: {}", e);
+                Vec::new()
+            }
+        };
+
+        Ok(spatial_providers(registered))
+    }
+
+    /// Time a switch to `device_id` and back to the original default. The original
+    /// defaults are restored even when the forward switch fails.
+    pub async fn measure_switch_latency(&self, device_id: &str) -> AudioResult<SwitchLatency> {
+        self.ensure_writable("switch devices")?;
+        let _guard = self.operation_lock.lock().await;
+
+        let devices = self.get_audio_devices().await?;
+        let device = devices
+            .iter()
+            .find(|d| d.id == device_id)
+            .ok_or_else(|| AudioError::DeviceNotFound(device_id.to_string()))?;
+        if device.is_default {
+            info!(
+                "{} is already the default, skipping latency measurement",
+                device.name
+            );
+            return Ok(SwitchLatency {
+                round_trip_ms: 0,
+                already_default: true,
+            });
+        }
+
+        let original_defaults = self.get_current_defaults().await?;
+        info!(
+            "Measuring switch latency to {} (session: {})",
+            device.name, self.session_id
+        );
+
+        let start = Instant::now();
+        let switched = self
+            .change_default_device(device_id, DefaultRole::Both)
+            .await;
+        let restored = self.fallback_to_previous_device(&original_defaults).await;
+        let round_trip_ms = start.elapsed().as_millis() as u64;
+        self.invalidate_cache().await;
+
+        switched?;
+        restored?;
+        info!(
+            "Switch round trip to {} took {}ms",
+            device_id, round_trip_ms
+        );
+        Ok(SwitchLatency {
+            round_trip_ms,
+            already_default: false,
+        })
+    }
+
+    /// Render a device's icon as a base64 PNG. Returns `None` when the device has no
+    /// icon reference or the reference can't be resolved.
+    pub async fn get_device_icon_data(&self, device_id: &str) -> AudioResult<Option<String>> {
+        let devices = self.get_audio_devices().await?;
+        let device = devices
+            .iter()
+            .find(|d| d.id == device_id)
+            .ok_or_else(|| AudioError::DeviceNotFound(device_id.to_string()))?;
+
+        let Some((file, index)) = device.icon_path.as_deref().and_then(parse_icon_reference) else {
+            debug!("No usable icon reference for {}", device.name);
+            return Ok(None);
+        };
+
+        let powershell_script = format!(
+            r#"
+                try {{
+                    Add-Type -AssemblyName System.Drawing
+                    Add-Type -TypeDefinition @"
+using System;
+using System.Runtime.InteropServices;
+public static class IconExtractor {{
+    [DllImport("shell32.dll", CharSet = CharSet.Unicode)]
+    public static extern uint ExtractIconEx(string file, int index, out IntPtr large, out IntPtr small, uint count);
+    [DllImport("user32.dll")]
+    public static extern bool DestroyIcon(IntPtr handle);
+}}
+"@
+                    
+                    $file = [Environment]::ExpandEnvironmentVariables('{0}')
+                    if (-not (Test-Path $file)) {{
+                        $file = Join-Path ([Environment]::SystemDirectory) $file
+                    }}
+                    
+                    $large = [IntPtr]::Zero
+                    $small = [IntPtr]::Zero
+                    [IconExtractor]::ExtractIconEx($file, {1}, [ref]$large, [ref]$small, 1) | Out-Null
+                    if ($large -eq [IntPtr]::Zero) {{
+                        @{{ icon = $null }} | ConvertTo-Json -Compress
+                        exit 0
+                    }}
+                    
+                    $stream = New-Object System.IO.MemoryStream
+                    [System.Drawing.Icon]::FromHandle($large).ToBitmap().Save($stream, [System.Drawing.Imaging.ImageFormat]::Png)
+                    [IconExtractor]::DestroyIcon($large) | Out-Null
+                    if ($small -ne [IntPtr]::Zero) {{
+                        [IconExtractor]::DestroyIcon($small) | Out-Null
+                    }}
+                    
+                    @{{ icon = [Convert]::ToBase64String($stream.ToArray()) }} | ConvertTo-Json -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            file.replace('\'', "''"),
+            index
+        );
+
+        let output = self
+            .execute_powershell_with_retry(
+                &powershell_script,
+                "extract device icon",
+                self.command_timeout().await,
+            )
+            .await?;
+        let response: serde_json::Value = parse_json_or_error(&output, "icon extraction")?;
+        Ok(response["icon"].as_str().map(|icon| icon.to_string()))
+    }
+
+    /// Dump an endpoint's property store as strings. Known keys are reported by name,
+    /// everything else by its raw `{fmtid},pid` key; binary values are skipped.
+    pub async fn get_device_properties(
+        &self,
+        device_id: &str,
+    ) -> AudioResult<HashMap<String, String>> {
+        self.validate_device_id(device_id).await?;
+        let registry_path = endpoint_registry_path(device_id)?;
+        let powershell_script = with_registry64(format!(
+            r#"
+                try {{
+                    $props = Get-Registry64Values "{0}\Properties"
+                    if ($null -eq $props) {{ throw "Registry key not found: HKLM\{0}\Properties" }}
+                    $result = @{{}}
+                    foreach ($prop in $props.PSObject.Properties) {{
+                        if ($prop.Value -is [byte[]]) {{
+                            continue
+                        }}
+                        $result[$prop.Name] = "$($prop.Value)"
+                    }}
+                    $result | ConvertTo-Json -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            registry_path
+        ));
+
+        let output = self
+            .execute_powershell_with_retry(
+                &powershell_script,
+                "endpoint property read",
+                self.command_timeout().await,
+            )
+            .await?;
+        let raw: HashMap<String, String> = parse_json_or_error(&output, "endpoint property read")?;
+        Ok(name_known_properties(raw))
+    }
+
+    /// Whether the Loudness Equalization enhancement is on for a playback device
+    pub async fn get_loudness_equalization(&self, device_id: &str) -> AudioResult<bool> {
+        let device_id = self.resolve_device_id(device_id).await?;
+        let blob = self.read_loudness_equalization(&device_id).await?;
+        parse_loudness_blob(&blob).ok_or_else(|| {
+            AudioError::ParseError(format!(
+                "Unrecognized loudness equalization value: {:?}",
+                blob
+            ))
+        })
+    }
+
+    /// Turn the Loudness Equalization enhancement on or off. Fails with
+    /// `AudioError::Unsupported` when the device's driver doesn't offer it, and with
+    /// `AudioError::ElevationRequired` when the app isn't allowed to write the setting.
+    pub async fn set_loudness_equalization(
+        &self,
+        device_id: &str,
+        enabled: bool,
+    ) -> AudioResult<()> {
+        self.ensure_writable("change audio enhancements")?;
+        let device_id = self.resolve_device_id(device_id).await?;
+        // Also confirms the enhancement exists before writing anything
+        self.read_loudness_equalization(&device_id).await?;
+
+        info!(
+            "Setting loudness equalization on {}: {}",
+            device_id, enabled
+        );
+        let bytes = loudness_blob(enabled)
+            .iter()
+            .map(|b| format!("0x{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(",");
+        let powershell_script = with_registry64(format!(
+            r#"
+                try {{
+                    Set-Registry64Value "{}\FxProperties" "{}" ([byte[]]({})) Binary
+                    @{{
+                        success = $true
+                    }} | ConvertTo-Json -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            endpoint_registry_path(&device_id)?,
+            LOUDNESS_EQUALIZATION_KEY,
+            bytes
+        ));
+
+        self.execute_powershell_with_retry(
+            &powershell_script,
+            "loudness equalization write",
+            self.command_timeout().await,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Raw Loudness Equalization property of a playback endpoint's FX property store
+    async fn read_loudness_equalization(&self, device_id: &str) -> AudioResult<Vec<u8>> {
+        if !device_id.starts_with("{0.0.0") {
+            return Err(AudioError::Unsupported(
+                "loudness equalization on recording devices".to_string(),
+            ));
+        }
+
+        let powershell_script = with_registry64(format!(
+            r#"
+                try {{
+                    $props = Get-Registry64Values "{}\FxProperties"
+                    $value = if ($props) {{ $props.'{}' }} else {{ $null }}
+                    @{{
+                        supported = ($value -is [byte[]])
+                        value = if ($value -is [byte[]]) {{ @($value | ForEach-Object {{ [int]$_ }}) }} else {{ @() }}
+                    }} | ConvertTo-Json -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            endpoint_registry_path(device_id)?,
+            LOUDNESS_EQUALIZATION_KEY
+        ));
+
+        let output = self
+            .execute_powershell_with_retry(
+                &powershell_script,
+                "loudness equalization read",
+                self.command_timeout().await,
+            )
+            .await?;
+        let response: serde_json::Value =
+            parse_json_or_error(&output, "loudness equalization read")?;
+        if !response["supported"].as_bool().unwrap_or(false) {
+            return Err(AudioError::Unsupported(format!(
+                "loudness equalization on {} (the driver offers no audio enhancements)",
+                device_id
+            )));
+        }
+
+        Ok(response["value"]
+            .as_array()
+            .map(|bytes| {
+                bytes
+                    .iter()
+                    .filter_map(|b| b.as_u64().and_then(|b| u8::try_from(b).ok()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Whether two endpoints belong to the same physical device, e.g. the speaker and
+    /// microphone of one USB headset, judged by their PnP container IDs. Uses the cached
+    /// container map and reads the containers one by one only for endpoints it lacks.
+    pub async fn same_physical_device(&self, id_a: &str, id_b: &str) -> AudioResult<bool> {
+        let id_a = self.resolve_device_id(id_a).await?;
+        let id_b = self.resolve_device_id(id_b).await?;
+        if id_a == id_b {
+            return Ok(true);
+        }
+
+        let containers = self.get_device_containers().await?;
+        if let (Some(container_a), Some(container_b)) = (
+            endpoint_container(&containers, &id_a),
+            endpoint_container(&containers, &id_b),
+        ) {
+            return Ok(container_a == container_b);
+        }
+
+        let container_a = self.container_id(&id_a).await?;
+        let container_b = self.container_id(&id_b).await?;
+        debug!(
+            "Container IDs: {} -> {}, {} -> {}",
+            id_a, container_a, id_b, container_b
+        );
+        Ok(same_container(&container_a, &container_b))
+    }
+
+    /// Enumerate devices and compute container grouping ahead of the first UI request
+    pub async fn warm_up(&self) {
+        info!("Warming up device caches (session: {})", self.session_id);
+        if let Err(e) = self.get_audio_devices().await {
+            warn!("Warm-up enumeration failed: {}", e);
+        }
+        if let Err(e) = self.refresh_device_containers().await {
+            warn!("Warm-up container grouping failed: {}", e);
+        }
+    }
+
+    /// Endpoint IDs grouped by PnP container, so endpoints of one physical device (the
+    /// speaker and microphone of a headset) share an entry. Computed once and cached.
+    pub async fn get_device_containers(&self) -> AudioResult<HashMap<String, Vec<String>>> {
+        if let Some(containers) = &self.state.read().await.device_containers {
+            return Ok(containers.clone());
+        }
+        self.refresh_device_containers().await
+    }
+
+    async fn refresh_device_containers(&self) -> AudioResult<HashMap<String, Vec<String>>> {
+        let output = self
+            .execute_powershell_with_retry(
+                CONTAINER_MAP_SCRIPT,
+                "container map",
+                self.command_timeout().await,
+            )
+            .await?;
+        let response: serde_json::Value = parse_json_or_error(&output, "container map")?;
+        if let Some(error) = response.get("error") {
+            return Err(AudioError::CommandFailed(
+                error.as_str().unwrap_or("Unknown error").to_string(),
+            ));
+        }
+
+        let endpoints: Vec<(String, String)> = response["endpoints"]
+            .as_array()
+            .map(|endpoints| {
+                endpoints
+                    .iter()
+                    .filter_map(|e| {
+                        Some((
+                            e["id"].as_str()?.to_string(),
+                            e["container_id"].as_str()?.to_string(),
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let containers = group_by_container(&endpoints);
+        debug!(
+            "Grouped {} endpoints into {} containers",
+            endpoints.len(),
+            containers.len()
+        );
+        self.state.write().await.device_containers = Some(containers.clone());
+        Ok(containers)
+    }
+
+    /// PnP container ID (`DEVPKEY_Device_ContainerId`) of an endpoint
+    async fn container_id(&self, device_id: &str) -> AudioResult<String> {
+        let powershell_script = format!(
+            r#"
+                try {{
+                    $property = Get-PnpDeviceProperty -InstanceId {} -KeyName DEVPKEY_Device_ContainerId -ErrorAction Stop
+                    @{{
+                        container_id = "$($property.Data)"
+                    }} | ConvertTo-Json -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            powershell_string(&format!(r"SWD\MMDEVAPI\{}", device_id))
+        );
+
+        let output = self
+            .execute_powershell_with_retry(
+                &powershell_script,
+                "container ID read",
+                self.command_timeout().await,
+            )
+            .await?;
+        let response: serde_json::Value = parse_json_or_error(&output, "container ID read")?;
+        if let Some(error) = response.get("error") {
+            return Err(AudioError::CommandFailed(
+                error.as_str().unwrap_or("Unknown error").to_string(),
+            ));
+        }
+
+        response["container_id"]
+            .as_str()
+            .map(|id| id.to_string())
+            .ok_or_else(|| AudioError::ParseError("Missing container_id".to_string()))
+    }
+
+    /// Driver health of the PnP device behind an endpoint, from its Device Manager
+    /// problem code. A device can enumerate while its driver has faulted.
+    pub async fn get_driver_status(&self, device_id: &str) -> AudioResult<DriverStatus> {
+        let device_id = self.resolve_device_id(device_id).await?;
+        let powershell_script = format!(
+            r#"
+                try {{
+                    $instanceId = {}
+                    $parent = Get-PnpDeviceProperty -InstanceId $instanceId -KeyName DEVPKEY_Device_Parent -ErrorAction SilentlyContinue
+                    if ($parent -and $parent.Data) {{ $instanceId = $parent.Data }}
+                    $device = Get-PnpDevice -InstanceId $instanceId -ErrorAction Stop
+                    $problem = Get-PnpDeviceProperty -InstanceId $instanceId -KeyName DEVPKEY_Device_ProblemCode -ErrorAction SilentlyContinue
+                    @{{
+                        instance_id = $device.InstanceId
+                        name = $device.FriendlyName
+                        status = "$($device.Status)"
+                        problem_code = if ($problem -and $problem.Data) {{ [int]$problem.Data }} else {{ 0 }}
+                    }} | ConvertTo-Json -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            powershell_string(&format!(r"SWD\MMDEVAPI\{}", device_id))
+        );
+
+        let output = self
+            .execute_powershell_with_retry(
+                &powershell_script,
+                "driver status read",
+                self.command_timeout().await,
+            )
+            .await?;
+        let status = DriverStatus::from_reading(&device_id, &output)?;
+        if !status.ok {
+            warn!(
+                "Driver problem on {}: code {} ({})",
+                device_id,
+                status.problem_code,
+                status.problem.as_deref().unwrap_or("unknown")
+            );
+        }
+        Ok(status)
+    }
+
+    /// Validate that a device exists, given either its full ID or its index (Step 12)
+    pub async fn validate_device_id(&self, device_id: &str) -> AudioResult<bool> {
+        debug!("Validating device ID: {}", device_id);
+        self.resolve_device_id(device_id).await?;
+        Ok(true)
+    }
+
+    /// Resolve a full device ID or an integer index to the full endpoint ID
+    pub async fn resolve_device_id(&self, id_or_index: &str) -> AudioResult<String> {
+        let devices = self.get_audio_devices().await?;
+
+        if let Ok(index) = id_or_index.trim().parse::<u32>() {
+            return devices
+                .iter()
+                .find(|d| d.index == Some(index))
+                .map(|d| d.id.clone())
+                .ok_or_else(|| {
+                    AudioError::DeviceNotFound(format!("no device with index {}", index))
+                });
+        }
+
+        devices
+            .iter()
+            .find(|d| d.id == id_or_index)
+            .map(|d| d.id.clone())
+            .ok_or_else(|| AudioError::DeviceNotFound(format!("no device with ID {}", id_or_index)))
+    }
+
+    /// Check module availability, or a native backend's tools, with detailed
+    /// diagnostics (Step 18)
+    pub async fn check_module_availability(&self) -> AudioResult<bool> {
+        if let Some(result) = self
+            .with_native_backend(|backend| backend.check_available())
+            .await
+        {
+            return match result {
+                Ok(()) => Ok(true),
+                Err(AudioError::ModuleNotInstalled(missing)) => {
+                    info!("Audio backend unavailable: {}", missing);
+                    Ok(false)
+                }
+                Err(e) => Err(e),
+            };
+        }
+
+        debug!("Checking AudioDeviceCmdlets module availability...");
+
+        let response = match self.run_module_check().await {
+            Err(AudioError::ModuleNotInstalled(_)) => return Ok(false),
+            result => result?,
+        };
+        let is_available = response["available"].as_bool().unwrap_or(false);
+        debug!("AudioDeviceCmdlets module available: {}", is_available);
+
+        if !is_available {
+            if let Some(message) = response["message"].as_str() {
+                info!("Module availability check: {}", message);
+            }
+        }
+
+        Ok(is_available)
+    }
+
+    /// Installed AudioDeviceCmdlets version, `None` when the module isn't installed
+    async fn module_version(&self) -> AudioResult<Option<String>> {
+        let response = self.run_module_check().await?;
+        Ok(response["version"].as_str().map(str::to_string))
+    }
+
+    async fn run_module_check(&self) -> AudioResult<serde_json::Value> {
+        let output = self
+            .execute_powershell_with_retry(
+                MODULE_CHECK_SCRIPT,
+                "module availability check",
+                self.command_timeout().await,
+            )
+            .await?;
+        parse_json_or_error(&output, "module check")
+    }
+
+    /// Report PowerShell version, module, execution policy, elevation and whether devices
+    /// can be listed, from a single script run
+    pub async fn diagnose(&self) -> AudioResult<Diagnostics> {
+        info!(
+            "Running PowerShell diagnostics (session: {})",
+            self.session_id
+        );
+
+        let output = self
+            .execute_powershell_with_retry(
+                DIAGNOSTICS_SCRIPT,
+                "diagnostics",
+                self.command_timeout().await,
+            )
+            .await?;
+        let diagnostics: Diagnostics = parse_json_or_error(&output, "diagnostics")?;
+        debug!("Diagnostics: {:?}", diagnostics);
+        Ok(diagnostics)
+    }
+
+    /// Compare the installed AudioDeviceCmdlets version with the latest one on PSGallery.
+    /// Fails rather than waiting indefinitely when the gallery can't be reached.
+    pub async fn check_module_update(&self) -> AudioResult<ModuleUpdateInfo> {
+        info!("Checking PSGallery for AudioDeviceCmdlets updates");
+
+        let output = self
+            .execute_powershell_with_retry(
+                MODULE_UPDATE_SCRIPT,
+                "module update check",
+                MODULE_UPDATE_TIMEOUT,
+            )
+            .await?;
+        let response: serde_json::Value = parse_json_or_error(&output, "module update check")?;
+        if let Some(error) = response["error"].as_str() {
+            return Err(AudioError::CommandFailed(error.to_string()));
+        }
+
+        let latest = response["latest"]
+            .as_str()
+            .ok_or_else(|| {
+                AudioError::ParseError("Module update check returned no version".to_string())
+            })?
+            .to_string();
+        let installed = response["installed"].as_str().map(str::to_string);
+        let update_available = installed
+            .as_deref()
+            .is_some_and(|installed| is_newer_version(&latest, installed));
+
+        info!(
+            "AudioDeviceCmdlets installed: {:?}, latest: {} (update available: {})",
+            installed, latest, update_available
+        );
+        Ok(ModuleUpdateInfo {
+            installed,
+            latest,
+            update_available,
+        })
+    }
+
+    /// Run one of the app's own scripts once, without retries or parsing, and return
+    /// exactly what PowerShell emitted
+    pub async fn debug_run_known_script(
+        &self,
+        script: KnownScript,
+    ) -> AudioResult<RawScriptOutput> {
+        info!(
+            "Running {:?} script for debugging (session: {})",
+            script, self.session_id
+        );
+
+        let start = Instant::now();
+        let output = tokio::time::timeout(
+            self.command_timeout().await,
+            powershell_command(self.powershell_executable(), script.script()).output(),
+        )
+        .await
+        .map_err(|_| AudioError::Timeout {
+            operation: format!("{:?} debug run", script),
+            elapsed_ms: start.elapsed().as_millis() as u64,
+        })??;
+
+        Ok(RawScriptOutput {
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            exit_code: output.status.code(),
+            elapsed_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Enable or disable installing the module automatically when enumeration finds it missing
+    pub async fn set_auto_install_module(&self, enabled: bool) -> AudioResult<()> {
+        self.update_config(|config| {
+            config.auto_install_module = enabled;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Re-probe for a PowerShell executable and the AudioDeviceCmdlets module, e.g. after
+    /// the user installed either while the app was running
+    pub async fn redetect_backend(&self) -> AudioResult<SetupStatus> {
+        let _guard = self.operation_lock.lock().await;
+        info!(
+            "Re-detecting PowerShell backend (session: {})",
+            self.session_id
+        );
+
+        let Some(executable) = detect_powershell().await else {
+            warn!("No PowerShell executable could be started");
+            return Ok(SetupStatus {
+                powershell_executable: None,
+                module_available: false,
+                ready: false,
+            });
+        };
+
+        *self
+            .powershell_exe
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = executable;
+        self.invalidate_cache().await;
+
+        let module_available = self.check_module_availability().await.unwrap_or(false);
+        info!(
+            "Using {} (AudioDeviceCmdlets available: {})",
+            executable, module_available
+        );
+        Ok(SetupStatus {
+            powershell_executable: Some(executable.to_string()),
+            module_available,
+            ready: module_available,
+        })
+    }
+
+    /// Capabilities of the backends available in this build
+    pub fn get_backend_capabilities(&self) -> BackendCapabilities {
+        BackendCapabilities::combined(&BackendKind::available())
+    }
+
+    /// Route Windows system sounds to `device_id` by making it the communication playback
+    /// default, which many notification sounds follow (Windows has no per-device system
+    /// sound setting). Returns the previous communication device so it can be restored.
+    pub async fn set_system_sounds_device(&self, device_id: &str) -> AudioResult<Option<String>> {
+        self.ensure_writable("route system sounds")?;
+        if !cfg!(windows) {
+            return Err(AudioError::Unsupported(
+                "Routing system sounds is only possible on Windows".to_string(),
+            ));
+        }
+
+        let device_id = self.resolve_device_id(device_id).await?;
+        let devices = self.get_audio_devices().await?;
+        let device = devices
+            .iter()
+            .find(|d| d.id == device_id)
+            .ok_or_else(|| AudioError::DeviceNotFound(device_id.clone()))?;
+        let role = system_sounds_role(device, &self.get_backend_capabilities())?;
+        let previous = RoleDefaults::from_devices(&devices)
+            .playback_communication
+            .map(|d| d.id);
+
+        info!("Routing system sounds to {} ({:?} role)", device.name, role);
+        let _guard = self.operation_lock.lock().await;
+        let result = self.change_default_device(&device_id, role).await;
+        self.invalidate_cache().await;
+        result.map(|_| previous)
+    }
+
+    /// Enumerate with both the PowerShell and Core Audio backends and report where they
+    /// disagree, to validate the native backend against the established one
+    pub async fn cross_check_backends(&self) -> AudioResult<BackendComparison> {
+        #[cfg(windows)]
+        {
+            let powershell = self.fetch_devices().await?.devices;
+            let native = tokio::task::spawn_blocking(crate::wasapi::list_devices)
+                .await
+                .map_err(|e| AudioError::Unknown(e.to_string()))??;
+            let comparison = BackendComparison::between(
+                (BackendKind::PowerShell, &powershell),
+                (BackendKind::CoreAudio, &native),
+            );
+            info!(
+                "Backend cross-check: {} matched, {} PowerShell only, {} Core Audio only, {} default mismatches",
+                comparison.matched,
+                comparison.only_in_primary.len(),
+                comparison.only_in_secondary.len(),
+                comparison.default_mismatches.len()
+            );
+            Ok(comparison)
+        }
+
+        #[cfg(not(windows))]
+        {
+            Err(AudioError::Unsupported(
+                "Cross-checking needs the Core Audio backend, which is only available on Windows"
+                    .to_string(),
+            ))
+        }
+    }
+
+    /// Install AudioDeviceCmdlets module (Step 9)
+    pub async fn install_module(&self) -> AudioResult<()> {
+        self.ensure_writable("install the AudioDeviceCmdlets module")?;
+        info!("Installing AudioDeviceCmdlets module...");
+
+        let powershell_script = r#"
+            try {
+                # Check if running as administrator for system-wide install
+                $currentUser = [Security.Principal.WindowsIdentity]::GetCurrent()
+                $principal = New-Object Security.Principal.WindowsPrincipal($currentUser)
+                $isAdmin = $principal.IsInRole([Security.Principal.WindowsBuiltInRole]::Administrator)
+                
+                if ($isAdmin) {
+                    Write-Output "Installing AudioDeviceCmdlets for all users..."
+                    Install-Module AudioDeviceCmdlets -Force -Scope AllUsers -AllowClobber
+                } else {
+                    Write-Output "Installing AudioDeviceCmdlets for current user..."
+                    Install-Module AudioDeviceCmdlets -Force -Scope CurrentUser -AllowClobber
+                }
+                
+                # Verify installation
+                Import-Module AudioDeviceCmdlets -ErrorAction Stop
+                $version = (Get-Module AudioDeviceCmdlets).Version
+                
+                @{
+                    success = $true
+                    version = $version.ToString()
+                    scope = if ($isAdmin) { "AllUsers" } else { "CurrentUser" }
+                    message = "AudioDeviceCmdlets module installed successfully"
+                } | ConvertTo-Json -Compress
+            }
+            catch {
+                @{
+                    success = $false
+                    error = $_.Exception.Message
+                    suggestion = "Try running as administrator or check internet connection"
+                } | ConvertTo-Json -Compress
+                exit 1
+            }
+        "#;
+
+        let output = self
+            .execute_powershell_with_retry(
+                powershell_script,
+                "module installation",
+                MODULE_INSTALL_TIMEOUT,
+            )
+            .await?;
+        let response: serde_json::Value = parse_json_or_error(&output, "module install")?;
+
+        if response["success"].as_bool().unwrap_or(false) {
+            info!("Successfully installed AudioDeviceCmdlets module");
+        } else {
+            let error_msg = response["error"]
+                .as_str()
+                .unwrap_or("Unknown installation error");
+            return Err(AudioError::CommandFailed(error_msg.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Install the module after enumeration reported it missing, if the user opted in.
+    /// Attempted at most once per session so a failing install can't loop, and never
+    /// for a native backend's tools, which the user installs themselves.
+    async fn try_auto_install_module(&self) -> bool {
+        if self.native_backend.is_some()
+            || !self.config.read().await.auto_install_module
+            || self.auto_install_attempted.swap(true, Ordering::SeqCst)
+        {
+            return false;
+        }
+
+        info!("AudioDeviceCmdlets missing, installing automatically");
+        self.emit("module-install-progress", ModuleInstallProgress::Started);
+        match self.install_module().await {
+            Ok(()) => {
+                self.emit("module-install-progress", ModuleInstallProgress::Succeeded);
+                true
+            }
+            Err(e) => {
+                error!("Automatic module install failed: {}", e);
+                self.emit(
+                    "module-install-progress",
+                    ModuleInstallProgress::Failed {
+                        error: e.to_string(),
+                    },
+                );
+                false
+            }
+        }
+    }
+
+    /// Execute PowerShell with retry logic (Step 20)
+    ///
+    /// Each attempt is killed once `timeout` elapses; a timeout on the final
+    /// attempt surfaces as `AudioError::Timeout` for the given operation.
+    async fn execute_powershell_with_retry(
+        &self,
+        script: &str,
+        operation: &str,
+        timeout: Duration,
+    ) -> AudioResult<String> {
+        self.execute_powershell_streaming(script, operation, timeout, &mut |_| {})
+            .await
+    }
+
+    /// `execute_powershell_with_retry`, handing each `Send-Progress` payload the script
+    /// reports to `on_progress` as it arrives. Retried attempts report their steps again.
+    async fn execute_powershell_streaming(
+        &self,
+        script: &str,
+        operation: &str,
+        timeout: Duration,
+        on_progress: &mut (dyn FnMut(&str) + Send),
+    ) -> AudioResult<String> {
+        let start = Instant::now();
+        let mut last_error = None;
+        let mut attempts = 0;
+        let (max_attempts, retry_base_delay) = {
+            let config = self.config.read().await;
+            (
+                config
+                    .max_retry_attempts
+                    .unwrap_or(MAX_RETRY_ATTEMPTS)
+                    .max(1),
+                config
+                    .retry_base_delay_ms
+                    .map_or(RETRY_BASE_DELAY, Duration::from_millis),
+            )
+        };
+
+        for attempt in 1..=max_attempts {
+            debug!(
+                "Executing PowerShell {} (attempt {}/{})",
+                operation, attempt, max_attempts
+            );
+
+            let attempt_start = Instant::now();
+            let result = tokio::time::timeout(
+                timeout,
+                self.runner.run_with_progress(script, &mut *on_progress),
+            )
+            .await;
+            self.trace_invocation(
+                operation,
+                self.powershell_executable(),
+                script,
+                attempt,
+                matches!(result, Ok(Ok(_))),
+                attempt_start.elapsed(),
+            );
+            attempts = attempt;
+
+            match result {
+                Ok(Ok(stdout)) => {
+                    debug!("PowerShell {} succeeded on attempt {}", operation, attempt);
+                    self.record_operation(operation, attempt, start, None).await;
+                    return Ok(stdout);
+                }
+                Ok(Err(
+                    error @ (AudioError::ModuleNotInstalled(_) | AudioError::ElevationRequired(_)),
+                )) => {
+                    // Retrying can't help until the module is installed or the app is
+                    // elevated
+                    warn!("PowerShell {} failed: {}", operation, error);
+                    last_error = Some(error);
+                    break;
+                }
+                Ok(Err(error)) => {
+                    warn!(
+                        "PowerShell {} failed on attempt {}: {}",
+                        operation, attempt, error
+                    );
+                    last_error = Some(error);
+                }
+                Err(_) => {
+                    let elapsed_ms = attempt_start.elapsed().as_millis() as u64;
+                    last_error = Some(AudioError::Timeout {
+                        operation: operation.to_string(),
+                        elapsed_ms,
+                    });
+                    warn!(
+                        "PowerShell {} timed out after {}ms on attempt {}",
+                        operation, elapsed_ms, attempt
+                    );
+                }
+            }
+
+            if attempt < max_attempts {
+                let delay = retry_base_delay
+                    .saturating_mul(attempt)
+                    .min(MAX_RETRY_DELAY);
+                debug!("Retrying {} in {}ms", operation, delay.as_millis());
+                tokio::time::sleep(delay).await;
+            }
+        }
+
+        error!(
+            "PowerShell {} failed after {} attempts",
+            operation, attempts
+        );
+        let error = last_error
+            .unwrap_or_else(|| AudioError::CommandFailed("Unknown PowerShell error".to_string()));
+        self.record_operation(operation, attempts, start, Some(&error))
+            .await;
+        Err(error)
+    }
+
+    /// Log one PowerShell invocation in full when tracing is on. Scripts are verbose, so
+    /// nothing is logged otherwise.
+    fn trace_invocation(
+        &self,
+        operation: &str,
+        executable: &str,
+        script: &str,
+        attempt: u32,
+        success: bool,
+        duration: Duration,
+    ) {
+        if !self.is_powershell_tracing() {
+            return;
+        }
+        debug!(
+            session = %self.session_id,
+            executable,
+            operation,
+            attempt,
+            success,
+            duration_ms = duration.as_millis() as u64,
+            script,
+            "PowerShell invocation"
+        );
+    }
+
+    /// Append an operation to the bounded in-memory log
+    async fn record_operation(
+        &self,
+        operation: &str,
+        attempts: u32,
+        start: Instant,
+        error: Option<&AudioError>,
+    ) {
+        let mut state = self.state.write().await;
+        if state.operation_log.len() == OPERATION_LOG_CAPACITY {
+            state.operation_log.pop_front();
+        }
+        state.operation_log.push_back(OperationLogEntry {
+            timestamp: Utc::now(),
+            operation: operation.to_string(),
+            attempts,
+            duration_ms: start.elapsed().as_millis() as u64,
+            success: error.is_none(),
+            error: error.map(|e| e.to_string()),
+        });
+    }
+
+    /// Parse device list response from PowerShell, naming devices from `display_names`
+    fn parse_device_list_response(
+        &self,
+        json_output: &str,
+        display_names: &BTreeMap<String, String>,
+        on_device: &mut (dyn FnMut(&AudioDevice) + Send),
+    ) -> AudioResult<DeviceListResult> {
+        let response: serde_json::Value = parse_json_or_error(json_output, "device enumeration")?;
+
+        if let Some(error) = response.get("error") {
+            return Err(AudioError::CommandFailed(
+                error.as_str().unwrap_or("Unknown error").to_string(),
+            ));
+        }
+
+        let response: PowerShellDeviceResponse = serde_json::from_value(response)
+            .map_err(|e| AudioError::ParseError(format!("device enumeration: {}", e)))?;
+        debug!(
+            "Device list from {} at {}",
+            response.session, response.timestamp
+        );
+
+        let mut list = DeviceListResult::default();
+        for value in response.devices {
+            let name = value["name"]
+                .as_str()
+                .or(value["id"].as_str())
+                .unwrap_or("unnamed device")
+                .to_string();
+            let parsed = serde_json::from_value::<RawDevice>(value)
+                .map_err(|e| AudioError::ParseError(e.to_string()))
+                .and_then(AudioDevice::try_from);
+            match parsed {
+                Ok(mut audio_device) => {
+                    audio_device.display_name = display_names.get(&audio_device.id).cloned();
+                    on_device(&audio_device);
+                    list.devices.push(audio_device);
+                }
+                Err(e) => {
+                    let reason = match e {
+                        AudioError::ParseError(reason) => reason,
+                        e => e.to_string(),
+                    };
+                    warn!("Skipping device {}: {}", name, reason);
+                    list.skipped.push((name, reason));
+                }
+            }
+        }
+
+        Ok(list)
+    }
+
+    /// Save devices snapshot as a JSON line into the db_path. Best-effort, append-only.
+    async fn save_devices_snapshot(
+        db_path: &PathBuf,
+        devices: &Vec<AudioDevice>,
+        session_id: &str,
+    ) -> Result<(), String> {
+        // Ensure parent dir exists
+        if let Some(parent) = db_path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                return Err(format!("Failed to create db dir: {}", e));
+            }
+        }
+
+        let file_res = OpenOptions::new().create(true).append(true).open(db_path);
+
+        let mut file = match file_res {
+            Ok(f) => f,
+            Err(e) => return Err(format!("Failed to open db file: {}", e)),
+        };
+
+        let timestamp: DateTime<Utc> = Utc::now();
+        let snapshot = serde_json::json!({
+            "timestamp": timestamp.to_rfc3339(),
+            "session": session_id,
+            "count": devices.len(),
+            "devices": devices,
+        });
+
+        let line = match serde_json::to_string(&snapshot) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("Failed to serialize snapshot: {}", e)),
+        };
+
+        if let Err(e) = writeln!(file, "{}", line) {
+            return Err(format!("Failed to write snapshot: {}", e));
+        }
+
+        Ok(())
+    }
+
+    /// Load recent snapshots from the JSONL DB (returns up to `limit` latest entries)
+    pub fn load_devices_snapshot(
+        db_path: &PathBuf,
+        limit: usize,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let f = File::open(db_path).map_err(|e| format!("Failed to open db file: {}", e))?;
+        let reader = BufReader::new(f);
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            match line {
+                Ok(l) => match serde_json::from_str::<serde_json::Value>(&l) {
+                    Ok(v) => entries.push(v),
+                    Err(_) => continue,
+                },
+                Err(_) => continue,
+            }
+        }
+
+        // return latest `limit` entries
+        if entries.len() > limit {
+            let start = entries.len() - limit;
+            Ok(entries[start..].to_vec())
+        } else {
+            Ok(entries)
+        }
+    }
+
+    /// Change default device implementation for the given role(s)
+    async fn change_default_device(&self, device_id: &str, role: DefaultRole) -> AudioResult<()> {
+        if self.is_dry_run() {
+            info!("Dry run: would make {} the default ({:?})", device_id, role);
+            self.simulate_default(device_id, role).await;
+            return Ok(());
+        }
+
+        let id = device_id.to_string();
+        if let Some(result) = self
+            .with_native_backend(move |backend| backend.set_default(&id, role))
+            .await
+        {
+            result?;
+            return self.verify_default(device_id, role).await;
+        }
+
+        #[cfg(all(windows, feature = "native-wasapi"))]
+        match self.set_default_native(device_id, role).await {
+            Ok(()) => return self.verify_default(device_id, role).await,
+            Err(e) => warn!(
+                "Native switch to {} failed, falling back to AudioDeviceCmdlets: {}",
+                device_id, e
+            ),
+        }
+
+        let role_flags: &[&str] = match role {
+            DefaultRole::Multimedia => &["-DefaultOnly"],
+            DefaultRole::Communications => &["-CommunicationOnly"],
+            DefaultRole::Both => &["-DefaultOnly", "-CommunicationOnly"],
+        };
+        let quoted_id = powershell_string(device_id);
+        let role_commands = role_flags
+            .iter()
+            .map(|flag| format!("Set-AudioDevice -ID {} {}", quoted_id, flag))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        let powershell_script = format!(
+            r#"
+                try {{
+                    Import-Module AudioDeviceCmdlets -ErrorAction Stop
+                    
+                    $device = Get-AudioDevice -List | Where-Object {{ $_.ID -eq {0} }}
+                    
+                    if (-not $device) {{
+                        throw ("Device not found: " + {0})
+                    }}
+                    
+                    {1}
+                    
+                    @{{
+                        success = $true
+                        device_id = {0}
+                        device_name = $device.Name
+                        device_type = $device.Type
+                    }} | ConvertTo-Json -Compress
+                }}
+                catch {{
+                    @{{
+                        success = $false
+                        error = $_.Exception.Message
+                        device_id = {0}
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            quoted_id, role_commands
+        );
+
+        self.execute_powershell_with_retry(
+            &powershell_script,
+            "set default device",
+            self.command_timeout().await,
+        )
+        .await?;
+        self.verify_default(device_id, role).await
+    }
+
+    /// Make `device_id` the default for `role` through Core Audio's policy config,
+    /// without AudioDeviceCmdlets
+    #[cfg(all(windows, feature = "native-wasapi"))]
+    pub async fn set_default_native(&self, device_id: &str, role: DefaultRole) -> AudioResult<()> {
+        let id = device_id.to_string();
+        tokio::task::spawn_blocking(move || crate::wasapi::set_default_endpoint(&id, role))
+            .await
+            .map_err(|e| AudioError::Unknown(e.to_string()))?
+    }
+
+    /// Move the `role` flags of the cached devices to `device_id`, as a switch would
+    async fn simulate_default(&self, device_id: &str, role: DefaultRole) {
+        let mut state = self.state.write().await;
+        let Some(device_type) = state
+            .cached_devices
+            .get(device_id)
+            .map(|d| d.device_type.clone())
+        else {
+            warn!("Dry run: {} is not cached, nothing to simulate", device_id);
+            return;
+        };
+
+        for device in state
+            .cached_devices
+            .values_mut()
+            .filter(|d| d.device_type == device_type)
+        {
+            let is_target = device.id == device_id;
+            if role != DefaultRole::Communications {
+                device.is_default = is_target;
+            }
+            if role != DefaultRole::Multimedia {
+                device.is_communication_default = is_target;
+            }
+        }
+    }
+
+    /// Re-read the device list, bypassing the cache, and confirm `device_id` now holds
+    /// `role`. Windows sometimes accepts a switch on a locked endpoint without applying it.
+    async fn verify_default(&self, device_id: &str, role: DefaultRole) -> AudioResult<()> {
+        let list = self.fetch_devices().await?;
+        self.cache_device_list(&list, self.clock.now()).await;
+        if holds_default_role(&list.devices, device_id, role) {
+            return Ok(());
+        }
+
+        warn!(
+            "The switch reported success but {} is not the {:?} default",
+            device_id, role
+        );
+        Err(AudioError::CommandFailed(format!(
+            "Switch to {} did not take effect",
+            device_id
+        )))
+    }
+
+    /// Switch the playback and/or recording default together. If any switch fails the
+    /// previous defaults are restored so the pair never ends up half-applied.
+    async fn apply_defaults_atomic(
+        &self,
+        playback_id: Option<&str>,
+        recording_id: Option<&str>,
+    ) -> AudioResult<()> {
+        self.ensure_writable("change the default devices")?;
+        for id in playback_id.iter().chain(recording_id.iter()) {
+            self.validate_device_id(id).await?;
+        }
+
+        let current_defaults = self.get_current_defaults().await?;
+        let targets: Vec<(&str, DefaultRole)> = playback_id
+            .into_iter()
+            .chain(recording_id)
+            .map(|id| (id, DefaultRole::Both))
+            .collect();
+
+        let result = apply_or_roll_back(&targets, &current_defaults, |id, role| async move {
+            self.change_default_device(&id, role).await
+        })
+        .await;
+        self.invalidate_cache().await;
+        result
+    }
+
+    /// Set the endpoint volume of each device in one PowerShell round trip, or one call
+    /// per device on a native backend
+    async fn set_devices_volume(
+        &self,
+        device_ids: &[&String],
+        volume: u8,
+    ) -> AudioResult<Vec<DeviceOperationResult>> {
+        let ids: Vec<String> = device_ids.iter().map(|id| id.to_string()).collect();
+        if let Some(result) = self
+            .with_native_backend(move |backend| {
+                Ok(ids
+                    .into_iter()
+                    .map(|id| {
+                        let result = backend.set_volume(&id, volume);
+                        DeviceOperationResult {
+                            device_id: id,
+                            success: result.is_ok(),
+                            error: result.err().map(|e| e.to_string()),
+                        }
+                    })
+                    .collect())
+            })
+            .await
+        {
+            return result;
+        }
+
+        let id_list = device_ids
+            .iter()
+            .map(|id| powershell_string(id))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let powershell_script = format!(
+            r#"
+                try {{
+                    Import-Module AudioDeviceCmdlets -ErrorAction Stop
+                    
+                    $results = @()
+                    foreach ($id in @({})) {{
+                        try {{
+                            $device = Get-AudioDevice -ID $id
+                            $device.Device.AudioEndpointVolume.MasterVolumeLevelScalar = {:.2}
+                            $results += @{{ device_id = $id; success = $true }}
+                        }}
+                        catch {{
+                            $results += @{{ device_id = $id; success = $false; error = $_.Exception.Message }}
+                        }}
+                    }}
+                    
+                    ConvertTo-Json -InputObject @($results) -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            id_list,
+            f32::from(volume) / 100.0
+        );
+
+        let output = self
+            .execute_powershell_with_retry(
+                &powershell_script,
+                "set device volume",
+                self.command_timeout().await,
+            )
+            .await?;
+        parse_json_or_error(&output, "volume update")
+    }
+
+    /// Unmute a device and raise it to `min_volume` if it's currently below it
+    async fn ensure_audible(&self, device_id: &str, min_volume: Option<u8>) -> AudioResult<()> {
+        let min_scalar = f32::from(min_volume.unwrap_or(0)) / 100.0;
+        let powershell_script = format!(
+            r#"
+                try {{
+                    Import-Module AudioDeviceCmdlets -ErrorAction Stop
+                    
+                    $endpoint = (Get-AudioDevice -ID {}).Device.AudioEndpointVolume
+                    $wasMuted = $endpoint.Mute
+                    if ($wasMuted) {{
+                        $endpoint.Mute = $false
+                    }}
+                    if ($endpoint.MasterVolumeLevelScalar -lt {:.2}) {{
+                        $endpoint.MasterVolumeLevelScalar = {:.2}
+                    }}
+                    
+                    @{{
+                        was_muted = $wasMuted
+                    }} | ConvertTo-Json -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            powershell_string(device_id),
+            min_scalar,
+            min_scalar
+        );
+
+        let output = self
+            .execute_powershell_with_retry(
+                &powershell_script,
+                "unmute device",
+                self.command_timeout().await,
+            )
+            .await?;
+        let response: serde_json::Value = parse_json_or_error(&output, "unmute device")?;
+        if response["was_muted"].as_bool().unwrap_or(false) {
+            info!("Unmuted {} after auto-switch", device_id);
+        }
+
+        self.invalidate_cache().await;
+        Ok(())
+    }
+
+    /// Read the shared-mode default format and exclusive-mode flags of an endpoint
+    async fn read_endpoint_format_settings(
+        &self,
+        device_id: &str,
+    ) -> AudioResult<EndpointFormatSettings> {
+        let registry_path = endpoint_registry_path(device_id)?;
+        let powershell_script = with_registry64(format!(
+            r#"
+                try {{
+                    $props = Get-Registry64Values "{0}\Properties"
+                    if ($null -eq $props) {{ throw "Registry key not found: HKLM\{0}\Properties" }}
+                    $result = @{{}}
+                    
+                    # PKEY_AudioEngine_DeviceFormat: WAVEFORMATEX, optionally behind an 8-byte VT_BLOB header
+                    $format = $props.'{{f19f064d-082c-4e27-bc73-6882a1bb8e4c}},0'
+                    if ($format -is [byte[]]) {{
+                        $offset = if ($format.Length -ge 8 -and $format[0] -eq 0x41) {{ 8 }} else {{ 0 }}
+                        if ($format.Length -ge ($offset + 16)) {{
+                            $result.channels = [BitConverter]::ToUInt16($format, $offset + 2)
+                            $result.sample_rate = [BitConverter]::ToUInt32($format, $offset + 4)
+                            $result.bits_per_sample = [BitConverter]::ToUInt16($format, $offset + 14)
+                        }}
+                    }}
+                    
+                    $result.exclusive_allowed = $props.'{{b3f8fa53-0004-438e-9003-51a46e139bfc}},3'
+                    $result.exclusive_priority = $props.'{{b3f8fa53-0004-438e-9003-51a46e139bfc}},4'
+                    $result | ConvertTo-Json -Compress
+                }}
+                catch {{
+                    @{{
+                        error = $_.Exception.Message
+                    }} | ConvertTo-Json -Compress
+                    exit 1
+                }}
+            "#,
+            registry_path
+        ));
+
+        let output = self
+            .execute_powershell_with_retry(
+                &powershell_script,
+                "endpoint format read",
+                self.command_timeout().await,
+            )
+            .await?;
+        parse_json_or_error(&output, "format settings read")
+    }
+
+    /// Look up an alias, returning `None` if no such alias is defined
+    async fn lookup_alias(&self, alias: &str) -> AudioResult<Option<AliasResolution>> {
+        let Ok(alias) = normalize_alias(alias) else {
+            return Ok(None);
+        };
+        let Some(stable_key) = self.config.read().await.aliases.get(&alias).cloned() else {
+            return Ok(None);
+        };
+
+        let devices = self.get_audio_devices().await?;
+        let device = devices.into_iter().find(|d| d.stable_key() == stable_key);
+        Ok(Some(AliasResolution {
+            alias,
+            stable_key,
+            device,
+        }))
+    }
+
+    /// Get current default devices for every role, used for fallback
+    async fn get_current_defaults(&self) -> AudioResult<DefaultDevices> {
+        let devices = self.get_audio_devices().await?;
+
+        let find_default = |device_type: DeviceType, communication: bool| {
+            devices
+                .iter()
+                .find(|d| {
+                    d.device_type == device_type
+                        && if communication {
+                            d.is_communication_default
+                        } else {
+                            d.is_default
+                        }
+                })
+                .map(|d| d.id.clone())
+        };
+
+        Ok(DefaultDevices {
+            playback: find_default(DeviceType::Playback, false),
+            recording: find_default(DeviceType::Recording, false),
+            communication_playback: find_default(DeviceType::Playback, true),
+            communication_recording: find_default(DeviceType::Recording, true),
+        })
+    }
+
+    /// Fallback to previous device on failure, restoring the multimedia and communication
+    /// roles separately when they pointed at different devices
+    async fn fallback_to_previous_device(&self, defaults: &DefaultDevices) -> AudioResult<()> {
+        warn!("Attempting to fallback to previous default devices");
+
+        self.restore_roles(
+            "playback",
+            defaults.playback.as_deref(),
+            defaults.communication_playback.as_deref(),
+        )
+        .await;
+        self.restore_roles(
+            "recording",
+            defaults.recording.as_deref(),
+            defaults.communication_recording.as_deref(),
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// Restore one device type's multimedia and communication defaults, best-effort
+    async fn restore_roles(
+        &self,
+        label: &str,
+        multimedia: Option<&str>,
+        communication: Option<&str>,
+    ) {
+        for (device_id, role) in role_targets(multimedia, communication) {
+            match self.change_default_device(device_id, role).await {
+                Ok(_) => info!(
+                    "Successfully restored previous {} device ({:?}): {}",
+                    label, role, device_id
+                ),
+                Err(e) => error!(
+                    "Failed to restore previous {} device ({:?}): {}",
+                    label, role, e
+                ),
+            }
+        }
+    }
+
+    /// Run `operation` as a bulk operation: the cache invalidations it requests are
+    /// deferred and coalesced into one when it ends. Only invalidations made by
+    /// `operation` itself are deferred; a nested bulk operation joins the outer one.
+    async fn in_bulk_operation<T>(&self, operation: impl Future<Output = T>) -> T {
+        if BULK_INVALIDATION.try_with(|_| ()).is_ok() {
+            return operation.await;
+        }
+
+        let (result, deferred) = BULK_INVALIDATION
+            .scope(std::cell::Cell::new(false), async {
+                let result = operation.await;
+                (result, BULK_INVALIDATION.with(|deferred| deferred.get()))
+            })
+            .await;
+        if deferred {
+            debug!("Bulk operation finished, applying its deferred cache invalidation");
+            self.invalidate_cache().await;
+        }
+        result
+    }
+
+    /// Invalidate device cache
+    async fn invalidate_cache(&self) {
+        if self.is_dry_run() {
+            // The cache holds the simulated defaults; re-enumerating would discard them
+            debug!("Keeping the device cache in dry-run mode");
+            return;
+        }
+        if BULK_INVALIDATION
+            .try_with(|deferred| deferred.set(true))
+            .is_ok()
+        {
+            debug!("Deferring cache invalidation until the bulk operation ends");
+            return;
+        }
+
+        let mut state = self.state.write().await;
+        state.last_refresh = None;
+        state.cached_devices.clear();
+        debug!("Audio device cache invalidated");
+    }
+
+    /// Snapshot the current config into the `backups` directory next to `config.json`,
+    /// pruning the oldest backups beyond the configured limit
+    pub async fn backup_config(&self) -> AudioResult<String> {
+        let dir = self.backups_dir()?;
+        let config = self.config.read().await.clone();
+        let keep = config.backup_limit.unwrap_or(config::DEFAULT_BACKUP_LIMIT);
+        let backup = config::create_backup(&dir, &config, Utc::now(), keep)?;
+        info!("Backed up config to {}", backup.path);
+        Ok(backup.path)
+    }
+
+    /// Back up the config, then return every setting to its default: the config (profiles,
+    /// groups, aliases, hotkeys, quiet hours and all options, including the cache TTL and
+    /// retry settings), both priority chains and session overrides. Returns the backup's path so the reset can be
+    /// undone with `restore_config_backup`.
+    pub async fn factory_reset(&self) -> AudioResult<String> {
+        self.ensure_writable("reset all settings")?;
+        let backup = self.backup_config().await?;
+        warn!("Resetting all settings to defaults (backup: {})", backup);
+
+        self.update_config(|config| {
+            *config = AudioManagerConfig::default();
+            Ok(())
+        })
+        .await?;
+        self.priorities.clear().await?;
+        {
+            let mut state = self.state.write().await;
+            state.device_overrides.clear();
+        }
+        self.invalidate_cache().await;
+        Ok(backup)
+    }
+
+    /// Saved config backups, newest first
+    pub async fn list_config_backups(&self) -> AudioResult<Vec<ConfigBackup>> {
+        config::list_backups(&self.backups_dir()?)
+    }
+
+    /// Replace the current config with the backup named `name`
+    pub async fn restore_config_backup(&self, name: &str) -> AudioResult<()> {
+        let restored = config::load_backup(&self.backups_dir()?, name)?;
+        info!("Restoring config from backup {}", name);
+        self.update_config(|config| {
+            *config = restored;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Configure how many config backups are kept (`None` restores the default)
+    pub async fn set_config_backup_limit(&self, limit: Option<usize>) -> AudioResult<()> {
+        if limit == Some(0) {
+            return Err(AudioError::InvalidArgument(
+                "At least one backup must be kept".to_string(),
+            ));
+        }
+        self.update_config(|config| {
+            config.backup_limit = limit;
+            Ok(())
+        })
+        .await
+    }
+
+    fn backups_dir(&self) -> AudioResult<PathBuf> {
+        self.config_path
+            .as_deref()
+            .and_then(|path| path.parent())
+            .map(|dir| dir.join("backups"))
+            .ok_or_else(|| {
+                AudioError::InvalidConfig("No config location to back up to".to_string())
+            })
+    }
+
+    /// Apply a change to the config and persist it. The in-memory config is only
+    /// replaced once the write succeeds. Refused in read-only mode.
+    async fn update_config<R>(
+        &self,
+        mutate: impl FnOnce(&mut AudioManagerConfig) -> AudioResult<R>,
+    ) -> AudioResult<R> {
+        self.ensure_writable("change settings")?;
+        let mut config = self.config.write().await;
+        let mut updated = config.clone();
+        let result = mutate(&mut updated)?;
+        updated.validate_timing()?;
+
+        if let Some(path) = &self.config_path {
+            config::save_json_atomic(path, &updated)?;
+        }
+        *config = updated;
+        Ok(result)
+    }
+
+    /// Attach the app handle so the manager can emit events; later calls are ignored
+    pub fn attach_app_handle(&self, handle: AppHandle) {
+        let _ = self.app_handle.set(handle);
+    }
+
+    /// Emit an event to the frontend (no-op until an app handle is attached)
+    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Some(handle) = self.app_handle.get() {
+            if let Err(e) = handle.emit(event, payload) {
+                warn!("Failed to emit {}: {}", event, e);
+            }
+        }
+    }
+
+    /// Enable or disable read-only mode; reads keep working while it's on
+    pub fn set_read_only(&self, read_only: bool) {
+        info!(
+            "Read-only mode {}",
+            if read_only { "enabled" } else { "disabled" }
+        );
+        self.read_only.store(read_only, Ordering::SeqCst);
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::SeqCst)
+    }
+
+    /// Enable or disable dry-run mode, in which default-device switches are logged and
+    /// simulated on the cached device list instead of calling `Set-AudioDevice`. Device
+    /// IDs are still validated against the real device list, and the simulated defaults
+    /// last until the cache expires or is refreshed.
+    pub fn set_dry_run(&self, dry_run: bool) {
+        info!(
+            "Dry-run mode {}",
+            if dry_run { "enabled" } else { "disabled" }
+        );
+        self.dry_run.store(dry_run, Ordering::SeqCst);
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run.load(Ordering::SeqCst)
+    }
+
+    /// Enable or disable full logging of PowerShell invocations (at debug level)
+    pub fn set_powershell_tracing(&self, enabled: bool) {
+        info!(
+            "PowerShell tracing {}",
+            if enabled { "enabled" } else { "disabled" }
+        );
+        self.trace_powershell.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_powershell_tracing(&self) -> bool {
+        self.trace_powershell.load(Ordering::SeqCst)
+    }
+
+    fn powershell_executable(&self) -> &'static str {
+        *self
+            .powershell_exe
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Fail with `AudioError::ReadOnly` before any system state is touched
+    pub fn ensure_writable(&self, operation: &str) -> AudioResult<()> {
+        if self.is_read_only() {
+            warn!("Blocked '{}' in read-only mode", operation);
+            return Err(AudioError::ReadOnly(operation.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Get session ID for debugging
+    pub fn get_session_id(&self) -> &str {
+        &self.session_id
+    }
+}
+
+/// Attach nicknames to natively listed devices, handing each one to `on_device`
+fn label_devices(
+    mut devices: Vec<AudioDevice>,
+    display_names: &BTreeMap<String, String>,
+    on_device: &mut (dyn FnMut(&AudioDevice) + Send),
+) -> Vec<AudioDevice> {
+    for device in &mut devices {
+        device.display_name = display_names.get(&device.id).cloned();
+        on_device(device);
+    }
+    devices
+}
+
+/// Build a non-interactive PowerShell invocation that is killed if its future is dropped
+pub(crate) fn powershell_command(executable: &str, script: &str) -> Command {
+    let mut command = Command::new(executable);
+    command
+        .args([
+            "-ExecutionPolicy",
+            "Bypass",
+            "-NoProfile",
+            "-Command",
+            script,
+        ])
+        .kill_on_drop(true);
+    command
+}
+
+/// The typed error for a failure a script reported on stdout that retrying can't fix:
+/// the explicit `type` when the script set one, otherwise recognized from the message
+/// PowerShell raised (a failed `Import-Module`, or installing for all users unelevated)
+pub(crate) fn structured_error(stdout: &[u8]) -> Option<AudioError> {
+    let response: serde_json::Value = serde_json::from_slice(stdout).ok()?;
+    let message = response["error"].as_str().unwrap_or_default();
+    if response["type"] == "ModuleNotInstalled"
+        || (message.contains("AudioDeviceCmdlets") && message.contains("was not loaded"))
+    {
+        Some(AudioError::ModuleNotInstalled(
+            "AudioDeviceCmdlets module".to_string(),
+        ))
+    } else if response["type"] == "ElevationRequired"
+        || message.contains("Administrator rights are required")
+    {
+        Some(AudioError::ElevationRequired(message.to_string()))
+    } else {
+        None
+    }
+}
+
+/// Friendly names for the endpoint property keys worth recognizing in a property dump
+const KNOWN_PROPERTY_KEYS: [(&str, &str); 14] = [
+    (
+        "{a45c254e-df1c-4efd-8020-67d146a850e0},2",
+        "DeviceDescription",
+    ),
+    ("{a45c254e-df1c-4efd-8020-67d146a850e0},14", "FriendlyName"),
+    (
+        "{a45c254e-df1c-4efd-8020-67d146a850e0},24",
+        "EnumeratorName",
+    ),
+    (
+        "{026e516e-b814-414b-83cd-856d6fef4822},2",
+        "InterfaceFriendlyName",
+    ),
+    ("{1da5d803-d492-4edd-8c23-e0c0ffee7f0e},0", "FormFactor"),
+    (
+        "{1da5d803-d492-4edd-8c23-e0c0ffee7f0e},1",
+        "ControlPanelPageProvider",
+    ),
+    ("{1da5d803-d492-4edd-8c23-e0c0ffee7f0e},2", "Association"),
+    (
+        "{1da5d803-d492-4edd-8c23-e0c0ffee7f0e},3",
+        "PhysicalSpeakers",
+    ),
+    ("{1da5d803-d492-4edd-8c23-e0c0ffee7f0e},4", "EndpointGuid"),
+    ("{1da5d803-d492-4edd-8c23-e0c0ffee7f0e},5", "DisableSysFx"),
+    ("{1da5d803-d492-4edd-8c23-e0c0ffee7f0e},8", "JackSubType"),
+    ("{259abffc-50a7-47ce-af08-68c9a7d73366},12", "IconPath"),
+    (
+        "{b3f8fa53-0004-438e-9003-51a46e139bfc},3",
+        "ExclusiveModeAllowed",
+    ),
+    (
+        "{b3f8fa53-0004-438e-9003-51a46e139bfc},4",
+        "ExclusiveModePriority",
+    ),
+];
+
+/// Rename the keys of a raw property dump found in `KNOWN_PROPERTY_KEYS`, matching the
+/// registry's `{fmtid},pid` form case-insensitively; other keys are kept as they are
+fn name_known_properties(raw: HashMap<String, String>) -> HashMap<String, String> {
+    raw.into_iter()
+        .map(|(key, value)| {
+            let name = KNOWN_PROPERTY_KEYS
+                .iter()
+                .find(|(known, _)| known.eq_ignore_ascii_case(&key))
+                .map_or(key, |(_, name)| name.to_string());
+            (name, value)
+        })
+        .collect()
+}
+
+/// First PowerShell executable on the PATH that starts successfully, preferring
+/// Windows PowerShell over PowerShell 7
+async fn detect_powershell() -> Option<&'static str> {
+    for candidate in POWERSHELL_EXECUTABLES {
+        let probe = tokio::time::timeout(
+            COMMAND_TIMEOUT,
+            powershell_command(candidate, "$PSVersionTable.PSVersion.ToString()").output(),
+        )
+        .await;
+        match probe {
+            Ok(Ok(output)) if output.status.success() => return Some(candidate),
+            Ok(Ok(_)) => debug!("{} exited with an error", candidate),
+            Ok(Err(e)) => debug!("{} is not available: {}", candidate, e),
+            Err(_) => debug!("{} timed out during probe", candidate),
+        }
+    }
+    None
+}
+
+/// Application names are matched case-insensitively and without an `.exe` suffix
+fn normalize_app_name(app_name: &str) -> AudioResult<String> {
+    let name = app_name.trim().to_lowercase();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+    if name.is_empty() {
+        return Err(AudioError::InvalidArgument(
+            "Application name cannot be empty".to_string(),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// Quote `value` as a double-quoted PowerShell string literal, so a device ID or path
+/// can't end the string early or expand variables inside the script. Backticks, `$` and
+/// every character PowerShell accepts as a double quote are escaped with a backtick.
+fn powershell_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if matches!(c, '`' | '$' | '"' | '\u{201C}' | '\u{201D}' | '\u{201E}') {
+            quoted.push('`');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Aliases are matched case-insensitively and must not be blank
+fn normalize_alias(alias: &str) -> AudioResult<String> {
+    let alias = alias.trim().to_lowercase();
+    if alias.is_empty() {
+        return Err(AudioError::InvalidArgument(
+            "Alias cannot be empty".to_string(),
+        ));
+    }
+    Ok(alias)
+}
+
+/// Parse PowerShell JSON output, keeping the start of the raw output in the error so a
+/// failure shows what was actually printed
+fn parse_json_or_error<T: DeserializeOwned>(raw: &str, context: &str) -> AudioResult<T> {
+    serde_json::from_str(raw).map_err(|e| {
+        let snippet: String = raw.chars().take(PARSE_ERROR_SNIPPET_CHARS).collect();
+        let ellipsis = if raw.chars().count() > PARSE_ERROR_SNIPPET_CHARS {
+            "..."
+        } else {
+            ""
+        };
+        AudioError::ParseError(format!(
+            "{}: {} (output: {:?}{})",
+            context, e, snippet, ellipsis
+        ))
+    })
+}
+
+/// The default device of `device_type`, if any
+fn default_device(devices: Vec<AudioDevice>, device_type: &DeviceType) -> Option<AudioDevice> {
+    devices
+        .into_iter()
+        .find(|d| d.device_type == *device_type && d.is_default)
+}
+
+/// The endpoint Windows itself would use: its current default if still active, otherwise
+/// the first active device in enumeration order
+fn windows_preferred_device(
+    devices: Vec<AudioDevice>,
+    device_type: &DeviceType,
+) -> Option<AudioDevice> {
+    let mut candidates: Vec<AudioDevice> = devices
+        .into_iter()
+        .filter(|d| d.device_type == *device_type && d.state == DeviceState::Active)
+        .collect();
+    if let Some(position) = candidates.iter().position(|d| d.is_default) {
+        return Some(candidates.swap_remove(position));
+    }
+    candidates.sort_by_key(|d| d.index.unwrap_or(u32::MAX));
+    candidates.into_iter().next()
+}
+
+/// Pairs of devices whose default sample rates differ. Rates from different families
+/// (44.1 kHz vs 48 kHz multiples) are called out since they can't be converted exactly.
+fn format_conflicts(formats: &[(AudioDevice, AudioFormat)]) -> Vec<FormatConflict> {
+    let mut conflicts = Vec::new();
+    for (i, (device_a, format_a)) in formats.iter().enumerate() {
+        for (device_b, format_b) in &formats[i + 1..] {
+            if format_a.sample_rate == format_b.sample_rate {
+                continue;
+            }
+
+            let family = |rate: u32| (rate % 11_025 == 0, rate % 8_000 == 0);
+            let same_family = family(format_a.sample_rate) == family(format_b.sample_rate);
+            let mut reason = format!(
+                "{} runs at {} Hz but {} runs at {} Hz",
+                device_a.name, format_a.sample_rate, device_b.name, format_b.sample_rate
+            );
+            if !same_family {
+                reason.push_str("; the rates can't be converted exactly, expect drift");
+            }
+
+            conflicts.push(FormatConflict {
+                device_a: device_a.id.clone(),
+                device_b: device_b.id.clone(),
+                reason,
+            });
+        }
+    }
+    conflicts
+}
+
+/// Build the provider list from registry entries (`id` and `name` maps), always starting
+/// with the built-in Windows Sonic and skipping entries without a usable name
+fn spatial_providers(registered: Vec<HashMap<String, String>>) -> Vec<SpatialProvider> {
+    let mut providers = vec![SpatialProvider {
+        id: WINDOWS_SONIC_ID.to_string(),
+        name: "Windows Sonic for Headphones".to_string(),
+        built_in: true,
+    }];
+
+    for entry in registered {
+        let (Some(id), Some(name)) = (entry.get("id"), entry.get("name")) else {
+            continue;
+        };
+        let name = name.trim();
+        if name.is_empty() || name.to_lowercase().contains("windows sonic") {
+            continue;
+        }
+        providers.push(SpatialProvider {
+            id: id.clone(),
+            name: name.to_string(),
+            built_in: false,
+        });
+    }
+    providers
+}
+
+/// Registry encoding of a VT_BOOL PROPVARIANT: type tag, padding, then the VARIANT_BOOL
+/// (-1 for true) at offset 8
+fn loudness_blob(enabled: bool) -> [u8; 12] {
+    let mut blob = [0u8; 12];
+    blob[..2].copy_from_slice(&VT_BOOL.to_le_bytes());
+    blob[4] = 1;
+    if enabled {
+        blob[8..10].copy_from_slice(&[0xff, 0xff]);
+    }
+    blob
+}
+
+/// Read a VT_BOOL PROPVARIANT as written by `loudness_blob` (or by the Sound control panel)
+fn parse_loudness_blob(blob: &[u8]) -> Option<bool> {
+    if blob.len() < 10 || u16::from_le_bytes([blob[0], blob[1]]) != VT_BOOL {
+        return None;
+    }
+    Some(blob[8] != 0 || blob[9] != 0)
+}
+
+/// Decision-history reason for switching to `device`
+fn switched_reason(device: &AudioDevice, priority: usize) -> String {
+    format!(
+        "switched to {} (priority {}, {})",
+        device.name,
+        priority,
+        device.state.as_str()
+    )
+}
+
+/// Executable name of the process owning the foreground window; always `None` off Windows
+fn foreground_process_name() -> Option<String> {
+    #[cfg(windows)]
+    {
+        crate::focus::foreground_process_name()
+    }
+    #[cfg(not(windows))]
+    {
+        None
+    }
+}
+
+/// Whether the machine is running on battery; always `false` off Windows
+fn on_battery() -> bool {
+    #[cfg(windows)]
+    {
+        crate::power::on_battery()
+    }
+    #[cfg(not(windows))]
+    {
+        false
+    }
+}
+
+/// Defaults of `device_type` held by a device that isn't Active, suggesting the first
+/// Active device in `chain`, or any Active device of the type when none of it is
+fn default_anomalies(
+    devices: &[AudioDevice],
+    device_type: &DeviceType,
+    chain: &[DevicePriority],
+) -> Vec<DefaultAnomaly> {
+    let is_active =
+        |d: &&AudioDevice| d.device_type == *device_type && d.state == DeviceState::Active;
+    let suggested = chain
+        .iter()
+        .find_map(|entry| {
+            devices
+                .iter()
+                .filter(is_active)
+                .find(|d| d.id == entry.device_id)
+        })
+        .or_else(|| devices.iter().find(is_active));
+
+    let defaults = RoleDefaults::from_devices(devices);
+    let (multimedia, communication) = match device_type {
+        DeviceType::Playback => (
+            defaults.playback_multimedia,
+            defaults.playback_communication,
+        ),
+        DeviceType::Recording => (
+            defaults.recording_multimedia,
+            defaults.recording_communication,
+        ),
+    };
+    let roles = match (multimedia, communication) {
+        (Some(m), Some(c)) if m.id == c.id => vec![(m, DefaultRole::Both)],
+        (m, c) => m
+            .map(|m| (m, DefaultRole::Multimedia))
+            .into_iter()
+            .chain(c.map(|c| (c, DefaultRole::Communications)))
+            .collect(),
+    };
+
+    roles
+        .into_iter()
+        .filter(|(device, _)| device.state != DeviceState::Active)
+        .map(|(device, role)| DefaultAnomaly {
+            device_type: device_type.clone(),
+            role,
+            device_id: device.id,
+            device_name: device.name,
+            state: device.state,
+            suggested: suggested.cloned(),
+        })
+        .collect()
+}
+
+/// The default role system sounds follow on `device`, or why they can't be routed to it
+fn system_sounds_role(
+    device: &AudioDevice,
+    capabilities: &BackendCapabilities,
+) -> AudioResult<DefaultRole> {
+    if !capabilities.can_route_system_sounds {
+        return Err(AudioError::Unsupported(
+            "No available backend can route system sounds".to_string(),
+        ));
+    }
+    if device.device_type != DeviceType::Playback {
+        return Err(AudioError::InvalidArgument(format!(
+            "System sounds need a playback device, but {} is a recording device",
+            device.name
+        )));
+    }
+    Ok(DefaultRole::Communications)
+}
+
+/// What the watcher compares between polls: ID, state and default flag of each device,
+/// sorted by ID
+fn watch_snapshot(devices: &[AudioDevice]) -> Vec<(String, &'static str, bool)> {
+    let mut snapshot: Vec<(String, &'static str, bool)> = devices
+        .iter()
+        .map(|d| (d.id.clone(), d.state.as_str(), d.is_default))
+        .collect();
+    snapshot.sort();
+    snapshot
+}
+
+/// Devices present in both the `previous` snapshot and `devices` whose state differs.
+/// Devices that appeared or disappeared aren't state changes.
+fn device_state_changes(
+    previous: &[(String, &'static str, bool)],
+    devices: &[AudioDevice],
+) -> Vec<DeviceStateChange> {
+    devices
+        .iter()
+        .filter_map(|device| {
+            let (_, old_state, _) = previous.iter().find(|(id, _, _)| *id == device.id)?;
+            (*old_state != device.state.as_str()).then(|| DeviceStateChange {
+                device_id: device.id.clone(),
+                device_name: device.name.clone(),
+                old_state: DeviceState::parse(old_state),
+                new_state: device.state.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Devices added, removed or changed in state between the `previous` snapshot and `devices`
+fn device_change(
+    previous: &[(String, &'static str, bool)],
+    devices: &[AudioDevice],
+) -> DeviceChange {
+    let mut change = DeviceChange::default();
+    for device in devices {
+        match previous.iter().find(|(id, _, _)| *id == device.id) {
+            None => change.added.push(device.clone()),
+            Some((_, old_state, _)) if *old_state != device.state.as_str() => {
+                change.state_changed.push(device.clone())
+            }
+            Some(_) => {}
+        }
+    }
+    change.removed = previous
+        .iter()
+        .filter(|(id, _, _)| !devices.iter().any(|d| d.id == *id))
+        .map(|(id, _, _)| id.clone())
+        .collect();
+    change
+}
+
+/// The defaults of `device_type` that switching `role` to `device_id` displaces, each with
+/// the role it held. A device that held both roles gets one `Both` entry; `device_id`
+/// itself is left out.
+fn displaced_defaults(
+    outgoing: &DefaultDevices,
+    device_type: &DeviceType,
+    role: DefaultRole,
+    device_id: &str,
+) -> Vec<(String, DefaultRole)> {
+    let (multimedia, communication) = match device_type {
+        DeviceType::Playback => (&outgoing.playback, &outgoing.communication_playback),
+        DeviceType::Recording => (&outgoing.recording, &outgoing.communication_recording),
+    };
+    let displaced = |previous: &Option<String>| previous.clone().filter(|id| id != device_id);
+    let (multimedia, communication) = match role {
+        DefaultRole::Multimedia => (displaced(multimedia), None),
+        DefaultRole::Communications => (None, displaced(communication)),
+        DefaultRole::Both => (displaced(multimedia), displaced(communication)),
+    };
+    match (multimedia, communication) {
+        (Some(multimedia), Some(communication)) if multimedia == communication => {
+            vec![(multimedia, DefaultRole::Both)]
+        }
+        (multimedia, communication) => multimedia
+            .map(|id| (id, DefaultRole::Multimedia))
+            .into_iter()
+            .chain(communication.map(|id| (id, DefaultRole::Communications)))
+            .collect(),
+    }
+}
+
+/// Whether two PnP container IDs name the same container. Comparison ignores case and
+/// braces; the null GUID marks endpoints without a container and never matches.
+fn same_container(a: &str, b: &str) -> bool {
+    canonical_container_id(a).is_some_and(|a| Some(a) == canonical_container_id(b))
+}
+
+/// Lowercase container ID without braces, or `None` for the null GUID and empty IDs
+fn canonical_container_id(id: &str) -> Option<String> {
+    let id = id
+        .trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .to_lowercase();
+    (!id.is_empty() && id != "00000000-0000-0000-0000-000000000000").then_some(id)
+}
+
+/// Group `(endpoint ID, container ID)` pairs by canonical container ID, with each
+/// group's endpoint IDs lowercased and sorted. Endpoints without a container are left out.
+fn group_by_container(endpoints: &[(String, String)]) -> HashMap<String, Vec<String>> {
+    let mut containers: HashMap<String, Vec<String>> = HashMap::new();
+    for (endpoint_id, container_id) in endpoints {
+        if let Some(container_id) = canonical_container_id(container_id) {
+            containers
+                .entry(container_id)
+                .or_default()
+                .push(endpoint_id.to_lowercase());
+        }
+    }
+    for endpoint_ids in containers.values_mut() {
+        endpoint_ids.sort();
+    }
+    containers
+}
+
+/// Canonical container ID of an endpoint in a `group_by_container` map, ignoring the
+/// case of the endpoint ID
+fn endpoint_container<'a>(
+    containers: &'a HashMap<String, Vec<String>>,
+    endpoint_id: &str,
+) -> Option<&'a str> {
+    let endpoint_id = endpoint_id.to_lowercase();
+    containers
+        .iter()
+        .find(|(_, endpoint_ids)| endpoint_ids.contains(&endpoint_id))
+        .map(|(container_id, _)| container_id.as_str())
+}
+
+/// Split a `DeviceIcon` reference like `%windir%\system32\mmres.dll,-3004` into the
+/// resource file and icon index (negative values are resource IDs)
+fn parse_icon_reference(reference: &str) -> Option<(&str, i32)> {
+    let (file, index) = reference.trim().rsplit_once(',')?;
+    let file = file.trim().trim_matches('"');
+    if file.is_empty() {
+        return None;
+    }
+    Some((file, index.trim().parse().ok()?))
+}
+
+/// Call `fetch` every `interval` until it returns an Active device matching `pattern`,
+/// giving up with `AudioError::Timeout` after `timeout`
+async fn poll_for_device<F, Fut>(
+    mut fetch: F,
+    pattern: &str,
+    match_mode: MatchMode,
+    interval: Duration,
+    timeout: Duration,
+) -> AudioResult<AudioDevice>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = AudioResult<Vec<AudioDevice>>>,
+{
+    let start = Instant::now();
+    loop {
+        let found = fetch()
+            .await?
+            .into_iter()
+            .find(|d| d.state == DeviceState::Active && match_mode.matches(&d.name, pattern));
+        if let Some(device) = found {
+            info!("Device '{}' appeared as {}", pattern, device.name);
+            return Ok(device);
+        }
+
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            return Err(AudioError::Timeout {
+                operation: format!("wait for device '{}'", pattern),
+                elapsed_ms: elapsed.as_millis() as u64,
+            });
+        }
+        tokio::time::sleep(interval.min(timeout - elapsed)).await;
+    }
+}
+
+/// Whether dotted version `candidate` is newer than `current`, comparing numeric
+/// components and treating missing ones as zero ("3.1" == "3.1.0.0")
+fn is_newer_version(candidate: &str, current: &str) -> bool {
+    let parts = |version: &str| -> Vec<u64> {
+        version
+            .trim()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    let (candidate, current) = (parts(candidate), parts(current));
+    let len = candidate.len().max(current.len());
+    let component = |parts: &[u64], i: usize| parts.get(i).copied().unwrap_or(0);
+    (0..len)
+        .map(|i| component(&candidate, i).cmp(&component(&current, i)))
+        .find(|ordering| ordering.is_ne())
+        .is_some_and(|ordering| ordering.is_gt())
+}
+
+/// What pressing a hotkey comes down to given the current devices
+#[derive(Debug, PartialEq)]
+enum HotkeyStep {
+    Switch {
+        id: String,
+        device_type: DeviceType,
+        role: DefaultRole,
+    },
+    PanicRestore,
+    ApplyProfile(String),
+    CyclePriority(DeviceType),
+    SwitchToSlot(DeviceType, usize),
+    Nothing,
+}
+
+fn hotkey_step(action: &HotkeyAction, devices: &[AudioDevice]) -> HotkeyStep {
+    let switch_for = |device: Option<&AudioDevice>, role: DefaultRole| match device {
+        Some(d) => HotkeyStep::Switch {
+            id: d.id.clone(),
+            device_type: d.device_type.clone(),
+            role,
+        },
+        None => HotkeyStep::Nothing,
+    };
+    let switch_to = |device: Option<&AudioDevice>| switch_for(device, DefaultRole::Both);
+
+    match action {
+        HotkeyAction::SwitchToDevice(id) => switch_to(devices.iter().find(|d| d.id == *id)),
+        HotkeyAction::CycleDefault(device_type) => switch_to(next_default(devices, device_type)),
+        HotkeyAction::ToggleBetween(first, second) => {
+            let first_is_default = devices.iter().any(|d| d.id == *first && d.is_default);
+            let target = if first_is_default { second } else { first };
+            switch_to(devices.iter().find(|d| d.id == *target))
+        }
+        HotkeyAction::ToggleCommunication(first, second) => {
+            let first_is_default = devices
+                .iter()
+                .any(|d| d.id == *first && d.is_communication_default);
+            let target = if first_is_default { second } else { first };
+            switch_for(
+                devices.iter().find(|d| d.id == *target),
+                DefaultRole::Communications,
+            )
+        }
+        HotkeyAction::PanicRestore => HotkeyStep::PanicRestore,
+        HotkeyAction::ApplyProfile(name) => HotkeyStep::ApplyProfile(name.clone()),
+        // These need the priority chain, which the manager looks up
+        HotkeyAction::CyclePriority(device_type) => HotkeyStep::CyclePriority(device_type.clone()),
+        HotkeyAction::SwitchToSlot(device_type, slot) => {
+            HotkeyStep::SwitchToSlot(device_type.clone(), *slot)
+        }
+    }
+}
+
+/// The active device of `device_type` after the current default in index order,
+/// wrapping around; `None` when no other active device exists
+fn next_default<'a>(
+    devices: &'a [AudioDevice],
+    device_type: &DeviceType,
+) -> Option<&'a AudioDevice> {
+    let mut candidates: Vec<&AudioDevice> = devices
+        .iter()
+        .filter(|d| d.device_type == *device_type && d.state == DeviceState::Active)
+        .collect();
+    candidates.sort_by(|a, b| {
+        (a.index.unwrap_or(u32::MAX), &a.name).cmp(&(b.index.unwrap_or(u32::MAX), &b.name))
+    });
+
+    let next = match candidates.iter().position(|d| d.is_default) {
+        Some(current) => candidates[(current + 1) % candidates.len()],
+        None => candidates.first()?,
+    };
+    (!next.is_default).then_some(next)
+}
+
+/// The device in priority `slot` of `chain`, when it's connected and Active
+fn slot_device<'a>(
+    chain: &[DevicePriority],
+    devices: &'a [AudioDevice],
+    slot: usize,
+) -> Option<&'a AudioDevice> {
+    let entry = chain.iter().find(|entry| entry.priority == slot)?;
+    devices
+        .iter()
+        .find(|d| d.id == entry.device_id && d.state == DeviceState::Active)
+}
+
+/// The Active chain member after the one holding `role` (the multimedia default for
+/// `Both`), wrapping around, or the top one when no member holds it. `None` when fewer
+/// than two members are Active.
+fn next_in_chain<'a>(
+    chain: &[DevicePriority],
+    devices: &'a [AudioDevice],
+    role: DefaultRole,
+) -> Option<&'a AudioDevice> {
+    let available: Vec<&AudioDevice> = chain
+        .iter()
+        .filter_map(|entry| {
+            devices
+                .iter()
+                .find(|d| d.id == entry.device_id && d.state == DeviceState::Active)
+        })
+        .collect();
+    if available.len() < 2 {
+        return None;
+    }
+
+    let holds_role = |d: &AudioDevice| match role {
+        DefaultRole::Communications => d.is_communication_default,
+        DefaultRole::Multimedia | DefaultRole::Both => d.is_default,
+    };
+    Some(match available.iter().position(|d| holds_role(d)) {
+        Some(current) => available[(current + 1) % available.len()],
+        None => available[0],
+    })
+}
+
+/// Whether `device_id` is the default for every role in `role`
+fn holds_default_role(devices: &[AudioDevice], device_id: &str, role: DefaultRole) -> bool {
+    devices.iter().any(|d| {
+        d.id == device_id
+            && match role {
+                DefaultRole::Multimedia => d.is_default,
+                DefaultRole::Communications => d.is_communication_default,
+                DefaultRole::Both => d.is_default && d.is_communication_default,
+            }
+    })
+}
+
+/// Switches that restore one device type's multimedia and communication defaults,
+/// combined into one when both roles were on the same device
+fn role_targets<'a>(
+    multimedia: Option<&'a str>,
+    communication: Option<&'a str>,
+) -> Vec<(&'a str, DefaultRole)> {
+    match (multimedia, communication) {
+        (Some(m), Some(c)) if m == c => vec![(m, DefaultRole::Both)],
+        (m, c) => m
+            .map(|id| (id, DefaultRole::Multimedia))
+            .into_iter()
+            .chain(c.map(|id| (id, DefaultRole::Communications)))
+            .collect(),
+    }
+}
+
+/// Make each of `targets` the default for its role via `switch`. On the first failure,
+/// every role in `previous` is switched back (best-effort) and that failure is returned.
+async fn apply_or_roll_back<F, Fut>(
+    targets: &[(&str, DefaultRole)],
+    previous: &DefaultDevices,
+    mut switch: F,
+) -> AudioResult<()>
+where
+    F: FnMut(String, DefaultRole) -> Fut,
+    Fut: Future<Output = AudioResult<()>>,
+{
+    for (id, role) in targets {
+        let Err(e) = switch(id.to_string(), *role).await else {
+            continue;
+        };
+
+        error!("Failed to switch {} ({:?}), rolling back", id, role);
+        let restore = role_targets(
+            previous.playback.as_deref(),
+            previous.communication_playback.as_deref(),
+        )
+        .into_iter()
+        .chain(role_targets(
+            previous.recording.as_deref(),
+            previous.communication_recording.as_deref(),
+        ));
+        for (device_id, role) in restore {
+            if let Err(e) = switch(device_id.to_string(), role).await {
+                error!("Failed to restore {} ({:?}): {}", device_id, role, e);
+            }
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Device type encoded in an MMDevice ID's flow prefix (`{0.0.0` render, `{0.0.1` capture)
+fn endpoint_device_type(device_id: &str) -> Option<DeviceType> {
+    if device_id.starts_with("{0.0.0") {
+        Some(DeviceType::Playback)
+    } else if device_id.starts_with("{0.0.1") {
+        Some(DeviceType::Recording)
+    } else {
+        None
+    }
+}
+
+/// Prefix a registry-accessing script with the 64-bit registry view helpers
+fn with_registry64(script: String) -> String {
+    format!("{}{}", REGISTRY64_FUNCTIONS, script)
+}
+
+/// Registry key (under HKLM, for the `Registry64` helpers) holding an endpoint's property
+/// store, derived from an MMDevice ID such as `{0.0.0.00000000}.{guid}`
+fn endpoint_registry_path(device_id: &str) -> AudioResult<String> {
+    let (flow, guid) = device_id.split_once("}.").ok_or_else(|| {
+        AudioError::InvalidArgument(format!("Unrecognized device ID: {}", device_id))
+    })?;
+
+    let flow = if flow.starts_with("{0.0.0") {
+        "Render"
+    } else if flow.starts_with("{0.0.1") {
+        "Capture"
+    } else {
+        return Err(AudioError::InvalidArgument(format!(
+            "Unrecognized device ID: {}",
+            device_id
+        )));
+    };
+
+    Ok(format!(
+        r"SOFTWARE\Microsoft\Windows\CurrentVersion\MMDevices\Audio\{}\{}",
+        flow, guid
+    ))
+}
+
+/// Turn raw device settings into troubleshooting findings
+fn diagnosis_findings(
+    device: &AudioDevice,
+    settings: &EndpointFormatSettings,
+    engine_support: Option<&FormatSupport>,
+) -> Vec<DiagnosisFinding> {
+    let mut findings = Vec::new();
+    let mut push =
+        |severity, message: String| findings.push(DiagnosisFinding { severity, message });
+
+    if (device.is_default || device.is_communication_default)
+        && !matches!(device.state, DeviceState::Active)
+    {
+        push(
+            FindingSeverity::Error,
+            format!(
+                "'{}' is a default device but is {:?}; apps using the default will be silent",
+                device.name, device.state
+            ),
+        );
+    }
+
+    match settings.default_format() {
+        Some(format) => {
+            if let Some(support) = engine_support.filter(|s| !s.supported) {
+                push(
+                    FindingSeverity::Warning,
+                    match support.closest_match {
+                        Some(closest) => format!(
+                            "Default format {} Hz / {}-bit / {} ch is not accepted by the audio engine (closest: {} Hz / {}-bit / {} ch)",
+                            format.sample_rate, format.bits_per_sample, format.channels,
+                            closest.sample_rate, closest.bits_per_sample, closest.channels
+                        ),
+                        None => format!(
+                            "Default format {} Hz / {}-bit / {} ch is not accepted by the audio engine",
+                            format.sample_rate, format.bits_per_sample, format.channels
+                        ),
+                    },
+                );
+            }
+            if format.sample_rate != 44_100 && format.sample_rate != 48_000 {
+                push(
+                    FindingSeverity::Warning,
+                    format!(
+                        "Default sample rate {} Hz differs from the 44.1/48 kHz most apps request; resampling can cause crackling",
+                        format.sample_rate
+                    ),
+                );
+            }
+        }
+        None => push(
+            FindingSeverity::Info,
+            "Default format could not be read".to_string(),
+        ),
+    }
+
+    if settings.exclusive_allowed.is_some_and(|v| v != 0) {
+        let message = if settings.exclusive_priority.is_some_and(|v| v != 0) {
+            "Exclusive mode is enabled with priority; one app can take over the device and silence others"
+        } else {
+            "Exclusive mode is enabled; apps may lock the device to their own format"
+        };
+        push(FindingSeverity::Info, message.to_string());
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::powershell_host::{canned_output, ScriptOutput};
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn cache_is_fresh_until_ttl_elapses() {
+        let clock = MockClock::new();
+        let state = AudioManagerState {
+            last_refresh: Some(clock.now()),
+            ..AudioManagerState::default()
+        };
+        assert!(state.is_cache_fresh(clock.now(), DEFAULT_CACHE_TTL));
+
+        clock.advance(DEFAULT_CACHE_TTL - Duration::from_millis(1));
+        assert!(state.is_cache_fresh(clock.now(), DEFAULT_CACHE_TTL));
+
+        clock.advance(Duration::from_millis(1));
+        assert!(!state.is_cache_fresh(clock.now(), DEFAULT_CACHE_TTL));
+    }
+
+    #[test]
+    fn override_expires_after_its_duration() {
+        let clock = MockClock::new();
+        let state = AudioManagerState {
+            device_overrides: vec![DeviceOverride {
+                device_id: "{0.0.0.00000000}.{headset}".to_string(),
+                device_type: DeviceType::Playback,
+                expires_at: Some(clock.now() + Duration::from_secs(300)),
+            }],
+            ..AudioManagerState::default()
+        };
+        assert!(state
+            .active_override(&DeviceType::Playback, clock.now())
+            .is_some());
+        assert!(state
+            .active_override(&DeviceType::Recording, clock.now())
+            .is_none());
+
+        clock.advance(Duration::from_secs(299));
+        assert!(state
+            .active_override(&DeviceType::Playback, clock.now())
+            .is_some());
+
+        clock.advance(Duration::from_secs(1));
+        assert!(state
+            .active_override(&DeviceType::Playback, clock.now())
+            .is_none());
+    }
+
+    #[test]
+    fn device_state_round_trips_through_serde() {
+        for state in DeviceState::ALL {
+            let json = serde_json::to_string(&state).unwrap();
+            assert_eq!(json, format!("\"{}\"", state.as_str()));
+            assert_eq!(serde_json::from_str::<DeviceState>(&json).unwrap(), state);
+        }
+        assert_eq!(DeviceState::parse("NotPresent"), DeviceState::NotPresent);
+        assert_eq!(DeviceState::parse("Active"), DeviceState::Active);
+        assert_eq!(DeviceState::parse("Hibernating"), DeviceState::Unknown);
+    }
+
+    fn device(id: &str, device_type: DeviceType, is_default: bool) -> AudioDevice {
+        AudioDevice {
+            id: id.to_string(),
+            name: format!("Device {}", id),
+            device_type,
+            state: DeviceState::Active,
+            is_default,
+            is_communication_default: false,
+            last_seen: None,
+            index: None,
+            icon_path: None,
+            display_name: None,
+        }
+    }
+
+    #[test]
+    fn grouped_devices_split_by_type_with_defaults_resolved() {
+        let mut webcam = device("webcam", DeviceType::Recording, false);
+        webcam.name = "B Webcam".to_string();
+        let mut microphone = device("mic", DeviceType::Recording, true);
+        microphone.name = "A Microphone".to_string();
+        let mut speakers = device("speakers", DeviceType::Playback, true);
+        speakers.name = "Speakers".to_string();
+        let mut headset = device("headset", DeviceType::Playback, false);
+        headset.name = "Headset".to_string();
+
+        let grouped = GroupedDevices::from_devices(vec![webcam, speakers, microphone, headset]);
+
+        let ids =
+            |devices: &[AudioDevice]| devices.iter().map(|d| d.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&grouped.playback), ["headset", "speakers"]);
+        assert_eq!(ids(&grouped.recording), ["mic", "webcam"]);
+        assert_eq!(grouped.default_playback.as_deref(), Some("speakers"));
+        assert_eq!(grouped.default_recording.as_deref(), Some("mic"));
+
+        let empty =
+            GroupedDevices::from_devices(vec![device("headset", DeviceType::Playback, false)]);
+        assert_eq!(empty.default_playback, None);
+        assert!(empty.recording.is_empty());
+    }
+
+    #[test]
+    fn role_defaults_resolve_each_role_separately() {
+        let mut headset = device("{0.0.0.00000000}.{headset}", DeviceType::Playback, false);
+        headset.is_communication_default = true;
+        let mut microphone = device("{0.0.1.00000000}.{mic}", DeviceType::Recording, true);
+        microphone.is_communication_default = true;
+        let devices = vec![
+            device("{0.0.0.00000000}.{speakers}", DeviceType::Playback, true),
+            headset,
+            microphone,
+            device("{0.0.1.00000000}.{line-in}", DeviceType::Recording, false),
+        ];
+
+        let defaults = RoleDefaults::from_devices(&devices);
+        let id = |device: &Option<AudioDevice>| device.as_ref().map(|d| d.id.clone());
+        assert_eq!(
+            id(&defaults.playback_multimedia).as_deref(),
+            Some("{0.0.0.00000000}.{speakers}")
+        );
+        assert_eq!(
+            id(&defaults.playback_communication).as_deref(),
+            Some("{0.0.0.00000000}.{headset}")
+        );
+        assert_eq!(
+            id(&defaults.recording_multimedia).as_deref(),
+            Some("{0.0.1.00000000}.{mic}")
+        );
+        assert_eq!(
+            id(&defaults.recording_communication).as_deref(),
+            Some("{0.0.1.00000000}.{mic}")
+        );
+
+        let none = RoleDefaults::from_devices(&devices[1..2]);
+        assert!(none.playback_multimedia.is_none());
+        assert!(none.recording_communication.is_none());
+    }
+
+    #[test]
+    fn communication_defaults_differ_from_multimedia_defaults() {
+        let manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        let output = serde_json::json!({
+            "devices": [
+                {
+                    "id": "{0.0.0.00000000}.{speakers}",
+                    "name": "Speakers",
+                    "device_type": "Playback",
+                    "state": "Active",
+                    "is_default": true,
+                    "is_communication_default": false
+                },
+                {
+                    "id": "{0.0.0.00000000}.{headset}",
+                    "name": "Headset",
+                    "device_type": "Playback",
+                    "state": "Active",
+                    "is_default": false,
+                    "is_communication_default": true
+                },
+                {
+                    "id": "{0.0.1.00000000}.{mic}",
+                    "name": "Microphone",
+                    "device_type": "Recording",
+                    "state": "Active",
+                    "is_default": true
+                }
+            ]
+        })
+        .to_string();
+
+        let devices = manager
+            .parse_device_list_response(&output, &BTreeMap::new(), &mut |_| {})
+            .unwrap()
+            .devices;
+        let communication = CommunicationDefaults::from(RoleDefaults::from_devices(&devices));
+        assert_eq!(
+            communication,
+            CommunicationDefaults {
+                playback: Some("{0.0.0.00000000}.{headset}".to_string()),
+                // A missing flag (older scripts) means the role is unknown, not the default
+                recording: None,
+            }
+        );
+        assert!(devices
+            .iter()
+            .any(|d| d.is_default && !d.is_communication_default));
+    }
+
+    #[test]
+    fn malformed_devices_are_skipped_with_the_field_named() {
+        let manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        let listing = |device: serde_json::Value| {
+            let output = serde_json::json!({
+                "devices": [device],
+                "timestamp": "2024-01-01T00:00:00.000Z",
+                "session": "DESKTOP",
+            })
+            .to_string();
+            manager.parse_device_list_response(&output, &BTreeMap::new(), &mut |_| {})
+        };
+
+        let devices = listing(serde_json::json!({
+            "id": "speakers", "index": 1, "name": "Speakers", "device_type": "Playback",
+            "state": "NotPresent", "is_default": false, "is_communication_default": false,
+            "last_seen": null, "icon_path": ""
+        }))
+        .unwrap()
+        .devices;
+        assert_eq!(devices[0].state, DeviceState::NotPresent);
+        assert_eq!(devices[0].index, Some(1));
+        assert!(devices[0].icon_path.is_none());
+
+        let skipped = |device: serde_json::Value| {
+            let list = listing(device).unwrap();
+            assert!(list.devices.is_empty());
+            list.skipped.into_iter().next().unwrap()
+        };
+        let (name, reason) = skipped(serde_json::json!({
+            "id": "speakers", "name": "Speakers", "device_type": "Loopback",
+            "state": "Active", "is_default": false
+        }));
+        assert_eq!(name, "Speakers");
+        assert!(reason.starts_with("device_type of speakers"));
+
+        let (_, reason) = skipped(serde_json::json!({
+            "id": "speakers", "name": "Speakers", "device_type": "Playback",
+            "state": "Hibernating", "is_default": false
+        }));
+        assert!(reason.contains("state of speakers"));
+
+        // Without a name the entry is reported by its ID
+        let (name, reason) = skipped(serde_json::json!({
+            "id": "speakers", "device_type": "Playback", "state": "Active", "is_default": false
+        }));
+        assert_eq!(name, "speakers");
+        assert!(reason.contains("missing field `name`"));
+    }
+
+    #[tokio::test]
+    async fn a_malformed_device_does_not_hide_the_others() {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        let output = serde_json::json!({
+            "devices": [
+                { "id": "speakers", "name": "Speakers", "device_type": "Playback",
+                  "state": "Active", "is_default": true },
+                { "id": "loopback", "name": "Stereo Mix", "device_type": "Loopback",
+                  "state": "Active", "is_default": false },
+            ]
+        })
+        .to_string();
+        use_host(
+            &mut manager,
+            PowerShellHost::canned(vec![("$allAudioDevices", Ok(output))]),
+        );
+
+        let list = manager.get_audio_devices_detailed().await.unwrap();
+        assert_eq!(list.devices.len(), 1);
+        assert_eq!(list.skipped.len(), 1);
+        assert_eq!(list.skipped[0].0, "Stereo Mix");
+
+        // Served from the cache, the skipped entry is still reported
+        let list = manager.get_audio_devices_detailed().await.unwrap();
+        assert_eq!(list.skipped.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn diagnostics_report_what_the_script_could_gather() {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        // The execution-policy and elevation probes failed and left their fields out
+        use_host(&mut manager, PowerShellHost::canned(vec![(
+            "Get-ExecutionPolicy",
+            Ok(r#"{"powershell_version":"5.1.22621.4249","module_installed":true,"module_version":"3.1.0.2","can_list_devices":false}"#.to_string()),
+        )]));
+
+        let diagnostics = manager.diagnose().await.unwrap();
+        assert_eq!(
+            diagnostics.powershell_version.as_deref(),
+            Some("5.1.22621.4249")
+        );
+        assert_eq!(diagnostics.module_installed, Some(true));
+        assert_eq!(diagnostics.module_version.as_deref(), Some("3.1.0.2"));
+        assert_eq!(diagnostics.execution_policy, None);
+        assert_eq!(diagnostics.is_admin, None);
+        assert_eq!(diagnostics.can_list_devices, Some(false));
+    }
+
+    #[test]
+    fn setup_summary_names_defaults_and_module_version() {
+        let mut speakers = device("{0.0.0.00000000}.{speakers}", DeviceType::Playback, true);
+        speakers.name = "Speakers (Realtek(R) Audio)".to_string();
+        let mut microphone = device("{0.0.1.00000000}.{mic}", DeviceType::Recording, true);
+        microphone.name = "Microphone (Yeti Stereo)".to_string();
+        let mut unplugged = device("{0.0.0.00000000}.{hdmi}", DeviceType::Playback, false);
+        unplugged.state = DeviceState::Unplugged;
+        let devices = vec![speakers, microphone, unplugged];
+
+        let summary = SetupSummary::new(
+            &RoleDefaults::from_devices(&devices),
+            &devices,
+            Some("3.1.0.2".to_string()),
+            "powershell + Core Audio".to_string(),
+            Vec::new(),
+        );
+        assert_eq!(summary.active_devices, 2);
+        assert!(summary.auto_switch_on);
+        assert!(summary
+            .text
+            .contains("Default playback: Speakers (Realtek(R) Audio)"));
+        assert!(summary
+            .text
+            .contains("Default recording: Microphone (Yeti Stereo)"));
+        assert!(summary.text.contains("AudioDeviceCmdlets: 3.1.0.2"));
+        assert!(summary.text.contains("Auto-switch: on"));
+
+        let pinned = SetupSummary::new(
+            &RoleDefaults::default(),
+            &[],
+            None,
+            "powershell".to_string(),
+            vec!["Speakers (Realtek(R) Audio)".to_string()],
+        );
+        assert!(pinned.text.contains("Default playback: none"));
+        assert!(pinned.text.contains("AudioDeviceCmdlets: not installed"));
+        assert!(pinned
+            .text
+            .contains("Auto-switch: paused (pinned: Speakers (Realtek(R) Audio))"));
+    }
+
+    #[test]
+    fn active_device_state_reads_the_default_playback_device() {
+        let devices = vec![
+            device("mic", DeviceType::Recording, true),
+            device("speakers", DeviceType::Playback, false),
+            device("headset", DeviceType::Playback, true),
+        ];
+        let default = default_device(devices, &DeviceType::Playback).unwrap();
+        assert_eq!(default.id, "headset");
+
+        let state =
+            ActiveDeviceState::from_reading(default, r#"{"volume":0.456,"muted":true}"#).unwrap();
+        assert_eq!(state.device.id, "headset");
+        assert_eq!(state.volume, 46);
+        assert!(state.muted);
+    }
+
+    #[test]
+    fn active_device_state_is_none_without_a_default() {
+        let devices = vec![
+            device("mic", DeviceType::Recording, true),
+            device("speakers", DeviceType::Playback, false),
+        ];
+        assert!(default_device(devices, &DeviceType::Playback).is_none());
+        assert!(default_device(Vec::new(), &DeviceType::Playback).is_none());
+    }
+
+    #[test]
+    fn windows_preferred_device_is_the_os_default() {
+        let mut speakers = device("speakers", DeviceType::Playback, false);
+        speakers.index = Some(1);
+        let mut monitor = device("monitor", DeviceType::Playback, false);
+        monitor.index = Some(2);
+        let headset = device("headset", DeviceType::Playback, true);
+        let mic = device("mic", DeviceType::Recording, true);
+
+        let devices = vec![monitor.clone(), headset, speakers.clone(), mic.clone()];
+        let preferred = windows_preferred_device(devices, &DeviceType::Playback).unwrap();
+        assert_eq!(preferred.id, "headset");
+
+        // Without a default, fall back to Windows' enumeration order
+        let preferred =
+            windows_preferred_device(vec![monitor, speakers, mic], &DeviceType::Playback).unwrap();
+        assert_eq!(preferred.id, "speakers");
+    }
+
+    fn format(sample_rate: u32) -> AudioFormat {
+        AudioFormat {
+            sample_rate,
+            bits_per_sample: 24,
+            channels: 2,
+        }
+    }
+
+    #[test]
+    fn format_conflicts_flags_mismatched_sample_rates() {
+        let formats = vec![
+            (
+                device("speakers", DeviceType::Playback, true),
+                format(48_000),
+            ),
+            (
+                device("headset", DeviceType::Playback, false),
+                format(44_100),
+            ),
+            (
+                device("monitor", DeviceType::Playback, false),
+                format(48_000),
+            ),
+        ];
+
+        let conflicts = format_conflicts(&formats);
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].device_a, "speakers");
+        assert_eq!(conflicts[0].device_b, "headset");
+        assert!(conflicts[0].reason.contains("drift"));
+        assert_eq!(conflicts[1].device_a, "headset");
+        assert_eq!(conflicts[1].device_b, "monitor");
+    }
+
+    #[test]
+    fn format_conflicts_is_empty_for_matching_rates() {
+        let formats = vec![
+            (
+                device("speakers", DeviceType::Playback, true),
+                format(48_000),
+            ),
+            (
+                device("monitor", DeviceType::Playback, false),
+                format(48_000),
+            ),
+        ];
+        assert!(format_conflicts(&formats).is_empty());
+        assert!(format_conflicts(&[]).is_empty());
+    }
+
+    #[test]
+    fn parse_errors_quote_context_and_output() {
+        let raw = "WARNING: module is outdated\r\n{\"devices\": []}";
+        let error = parse_json_or_error::<serde_json::Value>(raw, "device enumeration")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("device enumeration"));
+        assert!(error.contains("WARNING: module is outdated"));
+
+        let long = "x".repeat(500);
+        let error = parse_json_or_error::<serde_json::Value>(&long, "module check")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains(&"x".repeat(200)));
+        assert!(!error.contains(&"x".repeat(201)));
+        assert!(error.contains("..."));
+    }
+
+    /// Run `manager`'s scripts on `host`, keeping its executable selection
+    fn use_host(manager: &mut AudioManager, host: PowerShellHost) {
+        manager.runner = Box::new(PowerShellRunner::new(host, manager.powershell_exe.clone()));
+    }
+
+    /// Config for tests: switches apply at once instead of waiting out the coalescing
+    /// window, and scripts run once so failures surface without retry delays
+    fn test_config() -> AudioManagerConfig {
+        AudioManagerConfig {
+            switch_coalesce_window_ms: Some(0),
+            max_retry_attempts: Some(1),
+            ..AudioManagerConfig::default()
+        }
+    }
+
+    /// A manager whose playback chain prefers an active headset over the default speakers
+    async fn manager_preferring_headset(clock: &Arc<MockClock>) -> AudioManager {
+        let mut manager = AudioManager::in_memory(clock.clone());
+        *manager.config.get_mut() = test_config();
+
+        let priority = |device_id: &str, priority: usize| DevicePriority {
+            device_id: device_id.to_string(),
+            device_name: format!("Device {}", device_id),
+            device_type: DeviceType::Playback,
+            priority,
+        };
+        manager
+            .priorities
+            .import_from_frontend(
+                vec![priority("headset", 0), priority("speakers", 1)],
+                Vec::new(),
+            )
+            .await
+            .unwrap();
+
+        let devices = [
+            device("headset", DeviceType::Playback, false),
+            device("speakers", DeviceType::Playback, true),
+        ];
+        manager.cache_devices(&devices, clock.now()).await;
+        manager
+    }
+
+    /// Move the mock clock forward, keeping the device cache fresh
+    async fn advance(manager: &AudioManager, clock: &MockClock, duration: Duration) {
+        clock.advance(duration);
+        let devices: Vec<AudioDevice> = manager
+            .state
+            .read()
+            .await
+            .cached_devices
+            .values()
+            .cloned()
+            .collect();
+        manager.cache_devices(&devices, clock.now()).await;
+    }
+
+    #[tokio::test]
+    async fn preview_selects_the_highest_priority_device() {
+        let clock = Arc::new(MockClock::new());
+        let manager = manager_preferring_headset(&clock).await;
+
+        let target = manager
+            .preview_autoswitch_target(&DeviceType::Playback)
+            .await
+            .unwrap();
+        assert_eq!(target.unwrap().id, "headset");
+        assert!(manager
+            .preview_autoswitch_target(&DeviceType::Recording)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn preview_is_suppressed_until_a_temporary_override_expires() {
+        let clock = Arc::new(MockClock::new());
+        let manager = manager_preferring_headset(&clock).await;
+        manager.state.write().await.set_override(DeviceOverride {
+            device_id: "speakers".to_string(),
+            device_type: DeviceType::Playback,
+            expires_at: Some(clock.now() + Duration::from_secs(60)),
+        });
+
+        assert!(manager
+            .preview_autoswitch_target(&DeviceType::Playback)
+            .await
+            .unwrap()
+            .is_none());
+
+        advance(&manager, &clock, Duration::from_secs(60)).await;
+        let target = manager
+            .preview_autoswitch_target(&DeviceType::Playback)
+            .await
+            .unwrap();
+        assert_eq!(target.unwrap().id, "headset");
+    }
+
+    #[tokio::test]
+    async fn overlong_override_is_rejected_before_switching() {
+        let clock = Arc::new(MockClock::new());
+        let manager = Arc::new(manager_preferring_headset(&clock).await);
+
+        for duration in [
+            MAX_OVERRIDE_DURATION + Duration::from_secs(60),
+            Duration::MAX,
+        ] {
+            assert!(matches!(
+                manager.temporary_override("headset", duration).await,
+                Err(AudioError::InvalidArgument(_))
+            ));
+        }
+        assert!(manager.state.read().await.device_overrides.is_empty());
+    }
+
+    #[tokio::test]
+    async fn preview_is_suppressed_by_a_manual_override() {
+        let clock = Arc::new(MockClock::new());
+        let manager = manager_preferring_headset(&clock).await;
+        manager.state.write().await.set_override(DeviceOverride {
+            device_id: "speakers".to_string(),
+            device_type: DeviceType::Playback,
+            expires_at: None,
+        });
+
+        advance(&manager, &clock, Duration::from_secs(3600)).await;
+        assert!(manager
+            .preview_autoswitch_target(&DeviceType::Playback)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn decisions_record_why_nothing_was_switched() {
+        let clock = Arc::new(MockClock::new());
+        let manager = manager_preferring_headset(&clock).await;
+
+        manager
+            .evaluate_and_switch(&DeviceType::Recording)
+            .await
+            .unwrap();
+        let decisions = manager.get_autoswitch_decisions(LogPage::default()).await;
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].device_type, DeviceType::Recording);
+        assert_eq!(
+            decisions[0].reason,
+            "no switch: the priority chain is empty"
+        );
+        assert_eq!(decisions[0].timestamp, clock.utc_now());
+    }
+
+    #[test]
+    fn switched_reason_names_device_priority_and_state() {
+        let headset = AudioDevice {
+            name: "Headphones".to_string(),
+            ..device("headset", DeviceType::Playback, false)
+        };
+        assert_eq!(
+            switched_reason(&headset, 0),
+            "switched to Headphones (priority 0, active)"
+        );
+    }
+
+    /// A host answering the listing script with `DEVICE_LIST_FIXTURE` and counting how
+    /// often it ran
+    fn listing_counting_host() -> (PowerShellHost, Arc<AtomicUsize>) {
+        let listings = Arc::new(AtomicUsize::new(0));
+        let counter = listings.clone();
+        let host = PowerShellHost::scripted(move |script| {
+            if script == DEVICE_LIST_SCRIPT {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+            canned_output(
+                &[(DEVICE_LIST_SCRIPT, Ok(DEVICE_LIST_FIXTURE.to_string()))],
+                script,
+            )
+        });
+        (host, listings)
+    }
+
+    #[tokio::test]
+    async fn bulk_operation_coalesces_invalidations_into_one_refresh() {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        let (host, listings) = listing_counting_host();
+        use_host(&mut manager, host);
+
+        manager.get_audio_devices().await.unwrap();
+        assert_eq!(listings.load(Ordering::SeqCst), 1);
+
+        manager
+            .in_bulk_operation(async {
+                manager.invalidate_cache().await;
+                manager
+                    .in_bulk_operation(async {
+                        manager.invalidate_cache().await;
+                        manager.invalidate_cache().await;
+                    })
+                    .await;
+                // Nothing is refetched mid-operation
+                manager.get_audio_devices().await.unwrap();
+            })
+            .await;
+        assert_eq!(listings.load(Ordering::SeqCst), 1);
+
+        manager.get_audio_devices().await.unwrap();
+        manager.get_audio_devices().await.unwrap();
+        assert_eq!(listings.load(Ordering::SeqCst), 2);
+
+        // Without deferred invalidations, ending a bulk operation refreshes nothing
+        manager.in_bulk_operation(async {}).await;
+        manager.get_audio_devices().await.unwrap();
+        assert_eq!(listings.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn invalidations_outside_a_bulk_operation_are_not_deferred() {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        let (host, listings) = listing_counting_host();
+        use_host(&mut manager, host);
+        manager.get_audio_devices().await.unwrap();
+
+        let (done, finished) = tokio::sync::oneshot::channel();
+        let bulk = manager.in_bulk_operation(async {
+            manager.invalidate_cache().await;
+            finished.await.unwrap();
+        });
+        let unrelated = async {
+            // Runs while the bulk operation is in progress
+            manager.invalidate_cache().await;
+            manager.get_audio_devices().await.unwrap();
+            done.send(()).unwrap();
+        };
+        tokio::join!(bulk, unrelated);
+        assert_eq!(listings.load(Ordering::SeqCst), 2);
+
+        // The bulk operation's own invalidation lands once it ends
+        manager.get_audio_devices().await.unwrap();
+        assert_eq!(listings.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn state_change_reports_old_and_new_state() {
+        let headset = device("{0.0.0.00000000}.{headset}", DeviceType::Playback, false);
+        let speakers = device("{0.0.0.00000000}.{speakers}", DeviceType::Playback, true);
+        let previous = watch_snapshot(&[headset.clone(), speakers.clone()]);
+
+        let mut sleeping = headset.clone();
+        sleeping.state = DeviceState::Unplugged;
+        let added = device("{0.0.0.00000000}.{hdmi}", DeviceType::Playback, false);
+        let current = vec![sleeping, speakers, added];
+
+        assert_eq!(
+            device_state_changes(&previous, &current),
+            vec![DeviceStateChange {
+                device_id: headset.id.clone(),
+                device_name: headset.name.clone(),
+                old_state: DeviceState::Active,
+                new_state: DeviceState::Unplugged,
+            }]
+        );
+        assert!(device_state_changes(&watch_snapshot(&current), &current).is_empty());
+    }
+
+    #[test]
+    fn device_change_reports_hotplug_and_reconnects() {
+        let mut headset = device("{0.0.0.00000000}.{headset}", DeviceType::Playback, false);
+        headset.state = DeviceState::Unplugged;
+        let speakers = device("{0.0.0.00000000}.{speakers}", DeviceType::Playback, true);
+        let dock = device("{0.0.0.00000000}.{dock}", DeviceType::Playback, false);
+        let previous = watch_snapshot(&[headset.clone(), speakers.clone(), dock.clone()]);
+
+        // The headset reconnects without changing the device count
+        headset.state = DeviceState::Active;
+        let hdmi = device("{0.0.0.00000000}.{hdmi}", DeviceType::Playback, false);
+        let current = vec![headset.clone(), speakers, hdmi.clone()];
+
+        let change = device_change(&previous, &current);
+        let ids =
+            |devices: &[AudioDevice]| devices.iter().map(|d| d.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&change.added), [hdmi.id]);
+        assert_eq!(change.removed, [dock.id]);
+        assert_eq!(ids(&change.state_changed), [headset.id]);
+        assert!(device_change(&watch_snapshot(&current), &current).is_empty());
+    }
+
+    #[tokio::test]
+    async fn monitoring_can_be_stopped_and_restarted() {
+        let manager = Arc::new(AudioManager::in_memory(Arc::new(MockClock::new())));
+
+        assert!(manager.spawn_monitor(Duration::from_millis(10)).is_err());
+        assert!(!manager.stop_monitoring());
+
+        manager.spawn_monitor(Duration::from_secs(60)).unwrap();
+        manager.spawn_monitor(Duration::from_secs(30)).unwrap();
+        assert!(manager.stop_monitoring());
+        assert!(!manager.stop_monitoring());
+    }
+
+    #[tokio::test]
+    async fn switch_that_does_not_take_effect_is_rolled_back() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+
+        // Every Set-AudioDevice "succeeds", but the speakers stay the default
+        let scripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = scripts.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |script| {
+                recorded.lock().unwrap().push(script.to_string());
+                let stdout = if script.contains("Set-AudioDevice") {
+                    r#"{"success":true}"#.to_string()
+                } else {
+                    serde_json::json!({
+                    "devices": [
+                        { "id": "headset", "name": "Device headset", "device_type": "Playback",
+                          "state": "Active", "is_default": false },
+                        { "id": "speakers", "name": "Device speakers", "device_type": "Playback",
+                          "state": "Active", "is_default": true },
+                    ]
+                })
+                .to_string()
+                };
+                ScriptOutput {
+                    exit_code: Some(0),
+                    stdout,
+                    stderr: String::new(),
+                }
+            }),
+        );
+
+        let error = manager
+            .set_default_audio_device("headset")
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&error, AudioError::CommandFailed(message) if message.contains("did not take effect"))
+        );
+
+        let scripts = scripts.lock().unwrap();
+        let switches: Vec<_> = scripts
+            .iter()
+            .filter(|script| script.contains("Set-AudioDevice"))
+            .collect();
+        assert_eq!(switches.len(), 2);
+        assert!(switches[0].contains(r#"Set-AudioDevice -ID "headset""#));
+        // The fallback put the previous default back
+        assert!(switches[1].contains(r#"Set-AudioDevice -ID "speakers" -DefaultOnly"#));
+    }
+
+    #[tokio::test]
+    async fn profile_status_names_the_absent_devices() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        let scripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = scripts.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |script| {
+                recorded.lock().unwrap().push(script.to_string());
+                canned_output(&[], script)
+            }),
+        );
+        let profile = |name: &str, playback: &str| Profile {
+            name: name.to_string(),
+            default_playback: Some(playback.to_string()),
+            communication_playback: None,
+            default_recording: None,
+            communication_recording: None,
+            priorities: None,
+        };
+        {
+            let mut config = manager.config.write().await;
+            config
+                .profiles
+                .insert("Desk".to_string(), profile("Desk", "headset"));
+            config
+                .profiles
+                .insert("Dock".to_string(), profile("Dock", "dock-speakers"));
+        }
+
+        let statuses = manager.list_profiles_with_status().await.unwrap();
+        let status = |name: &str| statuses.iter().find(|s| s.profile.name == name).unwrap();
+        assert!(status("Desk").all_devices_present);
+        assert!(status("Desk").missing_devices.is_empty());
+        assert!(!status("Dock").all_devices_present);
+        assert_eq!(status("Dock").missing_devices, ["dock-speakers"]);
+
+        // Applying the profile whose device is absent skips it without switching anything
+        let applied = manager.apply_profile("Dock").await.unwrap();
+        assert_eq!(applied.unavailable_devices, ["dock-speakers"]);
+        assert!(scripts.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn applying_a_profile_skips_missing_devices() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        let scripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = scripts.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |script| {
+                let stdout = if script.contains("Set-AudioDevice") {
+                    recorded.lock().unwrap().push(script.to_string());
+                    r#"{"success":true}"#.to_string()
+                } else {
+                    serde_json::json!({
+                    "devices": [
+                        { "id": "headset", "name": "Device headset", "device_type": "Playback",
+                          "state": "Active", "is_default": true,
+                          "is_communication_default": true },
+                        { "id": "speakers", "name": "Device speakers", "device_type": "Playback",
+                          "state": "Active", "is_default": false },
+                    ]
+                })
+                .to_string()
+                };
+                ScriptOutput {
+                    exit_code: Some(0),
+                    stdout,
+                    stderr: String::new(),
+                }
+            }),
+        );
+        let chain = vec![DevicePriority {
+            device_id: "headset".to_string(),
+            device_name: "Device headset".to_string(),
+            device_type: DeviceType::Playback,
+            priority: 0,
+        }];
+        manager.config.write().await.profiles.insert(
+            "Desk".to_string(),
+            Profile {
+                name: "Desk".to_string(),
+                default_playback: Some("headset".to_string()),
+                communication_playback: Some("headset".to_string()),
+                default_recording: Some("dock-mic".to_string()),
+                communication_recording: None,
+                priorities: Some(PriorityChains {
+                    playback: chain,
+                    recording: Vec::new(),
+                }),
+            },
+        );
+
+        let applied = manager.apply_profile("Desk").await.unwrap();
+        assert_eq!(applied.unavailable_devices, ["dock-mic"]);
+
+        let scripts = scripts.lock().unwrap();
+        assert_eq!(scripts.len(), 1);
+        assert!(scripts[0].contains(r#"Set-AudioDevice -ID "headset" -DefaultOnly"#));
+        assert!(scripts[0].contains(r#"Set-AudioDevice -ID "headset" -CommunicationOnly"#));
+
+        let playback = manager.load_priorities().await.playback;
+        assert_eq!(playback.len(), 1);
+        assert_eq!(playback[0].device_id, "headset");
+    }
+
+    #[tokio::test]
+    async fn communications_switch_leaves_the_multimedia_default() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        let scripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = scripts.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |script| {
+                let stdout = if script.contains("Set-AudioDevice") {
+                    recorded.lock().unwrap().push(script.to_string());
+                    r#"{"success":true}"#.to_string()
+                } else {
+                    serde_json::json!({
+                    "devices": [
+                        { "id": "headset", "name": "Device headset", "device_type": "Playback",
+                          "state": "Active", "is_default": false,
+                          "is_communication_default": true },
+                        { "id": "speakers", "name": "Device speakers", "device_type": "Playback",
+                          "state": "Active", "is_default": true,
+                          "is_communication_default": false },
+                    ]
+                })
+                .to_string()
+                };
+                ScriptOutput {
+                    exit_code: Some(0),
+                    stdout,
+                    stderr: String::new(),
+                }
+            }),
+        );
+
+        manager
+            .set_default_device(
+                "headset",
+                &DeviceType::Playback,
+                DefaultRole::Communications,
+                true,
+            )
+            .await
+            .unwrap();
+
+        let scripts = scripts.lock().unwrap();
+        assert_eq!(scripts.len(), 1);
+        assert!(scripts[0].contains(r#"Set-AudioDevice -ID "headset" -CommunicationOnly"#));
+        assert!(!scripts[0].contains("-DefaultOnly"));
+        // Auto-switch only manages the multimedia role, so nothing is pinned
+        assert!(manager
+            .state
+            .read()
+            .await
+            .active_override(&DeviceType::Playback, clock.now())
+            .is_none());
+    }
+
+    #[test]
+    fn known_script_failures_map_to_typed_errors() {
+        let typed =
+            br#"{"error":"AudioDeviceCmdlets module not installed","type":"ModuleNotInstalled"}"#;
+        assert!(matches!(
+            structured_error(typed),
+            Some(AudioError::ModuleNotInstalled(_))
+        ));
+
+        let import_failed = br#"{"error":"The specified module 'AudioDeviceCmdlets' was not loaded because no valid module file was found in any module directory."}"#;
+        assert!(matches!(
+            structured_error(import_failed),
+            Some(AudioError::ModuleNotInstalled(_))
+        ));
+
+        let unelevated = br#"{"success":false,"error":"Administrator rights are required to install modules in 'C:\Program Files\WindowsPowerShell\Modules'."}"#;
+        assert!(matches!(
+            structured_error(unelevated),
+            Some(AudioError::ElevationRequired(message)) if message.starts_with("Administrator rights")
+        ));
+
+        // Anything else is left to the retry loop
+        assert!(structured_error(br#"{"error":"Device not found: x"}"#).is_none());
+        assert!(structured_error(b"not json").is_none());
+    }
+
+    #[test]
+    fn channel_support_suggests_the_nearest_count() {
+        let stereo_only = [2];
+        assert_eq!(
+            ChannelSupport::from_counts(2, &stereo_only),
+            ChannelSupport {
+                supported: true,
+                closest_channels: None,
+            }
+        );
+        assert_eq!(
+            ChannelSupport::from_counts(8, &stereo_only),
+            ChannelSupport {
+                supported: false,
+                closest_channels: Some(2),
+            }
+        );
+
+        let surround = [2, 6, 8];
+        assert!(ChannelSupport::from_counts(8, &surround).supported);
+        assert_eq!(
+            ChannelSupport::from_counts(4, &surround).closest_channels,
+            Some(2)
+        );
+        assert_eq!(
+            ChannelSupport::from_counts(7, &surround).closest_channels,
+            Some(6)
+        );
+        assert_eq!(ChannelSupport::from_counts(2, &[]).closest_channels, None);
+    }
+
+    #[tokio::test]
+    async fn devices_resolve_by_index_or_full_id() {
+        let clock = Arc::new(MockClock::new());
+        let manager = AudioManager::in_memory(clock.clone());
+        let mut speakers = device("{0.0.0.00000000}.{speakers}", DeviceType::Playback, true);
+        speakers.index = Some(1);
+        let mut headset = device("{0.0.0.00000000}.{headset}", DeviceType::Playback, false);
+        headset.index = Some(2);
+        manager
+            .cache_devices(&[speakers, headset], clock.now())
+            .await;
+
+        assert_eq!(
+            manager.resolve_device_id(" 1 ").await.unwrap(),
+            "{0.0.0.00000000}.{speakers}"
+        );
+        assert_eq!(
+            manager
+                .resolve_device_id("{0.0.0.00000000}.{headset}")
+                .await
+                .unwrap(),
+            "{0.0.0.00000000}.{headset}"
+        );
+        assert!(matches!(
+            manager.resolve_device_id("3").await,
+            Err(AudioError::DeviceNotFound(message)) if message == "no device with index 3"
+        ));
+        assert!(matches!(
+            manager.resolve_device_id("{0.0.0.00000000}.{dock}").await,
+            Err(AudioError::DeviceNotFound(message)) if message.contains("no device with ID")
+        ));
+    }
+
+    #[test]
+    fn powershell_strings_escape_quotes_and_expansion() {
+        assert_eq!(
+            powershell_string("{0.0.0.00000000}.{a}"),
+            r#""{0.0.0.00000000}.{a}""#
+        );
+        assert_eq!(
+            powershell_string("a\"b`c$d\u{201C}"),
+            "\"a`\"b``c`$d`\u{201C}\""
+        );
+    }
+
+    #[tokio::test]
+    async fn switch_script_escapes_the_device_id() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = AudioManager::in_memory(clock.clone());
+        let scripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = scripts.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |script| {
+                recorded.lock().unwrap().push(script.to_string());
+                ScriptOutput {
+                    exit_code: Some(0),
+                    stdout: r#"{"success":true}"#.to_string(),
+                    stderr: String::new(),
+                }
+            }),
+        );
+        *manager.config.get_mut() = test_config();
+        let hostile = r#"{0.0.0.00000000}.{x"; Remove-Item $HOME; "}"#;
+        manager
+            .cache_devices(
+                &[
+                    device("speakers", DeviceType::Playback, true),
+                    device(hostile, DeviceType::Playback, false),
+                ],
+                clock.now(),
+            )
+            .await;
+
+        let _ = manager.set_default_audio_device(hostile).await;
+
+        let scripts = scripts.lock().unwrap();
+        let switch = scripts
+            .iter()
+            .find(|script| script.contains("Set-AudioDevice"))
+            .unwrap();
+        assert!(switch.contains(
+            r#"Set-AudioDevice -ID "{0.0.0.00000000}.{x`"; Remove-Item `$HOME; `"}" -DefaultOnly"#
+        ));
+        assert!(!switch.contains(hostile));
+    }
+
+    #[test]
+    fn only_known_scripts_can_be_named() {
+        assert!(matches!(
+            KnownScript::from_name("list"),
+            Ok(KnownScript::List)
+        ));
+        assert!(matches!(
+            KnownScript::from_name("module-check"),
+            Ok(KnownScript::ModuleCheck)
+        ));
+
+        for name in ["LIST", "install", "Get-AudioDevice -List", ""] {
+            assert!(matches!(
+                KnownScript::from_name(name),
+                Err(AudioError::InvalidArgument(message))
+                    if message.ends_with("expected one of: list, module-check")
+            ));
+        }
+    }
+
+    #[test]
+    fn known_property_keys_are_reported_by_name() {
+        let raw = HashMap::from([
+            (
+                "{A45C254E-DF1C-4EFD-8020-67D146A850E0},2".to_string(),
+                "Speakers".to_string(),
+            ),
+            (
+                "{a45c254e-df1c-4efd-8020-67d146a850e0},24".to_string(),
+                "USB".to_string(),
+            ),
+            (
+                "{1da5d803-d492-4edd-8c23-e0c0ffee7f0e},0".to_string(),
+                "1".to_string(),
+            ),
+            (
+                "{b3f8fa53-0004-438e-9003-51a46e139bfc},6".to_string(),
+                "x".to_string(),
+            ),
+        ]);
+
+        let named = name_known_properties(raw);
+
+        assert_eq!(named.len(), 4);
+        assert_eq!(named["DeviceDescription"], "Speakers");
+        assert_eq!(named["EnumeratorName"], "USB");
+        assert_eq!(named["FormFactor"], "1");
+        assert_eq!(named["{b3f8fa53-0004-438e-9003-51a46e139bfc},6"], "x");
+    }
+
+    fn stereo_48k() -> EndpointFormatSettings {
+        EndpointFormatSettings {
+            sample_rate: Some(48_000),
+            bits_per_sample: Some(24),
+            channels: Some(2),
+            ..Default::default()
+        }
+    }
+
+    fn finding_messages(findings: &[DiagnosisFinding]) -> Vec<(FindingSeverity, &str)> {
+        findings
+            .iter()
+            .map(|f| (f.severity, f.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn healthy_device_has_no_findings() {
+        let speakers = device("speakers", DeviceType::Playback, true);
+        assert!(diagnosis_findings(&speakers, &stereo_48k(), None).is_empty());
+    }
+
+    #[test]
+    fn inactive_default_is_an_error() {
+        let mut speakers = device("speakers", DeviceType::Playback, true);
+        speakers.state = DeviceState::Unplugged;
+
+        let findings = diagnosis_findings(&speakers, &stereo_48k(), None);
+        assert_eq!(
+            finding_messages(&findings),
+            [(
+                FindingSeverity::Error,
+                "'Device speakers' is a default device but is Unplugged; apps using the default will be silent"
+            )]
+        );
+
+        // The same state on a device nothing plays through isn't worth flagging
+        let mut headset = device("headset", DeviceType::Playback, false);
+        headset.state = DeviceState::Unplugged;
+        assert!(diagnosis_findings(&headset, &stereo_48k(), None).is_empty());
+    }
+
+    #[test]
+    fn format_the_engine_rejects_names_the_closest_match() {
+        let speakers = device("speakers", DeviceType::Playback, true);
+        let rejected = FormatSupport {
+            supported: false,
+            closest_match: Some(AudioFormat {
+                sample_rate: 48_000,
+                bits_per_sample: 16,
+                channels: 2,
+            }),
+        };
+
+        let findings = diagnosis_findings(&speakers, &stereo_48k(), Some(&rejected));
+        assert_eq!(
+            finding_messages(&findings),
+            [(
+                FindingSeverity::Warning,
+                "Default format 48000 Hz / 24-bit / 2 ch is not accepted by the audio engine (closest: 48000 Hz / 16-bit / 2 ch)"
+            )]
+        );
+
+        let no_match = FormatSupport {
+            supported: false,
+            closest_match: None,
+        };
+        let findings = diagnosis_findings(&speakers, &stereo_48k(), Some(&no_match));
+        assert_eq!(
+            finding_messages(&findings),
+            [(
+                FindingSeverity::Warning,
+                "Default format 48000 Hz / 24-bit / 2 ch is not accepted by the audio engine"
+            )]
+        );
+    }
+
+    #[test]
+    fn unusual_sample_rate_is_a_warning() {
+        let speakers = device("speakers", DeviceType::Playback, true);
+        let settings = EndpointFormatSettings {
+            sample_rate: Some(96_000),
+            ..stereo_48k()
+        };
+
+        let findings = diagnosis_findings(&speakers, &settings, None);
+        assert_eq!(
+            finding_messages(&findings),
+            [(
+                FindingSeverity::Warning,
+                "Default sample rate 96000 Hz differs from the 44.1/48 kHz most apps request; resampling can cause crackling"
+            )]
+        );
+    }
+
+    #[test]
+    fn unreadable_format_is_reported() {
+        let speakers = device("speakers", DeviceType::Playback, true);
+        let settings = EndpointFormatSettings {
+            channels: None,
+            ..stereo_48k()
+        };
+
+        let findings = diagnosis_findings(&speakers, &settings, None);
+        assert_eq!(
+            finding_messages(&findings),
+            [(FindingSeverity::Info, "Default format could not be read")]
+        );
+    }
+
+    #[test]
+    fn exclusive_mode_findings_depend_on_priority() {
+        let speakers = device("speakers", DeviceType::Playback, true);
+        let exclusive = EndpointFormatSettings {
+            exclusive_allowed: Some(1),
+            exclusive_priority: Some(0),
+            ..stereo_48k()
+        };
+
+        let findings = diagnosis_findings(&speakers, &exclusive, None);
+        assert_eq!(
+            finding_messages(&findings),
+            [(
+                FindingSeverity::Info,
+                "Exclusive mode is enabled; apps may lock the device to their own format"
+            )]
+        );
+
+        let with_priority = EndpointFormatSettings {
+            exclusive_priority: Some(1),
+            ..exclusive
+        };
+        let findings = diagnosis_findings(&speakers, &with_priority, None);
+        assert_eq!(
+            finding_messages(&findings),
+            [(
+                FindingSeverity::Info,
+                "Exclusive mode is enabled with priority; one app can take over the device and silence others"
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn app_preferences_are_stored_by_stable_key_and_resolved() {
+        let clock = Arc::new(MockClock::new());
+        let manager = manager_preferring_headset(&clock).await;
+        let headset_key = device("headset", DeviceType::Playback, false).stable_key();
+
+        manager
+            .set_app_preference(" Discord.EXE ", "headset")
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.config.read().await.app_preferences.get("discord"),
+            Some(&headset_key)
+        );
+
+        let preferences = manager.list_app_preferences().await.unwrap();
+        assert_eq!(preferences.len(), 1);
+        assert_eq!(preferences[0].app_name, "discord");
+        assert_eq!(
+            preferences[0].device.as_ref().map(|d| d.id.as_str()),
+            Some("headset")
+        );
+
+        // A preference for a device that's gone is kept, just unresolved
+        manager
+            .config
+            .write()
+            .await
+            .app_preferences
+            .insert("spotify".to_string(), "Playback:dock".to_string());
+        let preferences = manager.list_app_preferences().await.unwrap();
+        let spotify = preferences
+            .iter()
+            .find(|p| p.app_name == "spotify")
+            .unwrap();
+        assert!(spotify.device.is_none());
+
+        assert!(matches!(
+            manager.set_app_preference("obs", "dock").await,
+            Err(AudioError::DeviceNotFound(_))
+        ));
+        assert!(matches!(
+            manager.set_app_preference(".exe", "headset").await,
+            Err(AudioError::InvalidArgument(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn focusing_an_app_targets_its_preferred_device() {
+        let clock = Arc::new(MockClock::new());
+        let manager = manager_preferring_headset(&clock).await;
+        let headset_key = device("headset", DeviceType::Playback, false).stable_key();
+        let speakers_key = device("speakers", DeviceType::Playback, true).stable_key();
+        {
+            let mut config = manager.config.write().await;
+            config
+                .app_preferences
+                .insert("game".to_string(), headset_key);
+            config
+                .app_preferences
+                .insert("browser".to_string(), speakers_key);
+            config
+                .app_preferences
+                .insert("editor".to_string(), "Playback:device gone".to_string());
+        }
+
+        // Nothing happens until follow focus is turned on
+        assert!(manager.focus_target("Game.exe").await.unwrap().is_none());
+
+        manager.config.write().await.follow_focus = true;
+        let target = manager.focus_target("Game.exe").await.unwrap();
+        assert_eq!(target.map(|d| d.id), Some("headset".to_string()));
+
+        // Already the default, absent, or no preference at all
+        assert!(manager.focus_target("browser.exe").await.unwrap().is_none());
+        assert!(manager.focus_target("editor.exe").await.unwrap().is_none());
+        assert!(manager
+            .focus_target("explorer.exe")
+            .await
+            .unwrap()
+            .is_none());
+
+        // Pins and temporary overrides win over focus
+        manager.state.write().await.set_override(DeviceOverride {
+            device_id: "speakers".to_string(),
+            device_type: DeviceType::Playback,
+            expires_at: Some(clock.now() + Duration::from_secs(60)),
+        });
+        assert!(manager.focus_target("Game.exe").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn focus_refused_by_read_only_mode_is_not_retried() {
+        let clock = Arc::new(MockClock::new());
+        let manager = manager_preferring_headset(&clock).await;
+        {
+            let mut config = manager.config.write().await;
+            config.follow_focus = true;
+            config.app_preferences.insert(
+                "game".to_string(),
+                device("headset", DeviceType::Playback, false).stable_key(),
+            );
+        }
+        manager.set_read_only(true);
+
+        let mut focused = None;
+        manager
+            .focus_tick(&mut focused, Some("Game.exe".to_string()))
+            .await;
+        assert_eq!(focused.as_deref(), Some("Game.exe"));
+    }
+
+    #[tokio::test]
+    async fn focus_is_only_followed_once_the_switch_succeeds() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        {
+            let mut config = manager.config.write().await;
+            config.follow_focus = true;
+            config.app_preferences.insert(
+                "game".to_string(),
+                device("headset", DeviceType::Playback, false).stable_key(),
+            );
+        }
+        let switch_fails = Arc::new(AtomicBool::new(true));
+        let switched = Arc::new(AtomicBool::new(false));
+        let scripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let (fails, done, recorded) = (switch_fails.clone(), switched.clone(), scripts.clone());
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |script| {
+                if script.contains("Set-AudioDevice") {
+                    recorded.lock().unwrap().push(script.to_string());
+                    if fails.load(Ordering::SeqCst) {
+                        return ScriptOutput {
+                            exit_code: Some(1),
+                            stdout: String::new(),
+                            stderr: "Device is busy".to_string(),
+                        };
+                    }
+                    done.store(true, Ordering::SeqCst);
+                    return ScriptOutput {
+                        exit_code: Some(0),
+                        stdout: r#"{"success":true}"#.to_string(),
+                        stderr: String::new(),
+                    };
+                }
+                let headset_default = done.load(Ordering::SeqCst);
+                ScriptOutput {
+                exit_code: Some(0),
+                stdout: serde_json::json!({
+                    "devices": [
+                        { "id": "headset", "name": "Device headset", "device_type": "Playback",
+                          "state": "Active", "is_default": headset_default },
+                        { "id": "speakers", "name": "Device speakers", "device_type": "Playback",
+                          "state": "Active", "is_default": !headset_default },
+                    ]
+                })
+                .to_string(),
+                stderr: String::new(),
+            }
+            }),
+        );
+
+        // A failed switch leaves the focus unfollowed, so the next tick tries again
+        let mut focused = None;
+        manager
+            .focus_tick(&mut focused, Some("Game.exe".to_string()))
+            .await;
+        assert_eq!(focused, None);
+        assert!(!scripts.lock().unwrap().is_empty());
+
+        switch_fails.store(false, Ordering::SeqCst);
+        scripts.lock().unwrap().clear();
+        manager
+            .focus_tick(&mut focused, Some("Game.exe".to_string()))
+            .await;
+        assert_eq!(focused.as_deref(), Some("Game.exe"));
+        assert!(switched.load(Ordering::SeqCst));
+
+        // Still focused: nothing more to do
+        manager
+            .focus_tick(&mut focused, Some("Game.exe".to_string()))
+            .await;
+        let scripts = scripts.lock().unwrap();
+        assert_eq!(scripts.len(), 1);
+        assert!(scripts[0].contains(r#"Set-AudioDevice -ID "headset""#));
+    }
+
+    #[tokio::test]
+    async fn watcher_skips_polling_on_battery_when_paused() {
+        let clock = Arc::new(MockClock::new());
+        let manager = manager_preferring_headset(&clock).await;
+        manager.config.write().await.pause_watcher_on_battery = true;
+
+        assert!(!manager.watcher_tick(true).await);
+        assert!(manager.state.read().await.watched_devices.is_none());
+
+        assert!(manager.watcher_tick(false).await);
+        assert_eq!(
+            manager
+                .state
+                .read()
+                .await
+                .watched_devices
+                .as_ref()
+                .map(Vec::len),
+            Some(2)
+        );
+    }
+
+    #[tokio::test]
+    async fn quiet_hours_are_not_enforced_on_battery_when_paused() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        {
+            let mut config = manager.config.write().await;
+            config.pause_watcher_on_battery = true;
+            config.quiet_hours = Some(QuietHours {
+                start: chrono::NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+                end: chrono::NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+                max_volume: 20,
+            });
+        }
+        clock.set_local_time(chrono::NaiveTime::from_hms_opt(23, 0, 0).unwrap());
+        let scripts = Arc::new(AtomicUsize::new(0));
+        let counter = scripts.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |script| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                canned_output(&[], script)
+            }),
+        );
+
+        assert!(!manager.watcher_tick(true).await);
+        assert_eq!(scripts.load(Ordering::SeqCst), 0);
+
+        // On AC the tick reads the active volume to enforce the cap
+        assert!(manager.watcher_tick(false).await);
+        assert!(scripts.load(Ordering::SeqCst) > 0);
+    }
+
+    #[tokio::test]
+    async fn manual_override_blocks_auto_switch() {
+        let clock = Arc::new(MockClock::new());
+        let manager = manager_preferring_headset(&clock).await;
+
+        // The higher-priority headset is active but the user picked the speakers
+        manager.state.write().await.set_override(DeviceOverride {
+            device_id: "speakers".to_string(),
+            device_type: DeviceType::Playback,
+            expires_at: None,
+        });
+
+        let switched = manager
+            .evaluate_and_switch(&DeviceType::Playback)
+            .await
+            .unwrap();
+        assert!(switched.is_none());
+
+        let decisions = manager.get_autoswitch_decisions(LogPage::default()).await;
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].chosen, None);
+        assert_eq!(
+            decisions[0].reason,
+            "no switch: pinned to speakers by an override"
+        );
+        assert!(manager
+            .state
+            .read()
+            .await
+            .active_override(&DeviceType::Playback, clock.now())
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn switch_of_a_pinned_device_type_is_reported() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        let switches = Arc::new(std::sync::Mutex::new(Vec::new()));
+        use_host(
+            &mut manager,
+            simulated_cmdlets(
+                simulated_devices(&[
+                    ("headset", DeviceType::Playback, false, false),
+                    ("speakers", DeviceType::Playback, true, true),
+                ]),
+                &[],
+                switches.clone(),
+            ),
+        );
+        manager.state.write().await.set_override(DeviceOverride {
+            device_id: "speakers".to_string(),
+            device_type: DeviceType::Playback,
+            expires_at: None,
+        });
+
+        let result = manager
+            .set_default_device("headset", &DeviceType::Playback, DefaultRole::Both, false)
+            .await;
+        assert!(matches!(result, Err(AudioError::Pinned(id)) if id == "speakers"));
+        assert!(switches.lock().unwrap().is_empty());
+
+        // Picking a device by hand replaces the pin
+        manager
+            .set_default_device("headset", &DeviceType::Playback, DefaultRole::Both, true)
+            .await
+            .unwrap();
+        assert!(!switches.lock().unwrap().is_empty());
+    }
+
+    /// Auto-switch to the muted headset with `unmute_on_autoswitch` set to `unmute`,
+    /// returning whether the headset is still muted afterwards
+    async fn autoswitch_to_muted_headset(unmute: bool) -> bool {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        let devices = simulated_devices(&[
+            ("headset", DeviceType::Playback, false, false),
+            ("speakers", DeviceType::Playback, true, true),
+        ]);
+        let muted = Arc::new(AtomicBool::new(true));
+        let headset_muted = muted.clone();
+        let scripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        use_host(
+            &mut manager,
+            simulated_cmdlets_with(devices, &[], scripts, move |script| {
+                script.contains("$wasMuted").then(|| {
+                    let was_muted = script.contains(r#"Get-AudioDevice -ID "headset""#)
+                        && headset_muted.swap(false, Ordering::SeqCst);
+                    ScriptOutput {
+                        exit_code: Some(0),
+                        stdout: serde_json::json!({ "was_muted": was_muted }).to_string(),
+                        stderr: String::new(),
+                    }
+                })
+            }),
+        );
+        manager.config.write().await.unmute_on_autoswitch = unmute;
+
+        let switched = manager
+            .evaluate_and_switch(&DeviceType::Playback)
+            .await
+            .unwrap();
+        assert_eq!(switched.map(|d| d.id).as_deref(), Some("headset"));
+        muted.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn autoswitch_unmutes_the_target_only_when_enabled() {
+        assert!(!autoswitch_to_muted_headset(true).await);
+        // Users who keep a device muted on purpose aren't overridden
+        assert!(autoswitch_to_muted_headset(false).await);
+    }
+
+    #[test]
+    fn spatial_providers_always_include_windows_sonic() {
+        let entry = |id: &str, name: &str| {
+            HashMap::from([
+                ("id".to_string(), id.to_string()),
+                ("name".to_string(), name.to_string()),
+            ])
+        };
+
+        let providers = spatial_providers(Vec::new());
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].id, WINDOWS_SONIC_ID);
+        assert!(providers[0].built_in);
+
+        let providers = spatial_providers(vec![
+            entry("{dolby}", "Dolby Atmos for Headphones"),
+            entry("{sonic}", "Windows Sonic for Headphones"),
+            entry("{dts}", "DTS Sound Unbound"),
+            entry("{broken}", "  "),
+        ]);
+        let names: Vec<&str> = providers.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Windows Sonic for Headphones",
+                "Dolby Atmos for Headphones",
+                "DTS Sound Unbound"
+            ]
+        );
+        assert_eq!(providers[1].id, "{dolby}");
+        assert!(!providers[1].built_in);
+    }
+
+    #[test]
+    fn own_sessions_are_filtered_unless_requested() {
+        let own_pid = std::process::id();
+        let session = |process_id: u32, display_name: &str| AudioSession {
+            session_id: format!(
+                "{{0.0.0.00000000}}.{{guid}}|app.exe%b{{}}|1%b{}",
+                process_id
+            ),
+            process_id,
+            display_name: display_name.to_string(),
+            active: true,
+        };
+        let sessions = vec![
+            session(own_pid, "Test tone"),
+            session(own_pid.wrapping_add(1), "Music player"),
+        ];
+
+        let manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        let filtered = manager.filter_sessions(sessions.clone(), false);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].display_name, "Music player");
+        assert_eq!(manager.filter_sessions(sessions.clone(), true).len(), 2);
+
+        assert!(manager.is_self_session(&sessions[0].session_id));
+        assert!(!manager.is_self_session(&sessions[1].session_id));
+        assert!(!manager.is_self_session("{0.0.0.00000000}.{guid}|SystemSounds"));
+    }
+
+    #[tokio::test]
+    async fn rapid_switches_coalesce_to_the_last_one() {
+        let manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        let stagger = |n: u64| tokio::time::sleep(Duration::from_millis(20 * n));
+
+        let (first, second, third, recording) = tokio::join!(
+            manager.coalesce_switch(&DeviceType::Playback),
+            async {
+                stagger(1).await;
+                manager.coalesce_switch(&DeviceType::Playback).await
+            },
+            async {
+                stagger(2).await;
+                manager.coalesce_switch(&DeviceType::Playback).await
+            },
+            async {
+                stagger(1).await;
+                manager.coalesce_switch(&DeviceType::Recording).await
+            },
+        );
+
+        // Only the last playback switch goes ahead; recording is unaffected
+        assert_eq!(first, None);
+        assert_eq!(second, None);
+        assert_eq!(third, Some(3));
+        assert_eq!(recording, Some(1));
+
+        // Outside the window a switch isn't held back by earlier ones
+        assert_eq!(
+            manager.coalesce_switch(&DeviceType::Playback).await,
+            Some(4)
+        );
+    }
+
+    #[tokio::test]
+    async fn zero_coalesce_window_applies_every_switch() {
+        let manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        manager.set_switch_coalesce_window(Some(0)).await.unwrap();
+
+        assert_eq!(
+            manager.coalesce_switch(&DeviceType::Playback).await,
+            Some(1)
+        );
+        assert_eq!(
+            manager.coalesce_switch(&DeviceType::Playback).await,
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn backend_capabilities_differ_per_backend() {
+        let powershell = BackendCapabilities::of(BackendKind::PowerShell);
+        assert!(powershell.can_switch_default);
+        assert!(powershell.can_set_volume && powershell.can_mute);
+        assert!(!powershell.can_enumerate_sessions);
+        assert!(!powershell.can_exclusive_mode);
+
+        let native = BackendCapabilities::of(BackendKind::CoreAudio);
+        assert!(native.can_enumerate_sessions && native.can_exclusive_mode);
+        assert!(native.can_enumerate && native.can_switch_default);
+        assert!(!native.can_set_volume);
+        assert_ne!(powershell, native);
+
+        let combined =
+            BackendCapabilities::combined(&[BackendKind::PowerShell, BackendKind::CoreAudio]);
+        assert_eq!(
+            combined.backends,
+            [BackendKind::PowerShell, BackendKind::CoreAudio]
+        );
+        assert!(combined.can_switch_default && combined.can_enumerate_sessions);
+        assert!(!combined.can_rename && !combined.can_set_format);
+    }
+
+    #[test]
+    fn disabled_default_is_reported_with_a_replacement() {
+        let mut speakers = device("{0.0.0.00000000}.{speakers}", DeviceType::Playback, true);
+        speakers.is_communication_default = true;
+        speakers.state = DeviceState::Disabled;
+        let hdmi = device("{0.0.0.00000000}.{hdmi}", DeviceType::Playback, false);
+        let headset = device("{0.0.0.00000000}.{headset}", DeviceType::Playback, false);
+        let microphone = device("{0.0.1.00000000}.{mic}", DeviceType::Recording, true);
+        let devices = vec![speakers, hdmi, headset, microphone];
+        let chain = vec![DevicePriority {
+            device_id: "{0.0.0.00000000}.{headset}".to_string(),
+            device_name: "Device {0.0.0.00000000}.{headset}".to_string(),
+            device_type: DeviceType::Playback,
+            priority: 0,
+        }];
+
+        let anomalies = default_anomalies(&devices, &DeviceType::Playback, &chain);
+        assert_eq!(anomalies.len(), 1);
+        let anomaly = &anomalies[0];
+        assert_eq!(anomaly.device_id, "{0.0.0.00000000}.{speakers}");
+        assert_eq!(anomaly.role, DefaultRole::Both);
+        assert_eq!(anomaly.state, DeviceState::Disabled);
+        assert_eq!(
+            anomaly.suggested.as_ref().map(|d| d.id.as_str()),
+            Some("{0.0.0.00000000}.{headset}")
+        );
+
+        // Without a priority chain any Active device of the type is suggested
+        let anomalies = default_anomalies(&devices, &DeviceType::Playback, &[]);
+        assert_eq!(
+            anomalies[0].suggested.as_ref().map(|d| d.id.as_str()),
+            Some("{0.0.0.00000000}.{hdmi}")
+        );
+        assert!(default_anomalies(&devices, &DeviceType::Recording, &chain).is_empty());
+    }
+
+    #[test]
+    fn system_sounds_route_through_the_communication_default() {
+        let speakers = device("{0.0.0.00000000}.{speakers}", DeviceType::Playback, true);
+        let capabilities = BackendCapabilities::of(BackendKind::PowerShell);
+        assert_eq!(
+            system_sounds_role(&speakers, &capabilities).unwrap(),
+            DefaultRole::Communications
+        );
+
+        let microphone = device("{0.0.1.00000000}.{mic}", DeviceType::Recording, true);
+        assert!(matches!(
+            system_sounds_role(&microphone, &capabilities),
+            Err(AudioError::InvalidArgument(_))
+        ));
+
+        let native_only = BackendCapabilities::of(BackendKind::CoreAudio);
+        assert!(matches!(
+            system_sounds_role(&speakers, &native_only),
+            Err(AudioError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn backend_comparison_reports_discrepancies() {
+        let powershell = vec![
+            device("{0.0.0.00000000}.{speakers}", DeviceType::Playback, true),
+            device("{0.0.0.00000000}.{headset}", DeviceType::Playback, false),
+            device("{0.0.1.00000000}.{mic}", DeviceType::Recording, true),
+        ];
+        let mut native = vec![
+            device("{0.0.0.00000000}.{SPEAKERS}", DeviceType::Playback, false),
+            device("{0.0.0.00000000}.{headset}", DeviceType::Playback, true),
+            device("{0.0.0.00000000}.{virtual}", DeviceType::Playback, false),
+        ];
+        native[1].is_communication_default = true;
+
+        let comparison = BackendComparison::between(
+            (BackendKind::PowerShell, &powershell),
+            (BackendKind::CoreAudio, &native),
+        );
+        assert_eq!(comparison.matched, 2);
+        assert_eq!(comparison.only_in_primary, ["{0.0.1.00000000}.{mic}"]);
+        assert_eq!(comparison.only_in_secondary, ["{0.0.0.00000000}.{virtual}"]);
+        assert_eq!(
+            comparison.default_mismatches,
+            [
+                DefaultMismatch {
+                    device_id: "{0.0.0.00000000}.{speakers}".to_string(),
+                    primary_is_default: true,
+                    secondary_is_default: false,
+                    primary_is_communication_default: false,
+                    secondary_is_communication_default: false,
+                },
+                DefaultMismatch {
+                    device_id: "{0.0.0.00000000}.{headset}".to_string(),
+                    primary_is_default: false,
+                    secondary_is_default: true,
+                    primary_is_communication_default: false,
+                    secondary_is_communication_default: true,
+                },
+            ]
+        );
+        assert!(!comparison.is_consistent());
+
+        let same = BackendComparison::between(
+            (BackendKind::PowerShell, &powershell),
+            (BackendKind::CoreAudio, &powershell),
+        );
+        assert!(same.is_consistent());
+        assert_eq!(same.matched, 3);
+    }
+
+    #[tokio::test]
+    async fn wait_for_device_finds_a_device_on_a_later_poll() {
+        let polls = &AtomicUsize::new(0);
+        let fetch = move || async move {
+            let mut devices = vec![device("speakers", DeviceType::Playback, true)];
+            if polls.fetch_add(1, Ordering::SeqCst) >= 1 {
+                let mut headset = device("headset", DeviceType::Playback, false);
+                headset.name = "Headphones (HyperX Cloud II)".to_string();
+                devices.push(headset);
+            }
+            Ok(devices)
+        };
+
+        let found = poll_for_device(
+            fetch,
+            "hyperx",
+            MatchMode::Substring,
+            Duration::from_millis(10),
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+        assert_eq!(found.id, "headset");
+        assert_eq!(polls.load(Ordering::SeqCst), 2);
+    }
+
+    /// Representative device list output
+    const DEVICE_LIST_FIXTURE: &str = include_str!("../tests/fixtures/device_list.json");
+    const FIXTURE_SPEAKERS: &str = "{0.0.0.00000000}.{5f2a8c1e-3b4d-4e6f-9a7b-1c2d3e4f5a6b}";
+    const FIXTURE_HEADSET: &str = "{0.0.0.00000000}.{9c1b2d3e-4f5a-4b6c-8d7e-2f3a4b5c6d7e}";
+
+    /// A host where listing reports the module missing until the install script runs;
+    /// installs fail unless `install_succeeds`. Returns the host and the install count.
+    fn host_missing_the_module(install_succeeds: bool) -> (PowerShellHost, Arc<AtomicUsize>) {
+        let installs = Arc::new(AtomicUsize::new(0));
+        let counter = installs.clone();
+        let installed = Arc::new(AtomicBool::new(false));
+        let host = PowerShellHost::scripted(move |script| {
+            if script.contains("Install-Module") {
+                counter.fetch_add(1, Ordering::SeqCst);
+                installed.store(install_succeeds, Ordering::SeqCst);
+                return ScriptOutput {
+                    exit_code: Some(if install_succeeds { 0 } else { 1 }),
+                    stdout: serde_json::json!({ "success": install_succeeds }).to_string(),
+                    stderr: String::new(),
+                };
+            }
+            if installed.load(Ordering::SeqCst) {
+                return ScriptOutput {
+                    exit_code: Some(0),
+                    stdout: DEVICE_LIST_FIXTURE.to_string(),
+                    stderr: String::new(),
+                };
+            }
+            ScriptOutput {
+                exit_code: Some(1),
+                stdout: r#"{"error":"AudioDeviceCmdlets module not installed","type":"ModuleNotInstalled"}"#
+                    .to_string(),
+                stderr: String::new(),
+            }
+        });
+        (host, installs)
+    }
+
+    #[tokio::test]
+    async fn missing_module_is_installed_and_the_listing_retried() {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        let (host, installs) = host_missing_the_module(true);
+        use_host(&mut manager, host);
+        *manager.config.get_mut() = AudioManagerConfig {
+            auto_install_module: true,
+            ..test_config()
+        };
+
+        let devices = manager.get_audio_devices().await.unwrap();
+        assert!(devices.iter().any(|d| d.id == FIXTURE_HEADSET));
+        assert_eq!(installs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_auto_install_returns_the_original_error_once() {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        let (host, installs) = host_missing_the_module(false);
+        use_host(&mut manager, host);
+        *manager.config.get_mut() = AudioManagerConfig {
+            auto_install_module: true,
+            ..test_config()
+        };
+
+        for _ in 0..2 {
+            assert!(matches!(
+                manager.get_audio_devices_force().await,
+                Err(AudioError::ModuleNotInstalled(_))
+            ));
+        }
+        // The second listing doesn't try again
+        assert_eq!(installs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn enumeration_parses_the_device_list_fixture() {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        use_host(
+            &mut manager,
+            PowerShellHost::canned(vec![(
+                "$allAudioDevices",
+                Ok(DEVICE_LIST_FIXTURE.to_string()),
+            )]),
+        );
+
+        let mut devices = manager.get_audio_devices().await.unwrap();
+        devices.sort_by_key(|d| d.index);
+        let summary: Vec<(&str, &DeviceType, &DeviceState, bool)> = devices
+            .iter()
+            .map(|d| (d.name.as_str(), &d.device_type, &d.state, d.is_default))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "Speakers (Realtek(R) Audio)",
+                    &DeviceType::Playback,
+                    &DeviceState::Active,
+                    true
+                ),
+                (
+                    "Headset Earphone (HyperX Cloud Flight)",
+                    &DeviceType::Playback,
+                    &DeviceState::Active,
+                    false
+                ),
+                (
+                    "DELL U2720Q (NVIDIA High Definition Audio)",
+                    &DeviceType::Playback,
+                    &DeviceState::NotPresent,
+                    false
+                ),
+                (
+                    "Microphone (Realtek(R) Audio)",
+                    &DeviceType::Recording,
+                    &DeviceState::Active,
+                    true
+                ),
+            ]
+        );
+        assert_eq!(devices[0].id, FIXTURE_SPEAKERS);
+        assert_eq!(
+            devices[0].icon_path.as_deref(),
+            Some(r"%windir%\system32\mmres.dll,-3010")
+        );
+        assert!(devices[2].icon_path.is_none());
+        assert!(devices[3].icon_path.is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_switch_falls_back_to_the_previous_default() {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        *manager.config.get_mut() = AudioManagerConfig {
+            retry_base_delay_ms: Some(0),
+            ..test_config()
+        };
+        let scripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = scripts.clone();
+        let responses = vec![
+            (
+                "Set-AudioDevice -ID \"{0.0.0.00000000}.{9c1b",
+                Err("Set-AudioDevice : The device is in use".to_string()),
+            ),
+            ("Set-AudioDevice", Ok(r#"{"success":true}"#.to_string())),
+            ("$allAudioDevices", Ok(DEVICE_LIST_FIXTURE.to_string())),
+        ];
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |script| {
+                if script.contains("Set-AudioDevice") {
+                    recorded.lock().unwrap().push(script.to_string());
+                }
+                canned_output(&responses, script)
+            }),
+        );
+
+        let error = manager
+            .set_default_audio_device(FIXTURE_HEADSET)
+            .await
+            .unwrap_err();
+        assert!(matches!(&error, AudioError::CommandFailed(message) if message.contains("in use")));
+
+        let scripts = scripts.lock().unwrap();
+        assert!(scripts[0].contains(FIXTURE_HEADSET));
+        // The speakers held both roles, so they're restored in one switch
+        let restores: Vec<_> = scripts[1..]
+            .iter()
+            .filter(|script| script.contains(FIXTURE_SPEAKERS))
+            .collect();
+        assert_eq!(restores.len(), 1);
+        assert!(restores[0].contains(&format!(
+            r#"Set-AudioDevice -ID "{}" -DefaultOnly; Set-AudioDevice -ID "{}" -CommunicationOnly"#,
+            FIXTURE_SPEAKERS, FIXTURE_SPEAKERS
+        )));
+    }
+
+    #[tokio::test]
+    async fn undo_restores_the_default_a_switch_replaced() {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        *manager.config.get_mut() = test_config();
+        // Set-AudioDevice moves the playback default, which the list script reports
+        let current = Arc::new(std::sync::Mutex::new("speakers".to_string()));
+        let switched = current.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |script| {
+                let stdout = if let Some((_, rest)) = script.split_once("Set-AudioDevice -ID \"") {
+                    *switched.lock().unwrap() = rest.split('"').next().unwrap().to_string();
+                    r#"{"success":true}"#.to_string()
+                } else {
+                    let default = switched.lock().unwrap().clone();
+                    let entry = |id: &str| {
+                        serde_json::json!({ "id": id, "name": format!("Device {}", id),
+                        "device_type": "Playback", "state": "Active", "is_default": id == default,
+                        "is_communication_default": id == default })
+                    };
+                    serde_json::json!({ "devices": [entry("speakers"), entry("headset")] })
+                        .to_string()
+                };
+                ScriptOutput {
+                    exit_code: Some(0),
+                    stdout,
+                    stderr: String::new(),
+                }
+            }),
+        );
+
+        manager.set_default_audio_device("headset").await.unwrap();
+        assert_eq!(*current.lock().unwrap(), "headset");
+
+        manager.undo_last_switch(None).await.unwrap();
+        assert_eq!(*current.lock().unwrap(), "speakers");
+        let devices = manager.get_audio_devices().await.unwrap();
+        assert!(devices.iter().any(|d| d.id == "speakers" && d.is_default));
+    }
+
+    #[tokio::test]
+    async fn undo_without_a_switch_fails() {
+        let manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        assert!(matches!(
+            manager.undo_last_switch(None).await,
+            Err(AudioError::InvalidArgument(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn undo_restores_each_role_and_only_the_latest_device_type() {
+        const SPEAKERS: &str = "{0.0.0.00000000}.{speakers}";
+        const HEADSET: &str = "{0.0.0.00000000}.{headset}";
+        const USB: &str = "{0.0.0.00000000}.{usb}";
+        const WEBCAM: &str = "{0.0.1.00000000}.{webcam}";
+        const MICROPHONE: &str = "{0.0.1.00000000}.{mic}";
+        // Music on the speakers, calls on the headset
+        let devices = simulated_devices(&[
+            (SPEAKERS, DeviceType::Playback, true, false),
+            (HEADSET, DeviceType::Playback, false, true),
+            (USB, DeviceType::Playback, false, false),
+            (WEBCAM, DeviceType::Recording, true, true),
+            (MICROPHONE, DeviceType::Recording, false, false),
+        ]);
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        *manager.config.get_mut() = test_config();
+        let switches = Arc::new(std::sync::Mutex::new(Vec::new()));
+        use_host(
+            &mut manager,
+            simulated_cmdlets(devices.clone(), &[], switches),
+        );
+        let some = |id: &str| Some(id.to_string());
+
+        manager.set_default_audio_device(USB).await.unwrap();
+        manager.set_default_audio_device(MICROPHONE).await.unwrap();
+
+        // Undo takes back the recording switch and leaves the playback one alone
+        manager.undo_last_switch(None).await.unwrap();
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Recording),
+            (some(WEBCAM), some(WEBCAM))
+        );
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Playback),
+            (some(USB), some(USB))
+        );
+
+        // The playback switch can still be undone, each role going back to its own device
+        manager
+            .undo_last_switch(Some(DeviceType::Playback))
+            .await
+            .unwrap();
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Playback),
+            (some(SPEAKERS), some(HEADSET))
+        );
+
+        // Undoing again switches forward
+        manager
+            .undo_last_switch(Some(DeviceType::Playback))
+            .await
+            .unwrap();
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Playback),
+            (some(USB), some(USB))
+        );
+    }
+
+    /// A native backend over an in-memory device list, logging the changes asked of it
+    struct FakeBackend {
+        devices: std::sync::Mutex<Vec<AudioDevice>>,
+        calls: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl AudioBackend for FakeBackend {
+        fn list_devices(&self) -> AudioResult<DeviceListResult> {
+            Ok(DeviceListResult {
+                devices: self.devices.lock().unwrap().clone(),
+                skipped: Vec::new(),
+            })
+        }
+
+        fn set_default(&self, device_id: &str, _role: DefaultRole) -> AudioResult<()> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("default {}", device_id));
+            for device in self.devices.lock().unwrap().iter_mut() {
+                device.is_default = device.id == device_id;
+                device.is_communication_default = device.is_default;
+            }
+            Ok(())
+        }
+
+        fn set_volume(&self, device_id: &str, volume: u8) -> AudioResult<()> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("volume {} {}", device_id, volume));
+            Ok(())
+        }
 
-        if !exists {
-            return Err(AudioError::DeviceNotFound(device_id.to_string()));
+        fn set_mute(&self, device_id: &str, muted: Option<bool>) -> AudioResult<bool> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("mute {} {:?}", device_id, muted));
+            Ok(muted.unwrap_or(true))
         }
+    }
 
-        Ok(true)
+    #[tokio::test]
+    async fn native_backend_replaces_powershell() {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        // Any script reaching PowerShell fails
+        use_host(&mut manager, PowerShellHost::canned(Vec::new()));
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut speakers = device("speakers", DeviceType::Playback, true);
+        speakers.is_communication_default = true;
+        manager.native_backend = Some(Arc::new(FakeBackend {
+            devices: std::sync::Mutex::new(vec![
+                speakers,
+                device("headset", DeviceType::Playback, false),
+            ]),
+            calls: calls.clone(),
+        }));
+        manager.set_device_alias("headset", "Cans").await.unwrap();
+
+        let devices = manager.get_audio_devices().await.unwrap();
+        let headset = devices.iter().find(|d| d.id == "headset").unwrap();
+        assert_eq!(headset.display_name.as_deref(), Some("Cans"));
+
+        manager.set_default_audio_device("headset").await.unwrap();
+        let results = manager
+            .set_group_volume(&["headset".to_string()], 40)
+            .await
+            .unwrap();
+        assert!(results.iter().all(|r| r.success));
+        assert!(manager.toggle_device_mute("headset").await.unwrap());
+
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["default headset", "volume headset 40", "mute headset None"]
+        );
+        let devices = manager.get_audio_devices().await.unwrap();
+        assert!(devices.iter().any(|d| d.id == "headset" && d.is_default));
     }
 
-    /// Check module availability with detailed diagnostics (Step 18)
-    pub async fn check_module_availability(&self) -> AudioResult<bool> {
-        debug!("Checking AudioDeviceCmdlets module availability...");
+    /// A manager switching through a `FakeBackend` over three playback endpoints with
+    /// real-format IDs, so switches are coalesced, with the speakers the default
+    fn manager_coalescing_switches() -> (AudioManager, Arc<std::sync::Mutex<Vec<String>>>) {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        use_host(&mut manager, PowerShellHost::canned(Vec::new()));
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        manager.native_backend = Some(Arc::new(FakeBackend {
+            devices: std::sync::Mutex::new(vec![
+                device("{0.0.0.00000000}.{speakers}", DeviceType::Playback, true),
+                device("{0.0.0.00000000}.{headset}", DeviceType::Playback, false),
+                device("{0.0.0.00000000}.{dac}", DeviceType::Playback, false),
+            ]),
+            calls: calls.clone(),
+        }));
+        (manager, calls)
+    }
 
-        let powershell_script = r#"
-            try {
-                $module = Get-Module -ListAvailable -Name AudioDeviceCmdlets
-                if ($module) {
-                    @{
-                        available = $true
-                        version = $module.Version.ToString()
-                        path = $module.ModuleBase
-                    } | ConvertTo-Json -Compress
-                } else {
-                    @{
-                        available = $false
-                        message = "AudioDeviceCmdlets module not found"
-                        install_command = "Install-Module AudioDeviceCmdlets -Force -Scope CurrentUser"
-                    } | ConvertTo-Json -Compress
-                }
+    #[tokio::test]
+    async fn superseded_switch_reports_it_and_only_the_newest_applies() {
+        let (manager, calls) = manager_coalescing_switches();
+        manager.config.write().await.switch_coalesce_window_ms = Some(100);
+
+        let (older, newer) = tokio::join!(
+            manager.set_default_audio_device("{0.0.0.00000000}.{headset}"),
+            async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                manager
+                    .set_default_audio_device("{0.0.0.00000000}.{dac}")
+                    .await
             }
-            catch {
-                @{
-                    available = $false
-                    error = $_.Exception.Message
-                } | ConvertTo-Json -Compress
+        );
+        assert!(matches!(
+            older,
+            Err(AudioError::Superseded(id)) if id == "{0.0.0.00000000}.{headset}"
+        ));
+        newer.unwrap();
+        assert_eq!(*calls.lock().unwrap(), ["default {0.0.0.00000000}.{dac}"]);
+    }
+
+    #[tokio::test]
+    async fn superseded_auto_switch_is_recorded_as_no_switch() {
+        let (manager, calls) = manager_coalescing_switches();
+        manager.config.write().await.switch_coalesce_window_ms = Some(100);
+        manager
+            .priorities
+            .import_from_frontend(
+                vec![DevicePriority {
+                    device_id: "{0.0.0.00000000}.{headset}".to_string(),
+                    device_name: "Device headset".to_string(),
+                    device_type: DeviceType::Playback,
+                    priority: 0,
+                }],
+                Vec::new(),
+            )
+            .await
+            .unwrap();
+
+        let (evaluated, manual) =
+            tokio::join!(manager.evaluate_and_switch(&DeviceType::Playback), async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                manager
+                    .set_default_audio_device("{0.0.0.00000000}.{dac}")
+                    .await
+            });
+        assert!(evaluated.unwrap().is_none());
+        manual.unwrap();
+        assert_eq!(*calls.lock().unwrap(), ["default {0.0.0.00000000}.{dac}"]);
+
+        let decisions = manager.get_autoswitch_decisions(LogPage::default()).await;
+        assert_eq!(
+            decisions.last().unwrap().reason,
+            "no switch: the switch to Device {0.0.0.00000000}.{headset} was superseded by a newer one"
+        );
+    }
+
+    #[tokio::test]
+    async fn coalesce_window_is_capped() {
+        let manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        assert!(matches!(
+            manager.set_switch_coalesce_window(Some(60_000)).await,
+            Err(AudioError::InvalidArgument(_))
+        ));
+        manager
+            .set_switch_coalesce_window(Some(2_000))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn group_volume_reports_an_absent_device_and_sets_the_rest() {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        use_host(&mut manager, PowerShellHost::canned(Vec::new()));
+        let calls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        manager.native_backend = Some(Arc::new(FakeBackend {
+            devices: std::sync::Mutex::new(vec![
+                device("speakers", DeviceType::Playback, true),
+                device("headset", DeviceType::Playback, false),
+            ]),
+            calls: calls.clone(),
+        }));
+
+        let results = manager
+            .set_group_volume(&["dock".to_string(), "headset".to_string()], 40)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        let dock = results.iter().find(|r| r.device_id == "dock").unwrap();
+        assert!(!dock.success);
+        assert_eq!(
+            dock.error.as_deref(),
+            Some(
+                AudioError::DeviceNotFound("dock".to_string())
+                    .to_string()
+                    .as_str()
+            )
+        );
+        assert!(results
+            .iter()
+            .any(|r| r.device_id == "headset" && r.success));
+        assert_eq!(*calls.lock().unwrap(), ["volume headset 40"]);
+    }
+
+    /// Exercises the real policy config, so it switches to the current default to leave
+    /// the machine as it was
+    #[cfg(all(windows, feature = "native-wasapi"))]
+    #[tokio::test]
+    async fn native_switch_to_the_current_default_is_a_no_op() {
+        let manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        // Every endpoint with its default flags, to compare the machine before and after
+        let defaults = || async {
+            let devices = tokio::task::spawn_blocking(crate::wasapi::list_devices)
+                .await
+                .unwrap()
+                .unwrap();
+            let current = devices
+                .iter()
+                .find(|d| d.device_type == DeviceType::Playback && d.is_default)
+                .map(|d| d.id.clone());
+            let mut flags: Vec<(String, bool, bool)> = devices
+                .into_iter()
+                .map(|d| (d.id, d.is_default, d.is_communication_default))
+                .collect();
+            flags.sort();
+            (current, flags)
+        };
+        let (current, before) = defaults().await;
+        // Machines without an output device, such as CI runners, have nothing to switch
+        let Some(current) = current else {
+            return;
+        };
+
+        manager
+            .set_default_native(&current, DefaultRole::Multimedia)
+            .await
+            .unwrap();
+        assert_eq!(defaults().await.1, before);
+    }
+
+    #[tokio::test]
+    async fn dry_run_switches_only_the_cached_defaults() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                ScriptOutput {
+                    exit_code: Some(1),
+                    stdout: String::new(),
+                    stderr: "no audio hardware".to_string(),
+                }
+            }),
+        );
+        manager.set_dry_run(true);
+        let default_id =
+            |devices: &[AudioDevice]| devices.iter().find(|d| d.is_default).map(|d| d.id.clone());
+
+        manager.set_default_audio_device("headset").await.unwrap();
+        let devices = manager.get_audio_devices().await.unwrap();
+        assert_eq!(default_id(&devices).as_deref(), Some("headset"));
+        assert!(devices
+            .iter()
+            .any(|d| d.id == "headset" && d.is_communication_default));
+
+        manager
+            .change_audio_output("headset", "speakers")
+            .await
+            .unwrap();
+        let devices = manager.get_audio_devices().await.unwrap();
+        assert_eq!(default_id(&devices).as_deref(), Some("speakers"));
+
+        // Validation still runs against the device list
+        assert!(matches!(
+            manager.set_default_audio_device("missing").await,
+            Err(AudioError::DeviceNotFound(_))
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn device_aliases_follow_the_endpoint_id() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        // Windows renamed the speakers after a driver update; the ID is unchanged
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |_| ScriptOutput {
+                exit_code: Some(0),
+                stdout: serde_json::json!({
+                    "devices": [
+                        { "id": "speakers", "name": "Speakers (Realtek(R) Audio)",
+                          "device_type": "Playback", "state": "Active", "is_default": true },
+                    ]
+                })
+                .to_string(),
+                stderr: String::new(),
+            }),
+        );
+
+        manager
+            .set_device_alias("speakers", " Desk speakers ")
+            .await
+            .unwrap();
+        let cached = manager.get_audio_devices().await.unwrap();
+        let speakers = cached.iter().find(|d| d.id == "speakers").unwrap();
+        assert_eq!(speakers.display_name.as_deref(), Some("Desk speakers"));
+        assert_eq!(speakers.name, "Device speakers");
+
+        let refreshed = manager.get_audio_devices_force().await.unwrap();
+        assert_eq!(refreshed[0].name, "Speakers (Realtek(R) Audio)");
+        assert_eq!(refreshed[0].display_name.as_deref(), Some("Desk speakers"));
+
+        assert!(manager.clear_device_alias("speakers").await.unwrap());
+        assert!(!manager.clear_device_alias("speakers").await.unwrap());
+        assert!(manager.get_audio_devices().await.unwrap()[0]
+            .display_name
+            .is_none());
+        assert!(matches!(
+            manager.set_device_alias("missing", "Nope").await,
+            Err(AudioError::DeviceNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn devices_by_type_share_one_enumeration() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = AudioManager::in_memory(clock.clone());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                ScriptOutput {
+                exit_code: Some(0),
+                stdout: serde_json::json!({
+                    "devices": [
+                        { "id": "speakers", "name": "Device speakers", "device_type": "Playback",
+                          "state": "Active", "is_default": true },
+                        { "id": "mic", "name": "Device mic", "device_type": "Recording",
+                          "state": "Active", "is_default": true },
+                    ]
+                })
+                .to_string(),
+                stderr: String::new(),
             }
-        "#;
+            }),
+        );
 
-        let output = self
-            .execute_powershell_with_retry(powershell_script, "module availability check")
-            .await?;
-        let response: serde_json::Value = serde_json::from_str(&output)?;
+        let playback = manager
+            .get_devices_by_type(&DeviceType::Playback)
+            .await
+            .unwrap();
+        let recording = manager
+            .get_devices_by_type(&DeviceType::Recording)
+            .await
+            .unwrap();
+        assert_eq!(playback.len(), 1);
+        assert_eq!(playback[0].id, "speakers");
+        assert_eq!(recording.len(), 1);
+        assert_eq!(recording[0].id, "mic");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
 
-        let is_available = response["available"].as_bool().unwrap_or(false);
-        debug!("AudioDeviceCmdlets module available: {}", is_available);
+    #[tokio::test]
+    async fn force_refresh_bypasses_a_fresh_cache() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                ScriptOutput {
+                    exit_code: Some(0),
+                    stdout: serde_json::json!({
+                        "devices": [
+                            { "id": "usb", "name": "Device usb", "device_type": "Playback",
+                              "state": "Active", "is_default": true },
+                        ]
+                    })
+                    .to_string(),
+                    stderr: String::new(),
+                }
+            }),
+        );
 
-        if !is_available {
-            if let Some(message) = response["message"].as_str() {
-                info!("Module availability check: {}", message);
+        assert_eq!(manager.get_audio_devices().await.unwrap().len(), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let refreshed = manager.get_audio_devices_force().await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(refreshed.len(), 1);
+        assert_eq!(refreshed[0].id, "usb");
+
+        // The forced result replaces the cache for the regular path
+        let cached = manager.get_audio_devices().await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(cached[0].id, "usb");
+    }
+
+    #[tokio::test]
+    async fn toggling_mute_returns_the_new_state() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        let scripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = scripts.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |script| {
+                let stdout = if script.contains("$endpoint.Mute") {
+                    recorded.lock().unwrap().push(script.to_string());
+                    r#"{"muted":true}"#.to_string()
+                } else {
+                    // Re-enumeration after the cache is invalidated
+                    serde_json::json!({
+                    "devices": [
+                        { "id": "headset", "name": "Device headset", "device_type": "Playback",
+                          "state": "Active", "is_default": false },
+                        { "id": "speakers", "name": "Device speakers", "device_type": "Playback",
+                          "state": "Active", "is_default": true },
+                    ]
+                })
+                .to_string()
+                };
+                ScriptOutput {
+                    exit_code: Some(0),
+                    stdout,
+                    stderr: String::new(),
+                }
+            }),
+        );
+
+        assert!(manager.toggle_device_mute("headset").await.unwrap());
+        manager.set_device_mute("speakers", false).await.unwrap();
+
+        let scripts = scripts.lock().unwrap();
+        assert_eq!(scripts.len(), 2);
+        assert!(scripts[0].contains(r#"Get-AudioDevice -ID "headset""#));
+        assert!(scripts[0].contains("$endpoint.Mute = (-not $endpoint.Mute)"));
+        assert!(scripts[1].contains(r#"Get-AudioDevice -ID "speakers""#));
+        assert!(scripts[1].contains("$endpoint.Mute = $false"));
+
+        assert!(matches!(
+            manager.toggle_device_mute("missing").await,
+            Err(AudioError::DeviceNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reloaded_config_sets_cache_ttl_and_retries() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        let path = dir.path().join("config.json");
+        manager.config_path = Some(path.clone());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                ScriptOutput {
+                    exit_code: Some(1),
+                    stdout: String::new(),
+                    stderr: "failed".to_string(),
+                }
+            }),
+        );
+        assert_eq!(manager.get_cache_info().await.ttl_ms, 30_000);
+
+        // Settings edited by hand take effect on reload
+        let edited = AudioManagerConfig {
+            cache_ttl_ms: Some(5_000),
+            max_retry_attempts: Some(1),
+            retry_base_delay_ms: Some(0),
+            ..AudioManagerConfig::default()
+        };
+        config::save_json_atomic(&path, &edited).unwrap();
+        manager.reload_config().await.unwrap();
+        assert_eq!(manager.get_cache_info().await.ttl_ms, 5_000);
+
+        let result = manager
+            .execute_powershell_with_retry("exit 1", "failing script", Duration::from_secs(5))
+            .await;
+        assert!(matches!(result, Err(AudioError::CommandFailed(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        manager.set_cache_ttl(0).await.unwrap();
+        assert_eq!(manager.get_cache_info().await.ttl_ms, 0);
+        let saved: AudioManagerConfig = config::load_json(&path).unwrap();
+        assert_eq!(saved.cache_ttl_ms, Some(0));
+
+        // Out-of-range settings are refused, whether set or edited by hand
+        assert!(matches!(
+            manager.set_cache_ttl(u64::MAX).await,
+            Err(AudioError::InvalidArgument(_))
+        ));
+        let edited = AudioManagerConfig {
+            command_timeout_ms: Some(0),
+            ..AudioManagerConfig::default()
+        };
+        config::save_json_atomic(&path, &edited).unwrap();
+        assert!(matches!(
+            manager.reload_config().await,
+            Err(AudioError::InvalidArgument(_))
+        ));
+        assert_eq!(manager.get_cache_info().await.ttl_ms, 0);
+    }
+
+    #[tokio::test]
+    async fn cache_turns_stale_once_the_ttl_passes() {
+        let clock = Arc::new(MockClock::new());
+        let manager = AudioManager::in_memory(clock.clone());
+        let empty = manager.get_cache_info().await;
+        assert_eq!(empty.age_ms, None);
+        assert!(empty.is_stale);
+
+        manager.set_cache_ttl(5_000).await.unwrap();
+        manager
+            .cache_devices(
+                &[device("speakers", DeviceType::Playback, true)],
+                clock.now(),
+            )
+            .await;
+        clock.advance(Duration::from_millis(4_999));
+        let fresh = manager.get_cache_info().await;
+        assert_eq!(fresh.age_ms, Some(4_999));
+        assert_eq!(fresh.device_count, 1);
+        assert!(!fresh.is_stale);
+
+        clock.advance(Duration::from_millis(1));
+        let stale = manager.get_cache_info().await;
+        assert_eq!(stale.age_ms, Some(5_000));
+        assert!(stale.is_stale);
+        assert!(manager.get_cached_devices().await.is_stale);
+    }
+
+    #[tokio::test]
+    async fn device_listing_that_never_answers_reports_a_timeout() {
+        use crate::powershell_host::HostSession;
+        use tokio::io::AsyncBufReadExt;
+
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        *manager.config.get_mut() = AudioManagerConfig {
+            command_timeout_ms: Some(50),
+            ..test_config()
+        };
+        // A session that reads the listing script and sleeps instead of answering
+        use_host(
+            &mut manager,
+            PowerShellHost::with_spawner(|executable| {
+                let (client, server) = tokio::io::duplex(4096);
+                tokio::spawn(async move {
+                    let mut lines = tokio::io::BufReader::new(server).lines();
+                    while let Ok(Some(_)) = lines.next_line().await {}
+                });
+                let (client_read, client_write) = tokio::io::split(client);
+                Ok(HostSession::from_streams(
+                    executable,
+                    tokio::io::BufReader::new(client_read),
+                    client_write,
+                ))
+            }),
+        );
+
+        let error = manager.get_audio_devices().await.unwrap_err();
+        assert!(matches!(
+            error,
+            AudioError::Timeout { ref operation, elapsed_ms }
+                if operation == "device enumeration" && elapsed_ms >= 50
+        ));
+    }
+
+    #[tokio::test]
+    async fn streamed_devices_are_reported_as_the_script_finds_them() {
+        use crate::powershell_host::HostSession;
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        *manager.config.get_mut() = AudioManagerConfig {
+            command_timeout_ms: Some(1000),
+            ..test_config()
+        };
+        // The session reports the first two devices, then holds the full listing back
+        // until both have been handed over
+        let release = Arc::new(tokio::sync::Notify::new());
+        let gate = release.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::with_spawner(move |executable| {
+                let gate = gate.clone();
+                let (client, server) = tokio::io::duplex(64 * 1024);
+                let (server_read, mut server_write) = tokio::io::split(server);
+                tokio::spawn(async move {
+                    let mut lines = tokio::io::BufReader::new(server_read).lines();
+                    while let Ok(Some(_)) = lines.next_line().await {
+                        let listing: serde_json::Value =
+                            serde_json::from_str(DEVICE_LIST_FIXTURE).unwrap();
+                        for device in listing["devices"].as_array().unwrap().iter().take(2) {
+                            let line = serde_json::json!({ "progress": device.to_string() });
+                            let line = format!("{}\n", line);
+                            server_write.write_all(line.as_bytes()).await.unwrap();
+                        }
+                        gate.notified().await;
+                        let reply = serde_json::json!({
+                            "stdout": DEVICE_LIST_FIXTURE,
+                            "stderr": null,
+                            "exit_code": 0,
+                        });
+                        let reply = format!("{}\n", reply);
+                        server_write.write_all(reply.as_bytes()).await.unwrap();
+                    }
+                });
+                let (client_read, client_write) = tokio::io::split(client);
+                Ok(HostSession::from_streams(
+                    executable,
+                    tokio::io::BufReader::new(client_read),
+                    client_write,
+                ))
+            }),
+        );
+
+        let mut reported = Vec::new();
+        let count = manager
+            .stream_devices_with(&mut |device| {
+                reported.push(device.id.clone());
+                if reported.len() == 2 {
+                    release.notify_one();
+                }
+            })
+            .await
+            .unwrap();
+
+        // Streamed devices come first, in the script's order, and aren't repeated when
+        // the full listing arrives
+        assert_eq!(
+            reported,
+            [
+                FIXTURE_SPEAKERS,
+                FIXTURE_HEADSET,
+                "{0.0.0.00000000}.{0d3e4f5a-6b7c-4d8e-9f0a-3b4c5d6e7f8a}",
+                "{0.0.1.00000000}.{7a4c5d6e-7f8a-4b9c-0d1e-4c5d6e7f8a9b}",
+            ]
+        );
+        assert_eq!(count, 4);
+    }
+
+    #[tokio::test]
+    async fn redacted_diagnostics_hide_connected_and_absent_devices() {
+        let clock = Arc::new(MockClock::new());
+        let manager = manager_preferring_headset(&clock).await;
+        {
+            let mut config = manager.config.write().await;
+            config
+                .aliases
+                .insert("dock".to_string(), "Playback:thunderbolt dock".to_string());
+            config.app_preferences.insert(
+                "spotify".to_string(),
+                "Playback:living room dac".to_string(),
+            );
+            config.display_names.insert(
+                "{0.0.0.00000000}.{unplugged}".to_string(),
+                "Kitchen radio".to_string(),
+            );
+            config.hotkeys.insert(
+                "Ctrl+Alt+K".to_string(),
+                HotkeyAction::SwitchToDevice("{0.0.0.00000000}.{unplugged}".to_string()),
+            );
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let export = |name: &'static str| {
+            let path = dir.path().join(name);
+            let manager = &manager;
+            async move {
+                manager
+                    .export_diagnostics_bundle(&path, true)
+                    .await
+                    .unwrap();
+                std::fs::read_to_string(&path).unwrap()
             }
+        };
+        let first = export("first.json").await;
+        let second = export("second.json").await;
+
+        for identifier in [
+            "Device headset",
+            "device speakers",
+            "thunderbolt dock",
+            "living room dac",
+            "Kitchen radio",
+            "{unplugged}",
+        ] {
+            assert!(!first.contains(identifier), "{} leaked", identifier);
         }
+        // Aliases and app names are the user's own labels, not device identifiers
+        assert!(first.contains("spotify"));
 
-        Ok(is_available)
+        // Tokens are stable within a session, so exports from it can be compared
+        let tokens = |bundle: &str| {
+            let value: serde_json::Value = serde_json::from_str(bundle).unwrap();
+            value["config"]["aliases"]["dock"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        assert!(tokens(&first).starts_with("Playback:device-"));
+        assert_eq!(tokens(&first), tokens(&second));
+
+        // Another session salts differently, so bundles don't correlate across sessions
+        let mut other = manager_preferring_headset(&clock).await;
+        other.config = tokio::sync::RwLock::new(manager.config.read().await.clone());
+        let path = dir.path().join("other.json");
+        other.export_diagnostics_bundle(&path, true).await.unwrap();
+        assert_ne!(
+            tokens(&std::fs::read_to_string(&path).unwrap()),
+            tokens(&first)
+        );
     }
 
-    /// Install AudioDeviceCmdlets module (Step 9)
-    pub async fn install_module(&self) -> AudioResult<()> {
-        info!("Installing AudioDeviceCmdlets module...");
+    #[tokio::test]
+    async fn benchmark_results_are_exported_with_diagnostics() {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        *manager.config.get_mut() = test_config();
+        use_host(
+            &mut manager,
+            PowerShellHost::canned(vec![(
+                "$allAudioDevices",
+                Ok(DEVICE_LIST_FIXTURE.to_string()),
+            )]),
+        );
 
-        let powershell_script = r#"
-            try {
-                # Check if running as administrator for system-wide install
-                $currentUser = [Security.Principal.WindowsIdentity]::GetCurrent()
-                $principal = New-Object Security.Principal.WindowsPrincipal($currentUser)
-                $isAdmin = $principal.IsInRole([Security.Principal.WindowsBuiltInRole]::Administrator)
-                
-                if ($isAdmin) {
-                    Write-Output "Installing AudioDeviceCmdlets for all users..."
-                    Install-Module AudioDeviceCmdlets -Force -Scope AllUsers -AllowClobber
-                } else {
-                    Write-Output "Installing AudioDeviceCmdlets for current user..."
-                    Install-Module AudioDeviceCmdlets -Force -Scope CurrentUser -AllowClobber
+        let benchmark = manager.benchmark_enumeration(3).await.unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("diagnostics.json");
+        manager
+            .export_diagnostics_bundle(&path, false)
+            .await
+            .unwrap();
+
+        let bundle: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(bundle["enumeration_benchmark"]["iterations"], 3);
+        assert_eq!(bundle["enumeration_benchmark"]["max_ms"], benchmark.max_ms);
+    }
+
+    #[tokio::test]
+    async fn hung_script_times_out_and_is_retried() {
+        use crate::powershell_host::HostSession;
+        use tokio::io::AsyncBufReadExt;
+
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let counter = spawned.clone();
+        // Sessions that accept every script but never answer, like one stuck in Start-Sleep
+        use_host(
+            &mut manager,
+            PowerShellHost::with_spawner(move |executable| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let (client, server) = tokio::io::duplex(4096);
+                tokio::spawn(async move {
+                    let mut lines = tokio::io::BufReader::new(server).lines();
+                    while let Ok(Some(_)) = lines.next_line().await {}
+                });
+                let (client_read, client_write) = tokio::io::split(client);
+                Ok(HostSession::from_streams(
+                    executable,
+                    tokio::io::BufReader::new(client_read),
+                    client_write,
+                ))
+            }),
+        );
+
+        let result = manager
+            .execute_powershell_with_retry(
+                "Start-Sleep -Seconds 60",
+                "hung script",
+                Duration::from_millis(50),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(AudioError::Timeout { ref operation, .. }) if operation == "hung script"
+        ));
+        // Each timed-out attempt discarded its session, so every retry started a new one
+        assert_eq!(spawned.load(Ordering::SeqCst), MAX_RETRY_ATTEMPTS as usize);
+    }
+
+    #[tokio::test]
+    async fn wait_for_device_times_out() {
+        let polls = &AtomicUsize::new(0);
+        let fetch = move || async move {
+            polls.fetch_add(1, Ordering::SeqCst);
+            let mut unplugged = device("headset", DeviceType::Playback, false);
+            unplugged.name = "Headphones (HyperX Cloud II)".to_string();
+            unplugged.state = DeviceState::Unplugged;
+            Ok(vec![unplugged])
+        };
+
+        let result = poll_for_device(
+            fetch,
+            "hyperx",
+            MatchMode::Substring,
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+        )
+        .await;
+        assert!(matches!(result, Err(AudioError::Timeout { .. })));
+        assert!(polls.load(Ordering::SeqCst) > 1);
+    }
+
+    /// Log output captured by a test subscriber
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl CapturedLogs {
+        fn capture(&self, f: impl FnOnce()) -> String {
+            let writer = self.clone();
+            let subscriber = tracing_subscriber::fmt()
+                .with_max_level(tracing::Level::DEBUG)
+                .with_ansi(false)
+                .with_writer(move || writer.clone())
+                .finish();
+            tracing::subscriber::with_default(subscriber, f);
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    #[test]
+    fn powershell_tracing_logs_scripts_only_when_enabled() {
+        let manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        let script = "Get-AudioDevice -List # traced-script-body";
+        let invoke = || {
+            manager.trace_invocation(
+                "device enumeration",
+                "pwsh",
+                script,
+                1,
+                true,
+                Duration::from_millis(42),
+            )
+        };
+
+        let quiet = CapturedLogs::default().capture(invoke);
+        assert!(!quiet.contains("traced-script-body"));
+
+        manager.set_powershell_tracing(true);
+        let traced = CapturedLogs::default().capture(invoke);
+        assert!(traced.contains("traced-script-body"));
+        assert!(traced.contains("duration_ms=42"));
+        assert!(traced.contains("executable=\"pwsh\""));
+        assert!(traced.contains(&manager.session_id));
+    }
+
+    #[test]
+    fn is_newer_version_compares_numeric_components() {
+        // Equal, including differing component counts
+        assert!(!is_newer_version("3.1.0.2", "3.1.0.2"));
+        assert!(!is_newer_version("3.1", "3.1.0.0"));
+
+        // Gallery has a newer release
+        assert!(is_newer_version("3.1.0.10", "3.1.0.2"));
+        assert!(is_newer_version("3.2", "3.1.9.9"));
+
+        // Installed build is ahead of the gallery
+        assert!(!is_newer_version("3.1.0.2", "3.1.1"));
+        assert!(!is_newer_version("3.1.0.2", "4.0.0.0"));
+    }
+
+    #[tokio::test]
+    async fn quiet_hours_cap_applies_only_inside_the_window() {
+        let clock = Arc::new(MockClock::new());
+        let manager = AudioManager::in_memory(clock.clone());
+        let time = |hour| chrono::NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+
+        clock.set_local_time(time(23));
+        assert_eq!(manager.quiet_hours_cap(80).await, None);
+
+        manager
+            .set_quiet_hours(QuietHours {
+                start: time(22),
+                end: time(7),
+                max_volume: 25,
+            })
+            .await
+            .unwrap();
+        assert_eq!(manager.quiet_hours_cap(80).await, Some(25));
+        assert_eq!(manager.quiet_hours_cap(25).await, None);
+
+        // Past midnight, still inside the window
+        advance(&manager, &clock, Duration::from_secs(3 * 3600)).await;
+        assert_eq!(manager.quiet_hours_cap(80).await, Some(25));
+
+        // Morning, outside the window
+        clock.set_local_time(time(9));
+        assert_eq!(manager.quiet_hours_cap(80).await, None);
+
+        clock.set_local_time(time(23));
+        manager.clear_quiet_hours().await.unwrap();
+        assert_eq!(manager.quiet_hours_cap(80).await, None);
+    }
+
+    #[test]
+    fn driver_status_maps_problem_codes() {
+        let id = "{0.0.0.00000000}.{speakers}";
+        let healthy = DriverStatus::from_reading(
+            id,
+            r#"{"instance_id":"HDAUDIO\\FUNC_01&VEN_10EC","name":"Realtek(R) Audio","status":"OK","problem_code":0}"#,
+        )
+        .unwrap();
+        assert!(healthy.ok);
+        assert_eq!(healthy.problem, None);
+        assert_eq!(
+            healthy.pnp_instance_id.as_deref(),
+            Some("HDAUDIO\\FUNC_01&VEN_10EC")
+        );
+
+        let faulted = DriverStatus::from_reading(
+            id,
+            r#"{"instance_id":"USB\\VID_0951","name":"HyperX Cloud","status":"Error","problem_code":43}"#,
+        )
+        .unwrap();
+        assert!(!faulted.ok);
+        assert_eq!(faulted.problem_code, 43);
+        assert_eq!(
+            faulted.problem.as_deref(),
+            Some("Windows stopped the device because it reported problems")
+        );
+
+        let unusual = DriverStatus::from_reading(id, r#"{"problem_code":57}"#).unwrap();
+        assert_eq!(
+            unusual.problem.as_deref(),
+            Some("Device Manager problem code 57")
+        );
+
+        assert!(DriverStatus::from_reading(id, r#"{"error":"No matching device"}"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn driver_status_script_escapes_the_device_id() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = AudioManager::in_memory(clock.clone());
+        let scripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = scripts.clone();
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |script| {
+                recorded.lock().unwrap().push(script.to_string());
+                ScriptOutput {
+                    exit_code: Some(0),
+                    stdout: r#"{"status":"OK","problem_code":0}"#.to_string(),
+                    stderr: String::new(),
                 }
-                
-                # Verify installation
-                Import-Module AudioDeviceCmdlets -ErrorAction Stop
-                $version = (Get-Module AudioDeviceCmdlets).Version
-                
-                @{
-                    success = $true
-                    version = $version.ToString()
-                    scope = if ($isAdmin) { "AllUsers" } else { "CurrentUser" }
-                    message = "AudioDeviceCmdlets module installed successfully"
-                } | ConvertTo-Json -Compress
+            }),
+        );
+        *manager.config.get_mut() = test_config();
+        let hostile = r#"{0.0.0.00000000}.{x"; Remove-Item $HOME; "}"#;
+        manager
+            .cache_devices(&[device(hostile, DeviceType::Playback, true)], clock.now())
+            .await;
+
+        manager.get_driver_status(hostile).await.unwrap();
+
+        let scripts = scripts.lock().unwrap();
+        assert!(scripts[0].contains(
+            r#"$instanceId = "SWD\MMDEVAPI\{0.0.0.00000000}.{x`"; Remove-Item `$HOME; `"}""#
+        ));
+        assert!(!scripts[0].contains(hostile));
+    }
+
+    /// ID, type and (multimedia, communication) default flags of each simulated device
+    type SimulatedDevices = Arc<std::sync::Mutex<Vec<(String, DeviceType, bool, bool)>>>;
+
+    fn simulated_devices(rows: &[(&str, DeviceType, bool, bool)]) -> SimulatedDevices {
+        Arc::new(std::sync::Mutex::new(
+            rows.iter()
+                .map(|(id, device_type, multimedia, communication)| {
+                    (
+                        id.to_string(),
+                        device_type.clone(),
+                        *multimedia,
+                        *communication,
+                    )
+                })
+                .collect(),
+        ))
+    }
+
+    /// A scripted host standing in for AudioDeviceCmdlets: the list script reports
+    /// `devices` and each `Set-AudioDevice` moves the role it names. The `failing` IDs
+    /// get as far as their multimedia switch, then their communication switch errors,
+    /// leaving the switch half-applied. Switch scripts are recorded in `switches`.
+    fn simulated_cmdlets(
+        devices: SimulatedDevices,
+        failing: &'static [&'static str],
+        switches: Arc<std::sync::Mutex<Vec<String>>>,
+    ) -> PowerShellHost {
+        simulated_cmdlets_with(devices, failing, switches, |_| None)
+    }
+
+    /// `simulated_cmdlets` where `answer` gets the first go at every script. Scripts it
+    /// answers are recorded in `scripts` along with the switches.
+    fn simulated_cmdlets_with(
+        devices: SimulatedDevices,
+        failing: &'static [&'static str],
+        scripts: Arc<std::sync::Mutex<Vec<String>>>,
+        answer: impl Fn(&str) -> Option<ScriptOutput> + Clone + Send + Sync + 'static,
+    ) -> PowerShellHost {
+        let switches = scripts;
+        PowerShellHost::scripted(move |script| {
+            if let Some(output) = answer(script) {
+                switches.lock().unwrap().push(script.to_string());
+                return output;
             }
-            catch {
-                @{
-                    success = $false
-                    error = $_.Exception.Message
-                    suggestion = "Try running as administrator or check internet connection"
-                } | ConvertTo-Json -Compress
-                exit 1
+            let ok = |stdout: String| ScriptOutput {
+                exit_code: Some(0),
+                stdout,
+                stderr: String::new(),
+            };
+            let mut rows = devices.lock().unwrap();
+            if !script.contains("Set-AudioDevice") {
+                let entries: Vec<serde_json::Value> = rows
+                    .iter()
+                    .map(|(id, device_type, multimedia, communication)| {
+                        serde_json::json!({
+                            "id": id, "name": format!("Device {}", id),
+                            "device_type": device_type, "state": "Active",
+                            "is_default": multimedia, "is_communication_default": communication,
+                        })
+                    })
+                    .collect();
+                return ok(serde_json::json!({ "devices": entries }).to_string());
             }
-        "#;
 
-        let output = self
-            .execute_powershell_with_retry(powershell_script, "module installation")
-            .await?;
-        let response: serde_json::Value = serde_json::from_str(&output)?;
+            switches.lock().unwrap().push(script.to_string());
+            for call in script.split("Set-AudioDevice -ID \"").skip(1) {
+                let (id, rest) = call.split_once('"').unwrap();
+                let communication = rest.trim_start().starts_with("-CommunicationOnly");
+                if communication && failing.contains(&id) {
+                    return ScriptOutput {
+                        exit_code: Some(1),
+                        stdout: String::new(),
+                        stderr: format!("Set-AudioDevice : {} is busy", id),
+                    };
+                }
+                let Some(device_type) = rows
+                    .iter()
+                    .find(|(row_id, ..)| row_id == id)
+                    .map(|(_, device_type, ..)| device_type.clone())
+                else {
+                    continue;
+                };
+                for (row_id, row_type, multimedia, comm) in rows.iter_mut() {
+                    if *row_type == device_type {
+                        let flag = if communication { comm } else { multimedia };
+                        *flag = *row_id == id;
+                    }
+                }
+            }
+            ok(r#"{"success":true}"#.to_string())
+        })
+    }
+
+    /// The (multimedia, communication) default IDs of `device_type`
+    fn simulated_defaults(
+        devices: &SimulatedDevices,
+        device_type: DeviceType,
+    ) -> (Option<String>, Option<String>) {
+        let rows = devices.lock().unwrap();
+        let find = |communication: bool| {
+            rows.iter()
+                .find(|(_, row_type, multimedia, comm)| {
+                    *row_type == device_type && if communication { *comm } else { *multimedia }
+                })
+                .map(|(id, ..)| id.clone())
+        };
+        (find(false), find(true))
+    }
+
+    /// A manager over `devices` with the headset/headset-mic pair saved as a group,
+    /// retrying nothing so failures show up at once
+    async fn manager_with_headset_group(
+        devices: &SimulatedDevices,
+        failing: &'static [&'static str],
+    ) -> (AudioManager, Arc<std::sync::Mutex<Vec<String>>>) {
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        let switches = Arc::new(std::sync::Mutex::new(Vec::new()));
+        use_host(
+            &mut manager,
+            simulated_cmdlets(devices.clone(), failing, switches.clone()),
+        );
+        *manager.config.get_mut() = test_config();
+        let mut config = manager.config.write().await;
+        config.groups.insert(
+            "Headset".to_string(),
+            DeviceGroup {
+                name: "Headset".to_string(),
+                playback_id: "headset".to_string(),
+                recording_id: "headset-mic".to_string(),
+            },
+        );
+        drop(config);
+        (manager, switches)
+    }
+
+    fn desk_devices() -> SimulatedDevices {
+        simulated_devices(&[
+            ("speakers", DeviceType::Playback, true, true),
+            ("headset", DeviceType::Playback, false, false),
+            ("webcam-mic", DeviceType::Recording, true, true),
+            ("headset-mic", DeviceType::Recording, false, false),
+        ])
+    }
+
+    #[tokio::test]
+    async fn applying_a_group_switches_both_endpoints() {
+        let devices = desk_devices();
+        let (manager, _) = manager_with_headset_group(&devices, &[]).await;
+
+        manager.apply_group("Headset").await.unwrap();
+
+        let headset = Some("headset".to_string());
+        let headset_mic = Some("headset-mic".to_string());
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Playback),
+            (headset.clone(), headset)
+        );
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Recording),
+            (headset_mic.clone(), headset_mic)
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_group_member_rolls_back_the_others() {
+        let devices = desk_devices();
+        let (manager, switches) = manager_with_headset_group(&devices, &["headset-mic"]).await;
+
+        let error = manager.apply_group("Headset").await.unwrap_err();
+        assert!(matches!(&error, AudioError::CommandFailed(message) if message.contains("busy")));
+
+        // The headset did switch before the microphone failed, then got rolled back
+        assert!(switches.lock().unwrap()[0].contains(r#"Set-AudioDevice -ID "headset""#));
+        let speakers = Some("speakers".to_string());
+        let webcam_mic = Some("webcam-mic".to_string());
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Playback),
+            (speakers.clone(), speakers)
+        );
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Recording),
+            (webcam_mic.clone(), webcam_mic)
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_switch_restores_all_four_defaults() {
+        // Calls go to the headset while everything else plays on the speakers
+        let devices = simulated_devices(&[
+            ("speakers", DeviceType::Playback, true, false),
+            ("headset", DeviceType::Playback, false, true),
+            ("dock", DeviceType::Playback, false, false),
+            ("webcam-mic", DeviceType::Recording, true, false),
+            ("headset-mic", DeviceType::Recording, false, true),
+        ]);
+        let (manager, switches) = manager_with_headset_group(&devices, &["dock"]).await;
 
-        if response["success"].as_bool().unwrap_or(false) {
-            info!("Successfully installed AudioDeviceCmdlets module");
-        } else {
-            let error_msg = response["error"]
-                .as_str()
-                .unwrap_or("Unknown installation error");
-            return Err(AudioError::CommandFailed(error_msg.to_string()));
+        // The dock takes the multimedia role, then its communication switch fails
+        let error = manager.set_default_audio_device("dock").await.unwrap_err();
+        assert!(matches!(&error, AudioError::CommandFailed(message) if message.contains("busy")));
+
+        let some = |id: &str| Some(id.to_string());
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Playback),
+            (some("speakers"), some("headset"))
+        );
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Recording),
+            (some("webcam-mic"), some("headset-mic"))
+        );
+        let switches = switches.lock().unwrap();
+        for restore in [
+            r#"Set-AudioDevice -ID "speakers" -DefaultOnly"#,
+            r#"Set-AudioDevice -ID "headset" -CommunicationOnly"#,
+            r#"Set-AudioDevice -ID "webcam-mic" -DefaultOnly"#,
+            r#"Set-AudioDevice -ID "headset-mic" -CommunicationOnly"#,
+        ] {
+            assert!(switches[1..].iter().any(|script| script.contains(restore)));
         }
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn alias_takes_precedence_over_device_names() {
+        let devices = desk_devices();
+        let (manager, _switches) = manager_with_headset_group(&devices, &[]).await;
+        manager.config.write().await.match_mode = MatchMode::Substring;
+        let playback_default = || simulated_defaults(&devices, DeviceType::Playback).0;
+
+        // "speakers" names the speakers by their device name, but the alias wins
+        manager.set_alias("Speakers", "headset").await.unwrap();
+        manager.quick_switch_to_device("speakers").await.unwrap();
+        assert_eq!(playback_default().as_deref(), Some("headset"));
+
+        // Display names label devices in the UI; they aren't matched when switching
+        manager
+            .set_device_alias("speakers", "Headphones")
+            .await
+            .unwrap();
+        assert!(matches!(
+            manager.quick_switch_to_device("headphones").await,
+            Err(AudioError::DeviceNotFound(_))
+        ));
+
+        // Without the alias the device name matches again
+        assert!(manager.remove_alias("speakers").await.unwrap());
+        manager.quick_switch_to_device("speakers").await.unwrap();
+        assert_eq!(playback_default().as_deref(), Some("speakers"));
     }
 
-    /// Execute PowerShell with retry logic (Step 20)
-    async fn execute_powershell_with_retry(
-        &self,
-        script: &str,
-        operation: &str,
-    ) -> AudioResult<String> {
-        let mut last_error = None;
+    #[tokio::test]
+    async fn latency_measurement_restores_the_original_defaults() {
+        let some = |id: &str| Some(id.to_string());
+        let devices = desk_devices();
+        let (manager, switches) = manager_with_headset_group(&devices, &[]).await;
 
-        for attempt in 1..=MAX_RETRY_ATTEMPTS {
-            debug!(
-                "Executing PowerShell {} (attempt {}/{})",
-                operation, attempt, MAX_RETRY_ATTEMPTS
-            );
+        let latency = manager.measure_switch_latency("headset").await.unwrap();
+        assert!(!latency.already_default);
+        assert!(switches.lock().unwrap()[0].contains(r#"Set-AudioDevice -ID "headset""#));
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Playback),
+            (some("speakers"), some("speakers"))
+        );
 
-            let result = Command::new("powershell")
-                .args(&[
-                    "-ExecutionPolicy",
-                    "Bypass",
-                    "-NoProfile",
-                    "-Command",
-                    script,
-                ])
-                .output();
+        // A forward switch that fails halfway is still undone
+        let devices = desk_devices();
+        let (manager, _switches) = manager_with_headset_group(&devices, &["headset"]).await;
+        assert!(manager.measure_switch_latency("headset").await.is_err());
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Playback),
+            (some("speakers"), some("speakers"))
+        );
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Recording),
+            (some("webcam-mic"), some("webcam-mic"))
+        );
+    }
 
-            match result {
-                Ok(output) => {
-                    if output.status.success() {
-                        let stdout = String::from_utf8_lossy(&output.stdout);
-                        debug!("PowerShell {} succeeded on attempt {}", operation, attempt);
-                        return Ok(stdout.to_string());
-                    } else {
-                        let stderr = String::from_utf8_lossy(&output.stderr);
-                        last_error = Some(AudioError::CommandFailed(stderr.to_string()));
-                        warn!(
-                            "PowerShell {} failed on attempt {}: {}",
-                            operation, attempt, stderr
-                        );
-                    }
-                }
-                Err(e) => {
-                    last_error = Some(AudioError::from(e));
-                    warn!(
-                        "PowerShell execution error on attempt {}: {:?}",
-                        attempt, last_error
-                    );
-                }
-            }
+    #[tokio::test]
+    async fn read_only_mode_blocks_every_change_before_running_a_script() {
+        let devices = desk_devices();
+        let (manager, switches) = manager_with_headset_group(&devices, &[]).await;
+        let listed = manager.get_audio_devices().await.unwrap();
+        manager.set_read_only(true);
 
-            if attempt < MAX_RETRY_ATTEMPTS {
-                let delay = RETRY_BASE_DELAY * attempt;
-                debug!("Retrying {} in {}ms", operation, delay.as_millis());
-                tokio::time::sleep(delay).await;
-            }
-        }
+        let read_only = |result: AudioResult<()>| matches!(result, Err(AudioError::ReadOnly(_)));
+        assert!(read_only(manager.set_default_audio_device("headset").await));
+        assert!(read_only(
+            manager
+                .set_group_volume(&["headset".to_string()], 40)
+                .await
+                .map(drop)
+        ));
+        assert!(read_only(manager.set_device_mute("headset", true).await));
+        assert!(read_only(
+            manager.toggle_device_mute("headset").await.map(drop)
+        ));
+        assert!(read_only(
+            manager.measure_switch_latency("headset").await.map(drop)
+        ));
+        assert!(read_only(
+            manager.set_loudness_equalization("headset", true).await
+        ));
+        assert!(read_only(manager.apply_group("Headset").await));
+        assert!(read_only(manager.install_module().await));
+        assert!(read_only(manager.set_alias("desk", "headset").await));
+        assert!(read_only(manager.set_follow_focus(true).await));
+        assert!(read_only(
+            manager.remove_hotkey("Ctrl+Alt+H").await.map(drop)
+        ));
+        assert!(read_only(
+            manager.save_priorities(Vec::new(), Vec::new()).await
+        ));
+        assert!(read_only(manager.factory_reset().await.map(drop)));
 
-        error!(
-            "PowerShell {} failed after {} attempts",
-            operation, MAX_RETRY_ATTEMPTS
+        assert!(switches.lock().unwrap().is_empty());
+        // Reads keep working
+        assert_eq!(
+            manager.get_audio_devices().await.unwrap().len(),
+            listed.len()
         );
-        Err(last_error
-            .unwrap_or_else(|| AudioError::CommandFailed("Unknown PowerShell error".to_string())))
+        manager.set_read_only(false);
+        manager.set_default_audio_device("headset").await.unwrap();
     }
 
-    /// Parse device list response from PowerShell
-    fn parse_device_list_response(&self, json_output: &str) -> AudioResult<Vec<AudioDevice>> {
-        let response: serde_json::Value = serde_json::from_str(json_output)?;
+    #[tokio::test]
+    async fn best_available_switches_to_the_first_matching_pattern() {
+        let devices = desk_devices();
+        let (manager, _switches) = manager_with_headset_group(&devices, &[]).await;
+        manager.config.write().await.match_mode = MatchMode::Substring;
 
-        if let Some(error) = response.get("error") {
-            return Err(AudioError::CommandFailed(
-                error.as_str().unwrap_or("Unknown error").to_string(),
-            ));
-        }
+        let best = manager
+            .switch_to_best_available(
+                &DeviceType::Playback,
+                vec![
+                    "dock".to_string(),
+                    "headset".to_string(),
+                    "speakers".to_string(),
+                ],
+            )
+            .await
+            .unwrap();
 
-        let devices_array = response["devices"]
-            .as_array()
-            .ok_or_else(|| AudioError::ParseError("Missing devices array".to_string()))?;
-
-        let mut audio_devices = Vec::new();
-        for device in devices_array {
-            let device_type = match device["device_type"].as_str() {
-                Some("Playback") => DeviceType::Playback,
-                Some("Recording") => DeviceType::Recording,
-                _ => continue,
-            };
+        assert_eq!(best.pattern, "headset");
+        assert_eq!(best.device.id, "headset");
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Playback)
+                .0
+                .as_deref(),
+            Some("headset")
+        );
+    }
 
-            let state = match device["state"].as_str() {
-                Some("Active") => DeviceState::Active,
-                Some("Disabled") => DeviceState::Disabled,
-                Some("NotPresent") => DeviceState::NotPresent,
-                Some("Unplugged") => DeviceState::Unplugged,
-                _ => DeviceState::Unknown,
-            };
+    #[tokio::test]
+    async fn best_available_names_every_pattern_when_nothing_matches() {
+        let devices = desk_devices();
+        let (manager, switches) = manager_with_headset_group(&devices, &[]).await;
+        manager.config.write().await.match_mode = MatchMode::Substring;
 
-            audio_devices.push(AudioDevice {
-                id: device["id"].as_str().unwrap_or("").to_string(),
-                name: device["name"].as_str().unwrap_or("").to_string(),
-                device_type,
-                state,
-                is_default: device["is_default"].as_bool().unwrap_or(false),
-                is_communication_default: device["is_communication_default"]
-                    .as_bool()
-                    .unwrap_or(false),
-                last_seen: device["last_seen"].as_str().map(|s| s.to_string()),
-            });
-        }
+        // The headset microphone is a recording device, so it doesn't count
+        let error = manager
+            .switch_to_best_available(
+                &DeviceType::Playback,
+                vec!["dock".to_string(), "mic".to_string()],
+            )
+            .await
+            .unwrap_err();
 
-        Ok(audio_devices)
+        assert!(matches!(
+            &error,
+            AudioError::DeviceNotFound(message)
+                if message == "no active Playback device matches any of: dock, mic"
+        ));
+        assert!(switches.lock().unwrap().is_empty());
     }
 
-    /// Save devices snapshot as a JSON line into the db_path. Best-effort, append-only.
-    async fn save_devices_snapshot(
-        db_path: &PathBuf,
-        devices: &Vec<AudioDevice>,
-        session_id: &str,
-    ) -> Result<(), String> {
-        // Ensure parent dir exists
-        if let Some(parent) = db_path.parent() {
-            if let Err(e) = std::fs::create_dir_all(parent) {
-                return Err(format!("Failed to create db dir: {}", e));
-            }
-        }
+    /// Panic restore over a USB DAC (the default) and the onboard speakers, with
+    /// `enumerators` as their enumerator names. Returns the target and the scripts run
+    /// other than listings.
+    async fn panic_restore_with(enumerators: serde_json::Value) -> (String, Vec<String>) {
+        let devices = simulated_devices(&[
+            ("{0.0.0.00000000}.{dac}", DeviceType::Playback, true, true),
+            (
+                "{0.0.0.00000000}.{onboard}",
+                DeviceType::Playback,
+                false,
+                false,
+            ),
+        ]);
+        let scripts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let responses = vec![
+            (ENUMERATOR_NAME_KEY, Ok(enumerators.to_string())),
+            ("$wasMuted", Ok(r#"{"was_muted":false}"#.to_string())),
+            (
+                "MasterVolumeLevelScalar =",
+                Ok(r#"[{"device_id":"","success":true}]"#.to_string()),
+            ),
+        ];
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        *manager.config.get_mut() = test_config();
+        use_host(
+            &mut manager,
+            simulated_cmdlets_with(devices, &[], scripts.clone(), move |script| {
+                responses
+                    .iter()
+                    .any(|(key, _)| script.contains(key))
+                    .then(|| canned_output(&responses, script))
+            }),
+        );
 
-        let file_res = OpenOptions::new().create(true).append(true).open(db_path);
+        let target = manager.panic_restore().await.unwrap();
+        let scripts = scripts.lock().unwrap().clone();
+        (target.id, scripts)
+    }
 
-        let mut file = match file_res {
-            Ok(f) => f,
-            Err(e) => return Err(format!("Failed to open db file: {}", e)),
+    #[tokio::test]
+    async fn panic_restore_prefers_the_onboard_output() {
+        let (target, scripts) = panic_restore_with(serde_json::json!({
+            "{0.0.0.00000000}.{dac}": "USB",
+            "{0.0.0.00000000}.{onboard}": "HDAUDIO",
+        }))
+        .await;
+
+        assert_eq!(target, "{0.0.0.00000000}.{onboard}");
+        // Both candidates are checked in a single script
+        let reads: Vec<&String> = scripts
+            .iter()
+            .filter(|s| s.contains(ENUMERATOR_NAME_KEY))
+            .collect();
+        assert_eq!(reads.len(), 1);
+        assert!(reads[0].contains(r#"id = "{0.0.0.00000000}.{dac}""#));
+        assert!(reads[0].contains(r#"id = "{0.0.0.00000000}.{onboard}""#));
+        assert!(scripts
+            .iter()
+            .any(|s| s.contains(r#"Set-AudioDevice -ID "{0.0.0.00000000}.{onboard}""#)));
+    }
+
+    #[tokio::test]
+    async fn panic_restore_falls_back_to_the_first_active_output() {
+        let (target, _scripts) = panic_restore_with(serde_json::json!({
+            "{0.0.0.00000000}.{dac}": "USB",
+        }))
+        .await;
+        assert_eq!(target, "{0.0.0.00000000}.{dac}");
+    }
+
+    #[tokio::test]
+    async fn failed_recording_switch_rolls_back_the_pair() {
+        let previous = DefaultDevices {
+            playback: Some("speakers".to_string()),
+            recording: Some("webcam-mic".to_string()),
+            communication_playback: Some("speakers".to_string()),
+            communication_recording: Some("webcam-mic".to_string()),
+        };
+        let calls = &std::sync::Mutex::new(Vec::new());
+        let switch = move |id: String, role: DefaultRole| async move {
+            calls.lock().unwrap().push((id.clone(), role));
+            if id == "headset-mic" {
+                Err(AudioError::CommandFailed("device busy".to_string()))
+            } else {
+                Ok(())
+            }
         };
 
-        let timestamp: DateTime<Utc> = Utc::now();
-        let snapshot = serde_json::json!({
-            "timestamp": timestamp.to_rfc3339(),
-            "session": session_id,
-            "count": devices.len(),
-            "devices": devices,
-        });
+        let targets = [
+            ("headset", DefaultRole::Both),
+            ("headset-mic", DefaultRole::Both),
+        ];
+        let result = apply_or_roll_back(&targets, &previous, switch).await;
+        assert!(matches!(result, Err(AudioError::CommandFailed(_))));
 
-        let line = match serde_json::to_string(&snapshot) {
-            Ok(s) => s,
-            Err(e) => return Err(format!("Failed to serialize snapshot: {}", e)),
+        let calls = calls.lock().unwrap().clone();
+        assert_eq!(
+            calls,
+            [
+                ("headset".to_string(), DefaultRole::Both),
+                ("headset-mic".to_string(), DefaultRole::Both),
+                ("speakers".to_string(), DefaultRole::Both),
+                ("webcam-mic".to_string(), DefaultRole::Both),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn successful_pair_switch_does_not_roll_back() {
+        let previous = DefaultDevices {
+            playback: Some("speakers".to_string()),
+            ..DefaultDevices::default()
+        };
+        let calls = &std::sync::Mutex::new(Vec::new());
+        let switch = move |id: String, role: DefaultRole| async move {
+            calls.lock().unwrap().push((id, role));
+            Ok(())
         };
 
-        if let Err(e) = writeln!(file, "{}", line) {
-            return Err(format!("Failed to write snapshot: {}", e));
+        apply_or_roll_back(&[("headset", DefaultRole::Both)], &previous, switch)
+            .await
+            .unwrap();
+        assert_eq!(calls.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn registry_scripts_use_the_64_bit_view() {
+        assert!(REGISTRY64_FUNCTIONS.contains("[Microsoft.Win32.RegistryView]::Registry64"));
+        assert!(DEVICE_LIST_SCRIPT.starts_with(REGISTRY64_FUNCTIONS));
+        assert!(with_registry64(String::new()).contains("Get-Registry64Values"));
+        assert_eq!(
+            endpoint_registry_path("{0.0.0.00000000}.{guid}").unwrap(),
+            r"SOFTWARE\Microsoft\Windows\CurrentVersion\MMDevices\Audio\Render\{guid}"
+        );
+
+        // The PowerShell registry provider follows the process's bitness, so no script
+        // may go through it
+        // (line by line, so a CRLF checkout scans the same code)
+        let scripts: Vec<&str> = include_str!("audio_manager.rs")
+            .lines()
+            .take_while(|line| *line != "mod tests {")
+            .collect();
+        assert!(scripts
+            .iter()
+            .any(|line| line.contains("Open-Registry64Key")));
+        for provider_access in [
+            "HKLM:",
+            "Get-ItemProperty",
+            "Set-ItemProperty",
+            "Get-ChildItem",
+        ] {
+            assert!(
+                !scripts.iter().any(|line| line.contains(provider_access)),
+                "{} bypasses the 64-bit registry view",
+                provider_access
+            );
         }
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn cached_devices_are_read_without_enumerating() {
+        let clock = Arc::new(MockClock::new());
+        let manager = AudioManager::in_memory(clock.clone());
+
+        let empty = manager.get_cached_devices().await;
+        assert!(empty.devices.is_empty());
+        assert!(empty.is_stale);
+
+        // What `get_audio_devices` stores after enumerating
+        let devices = vec![
+            device("speakers", DeviceType::Playback, true),
+            device("mic", DeviceType::Recording, true),
+        ];
+        manager.cache_devices(&devices, clock.now()).await;
+        let cached = manager.get_cached_devices().await;
+        assert_eq!(cached.devices.len(), 2);
+        assert!(!cached.is_stale);
+
+        // A stale cache is reported, not refreshed
+        clock.advance(Duration::from_secs(60));
+        let stale = manager.get_cached_devices().await;
+        assert_eq!(stale.devices.len(), 2);
+        assert!(stale.is_stale);
+
+        manager.clear_device_cache().await;
+        assert!(manager.get_cached_devices().await.devices.is_empty());
+
+        // No PowerShell ran along the way
+        assert!(manager
+            .get_operation_log(LogPage::default())
+            .await
+            .is_empty());
     }
 
-    /// Load recent snapshots from the JSONL DB (returns up to `limit` latest entries)
-    pub fn load_devices_snapshot(
-        db_path: &PathBuf,
-        limit: usize,
-    ) -> Result<Vec<serde_json::Value>, String> {
-        let f = File::open(db_path).map_err(|e| format!("Failed to open db file: {}", e))?;
-        let reader = BufReader::new(f);
-        let mut entries = Vec::new();
-        for line in reader.lines() {
-            match line {
-                Ok(l) => match serde_json::from_str::<serde_json::Value>(&l) {
-                    Ok(v) => entries.push(v),
-                    Err(_) => continue,
-                },
-                Err(_) => continue,
-            }
+    #[test]
+    fn hotkey_actions_resolve_against_the_current_devices() {
+        let switch = |id: &str| HotkeyStep::Switch {
+            id: id.to_string(),
+            device_type: DeviceType::Playback,
+            role: DefaultRole::Both,
+        };
+        let mut speakers = device("speakers", DeviceType::Playback, true);
+        speakers.index = Some(1);
+        let mut headset = device("headset", DeviceType::Playback, false);
+        headset.index = Some(2);
+        let mut hdmi = device("hdmi", DeviceType::Playback, false);
+        hdmi.index = Some(3);
+        hdmi.state = DeviceState::Unplugged;
+        let devices = vec![hdmi, headset, speakers];
+        let mut headset_default = devices.clone();
+        for d in &mut headset_default {
+            d.is_default = d.id == "headset";
         }
 
-        // return latest `limit` entries
-        if entries.len() > limit {
-            let start = entries.len() - limit;
-            Ok(entries[start..].to_vec())
-        } else {
-            Ok(entries)
+        assert_eq!(
+            hotkey_step(&HotkeyAction::SwitchToDevice("headset".into()), &devices),
+            switch("headset")
+        );
+        assert_eq!(
+            hotkey_step(&HotkeyAction::SwitchToDevice("missing".into()), &devices),
+            HotkeyStep::Nothing
+        );
+
+        // Cycling skips the unplugged HDMI output and wraps around
+        let cycle = HotkeyAction::CycleDefault(DeviceType::Playback);
+        assert_eq!(hotkey_step(&cycle, &devices), switch("headset"));
+        assert_eq!(hotkey_step(&cycle, &headset_default), switch("speakers"));
+        assert_eq!(
+            hotkey_step(&HotkeyAction::CycleDefault(DeviceType::Recording), &devices),
+            HotkeyStep::Nothing
+        );
+
+        let toggle = HotkeyAction::ToggleBetween("speakers".into(), "headset".into());
+        assert_eq!(hotkey_step(&toggle, &devices), switch("headset"));
+        assert_eq!(hotkey_step(&toggle, &headset_default), switch("speakers"));
+
+        // Toggling calls goes by the communication default and moves only that role
+        let communication = |id: &str| HotkeyStep::Switch {
+            id: id.to_string(),
+            device_type: DeviceType::Playback,
+            role: DefaultRole::Communications,
+        };
+        let toggle_calls = HotkeyAction::ToggleCommunication("speakers".into(), "headset".into());
+        assert_eq!(
+            hotkey_step(&toggle_calls, &devices),
+            communication("speakers")
+        );
+        let mut speakers_for_calls = devices.clone();
+        for d in &mut speakers_for_calls {
+            d.is_communication_default = d.id == "speakers";
         }
+        assert_eq!(
+            hotkey_step(&toggle_calls, &speakers_for_calls),
+            communication("headset")
+        );
+
+        assert_eq!(
+            hotkey_step(&HotkeyAction::PanicRestore, &devices),
+            HotkeyStep::PanicRestore
+        );
+        assert_eq!(
+            hotkey_step(&HotkeyAction::ApplyProfile("Night".into()), &devices),
+            HotkeyStep::ApplyProfile("Night".to_string())
+        );
     }
 
-    /// Change default device implementation
-    async fn change_default_device(&self, device_id: &str) -> AudioResult<()> {
-        let powershell_script = format!(
-            r#"
-                try {{
-                    Import-Module AudioDeviceCmdlets -ErrorAction Stop
-                    
-                    $device = Get-AudioDevice -List | Where-Object {{ $_.ID -eq "{}" }}
-                    
-                    if (-not $device) {{
-                        throw "Device not found: {}"
-                    }}
-                    
-                    # Set as default for both regular and communication
-                    if ($device.Type -eq "Playback") {{
-                        Set-AudioDevice -ID "{}" -DefaultOnly
-                        Set-AudioDevice -ID "{}" -CommunicationOnly
-                    }} else {{
-                        Set-AudioDevice -ID "{}" -DefaultOnly
-                        Set-AudioDevice -ID "{}" -CommunicationOnly
-                    }}
-                    
-                    @{{
-                        success = $true
-                        device_id = "{}"
-                        device_name = $device.Name
-                        device_type = $device.Type
-                    }} | ConvertTo-Json -Compress
-                }}
-                catch {{
-                    @{{
-                        success = $false
-                        error = $_.Exception.Message
-                        device_id = "{}"
-                    }} | ConvertTo-Json -Compress
-                    exit 1
-                }}
-            "#,
-            device_id, device_id, device_id, device_id, device_id, device_id, device_id, device_id
+    #[test]
+    fn slot_hotkeys_pick_the_active_device_in_that_slot() {
+        let entry = |device_id: &str, priority: usize| DevicePriority {
+            device_id: device_id.to_string(),
+            device_name: format!("Device {}", device_id),
+            device_type: DeviceType::Playback,
+            priority,
+        };
+        let chain = vec![entry("headset", 0), entry("hdmi", 1), entry("speakers", 3)];
+        let mut hdmi = device("hdmi", DeviceType::Playback, false);
+        hdmi.state = DeviceState::Unplugged;
+        let devices = vec![
+            device("speakers", DeviceType::Playback, true),
+            device("headset", DeviceType::Playback, false),
+            hdmi,
+        ];
+
+        assert_eq!(
+            slot_device(&chain, &devices, 0).map(|d| d.id.as_str()),
+            Some("headset")
+        );
+        assert_eq!(
+            slot_device(&chain, &devices, 3).map(|d| d.id.as_str()),
+            Some("speakers")
+        );
+        // Unplugged, and empty
+        assert!(slot_device(&chain, &devices, 1).is_none());
+        assert!(slot_device(&chain, &devices, 2).is_none());
+        assert_eq!(
+            hotkey_step(
+                &HotkeyAction::SwitchToSlot(DeviceType::Playback, 0),
+                &devices
+            ),
+            HotkeyStep::SwitchToSlot(DeviceType::Playback, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn slot_hotkey_switches_to_the_slot_device() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        let devices = simulated_devices(&[
+            ("headset", DeviceType::Playback, false, false),
+            ("speakers", DeviceType::Playback, true, true),
+        ]);
+        let switches = Arc::new(std::sync::Mutex::new(Vec::new()));
+        use_host(
+            &mut manager,
+            simulated_cmdlets(devices.clone(), &[], switches.clone()),
         );
+        manager
+            .set_hotkey(
+                "Ctrl+Alt+1",
+                HotkeyAction::SwitchToSlot(DeviceType::Playback, 0),
+            )
+            .await
+            .unwrap();
 
-        self.execute_powershell_with_retry(&powershell_script, "set default device")
-            .await?;
-        Ok(())
+        manager.run_hotkey("Ctrl+Alt+1").await.unwrap();
+
+        let switches = switches.lock().unwrap();
+        assert_eq!(switches.len(), 1);
+        assert!(switches[0].contains(r#"Set-AudioDevice -ID "headset""#));
+        assert_eq!(
+            simulated_defaults(&devices, DeviceType::Playback),
+            (Some("headset".to_string()), Some("headset".to_string()))
+        );
     }
 
-    /// Get current default devices for fallback
-    async fn get_current_defaults(&self) -> AudioResult<(Option<String>, Option<String>)> {
-        let devices = self.get_audio_devices().await?;
+    #[test]
+    fn cycling_with_a_single_active_device_does_nothing() {
+        let devices = vec![
+            device("speakers", DeviceType::Playback, true),
+            device("mic", DeviceType::Recording, true),
+        ];
+        assert!(next_default(&devices, &DeviceType::Playback).is_none());
+    }
 
-        let default_playback = devices
-            .iter()
-            .find(|d| matches!(d.device_type, DeviceType::Playback) && d.is_default)
-            .map(|d| d.id.clone());
+    #[test]
+    fn priority_cycling_walks_the_chain_and_wraps() {
+        let entry = |id: &str, priority: usize| DevicePriority {
+            device_id: id.to_string(),
+            device_name: format!("Device {}", id),
+            device_type: DeviceType::Playback,
+            priority,
+        };
+        let chain = vec![entry("headset", 0), entry("dock", 1), entry("speakers", 2)];
+        let mut dock = device("dock", DeviceType::Playback, false);
+        dock.state = DeviceState::Unplugged;
+        let mut speakers = device("speakers", DeviceType::Playback, false);
+        speakers.is_communication_default = true;
+        let devices = vec![
+            device("headset", DeviceType::Playback, true),
+            dock,
+            speakers,
+            device("hdmi", DeviceType::Playback, false),
+        ];
+        let next = |role| next_in_chain(&chain, &devices, role).map(|d| d.id.as_str());
 
-        let default_recording = devices
-            .iter()
-            .find(|d| matches!(d.device_type, DeviceType::Recording) && d.is_default)
-            .map(|d| d.id.clone());
+        // The unplugged dock is skipped, and devices outside the chain are never picked
+        assert_eq!(next(DefaultRole::Both), Some("speakers"));
+        assert_eq!(next(DefaultRole::Multimedia), Some("speakers"));
+        // The communication role is on the last member, so it wraps to the top
+        assert_eq!(next(DefaultRole::Communications), Some("headset"));
 
-        Ok((default_playback, default_recording))
+        assert!(next_in_chain(&chain[..2], &devices, DefaultRole::Both).is_none());
     }
 
-    /// Fallback to previous device on failure
-    async fn fallback_to_previous_device(
-        &self,
-        defaults: &(Option<String>, Option<String>),
-    ) -> AudioResult<()> {
-        warn!("Attempting to fallback to previous default devices");
+    #[test]
+    fn log_pages_return_the_newest_entries_before_the_cursor() {
+        let start = Utc::now();
+        let at = |seconds: i64| start + chrono::Duration::seconds(seconds);
+        let log: VecDeque<DateTime<Utc>> = (0..10).map(at).collect();
+        let page = |limit, before| LogPage { limit, before }.apply(&log, |timestamp| *timestamp);
 
-        if let Some(playback_id) = &defaults.0 {
-            match self.change_default_device(playback_id).await {
-                Ok(_) => info!(
-                    "Successfully restored previous playback device: {}",
-                    playback_id
-                ),
-                Err(e) => error!("Failed to restore previous playback device: {}", e),
-            }
+        assert_eq!(page(None, None), log.iter().copied().collect::<Vec<_>>());
+        assert_eq!(page(Some(3), None), [at(7), at(8), at(9)]);
+        assert_eq!(page(Some(3), Some(at(7))), [at(4), at(5), at(6)]);
+        // Paging back from the oldest entry of the previous page
+        assert_eq!(page(Some(3), Some(at(4))), [at(1), at(2), at(3)]);
+        assert_eq!(page(Some(3), Some(at(1))), [at(0)]);
+        assert!(page(Some(3), Some(at(0))).is_empty());
+        assert!(page(Some(0), None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn factory_reset_clears_every_setting_after_backing_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = Arc::new(MockClock::new());
+        let mut manager = manager_preferring_headset(&clock).await;
+        manager.config_path = Some(dir.path().join("config.json"));
+        {
+            let mut config = manager.config.write().await;
+            config.profiles.insert(
+                "Work".to_string(),
+                Profile {
+                    name: "Work".to_string(),
+                    default_playback: Some("headset".to_string()),
+                    default_recording: None,
+                    communication_playback: None,
+                    communication_recording: None,
+                    priorities: None,
+                },
+            );
+            config.groups.insert(
+                "Headset".to_string(),
+                DeviceGroup {
+                    name: "Headset".to_string(),
+                    playback_id: "headset".to_string(),
+                    recording_id: "headset-mic".to_string(),
+                },
+            );
+            config
+                .aliases
+                .insert("cans".to_string(), "Playback:headset".to_string());
+            config
+                .app_preferences
+                .insert("discord.exe".to_string(), "Playback:headset".to_string());
+            config
+                .hotkeys
+                .insert("Ctrl+Alt+P".to_string(), HotkeyAction::PanicRestore);
+            config.quiet_hours = Some(QuietHours {
+                start: chrono::NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+                end: chrono::NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+                max_volume: 20,
+            });
+            config.auto_install_module = true;
+            config.unmute_on_autoswitch = true;
+            config.autoswitch_min_volume = Some(40);
+            config.match_mode = MatchMode::Exact;
+            config.pause_watcher_on_battery = true;
+            config.switch_coalesce_window_ms = Some(0);
+            config.backup_limit = Some(3);
+            config.cache_ttl_ms = Some(300_000);
+        }
+        {
+            let mut state = manager.state.write().await;
+            state.set_override(DeviceOverride {
+                device_id: "speakers".to_string(),
+                device_type: DeviceType::Playback,
+                expires_at: None,
+            });
         }
 
-        if let Some(recording_id) = &defaults.1 {
-            match self.change_default_device(recording_id).await {
-                Ok(_) => info!(
-                    "Successfully restored previous recording device: {}",
-                    recording_id
-                ),
-                Err(e) => error!("Failed to restore previous recording device: {}", e),
-            }
+        let backup = manager.factory_reset().await.unwrap();
+
+        let config = manager.config.read().await.clone();
+        assert!(config.profiles.is_empty());
+        assert!(config.groups.is_empty());
+        assert!(config.aliases.is_empty());
+        assert!(config.app_preferences.is_empty());
+        assert!(config.hotkeys.is_empty());
+        assert!(config.quiet_hours.is_none());
+        assert!(!config.auto_install_module);
+        assert!(!config.unmute_on_autoswitch);
+        assert_eq!(config.autoswitch_min_volume, None);
+        assert_eq!(config.match_mode, MatchMode::Substring);
+        assert!(!config.pause_watcher_on_battery);
+        assert_eq!(config.switch_coalesce_window_ms, None);
+        assert_eq!(config.backup_limit, None);
+        assert_eq!(config.cache_ttl_ms, None);
+
+        assert!(manager
+            .priorities
+            .chain(&DeviceType::Playback)
+            .await
+            .is_empty());
+        assert!(manager
+            .priorities
+            .chain(&DeviceType::Recording)
+            .await
+            .is_empty());
+        let state = manager.state.read().await;
+        assert!(state.device_overrides.is_empty());
+
+        let saved: AudioManagerConfig = config::load_json(&dir.path().join("config.json")).unwrap();
+        assert!(saved.profiles.is_empty());
+        let backed_up: AudioManagerConfig = config::load_json(Path::new(&backup)).unwrap();
+        assert!(backed_up.profiles.contains_key("Work"));
+        assert_eq!(backed_up.backup_limit, Some(3));
+    }
+
+    #[test]
+    fn loudness_blob_round_trips() {
+        for enabled in [true, false] {
+            assert_eq!(parse_loudness_blob(&loudness_blob(enabled)), Some(enabled));
         }
 
-        Ok(())
+        // As written by the Sound control panel
+        let enabled = [0x0b, 0, 0, 0, 1, 0, 0, 0, 0xff, 0xff, 0, 0];
+        let disabled = [0x0b, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(parse_loudness_blob(&enabled), Some(true));
+        assert_eq!(parse_loudness_blob(&disabled), Some(false));
+
+        // Not a VT_BOOL, or truncated
+        assert_eq!(
+            parse_loudness_blob(&[0x13, 0, 0, 0, 1, 0, 0, 0, 1, 0]),
+            None
+        );
+        assert_eq!(parse_loudness_blob(&[0x0b, 0, 0, 0]), None);
     }
 
-    /// Invalidate device cache
-    async fn invalidate_cache(&self) {
-        let mut state = self.state.write().await;
-        state.last_refresh = None;
-        state.cached_devices.clear();
-        debug!("Audio device cache invalidated");
+    #[tokio::test]
+    async fn denied_loudness_write_requires_elevation() {
+        let clock = Arc::new(MockClock::new());
+        let mut manager = AudioManager::in_memory(clock.clone());
+        manager
+            .cache_devices(
+                &[device(FIXTURE_SPEAKERS, DeviceType::Playback, true)],
+                clock.now(),
+            )
+            .await;
+        *manager.config.get_mut() = test_config();
+        let disabled = serde_json::json!({
+            "supported": true,
+            "value": loudness_blob(false),
+        });
+        use_host(
+            &mut manager,
+            PowerShellHost::scripted(move |script| {
+                if script.contains("Set-Registry64Value \"") {
+                    ScriptOutput {
+                    exit_code: Some(1),
+                    stdout: r#"{"error":"Administrator rights are required to change HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\MMDevices\\Audio\\Render\\{5f2a8c1e-3b4d-4e6f-9a7b-1c2d3e4f5a6b}\\FxProperties"}"#
+                        .to_string(),
+                    stderr: String::new(),
+                }
+                } else {
+                    canned_output(
+                        &[("$props = Get-Registry64Values", Ok(disabled.to_string()))],
+                        script,
+                    )
+                }
+            }),
+        );
+
+        let error = manager
+            .set_loudness_equalization(FIXTURE_SPEAKERS, true)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AudioError::ElevationRequired(_)));
     }
 
-    /// Get session ID for debugging
-    pub fn get_session_id(&self) -> &str {
-        &self.session_id
+    #[test]
+    fn headset_endpoints_share_a_container() {
+        let pair = |id: &str, container: &str| (id.to_string(), container.to_string());
+        let containers = group_by_container(&[
+            pair(
+                "{0.0.0.00000000}.{headset}",
+                "{8C4B3A1E-0F2D-4E6B-9A7C-1D2E3F405162}",
+            ),
+            pair(
+                "{0.0.1.00000000}.{headset-mic}",
+                "8c4b3a1e-0f2d-4e6b-9a7c-1d2e3f405162",
+            ),
+            pair(
+                "{0.0.1.00000000}.{webcam}",
+                "{5A6B7C8D-1E2F-4A3B-8C9D-0E1F2A3B4C5D}",
+            ),
+            pair(
+                "{0.0.0.00000000}.{virtual}",
+                "{00000000-0000-0000-0000-000000000000}",
+            ),
+        ]);
+
+        assert_eq!(containers.len(), 2);
+        assert_eq!(
+            containers["8c4b3a1e-0f2d-4e6b-9a7c-1d2e3f405162"],
+            [
+                "{0.0.0.00000000}.{headset}",
+                "{0.0.1.00000000}.{headset-mic}"
+            ]
+        );
+        assert_eq!(
+            containers["5a6b7c8d-1e2f-4a3b-8c9d-0e1f2a3b4c5d"],
+            ["{0.0.1.00000000}.{webcam}"]
+        );
+    }
+
+    #[test]
+    fn same_container_compares_container_ids() {
+        let headset = "{5f3a8c2e-1b4d-4e6f-9a0b-7c8d9e0f1a2b}";
+        let webcam = "{0c1d2e3f-4a5b-6c7d-8e9f-a0b1c2d3e4f5}";
+        let none = "{00000000-0000-0000-0000-000000000000}";
+
+        assert!(same_container(headset, headset));
+        assert!(same_container(
+            headset,
+            "5F3A8C2E-1B4D-4E6F-9A0B-7C8D9E0F1A2B"
+        ));
+        assert!(!same_container(headset, webcam));
+        assert!(!same_container(none, none));
+        assert!(!same_container("", ""));
+    }
+
+    #[tokio::test]
+    async fn container_map_matches_endpoints_regardless_of_case() {
+        const FIXTURE_MICROPHONE: &str = "{0.0.1.00000000}.{7a4c5d6e-7f8a-4b9c-0d1e-4c5d6e7f8a9b}";
+        let endpoint = |id: &str, container: &str| serde_json::json!({ "id": id.to_uppercase(), "container_id": container });
+        let container_map = serde_json::json!({
+            "endpoints": [
+                endpoint(FIXTURE_SPEAKERS, "{1A2B3C4D-5E6F-4A7B-8C9D-0E1F2A3B4C5D}"),
+                endpoint(FIXTURE_MICROPHONE, "{1A2B3C4D-5E6F-4A7B-8C9D-0E1F2A3B4C5D}"),
+                endpoint(FIXTURE_HEADSET, "{6E7F8A9B-0C1D-4E2F-8A3B-4C5D6E7F8A9B}"),
+            ]
+        });
+        let mut manager = AudioManager::in_memory(Arc::new(MockClock::new()));
+        // Only the container map answers; a per-device container read would fail
+        use_host(
+            &mut manager,
+            PowerShellHost::canned(vec![
+                ("$allAudioDevices", Ok(DEVICE_LIST_FIXTURE.to_string())),
+                ("-Class AudioEndpoint", Ok(container_map.to_string())),
+            ]),
+        );
+
+        assert!(manager
+            .same_physical_device(FIXTURE_SPEAKERS, FIXTURE_MICROPHONE)
+            .await
+            .unwrap());
+        assert!(!manager
+            .same_physical_device(FIXTURE_HEADSET, FIXTURE_MICROPHONE)
+            .await
+            .unwrap());
+
+        let suggestions = manager.suggest_groups().await.unwrap();
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].playback_id, FIXTURE_SPEAKERS);
+        assert_eq!(suggestions[0].recording_id, FIXTURE_MICROPHONE);
     }
 }
@@ -0,0 +1,115 @@
+use crate::error::{AudioError, AudioResult};
+use serde::Serialize;
+use tauri_plugin_autostart::AutoLaunchManager;
+
+/// Argument the login entry launches the app with, so start-up can tell a launch at
+/// login from one by the user
+pub const AUTOSTART_ARG: &str = "--autostart";
+
+/// Whether the app launches at login, and minimized when it does
+#[derive(Debug, Clone, Serialize)]
+pub struct AutostartStatus {
+    pub enabled: bool,
+    pub start_minimized: bool,
+}
+
+/// The launch-at-login entry (`HKCU\Software\Microsoft\Windows\CurrentVersion\Run` on
+/// Windows)
+pub trait LoginItem {
+    fn is_enabled(&self) -> Result<bool, String>;
+    fn enable(&self) -> Result<(), String>;
+    fn disable(&self) -> Result<(), String>;
+}
+
+impl LoginItem for AutoLaunchManager {
+    fn is_enabled(&self) -> Result<bool, String> {
+        AutoLaunchManager::is_enabled(self).map_err(|e| e.to_string())
+    }
+
+    fn enable(&self) -> Result<(), String> {
+        AutoLaunchManager::enable(self).map_err(|e| e.to_string())
+    }
+
+    fn disable(&self) -> Result<(), String> {
+        AutoLaunchManager::disable(self).map_err(|e| e.to_string())
+    }
+}
+
+/// Add or remove the login entry, returning whether it is registered afterwards
+pub fn set_enabled(item: &impl LoginItem, enabled: bool) -> AudioResult<bool> {
+    let result = if enabled {
+        item.enable()
+    } else {
+        item.disable()
+    };
+    result.map_err(|e| {
+        AudioError::CommandFailed(format!("Could not update the launch-at-login entry: {}", e))
+    })?;
+    is_enabled(item)
+}
+
+pub fn is_enabled(item: &impl LoginItem) -> AudioResult<bool> {
+    item.is_enabled().map_err(|e| {
+        AudioError::CommandFailed(format!("Could not read the launch-at-login entry: {}", e))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[derive(Default)]
+    struct FakeLoginItem {
+        registered: Cell<bool>,
+        read_only: bool,
+    }
+
+    impl LoginItem for FakeLoginItem {
+        fn is_enabled(&self) -> Result<bool, String> {
+            Ok(self.registered.get())
+        }
+
+        fn enable(&self) -> Result<(), String> {
+            if self.read_only {
+                return Err("Access is denied.".to_string());
+            }
+            self.registered.set(true);
+            Ok(())
+        }
+
+        fn disable(&self) -> Result<(), String> {
+            if self.read_only {
+                return Err("Access is denied.".to_string());
+            }
+            self.registered.set(false);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn enabling_and_disabling_toggles_the_entry() {
+        let item = FakeLoginItem::default();
+
+        assert!(set_enabled(&item, true).unwrap());
+        assert!(item.registered.get());
+        assert!(is_enabled(&item).unwrap());
+
+        assert!(!set_enabled(&item, false).unwrap());
+        assert!(!item.registered.get());
+    }
+
+    #[test]
+    fn failed_write_is_reported() {
+        let item = FakeLoginItem {
+            read_only: true,
+            ..FakeLoginItem::default()
+        };
+
+        let error = set_enabled(&item, true).unwrap_err();
+        assert!(
+            matches!(&error, AudioError::CommandFailed(message) if message.contains("Access is denied"))
+        );
+        assert!(!item.registered.get());
+    }
+}
@@ -0,0 +1,47 @@
+use crate::audio_manager::{DefaultRole, DeviceListResult};
+use crate::error::AudioResult;
+use crate::pulse::PulseBackend;
+use crate::switch_audio_source::MacBackend;
+use std::sync::Arc;
+
+/// Device operations implemented natively for a platform, in place of the PowerShell
+/// scripts the manager runs on Windows. Calls block, so the manager runs them on the
+/// blocking thread pool.
+pub trait AudioBackend: Send + Sync {
+    /// Whether the tools the backend drives are installed, failing with
+    /// `AudioError::ModuleNotInstalled` saying how to install them when they aren't
+    fn check_available(&self) -> AudioResult<()> {
+        Ok(())
+    }
+
+    /// Every playback and recording device with its default flags, plus the entries the
+    /// tool reported that couldn't be turned into devices
+    fn list_devices(&self) -> AudioResult<DeviceListResult>;
+
+    /// Make `device_id` the default for `role`
+    fn set_default(&self, device_id: &str, role: DefaultRole) -> AudioResult<()>;
+
+    /// Set the master volume, 0-100
+    fn set_volume(&self, device_id: &str, volume: u8) -> AudioResult<()>;
+
+    /// Mute or unmute a device, or flip it when `muted` is `None`, returning whether it
+    /// is muted afterwards
+    fn set_mute(&self, device_id: &str, muted: Option<bool>) -> AudioResult<bool>;
+}
+
+/// The native backend for the OS this was built for, or `None` on Windows.
+///
+/// There is deliberately no Windows backend: the manager runs its PowerShell scripts
+/// itself, since retries, timeouts, the pooled host, switch verification and rollback all
+/// live there, and a blocking wrapper would have to duplicate them. With the
+/// `native-wasapi` feature the manager calls `wasapi` directly for listing and switching,
+/// keeping AudioDeviceCmdlets as the fallback.
+pub fn platform_backend() -> Option<Arc<dyn AudioBackend>> {
+    if cfg!(target_os = "linux") {
+        return Some(Arc::new(PulseBackend));
+    }
+    if cfg!(target_os = "macos") {
+        return Some(Arc::new(MacBackend));
+    }
+    None
+}
@@ -0,0 +1,74 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use std::time::Instant;
+
+/// Source of the current time for time-based logic (cache TTL, debounce, snooze),
+/// so that it can be driven deterministically in tests
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// Local wall-clock time of day, for schedules such as quiet hours
+    fn local_time(&self) -> NaiveTime {
+        chrono::Local::now().time()
+    }
+
+    /// Wall-clock timestamp, for history entries shown to the user
+    fn utc_now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// The real monotonic clock
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when advanced explicitly
+#[cfg(test)]
+pub struct MockClock {
+    now: std::sync::Mutex<Instant>,
+    local_time: std::sync::Mutex<NaiveTime>,
+    utc_now: std::sync::Mutex<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+impl MockClock {
+    pub fn new() -> Self {
+        Self {
+            now: std::sync::Mutex::new(Instant::now()),
+            local_time: std::sync::Mutex::new(NaiveTime::MIN),
+            utc_now: std::sync::Mutex::new(Utc::now()),
+        }
+    }
+
+    /// Move the monotonic time, the time of day and the wall-clock timestamp forward
+    pub fn advance(&self, duration: std::time::Duration) {
+        *self.now.lock().unwrap() += duration;
+        let mut local_time = self.local_time.lock().unwrap();
+        let elapsed = chrono::Duration::from_std(duration).unwrap();
+        *local_time = local_time.overflowing_add_signed(elapsed).0;
+        *self.utc_now.lock().unwrap() += elapsed;
+    }
+
+    pub fn set_local_time(&self, time: NaiveTime) {
+        *self.local_time.lock().unwrap() = time;
+    }
+}
+
+#[cfg(test)]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    fn local_time(&self) -> NaiveTime {
+        *self.local_time.lock().unwrap()
+    }
+
+    fn utc_now(&self) -> DateTime<Utc> {
+        *self.utc_now.lock().unwrap()
+    }
+}
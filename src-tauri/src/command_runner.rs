@@ -0,0 +1,202 @@
+use crate::audio_manager::{powershell_command, structured_error};
+use crate::error::{AudioError, AudioResult};
+use crate::powershell_host::{PowerShellHost, ScriptOutput};
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Runs the scripts `AudioManager` builds. A runner makes a single attempt; retries,
+/// timeouts and the operation log stay with the manager, which drops the future of an
+/// attempt that ran out of time.
+#[async_trait]
+pub trait CommandRunner: Send + Sync {
+    /// Run `script` once and return its stdout. A script that fails surfaces the typed
+    /// error it reported, or `AudioError::CommandFailed` with its stderr.
+    async fn run(&self, script: &str) -> AudioResult<String>;
+
+    /// `run`, handing each `Send-Progress` payload to `on_progress` as it arrives.
+    /// Runners that can't stream report nothing.
+    async fn run_with_progress(
+        &self,
+        script: &str,
+        on_progress: &mut (dyn FnMut(&str) + Send),
+    ) -> AudioResult<String> {
+        let _ = on_progress;
+        self.run(script).await
+    }
+}
+
+#[async_trait]
+impl<R: CommandRunner + ?Sized> CommandRunner for Arc<R> {
+    async fn run(&self, script: &str) -> AudioResult<String> {
+        (**self).run(script).await
+    }
+
+    async fn run_with_progress(
+        &self,
+        script: &str,
+        on_progress: &mut (dyn FnMut(&str) + Send),
+    ) -> AudioResult<String> {
+        (**self).run_with_progress(script, on_progress).await
+    }
+}
+
+/// Runs scripts on the warm PowerShell host, falling back to a one-shot process when
+/// every host session is busy, or the host can't be started or keeps crashing. Only the
+/// host reports progress.
+pub struct PowerShellRunner {
+    host: PowerShellHost,
+    /// Shared with the manager, which swaps it when `redetect_backend` finds another
+    executable: Arc<RwLock<&'static str>>,
+}
+
+impl PowerShellRunner {
+    pub fn new(host: PowerShellHost, executable: Arc<RwLock<&'static str>>) -> Self {
+        Self { host, executable }
+    }
+
+    fn executable(&self) -> &'static str {
+        *self
+            .executable
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl CommandRunner for PowerShellRunner {
+    async fn run(&self, script: &str) -> AudioResult<String> {
+        self.run_with_progress(script, &mut |_| {}).await
+    }
+
+    async fn run_with_progress(
+        &self,
+        script: &str,
+        on_progress: &mut (dyn FnMut(&str) + Send),
+    ) -> AudioResult<String> {
+        let executable = self.executable();
+        // The manager bounds each attempt, and dropping it kills the session it ran on
+        let output = match self
+            .host
+            .run_with_progress(executable, script, Duration::MAX, on_progress)
+            .await
+        {
+            Ok(output) => output,
+            Err(e) => {
+                if e.kind() == std::io::ErrorKind::WouldBlock {
+                    debug!("PowerShell host busy, running one-shot");
+                } else {
+                    warn!("PowerShell host unavailable ({}), running one-shot", e);
+                }
+                ScriptOutput::from(powershell_command(executable, script).output().await?)
+            }
+        };
+        script_result(output)
+    }
+}
+
+/// The stdout of a script that succeeded, or the error it failed with: typed when the
+/// script reported one retrying can't fix, otherwise its stderr
+fn script_result(output: ScriptOutput) -> AudioResult<String> {
+    if output.success() {
+        Ok(output.stdout)
+    } else if let Some(error) = structured_error(output.stdout.as_bytes()) {
+        Err(error)
+    } else {
+        Err(AudioError::CommandFailed(output.stderr))
+    }
+}
+
+/// A runner answering each script with the first response whose key the script
+/// contains: `Ok(stdout)` succeeds, `Err(stderr)` fails with
+/// `AudioError::CommandFailed`, and a script matching no key fails too. Every script is
+/// recorded; share the runner through an `Arc` to inspect them afterwards.
+#[derive(Default)]
+pub struct MockRunner {
+    responses: Vec<(String, Result<String, String>)>,
+    scripts: Mutex<Vec<String>>,
+}
+
+impl MockRunner {
+    pub fn new(responses: Vec<(&str, Result<String, String>)>) -> Self {
+        Self {
+            responses: responses
+                .into_iter()
+                .map(|(key, response)| (key.to_string(), response))
+                .collect(),
+            scripts: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Scripts run so far, oldest first
+    pub fn scripts(&self) -> Vec<String> {
+        self.scripts.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl CommandRunner for MockRunner {
+    async fn run(&self, script: &str) -> AudioResult<String> {
+        self.scripts.lock().unwrap().push(script.to_string());
+        match self.responses.iter().find(|(key, _)| script.contains(key)) {
+            Some((_, Ok(stdout))) => Ok(stdout.clone()),
+            Some((_, Err(stderr))) => Err(AudioError::CommandFailed(stderr.clone())),
+            None => Err(AudioError::CommandFailed("no canned response".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripted_runner(output: ScriptOutput) -> PowerShellRunner {
+        PowerShellRunner::new(
+            PowerShellHost::scripted(move |_| output.clone()),
+            Arc::new(RwLock::new("powershell")),
+        )
+    }
+
+    #[tokio::test]
+    async fn failed_scripts_surface_their_error() {
+        let failed = scripted_runner(ScriptOutput {
+            exit_code: Some(1),
+            stdout: String::new(),
+            stderr: "Device is busy".to_string(),
+        });
+        assert!(matches!(
+            failed.run("Set-AudioDevice").await,
+            Err(AudioError::CommandFailed(message)) if message == "Device is busy"
+        ));
+
+        let typed = scripted_runner(ScriptOutput {
+            exit_code: Some(1),
+            stdout: r#"{"error":"missing","type":"ModuleNotInstalled"}"#.to_string(),
+            stderr: String::new(),
+        });
+        assert!(matches!(
+            typed.run("Get-AudioDevice -List").await,
+            Err(AudioError::ModuleNotInstalled(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn mock_answers_by_key_and_records_scripts() {
+        let runner = MockRunner::new(vec![
+            ("Set-AudioDevice", Err("in use".to_string())),
+            ("Get-AudioDevice", Ok("[]".to_string())),
+        ]);
+
+        assert_eq!(runner.run("Get-AudioDevice -List").await.unwrap(), "[]");
+        assert!(matches!(
+            runner.run("Set-AudioDevice -ID x").await,
+            Err(AudioError::CommandFailed(message)) if message == "in use"
+        ));
+        assert!(runner.run("Get-Date").await.is_err());
+        assert_eq!(
+            runner.scripts(),
+            ["Get-AudioDevice -List", "Set-AudioDevice -ID x", "Get-Date"]
+        );
+    }
+}
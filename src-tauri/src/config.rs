@@ -0,0 +1,581 @@
+use crate::audio_manager::DeviceType;
+use crate::error::{AudioError, AudioResult};
+use crate::matching::MatchMode;
+use crate::priority_store::PriorityChains;
+use chrono::{DateTime, NaiveDateTime, NaiveTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Directory holding everything the app persists (`<local app data>/sound-changer`)
+pub fn app_data_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|mut p| {
+        p.push("sound-changer");
+        p
+    })
+}
+
+/// Number of config backups kept when no limit is configured
+pub const DEFAULT_BACKUP_LIMIT: usize = 10;
+/// Longest `cache_ttl_ms` accepted (1 hour)
+const MAX_CACHE_TTL_MS: u64 = 60 * 60 * 1000;
+/// Longest `command_timeout_ms` accepted (5 minutes)
+const MAX_COMMAND_TIMEOUT_MS: u64 = 5 * 60 * 1000;
+/// Most `max_retry_attempts` accepted
+const MAX_RETRY_ATTEMPTS: u32 = 10;
+/// Longest `retry_base_delay_ms` accepted (1 minute)
+const MAX_RETRY_BASE_DELAY_MS: u64 = 60 * 1000;
+/// Timestamp embedded in backup file names; sorts chronologically as text
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+
+/// User configuration persisted as `config.json` in the app data directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioManagerConfig {
+    /// Saved profiles keyed by name
+    pub profiles: BTreeMap<String, Profile>,
+    /// Device groups keyed by name
+    pub groups: BTreeMap<String, DeviceGroup>,
+    /// User-defined aliases mapped to a device's stable key
+    pub aliases: BTreeMap<String, String>,
+    /// Nicknames shown instead of the Windows device name, keyed by endpoint ID so they
+    /// survive driver updates that rename the device
+    pub display_names: BTreeMap<String, String>,
+    /// Install AudioDeviceCmdlets automatically the first time enumeration finds it missing
+    pub auto_install_module: bool,
+    /// Preferred output device per application, mapped to the device's stable key
+    pub app_preferences: BTreeMap<String, String>,
+    /// Unmute the device auto-switch selects so a muted target doesn't look like a failed switch
+    pub unmute_on_autoswitch: bool,
+    /// Volume (0-100) an auto-switched device is raised to if it's below it
+    pub autoswitch_min_volume: Option<u8>,
+    /// How user-supplied device names are matched in quick switch and ranked switching
+    pub match_mode: MatchMode,
+    /// Skip the background device watcher's polling while the machine runs on battery
+    pub pause_watcher_on_battery: bool,
+    /// Minimize the window when the app is launched at login
+    pub start_minimized: bool,
+    /// Emit `device-state-changed` for every device whose state changes between polls
+    pub state_change_events: bool,
+    /// Make an application's preferred device the default whenever its window takes focus
+    pub follow_focus: bool,
+    /// How long (ms) a switch waits to be superseded by a newer one of the same device
+    /// type; the built-in default when unset, 0 to disable
+    pub switch_coalesce_window_ms: Option<u64>,
+    /// Number of config backups kept before the oldest are pruned
+    pub backup_limit: Option<usize>,
+    /// Nightly window during which playback volume is capped
+    pub quiet_hours: Option<QuietHours>,
+    /// Global hotkeys, keyed by accelerator (e.g. "Ctrl+Alt+P")
+    pub hotkeys: BTreeMap<String, HotkeyAction>,
+    /// How long (ms) enumerated devices are served from cache; 30 s when unset
+    pub cache_ttl_ms: Option<u64>,
+    /// Time limit (ms) for each attempt of an ordinary PowerShell script; 10 s when unset.
+    /// Module installs and updates keep their own, longer limits.
+    pub command_timeout_ms: Option<u64>,
+    /// Attempts per PowerShell operation before giving up; 3 when unset
+    pub max_retry_attempts: Option<u32>,
+    /// Delay (ms) before the first retry, growing linearly with each attempt; 500 when unset
+    pub retry_base_delay_ms: Option<u64>,
+}
+
+impl AudioManagerConfig {
+    /// Check the cache TTL, command timeout and retry settings are in range: a timeout of
+    /// at least 1 ms and at least one attempt, none above its `MAX_*` limit
+    pub fn validate_timing(&self) -> AudioResult<()> {
+        let in_range = |setting: &str, value: u64, min: u64, max: u64| {
+            if (min..=max).contains(&value) {
+                Ok(())
+            } else {
+                Err(AudioError::InvalidArgument(format!(
+                    "{} must be between {} and {}, got {}",
+                    setting, min, max, value
+                )))
+            }
+        };
+        if let Some(ttl_ms) = self.cache_ttl_ms {
+            in_range("cache_ttl_ms", ttl_ms, 0, MAX_CACHE_TTL_MS)?;
+        }
+        if let Some(timeout_ms) = self.command_timeout_ms {
+            in_range("command_timeout_ms", timeout_ms, 1, MAX_COMMAND_TIMEOUT_MS)?;
+        }
+        if let Some(attempts) = self.max_retry_attempts {
+            in_range(
+                "max_retry_attempts",
+                attempts.into(),
+                1,
+                MAX_RETRY_ATTEMPTS.into(),
+            )?;
+        }
+        if let Some(delay_ms) = self.retry_base_delay_ms {
+            in_range("retry_base_delay_ms", delay_ms, 0, MAX_RETRY_BASE_DELAY_MS)?;
+        }
+        Ok(())
+    }
+
+    /// Drop the cache TTL, command timeout and retry settings, returning them to their
+    /// built-in defaults
+    pub fn reset_timing(&mut self) {
+        self.cache_ttl_ms = None;
+        self.command_timeout_ms = None;
+        self.max_retry_attempts = None;
+        self.retry_base_delay_ms = None;
+    }
+
+    /// Move a profile to a new name, refusing unknown, blank or already taken names
+    pub fn rename_profile(&mut self, old_name: &str, new_name: &str) -> AudioResult<()> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err(AudioError::InvalidConfig(
+                "Profile name cannot be empty".to_string(),
+            ));
+        }
+        if self.profiles.contains_key(new_name) {
+            return Err(AudioError::InvalidConfig(format!(
+                "Profile '{}' already exists",
+                new_name
+            )));
+        }
+
+        let mut profile = self.profiles.remove(old_name).ok_or_else(|| {
+            AudioError::InvalidConfig(format!("Profile '{}' does not exist", old_name))
+        })?;
+        profile.name = new_name.to_string();
+        self.profiles.insert(profile.name.clone(), profile);
+
+        for action in self.hotkeys.values_mut() {
+            if matches!(action, HotkeyAction::ApplyProfile(name) if name == old_name) {
+                *action = HotkeyAction::ApplyProfile(new_name.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a profile along with any hotkeys that applied it
+    pub fn delete_profile(&mut self, name: &str) -> AudioResult<()> {
+        self.profiles.remove(name).ok_or_else(|| {
+            AudioError::InvalidConfig(format!("Profile '{}' does not exist", name))
+        })?;
+        self.hotkeys
+            .retain(|_, action| !matches!(action, HotkeyAction::ApplyProfile(n) if n == name));
+        Ok(())
+    }
+}
+
+/// What a global hotkey does when pressed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HotkeyAction {
+    /// Make the device with this ID the default
+    SwitchToDevice(String),
+    /// Move the default of a device type to the next active device
+    CycleDefault(DeviceType),
+    /// Move the default of a device type to the next available device in its priority chain
+    CyclePriority(DeviceType),
+    /// Make the device in this slot (0 = highest) of a device type's priority chain the
+    /// default, when it's Active
+    SwitchToSlot(DeviceType, usize),
+    /// Switch to the first device unless it's already the default, then the second
+    ToggleBetween(String, String),
+    /// Like `ToggleBetween`, for the communication default only, leaving the multimedia
+    /// default where it is
+    ToggleCommunication(String, String),
+    PanicRestore,
+    ApplyProfile(String),
+}
+
+/// A daily window during which playback volume is kept at or below `max_volume`.
+/// `end` before `start` means the window spans midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub max_volume: u8,
+}
+
+impl QuietHours {
+    /// Whether `time` falls inside the window (start inclusive, end exclusive)
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// A named snapshot of the default device for every role
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub default_playback: Option<String>,
+    pub default_recording: Option<String>,
+    pub communication_playback: Option<String>,
+    pub communication_recording: Option<String>,
+    /// Priority chains when the profile was saved; `None` for profiles saved before
+    /// chains were captured, which leave the current chains alone when applied
+    pub priorities: Option<PriorityChains>,
+}
+
+impl Profile {
+    /// Distinct device IDs referenced by this profile
+    pub fn device_ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = Vec::new();
+        for id in [
+            &self.default_playback,
+            &self.default_recording,
+            &self.communication_playback,
+            &self.communication_recording,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if !ids.contains(&id.as_str()) {
+                ids.push(id);
+            }
+        }
+        ids
+    }
+}
+
+/// Playback and recording endpoints of one physical device that switch together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceGroup {
+    pub name: String,
+    pub playback_id: String,
+    pub recording_id: String,
+}
+
+/// Load a JSON file, returning the type's default when the file doesn't exist yet
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> AudioResult<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let raw = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&raw)?)
+}
+
+/// Write a JSON file atomically by writing a sibling temp file and renaming it over the target
+pub fn save_json_atomic<T: Serialize>(path: &Path, value: &T) -> AudioResult<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let json = serde_json::to_string_pretty(value)?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// A config snapshot in the `backups` directory
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigBackup {
+    pub name: String,
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Write `config` to a new timestamped backup in `dir`, then prune all but the newest
+/// `keep` backups
+pub fn create_backup(
+    dir: &Path,
+    config: &AudioManagerConfig,
+    created_at: DateTime<Utc>,
+    keep: usize,
+) -> AudioResult<ConfigBackup> {
+    let name = format!("config-{}.json", created_at.format(BACKUP_TIMESTAMP_FORMAT));
+    let path = dir.join(&name);
+    if path.exists() {
+        return Err(AudioError::InvalidConfig(format!(
+            "Backup '{}' already exists",
+            name
+        )));
+    }
+
+    save_json_atomic(&path, config)?;
+    prune_backups(dir, keep)?;
+    Ok(ConfigBackup {
+        name,
+        path: path.to_string_lossy().to_string(),
+        created_at,
+    })
+}
+
+/// Backups in `dir`, newest first. Files that aren't backups are ignored.
+pub fn list_backups(dir: &Path) -> AudioResult<Vec<ConfigBackup>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if let Some(created_at) = backup_timestamp(name) {
+            backups.push(ConfigBackup {
+                name: name.to_string(),
+                path: path.to_string_lossy().to_string(),
+                created_at,
+            });
+        }
+    }
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(backups)
+}
+
+/// Read the backup named `name` from `dir`, refusing anything that isn't a listed backup
+pub fn load_backup(dir: &Path, name: &str) -> AudioResult<AudioManagerConfig> {
+    let backup = list_backups(dir)?
+        .into_iter()
+        .find(|b| b.name == name)
+        .ok_or_else(|| AudioError::InvalidArgument(format!("No config backup named '{}'", name)))?;
+    let raw = std::fs::read_to_string(&backup.path)?;
+    Ok(serde_json::from_str(&raw)?)
+}
+
+/// Delete all but the newest `keep` backups, returning the names removed
+pub fn prune_backups(dir: &Path, keep: usize) -> AudioResult<Vec<String>> {
+    let mut removed = Vec::new();
+    for backup in list_backups(dir)?.into_iter().skip(keep) {
+        std::fs::remove_file(&backup.path)?;
+        removed.push(backup.name);
+    }
+    Ok(removed)
+}
+
+/// Creation time encoded in a backup file name such as `config-20250101T120000000Z.json`
+fn backup_timestamp(name: &str) -> Option<DateTime<Utc>> {
+    let timestamp = name.strip_prefix("config-")?.strip_suffix(".json")?;
+    NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT)
+        .ok()
+        .map(|t| t.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_profiles(names: &[&str]) -> AudioManagerConfig {
+        let mut config = AudioManagerConfig::default();
+        for name in names {
+            config.profiles.insert(
+                name.to_string(),
+                Profile {
+                    name: name.to_string(),
+                    default_playback: Some(format!("{}-speakers", name)),
+                    ..Profile::default()
+                },
+            );
+        }
+        config
+    }
+
+    #[test]
+    fn timing_settings_out_of_range_are_rejected() {
+        let with = |mutate: fn(&mut AudioManagerConfig)| {
+            let mut config = AudioManagerConfig::default();
+            mutate(&mut config);
+            config.validate_timing()
+        };
+        assert!(with(|_| ()).is_ok());
+        assert!(with(|c| c.cache_ttl_ms = Some(0)).is_ok());
+        assert!(with(|c| c.retry_base_delay_ms = Some(0)).is_ok());
+        assert!(with(|c| c.max_retry_attempts = Some(MAX_RETRY_ATTEMPTS)).is_ok());
+
+        let invalid =
+            |result: AudioResult<()>| matches!(result, Err(AudioError::InvalidArgument(_)));
+        assert!(invalid(with(|c| c.command_timeout_ms = Some(0))));
+        assert!(invalid(with(|c| c.command_timeout_ms = Some(u64::MAX))));
+        assert!(invalid(with(|c| c.max_retry_attempts = Some(0))));
+        assert!(invalid(with(|c| c.max_retry_attempts = Some(u32::MAX))));
+        assert!(invalid(with(|c| c.retry_base_delay_ms = Some(u64::MAX))));
+        assert!(invalid(with(|c| c.cache_ttl_ms = Some(u64::MAX))));
+    }
+
+    #[test]
+    fn rename_profile_moves_the_profile() {
+        let mut config = config_with_profiles(&["Work", "Gaming"]);
+        config.rename_profile("Work", "Office").unwrap();
+
+        assert!(!config.profiles.contains_key("Work"));
+        let renamed = &config.profiles["Office"];
+        assert_eq!(renamed.name, "Office");
+        assert_eq!(renamed.default_playback.as_deref(), Some("Work-speakers"));
+    }
+
+    #[test]
+    fn rename_profile_rejects_collisions_and_unknown_names() {
+        let mut config = config_with_profiles(&["Work", "Gaming"]);
+        assert!(config.rename_profile("Work", "Gaming").is_err());
+        assert!(config.rename_profile("Music", "Listening").is_err());
+        assert!(config.rename_profile("Work", "  ").is_err());
+        assert_eq!(config.profiles.len(), 2);
+        assert_eq!(config.profiles["Work"].name, "Work");
+    }
+
+    #[test]
+    fn rename_profile_updates_hotkeys_applying_it() {
+        let mut config = config_with_profiles(&["Work", "Gaming"]);
+        config.hotkeys.insert(
+            "Ctrl+Alt+W".to_string(),
+            HotkeyAction::ApplyProfile("Work".to_string()),
+        );
+        config.hotkeys.insert(
+            "Ctrl+Alt+G".to_string(),
+            HotkeyAction::ApplyProfile("Gaming".to_string()),
+        );
+
+        config.rename_profile("Work", "Office").unwrap();
+        assert_eq!(
+            config.hotkeys["Ctrl+Alt+W"],
+            HotkeyAction::ApplyProfile("Office".to_string())
+        );
+        assert_eq!(
+            config.hotkeys["Ctrl+Alt+G"],
+            HotkeyAction::ApplyProfile("Gaming".to_string())
+        );
+    }
+
+    #[test]
+    fn delete_profile_drops_hotkeys_applying_it() {
+        let mut config = config_with_profiles(&["Work", "Gaming"]);
+        config.hotkeys.insert(
+            "Ctrl+Alt+W".to_string(),
+            HotkeyAction::ApplyProfile("Work".to_string()),
+        );
+        config
+            .hotkeys
+            .insert("Ctrl+Alt+P".to_string(), HotkeyAction::PanicRestore);
+
+        config.delete_profile("Work").unwrap();
+        assert!(!config.profiles.contains_key("Work"));
+        assert!(!config.hotkeys.contains_key("Ctrl+Alt+W"));
+        assert!(config.hotkeys.contains_key("Ctrl+Alt+P"));
+
+        assert!(config.delete_profile("Work").is_err());
+        assert_eq!(config.profiles.len(), 1);
+    }
+
+    #[test]
+    fn hotkey_actions_round_trip_through_json() {
+        let actions = [
+            (
+                HotkeyAction::SwitchToDevice("{0.0.0.00000000}.{a}".to_string()),
+                r#"{"SwitchToDevice":"{0.0.0.00000000}.{a}"}"#,
+            ),
+            (
+                HotkeyAction::CycleDefault(DeviceType::Recording),
+                r#"{"CycleDefault":"Recording"}"#,
+            ),
+            (
+                HotkeyAction::ToggleBetween("a".to_string(), "b".to_string()),
+                r#"{"ToggleBetween":["a","b"]}"#,
+            ),
+            (
+                HotkeyAction::ToggleCommunication("a".to_string(), "b".to_string()),
+                r#"{"ToggleCommunication":["a","b"]}"#,
+            ),
+            (HotkeyAction::PanicRestore, r#""PanicRestore""#),
+            (
+                HotkeyAction::ApplyProfile("Night".to_string()),
+                r#"{"ApplyProfile":"Night"}"#,
+            ),
+            (
+                HotkeyAction::SwitchToSlot(DeviceType::Playback, 0),
+                r#"{"SwitchToSlot":["Playback",0]}"#,
+            ),
+        ];
+
+        for (action, json) in actions {
+            assert_eq!(serde_json::to_string(&action).unwrap(), json);
+            assert_eq!(serde_json::from_str::<HotkeyAction>(json).unwrap(), action);
+        }
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn quiet_hours_contains_same_day_and_midnight_spanning_windows() {
+        let afternoon = QuietHours {
+            start: time(13, 0),
+            end: time(15, 0),
+            max_volume: 20,
+        };
+        assert!(afternoon.contains(time(13, 0)));
+        assert!(afternoon.contains(time(14, 59)));
+        assert!(!afternoon.contains(time(15, 0)));
+        assert!(!afternoon.contains(time(23, 0)));
+
+        let night = QuietHours {
+            start: time(22, 0),
+            end: time(7, 0),
+            max_volume: 20,
+        };
+        assert!(night.contains(time(22, 0)));
+        assert!(night.contains(time(0, 0)));
+        assert!(night.contains(time(6, 59)));
+        assert!(!night.contains(time(7, 0)));
+        assert!(!night.contains(time(12, 0)));
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_735_732_800 + seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn create_backup_writes_a_restorable_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config_with_profiles(&["Work"]);
+
+        let backup = create_backup(dir.path(), &config, at(0), DEFAULT_BACKUP_LIMIT).unwrap();
+        assert_eq!(backup.name, "config-20250101T120000000Z.json");
+        assert!(Path::new(&backup.path).exists());
+
+        let restored = load_backup(dir.path(), &backup.name).unwrap();
+        assert_eq!(restored.profiles["Work"].name, "Work");
+        assert!(load_backup(dir.path(), "../config.json").is_err());
+    }
+
+    #[test]
+    fn list_backups_is_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = AudioManagerConfig::default();
+        for seconds in [60, 0, 120] {
+            create_backup(dir.path(), &config, at(seconds), DEFAULT_BACKUP_LIMIT).unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), "not a backup").unwrap();
+
+        let names: Vec<String> = list_backups(dir.path())
+            .unwrap()
+            .into_iter()
+            .map(|b| b.name)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "config-20250101T120200000Z.json",
+                "config-20250101T120100000Z.json",
+                "config-20250101T120000000Z.json",
+            ]
+        );
+    }
+
+    #[test]
+    fn create_backup_prunes_the_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = AudioManagerConfig::default();
+        for seconds in 0..5 {
+            create_backup(dir.path(), &config, at(seconds), 3).unwrap();
+        }
+
+        let backups = list_backups(dir.path()).unwrap();
+        assert_eq!(backups.len(), 3);
+        assert_eq!(backups[0].created_at, at(4));
+        assert_eq!(backups[2].created_at, at(2));
+    }
+}
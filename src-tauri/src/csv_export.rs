@@ -0,0 +1,152 @@
+use crate::audio_manager::AudioDevice;
+
+const DEVICE_COLUMNS: [&str; 8] = [
+    "id",
+    "name",
+    "type",
+    "state",
+    "is_default",
+    "is_communication_default",
+    "connection_type",
+    "last_seen",
+];
+
+/// Render devices as RFC 4180 CSV with a header row and CRLF line endings. Each device
+/// is paired with its connection type, when known.
+pub fn devices_to_csv(devices: &[(AudioDevice, Option<String>)]) -> String {
+    let mut csv = record(DEVICE_COLUMNS.iter().map(|c| c.to_string()));
+    for (device, connection_type) in devices {
+        csv.push_str(&record([
+            device.id.clone(),
+            device.name.clone(),
+            format!("{:?}", device.device_type),
+            device.state.as_str().to_string(),
+            device.is_default.to_string(),
+            device.is_communication_default.to_string(),
+            connection_type.clone().unwrap_or_default(),
+            device.last_seen.clone().unwrap_or_default(),
+        ]));
+    }
+    csv
+}
+
+/// Connection type inferred from the PnP enumerator that created the endpoint
+pub fn connection_type(enumerator_name: &str) -> String {
+    match enumerator_name.to_ascii_uppercase().as_str() {
+        "HDAUDIO" | "INTELAUDIO" => "Internal",
+        "USB" => "USB",
+        "BTHENUM" | "BTHHFENUM" | "BTHLEDEVICE" => "Bluetooth",
+        "DISPLAY" => "HDMI/DisplayPort",
+        "SWD" | "ROOT" => "Virtual",
+        _ => return enumerator_name.to_string(),
+    }
+    .to_string()
+}
+
+fn record(fields: impl IntoIterator<Item = String>) -> String {
+    let mut line = fields
+        .into_iter()
+        .map(|field| escape(&field))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// Quote a field if it contains a comma, quote or line break, doubling embedded quotes
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_manager::{DeviceState, DeviceType};
+
+    /// Minimal RFC 4180 reader, enough to check what `devices_to_csv` writes
+    fn parse(csv: &str) -> Vec<Vec<String>> {
+        let mut records = Vec::new();
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = csv.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match (quoted, c) {
+                (true, '"') if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                (true, '"') => quoted = false,
+                (true, c) => field.push(c),
+                (false, '"') => quoted = true,
+                (false, ',') => record.push(std::mem::take(&mut field)),
+                (false, '\r') => {}
+                (false, '\n') => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                (false, c) => field.push(c),
+            }
+        }
+        records
+    }
+
+    fn device(id: &str, name: &str) -> AudioDevice {
+        AudioDevice {
+            id: id.to_string(),
+            name: name.to_string(),
+            device_type: DeviceType::Playback,
+            state: DeviceState::Active,
+            is_default: true,
+            is_communication_default: false,
+            last_seen: Some("2024-05-01T10:00:00Z".to_string()),
+            index: None,
+            icon_path: None,
+            display_name: None,
+        }
+    }
+
+    #[test]
+    fn devices_round_trip_through_csv() {
+        let devices = vec![
+            (
+                device("{0.0.0.00000000}.{a}", "Speakers, Rear (Realtek Audio)"),
+                Some("Internal".to_string()),
+            ),
+            (
+                device("{0.0.0.00000000}.{b}", "Studio \"Reference\" Monitors"),
+                None,
+            ),
+            (device("{0.0.0.00000000}.{c}", "Line\nOut"), None),
+        ];
+
+        let records = parse(&devices_to_csv(&devices));
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0], DEVICE_COLUMNS.map(String::from).to_vec());
+
+        for ((device, connection_type), record) in devices.iter().zip(&records[1..]) {
+            assert_eq!(record.len(), DEVICE_COLUMNS.len());
+            assert_eq!(record[0], device.id);
+            assert_eq!(record[1], device.name);
+            assert_eq!(record[2], "Playback");
+            assert_eq!(record[3], "active");
+            assert_eq!(record[4], "true");
+            assert_eq!(record[5], "false");
+            assert_eq!(record[6], connection_type.clone().unwrap_or_default());
+            assert_eq!(record[7], "2024-05-01T10:00:00Z");
+        }
+    }
+
+    #[test]
+    fn connection_type_maps_known_enumerators() {
+        assert_eq!(connection_type("HDAUDIO"), "Internal");
+        assert_eq!(connection_type("usb"), "USB");
+        assert_eq!(connection_type("BTHHFENUM"), "Bluetooth");
+        assert_eq!(connection_type("VIRTUALCABLE"), "VIRTUALCABLE");
+    }
+}
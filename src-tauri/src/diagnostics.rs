@@ -0,0 +1,134 @@
+use crate::audio_manager::{AudioDevice, EnumerationBenchmark, OperationLogEntry, OperationStats};
+use crate::config::{AudioManagerConfig, HotkeyAction};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Everything written by `export_diagnostics`, serialized as one JSON document
+#[derive(Debug, Serialize)]
+pub struct DiagnosticsBundle {
+    pub generated_at: DateTime<Utc>,
+    pub app_version: &'static str,
+    pub session_id: String,
+    pub redacted: bool,
+    pub module_available: Option<bool>,
+    pub read_only: bool,
+    pub config: AudioManagerConfig,
+    pub last_error: Option<String>,
+    pub perf_stats: Vec<OperationStats>,
+    /// Last `benchmark_enumeration` result this session, if one was run
+    pub enumeration_benchmark: Option<EnumerationBenchmark>,
+    pub operation_log: Vec<OperationLogEntry>,
+    pub devices: Vec<AudioDevice>,
+}
+
+/// Replaces device names and IDs with opaque tokens. Tokens are salted with a random
+/// value drawn once per session and never written out, so a device maps to the same
+/// token in every bundle of a session but can't be correlated across sessions or guessed
+/// by hashing known device names.
+pub struct Redactor {
+    /// (identifier, token) pairs, longest identifier first so that an ID containing
+    /// another identifier is replaced as a whole
+    replacements: Vec<(String, String)>,
+}
+
+impl Redactor {
+    /// Salt tokens with `salt` and cover the connected `devices` and every device `config` still refers to, including
+    /// ones that aren't connected right now
+    pub fn new(salt: &str, devices: &[AudioDevice], config: &AudioManagerConfig) -> Self {
+        let salted = |kind: &str, value: &str| token(salt, kind, value);
+        let mut replacements = Vec::new();
+        for device in devices {
+            let name_token = salted("device", &device.name);
+            replacements.push((device.id.clone(), salted("id", &device.id)));
+            // Stable keys and aliases store the lowercased name
+            replacements.push((device.name.to_lowercase(), name_token.clone()));
+            replacements.push((device.name.clone(), name_token));
+        }
+
+        // Stable keys are `<type>:<lowercased name>`
+        for stable_key in config
+            .aliases
+            .values()
+            .chain(config.app_preferences.values())
+        {
+            let name = stable_key
+                .split_once(':')
+                .map_or(stable_key.as_str(), |(_, n)| n);
+            replacements.push((name.to_string(), salted("device", name)));
+        }
+        for (id, display_name) in &config.display_names {
+            replacements.push((id.clone(), salted("id", id)));
+            replacements.push((display_name.clone(), salted("device", display_name)));
+        }
+        let mut ids: Vec<&str> = Vec::new();
+        for profile in config.profiles.values() {
+            ids.extend(profile.device_ids());
+            for priority in profile
+                .priorities
+                .iter()
+                .flat_map(|chains| chains.playback.iter().chain(&chains.recording))
+            {
+                ids.push(priority.device_id.as_str());
+                replacements.push((
+                    priority.device_name.clone(),
+                    salted("device", &priority.device_name),
+                ));
+            }
+        }
+        for group in config.groups.values() {
+            ids.extend([group.playback_id.as_str(), group.recording_id.as_str()]);
+        }
+        for action in config.hotkeys.values() {
+            match action {
+                HotkeyAction::SwitchToDevice(id) => ids.push(id.as_str()),
+                HotkeyAction::ToggleBetween(a, b) | HotkeyAction::ToggleCommunication(a, b) => {
+                    ids.extend([a.as_str(), b.as_str()])
+                }
+                _ => {}
+            }
+        }
+        for id in ids {
+            replacements.push((id.to_string(), salted("id", id)));
+        }
+
+        replacements.retain(|(identifier, _)| !identifier.is_empty());
+        replacements.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+        replacements.dedup_by(|a, b| a.0 == b.0);
+
+        Self { replacements }
+    }
+
+    /// Redact every string (and object key) in a JSON value in place, leaving the
+    /// structure untouched
+    pub fn redact(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => *text = self.redact_text(text),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|v| self.redact(v)),
+            serde_json::Value::Object(map) => {
+                let entries = std::mem::take(map);
+                for (key, mut v) in entries {
+                    self.redact(&mut v);
+                    map.insert(self.redact_text(&key), v);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn redact_text(&self, text: &str) -> String {
+        self.replacements
+            .iter()
+            .fold(text.to_string(), |acc, (identifier, token)| {
+                acc.replace(identifier.as_str(), token)
+            })
+    }
+}
+
+fn token(salt: &str, kind: &str, value: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    salt.hash(&mut hasher);
+    value.hash(&mut hasher);
+    format!("{}-{:016x}", kind, hasher.finish())
+}
@@ -15,6 +15,40 @@ pub enum AudioError {
     #[error("Parsing error: {0}")]
     ParseError(String),
     
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+    
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(String),
+    
+    /// What's missing, with how to install it when the app can't do it itself
+    #[error("Required audio component is not installed: {0}")]
+    ModuleNotInstalled(String),
+    
+    #[error("Administrator rights are required: {0}")]
+    ElevationRequired(String),
+    
+    #[error("PowerShell {operation} timed out after {elapsed_ms}ms")]
+    Timeout { operation: String, elapsed_ms: u64 },
+    
+    #[error("Read-only mode is enabled, refusing to {0}")]
+    ReadOnly(String),
+    
+    /// The OS refused a global hotkey, usually because another application holds it
+    #[error("Hotkey is already in use by another application: {0}")]
+    HotkeyUnavailable(String),
+    
+    /// A newer switch of the same device type arrived within the coalescing window
+    #[error("Switch to {0} was superseded by a newer switch")]
+    Superseded(String),
+    
+    /// A manual override pins the device type to another device, named here
+    #[error("Device type is pinned to {0} until the override is cleared")]
+    Pinned(String),
+    
+    #[error("Not supported: {0}")]
+    Unsupported(String),
+    
     #[error("Windows API error: {0}")]
     WindowsApiError(String),
     
@@ -28,6 +62,13 @@ impl From<std::io::Error> for AudioError {
     }
 }
 
+#[cfg(windows)]
+impl From<windows::core::Error> for AudioError {
+    fn from(error: windows::core::Error) -> Self {
+        AudioError::WindowsApiError(error.to_string())
+    }
+}
+
 impl From<serde_json::Error> for AudioError {
     fn from(error: serde_json::Error) -> Self {
         AudioError::ParseError(error.to_string())
@@ -0,0 +1,77 @@
+use crate::audio_manager::AudioManager;
+use crate::error::{AudioError, AudioResult};
+use std::sync::Arc;
+use tauri::AppHandle;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+use tracing::error;
+
+/// Parse an accelerator such as `Ctrl+Alt+1`
+fn parse(accelerator: &str) -> AudioResult<Shortcut> {
+    accelerator.parse().map_err(|e| {
+        AudioError::InvalidArgument(format!("'{}' is not a valid hotkey: {}", accelerator, e))
+    })
+}
+
+/// Check `accelerator` is a hotkey that could be registered
+pub fn validate(accelerator: &str) -> AudioResult<()> {
+    parse(accelerator).map(drop)
+}
+
+/// Register `accelerator` with the OS; pressing it runs whatever action is bound to it at
+/// that moment, so rebinding an accelerator doesn't need to re-register it. One this app
+/// already holds is left as is.
+pub fn register(
+    app: &AppHandle,
+    audio_manager: Arc<AudioManager>,
+    accelerator: &str,
+) -> AudioResult<()> {
+    let shortcut = parse(accelerator)?;
+    let global_shortcut = app.global_shortcut();
+    if global_shortcut.is_registered(shortcut) {
+        return Ok(());
+    }
+
+    let bound = accelerator.to_string();
+    global_shortcut
+        .on_shortcut(shortcut, move |_app, _shortcut, event| {
+            if event.state() != ShortcutState::Pressed {
+                return;
+            }
+            let audio_manager = audio_manager.clone();
+            let accelerator = bound.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = audio_manager.run_hotkey(&accelerator).await {
+                    error!("Hotkey {} failed: {}", accelerator, e);
+                }
+            });
+        })
+        // The accelerator parsed, so the OS refused it, usually because another
+        // application registered it first
+        .map_err(|e| AudioError::HotkeyUnavailable(format!("{} ({})", accelerator, e)))
+}
+
+/// Release `accelerator` if this app holds it
+pub fn unregister(app: &AppHandle, accelerator: &str) -> AudioResult<()> {
+    // One that doesn't parse was never registered
+    let Ok(shortcut) = parse(accelerator) else {
+        return Ok(());
+    };
+    let global_shortcut = app.global_shortcut();
+    if global_shortcut.is_registered(shortcut) {
+        global_shortcut
+            .unregister(shortcut)
+            .map_err(|e| AudioError::CommandFailed(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Register every saved binding at launch. One that can't be registered is logged and
+/// skipped so the others still work.
+pub fn restore_saved(app: &AppHandle, audio_manager: &Arc<AudioManager>) {
+    let hotkeys = tauri::async_runtime::block_on(audio_manager.list_hotkeys());
+    for accelerator in hotkeys.keys() {
+        if let Err(e) = register(app, audio_manager.clone(), accelerator) {
+            error!("{}", e);
+        }
+    }
+}
@@ -1,12 +1,49 @@
-mod audio_manager;
-mod error;
+pub mod audio_manager;
+mod autostart;
+mod backend;
+mod clock;
+pub mod command_runner;
+mod config;
+mod csv_export;
+mod diagnostics;
+pub mod error;
+#[cfg(windows)]
+mod focus;
+mod hotkeys;
+mod matching;
+#[cfg(windows)]
+mod power;
+mod powershell_host;
+mod priority_store;
+mod pulse;
+mod switch_audio_source;
+#[cfg(windows)]
+mod wasapi;
 
-use audio_manager::{AudioDevice, AudioManager, DeviceType};
-use error::AudioResult;
+use audio_manager::{
+    ActiveDeviceState, AliasResolution, AppPreference, AudioDevice, AudioFormat, AudioManager,
+    AudioSession, AutoSwitchDecision, BackendCapabilities, BackendComparison, BestAvailableMatch,
+    CacheInfo, CachedDevices, ChannelSupport, CommunicationDefaults, DefaultAnomaly, DefaultRole,
+    DeviceDiagnosis, DeviceListResult, DeviceOperationResult, DeviceType, Diagnostics,
+    DriverStatus, EnumerationBenchmark, FormatConflict, FormatSupport, GroupedDevices, KnownScript,
+    LogPage, ModuleUpdateInfo, OperationLogEntry, ProfileApplication, ProfileStatus,
+    RawScriptOutput, RoleDefaults, SetupStatus, SetupSummary, SpatialProvider, SwitchLatency,
+    WATCH_INTERVAL,
+};
+use autostart::AutostartStatus;
+use config::{ConfigBackup, DeviceGroup, HotkeyAction, Profile, QuietHours};
+use error::{AudioError, AudioResult};
+use matching::MatchMode;
+use priority_store::{DevicePriority, PriorityChains, PriorityOrderedDevice, PriorityValidation};
 use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tauri::State;
-use tracing::info;
+use std::time::Duration;
+use tauri::{Manager, State};
+use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
+use tauri_plugin_dialog::DialogExt;
+use tracing::{error, info};
 
 // Application State
 pub struct AppState {
@@ -19,12 +56,37 @@ async fn get_audio_devices(state: State<'_, AppState>) -> AudioResult<Vec<AudioD
     state.audio_manager.get_devices().await
 }
 
+#[tauri::command]
+async fn get_audio_devices_detailed(state: State<'_, AppState>) -> AudioResult<DeviceListResult> {
+    state.audio_manager.get_audio_devices_detailed().await
+}
+
+#[tauri::command]
+async fn refresh_devices(state: State<'_, AppState>) -> AudioResult<Vec<AudioDevice>> {
+    info!("Refreshing audio devices...");
+    state.audio_manager.get_audio_devices_force().await
+}
+
 #[derive(Deserialize)]
 struct SetDefaultArgs {
     #[serde(alias = "deviceId")]
     device_id: String,
     #[serde(alias = "deviceType")]
     device_type: String,
+    /// Pin the device so auto-switch leaves it alone until the override is cleared
+    #[serde(default = "default_manual_override", alias = "manualOverride")]
+    manual_override: bool,
+    /// Which default role(s) to switch; both unless the caller picks one
+    #[serde(default = "default_role")]
+    role: DefaultRole,
+}
+
+fn default_manual_override() -> bool {
+    true
+}
+
+fn default_role() -> DefaultRole {
+    DefaultRole::Both
 }
 
 #[tauri::command]
@@ -32,8 +94,13 @@ async fn set_default_device(args: SetDefaultArgs, state: State<'_, AppState>) ->
     let SetDefaultArgs {
         device_id,
         device_type,
+        manual_override,
+        role,
     } = args;
-    info!("Setting default device: {} ({})", device_id, device_type);
+    info!(
+        "Setting default device: {} ({}, {:?})",
+        device_id, device_type, role
+    );
 
     let device_type = match device_type.as_str() {
         "Playback" => DeviceType::Playback,
@@ -47,10 +114,24 @@ async fn set_default_device(args: SetDefaultArgs, state: State<'_, AppState>) ->
 
     state
         .audio_manager
-        .set_default_device(&device_id, &device_type)
+        .set_default_device(&device_id, &device_type, role, manual_override)
         .await
 }
 
+#[derive(Default, Deserialize)]
+struct UndoArgs {
+    #[serde(default, alias = "deviceType")]
+    device_type: Option<DeviceType>,
+}
+
+/// Undo the last switch of `args.device_type`, or of whichever device type switched last
+#[tauri::command]
+async fn undo_last_switch(args: Option<UndoArgs>, state: State<'_, AppState>) -> AudioResult<()> {
+    let device_type = args.unwrap_or_default().device_type;
+    info!("Undoing the last switch ({:?})", device_type);
+    state.audio_manager.undo_last_switch(device_type).await
+}
+
 #[derive(Deserialize)]
 struct AddToSlotArgs {
     #[serde(alias = "deviceId")]
@@ -58,7 +139,7 @@ struct AddToSlotArgs {
     #[serde(alias = "deviceName")]
     device_name: String,
     #[serde(alias = "deviceType")]
-    device_type: String,
+    device_type: DeviceType,
     #[serde(alias = "priorityType")]
     priority_type: String,
     #[serde(alias = "prioritySlot")]
@@ -82,46 +163,1475 @@ async fn add_device_to_priority_slot(
         device_name, priority_slot, priority_type
     );
 
-    // For now, just log the action since we're focusing on the UI
-    // In a full implementation, this would save to a priority database or config file
+    state
+        .audio_manager
+        .assign_priority_slot(DevicePriority {
+            device_id,
+            device_name,
+            device_type,
+            priority: priority_slot,
+        })
+        .await
+}
+
+#[tauri::command]
+async fn load_priorities(state: State<'_, AppState>) -> AudioResult<PriorityChains> {
+    Ok(state.audio_manager.load_priorities().await)
+}
+
+#[derive(Deserialize)]
+struct SavePrioritiesArgs {
+    playback: Vec<DevicePriority>,
+    recording: Vec<DevicePriority>,
+}
+
+#[tauri::command]
+async fn save_priorities(args: SavePrioritiesArgs, state: State<'_, AppState>) -> AudioResult<()> {
+    state
+        .audio_manager
+        .save_priorities(args.playback, args.recording)
+        .await
+}
+
+#[derive(Deserialize)]
+struct ClearSlotArgs {
+    #[serde(alias = "deviceType")]
+    device_type: DeviceType,
+    #[serde(alias = "prioritySlot")]
+    priority_slot: usize,
+}
+
+/// Empty one priority slot, returning whether it held a device. Other slots keep their
+/// positions, so removing a middle device leaves a gap rather than shifting the rest up.
+#[tauri::command]
+async fn clear_priority_slot(args: ClearSlotArgs, state: State<'_, AppState>) -> AudioResult<bool> {
     info!(
-        "Device '{}' ({}) assigned to priority slot {} in {} chain",
-        device_name, device_id, priority_slot, priority_type
+        "Removing device from priority slot {} for {:?} devices",
+        args.priority_slot, args.device_type
     );
+    state
+        .audio_manager
+        .clear_priority_slot(&args.device_type, args.priority_slot)
+        .await
+}
 
-    Ok(())
+#[derive(Deserialize)]
+struct ImportPrioritiesArgs {
+    playback: Vec<DevicePriority>,
+    recording: Vec<DevicePriority>,
 }
 
 #[tauri::command]
-async fn check_module_availability(state: State<'_, AppState>) -> AudioResult<bool> {
-    info!("Checking AudioDeviceCmdlets module availability...");
-    state.audio_manager.check_module_availability().await
+async fn import_priorities_from_frontend(
+    args: ImportPrioritiesArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<bool> {
+    let ImportPrioritiesArgs {
+        playback,
+        recording,
+    } = args;
+    info!(
+        "Importing {} playback and {} recording priorities from frontend storage",
+        playback.len(),
+        recording.len()
+    );
+
+    state
+        .audio_manager
+        .import_priorities_from_frontend(playback, recording)
+        .await
+}
+
+#[derive(Deserialize)]
+struct CreateGroupArgs {
+    name: String,
+    #[serde(alias = "playbackId")]
+    playback_id: String,
+    #[serde(alias = "recordingId")]
+    recording_id: String,
 }
 
 #[tauri::command]
-async fn install_audio_module(state: State<'_, AppState>) -> AudioResult<()> {
-    info!("Installing AudioDeviceCmdlets module...");
-    state.audio_manager.install_module().await
+async fn create_group(
+    args: CreateGroupArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<DeviceGroup> {
+    let CreateGroupArgs {
+        name,
+        playback_id,
+        recording_id,
+    } = args;
+    info!("Creating device group '{}'", name);
+
+    state
+        .audio_manager
+        .create_group(&name, &playback_id, &recording_id)
+        .await
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
+#[derive(Deserialize)]
+struct GroupNameArgs {
+    name: String,
+}
 
-    // Create application state
-    let audio_manager = Arc::new(AudioManager::new().expect("Failed to initialize AudioManager"));
-    let app_state = AppState { audio_manager };
+#[tauri::command]
+async fn apply_group(args: GroupNameArgs, state: State<'_, AppState>) -> AudioResult<()> {
+    info!("Applying device group '{}'", args.name);
+    state.audio_manager.apply_group(&args.name).await
+}
+
+#[tauri::command]
+async fn list_groups(state: State<'_, AppState>) -> AudioResult<Vec<DeviceGroup>> {
+    Ok(state.audio_manager.list_groups().await)
+}
+
+#[tauri::command]
+async fn suggest_groups(state: State<'_, AppState>) -> AudioResult<Vec<DeviceGroup>> {
+    info!("Suggesting device groups...");
+    state.audio_manager.suggest_groups().await
+}
+
+#[derive(Deserialize)]
+struct BenchmarkArgs {
+    iterations: u32,
+}
+
+#[tauri::command]
+async fn benchmark_enumeration(
+    args: BenchmarkArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<EnumerationBenchmark> {
+    info!("Benchmarking enumeration ({} iterations)", args.iterations);
+    state
+        .audio_manager
+        .benchmark_enumeration(args.iterations)
+        .await
+}
+
+#[derive(Deserialize)]
+struct TestFormatArgs {
+    #[serde(alias = "deviceId")]
+    device_id: String,
+    format: AudioFormat,
+    exclusive: bool,
+}
+
+#[tauri::command]
+async fn test_device_format(
+    args: TestFormatArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<FormatSupport> {
+    let TestFormatArgs {
+        device_id,
+        format,
+        exclusive,
+    } = args;
+    info!("Testing device format for {}", device_id);
+
+    state
+        .audio_manager
+        .test_format(&device_id, format, exclusive)
+        .await
+}
+
+#[derive(Deserialize)]
+struct GroupVolumeArgs {
+    #[serde(alias = "deviceIds")]
+    device_ids: Vec<String>,
+    volume: u8,
+}
+
+#[tauri::command]
+async fn set_group_volume(
+    args: GroupVolumeArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<Vec<DeviceOperationResult>> {
+    let GroupVolumeArgs { device_ids, volume } = args;
+    info!("Setting volume {} on {} devices", volume, device_ids.len());
+
+    state
+        .audio_manager
+        .set_group_volume(&device_ids, volume)
+        .await
+}
+
+#[derive(Deserialize)]
+struct DeviceIdArgs {
+    #[serde(alias = "deviceId")]
+    device_id: String,
+}
+
+#[tauri::command]
+async fn diagnose_device(
+    args: DeviceIdArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<DeviceDiagnosis> {
+    info!("Diagnosing device {}", args.device_id);
+    state.audio_manager.diagnose_device(&args.device_id).await
+}
+
+#[derive(Deserialize)]
+struct SetDefaultByNameArgs {
+    name: String,
+}
+
+#[tauri::command]
+async fn set_default_by_name(
+    args: SetDefaultByNameArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    info!("Setting default device by name or alias: {}", args.name);
+    state.audio_manager.quick_switch_to_device(&args.name).await
+}
+
+#[derive(Deserialize)]
+struct SetAliasArgs {
+    alias: String,
+    #[serde(alias = "deviceId")]
+    device_id: String,
+}
+
+#[tauri::command]
+async fn set_alias(args: SetAliasArgs, state: State<'_, AppState>) -> AudioResult<()> {
+    info!(
+        "Setting alias '{}' for device {}",
+        args.alias, args.device_id
+    );
+    state
+        .audio_manager
+        .set_alias(&args.alias, &args.device_id)
+        .await
+}
+
+#[derive(Deserialize)]
+struct AliasArgs {
+    alias: String,
+}
+
+#[tauri::command]
+async fn remove_alias(args: AliasArgs, state: State<'_, AppState>) -> AudioResult<bool> {
+    info!("Removing alias '{}'", args.alias);
+    state.audio_manager.remove_alias(&args.alias).await
+}
+
+#[tauri::command]
+async fn resolve_alias(
+    args: AliasArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<AliasResolution> {
+    state.audio_manager.resolve_alias(&args.alias).await
+}
+
+/// Nickname shown instead of a device's Windows name; unrelated to quick-switch aliases
+#[tauri::command]
+async fn set_device_alias(args: SetAliasArgs, state: State<'_, AppState>) -> AudioResult<()> {
+    state
+        .audio_manager
+        .set_device_alias(&args.device_id, &args.alias)
+        .await
+}
+
+#[tauri::command]
+async fn clear_device_alias(args: DeviceIdArgs, state: State<'_, AppState>) -> AudioResult<bool> {
+    state
+        .audio_manager
+        .clear_device_alias(&args.device_id)
+        .await
+}
+
+#[tauri::command]
+async fn measure_switch_latency(
+    args: DeviceIdArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<SwitchLatency> {
+    info!("Measuring switch latency for device: {}", args.device_id);
+    state
+        .audio_manager
+        .measure_switch_latency(&args.device_id)
+        .await
+}
+
+/// Debug-only diagnostics answer in debug builds or when the app is started with `--debug`
+fn debug_tools_enabled() -> bool {
+    cfg!(debug_assertions) || std::env::args().any(|arg| arg == "--debug")
+}
+
+#[tauri::command]
+async fn cross_check_backends(state: State<'_, AppState>) -> AudioResult<BackendComparison> {
+    if !debug_tools_enabled() {
+        return Err(AudioError::PermissionDenied(
+            "cross_check_backends is a debug tool; start the app with --debug".to_string(),
+        ));
+    }
+    state.audio_manager.cross_check_backends().await
+}
+
+#[derive(Deserialize)]
+struct DebugScriptArgs {
+    name: String,
+}
+
+#[tauri::command]
+async fn debug_run_known_script(
+    args: DebugScriptArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<RawScriptOutput> {
+    info!("Debug run of built-in script: {}", args.name);
+    let script = KnownScript::from_name(&args.name)?;
+    state.audio_manager.debug_run_known_script(script).await
+}
+
+#[derive(Deserialize)]
+struct AutoInstallArgs {
+    enabled: bool,
+}
+
+#[tauri::command]
+async fn set_auto_install_module(
+    args: AutoInstallArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    info!("Setting automatic module install: {}", args.enabled);
+    state
+        .audio_manager
+        .set_auto_install_module(args.enabled)
+        .await
+}
+
+#[tauri::command]
+async fn get_devices_grouped(state: State<'_, AppState>) -> AudioResult<GroupedDevices> {
+    info!("Getting devices grouped by type");
+    state.audio_manager.get_devices_grouped().await
+}
+
+#[derive(Deserialize)]
+struct AppPreferenceArgs {
+    #[serde(alias = "appName")]
+    app_name: String,
+    #[serde(alias = "deviceId")]
+    device_id: String,
+}
+
+#[tauri::command]
+async fn set_app_preference(
+    args: AppPreferenceArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    info!(
+        "Setting preferred device for {}: {}",
+        args.app_name, args.device_id
+    );
+    state
+        .audio_manager
+        .set_app_preference(&args.app_name, &args.device_id)
+        .await
+}
+
+#[tauri::command]
+async fn list_app_preferences(state: State<'_, AppState>) -> AudioResult<Vec<AppPreference>> {
+    state.audio_manager.list_app_preferences().await
+}
+
+#[derive(Deserialize)]
+struct ReadOnlyArgs {
+    #[serde(alias = "readOnly")]
+    read_only: bool,
+}
+
+#[tauri::command]
+fn set_read_only(args: ReadOnlyArgs, state: State<'_, AppState>) {
+    state.audio_manager.set_read_only(args.read_only);
+}
+
+#[tauri::command]
+fn is_read_only(state: State<'_, AppState>) -> bool {
+    state.audio_manager.is_read_only()
+}
+
+#[derive(Deserialize)]
+struct DryRunArgs {
+    #[serde(alias = "dryRun")]
+    dry_run: bool,
+}
+
+#[tauri::command]
+fn set_dry_run(args: DryRunArgs, state: State<'_, AppState>) {
+    state.audio_manager.set_dry_run(args.dry_run);
+}
+
+#[tauri::command]
+fn is_dry_run(state: State<'_, AppState>) -> bool {
+    state.audio_manager.is_dry_run()
+}
+
+#[derive(Deserialize)]
+struct PowershellTracingArgs {
+    enabled: bool,
+}
+
+#[tauri::command]
+fn set_powershell_tracing(args: PowershellTracingArgs, state: State<'_, AppState>) {
+    state.audio_manager.set_powershell_tracing(args.enabled);
+}
+
+/// The newest operation log entries, `args.limit` at a time and paging back with `args.before`
+#[tauri::command]
+async fn get_operation_log(
+    args: Option<LogPage>,
+    state: State<'_, AppState>,
+) -> AudioResult<Vec<OperationLogEntry>> {
+    Ok(state
+        .audio_manager
+        .get_operation_log(args.unwrap_or_default())
+        .await)
+}
+
+#[derive(Deserialize)]
+struct ExportDiagnosticsArgs {
+    #[serde(default)]
+    redact: bool,
+}
+
+/// Show a save dialog suggesting `file_name`, filtered to `(name, extensions)`;
+/// returns `None` if the user cancels
+async fn pick_save_path(
+    app: &tauri::AppHandle,
+    file_name: &str,
+    (filter_name, extensions): (&str, &[&str]),
+) -> AudioResult<Option<PathBuf>> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_file_name(file_name)
+        .add_filter(filter_name, extensions)
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+
+    let Some(path) = rx.await.ok().flatten() else {
+        return Ok(None);
+    };
+    path.into_path()
+        .map(Some)
+        .map_err(|e| AudioError::InvalidArgument(e.to_string()))
+}
+
+/// Ask the user where to save the diagnostics bundle; returns `None` if they cancel
+#[tauri::command]
+async fn export_diagnostics(
+    args: ExportDiagnosticsArgs,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> AudioResult<Option<String>> {
+    let Some(path) =
+        pick_save_path(&app, "sound-changer-diagnostics.json", ("JSON", &["json"])).await?
+    else {
+        info!("Diagnostics export cancelled");
+        return Ok(None);
+    };
+
+    state
+        .audio_manager
+        .export_diagnostics_bundle(&path, args.redact)
+        .await?;
+    Ok(Some(path.display().to_string()))
+}
+
+/// Ask the user where to save the device list as CSV; returns `None` if they cancel
+#[tauri::command]
+async fn export_devices_csv(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> AudioResult<Option<String>> {
+    let Some(path) = pick_save_path(&app, "audio-devices.csv", ("CSV", &["csv"])).await? else {
+        info!("Device CSV export cancelled");
+        return Ok(None);
+    };
+
+    state.audio_manager.export_devices_csv(&path).await?;
+    Ok(Some(path.display().to_string()))
+}
+
+#[derive(Deserialize)]
+struct DeviceTypeArgs {
+    #[serde(alias = "deviceType")]
+    device_type: DeviceType,
+}
+
+#[tauri::command]
+async fn get_devices_by_type(
+    args: DeviceTypeArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<Vec<AudioDevice>> {
+    state
+        .audio_manager
+        .get_devices_by_type(&args.device_type)
+        .await
+}
+
+#[tauri::command]
+async fn evaluate_and_switch(
+    args: DeviceTypeArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<Option<AudioDevice>> {
+    info!("Evaluating {:?} priority chain", args.device_type);
+    state
+        .audio_manager
+        .evaluate_and_switch(&args.device_type)
+        .await
+}
+
+#[derive(Deserialize)]
+struct AutoswitchUnmuteArgs {
+    enabled: bool,
+    #[serde(default, alias = "minVolume")]
+    min_volume: Option<u8>,
+}
+
+#[tauri::command]
+async fn set_autoswitch_unmute(
+    args: AutoswitchUnmuteArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    info!(
+        "Setting unmute on auto-switch: {} (min volume: {:?})",
+        args.enabled, args.min_volume
+    );
+    state
+        .audio_manager
+        .set_autoswitch_unmute(args.enabled, args.min_volume)
+        .await
+}
+
+#[derive(Deserialize)]
+struct MatchModeArgs {
+    #[serde(alias = "matchMode")]
+    match_mode: MatchMode,
+}
+
+#[tauri::command]
+async fn set_match_mode(args: MatchModeArgs, state: State<'_, AppState>) -> AudioResult<()> {
+    state.audio_manager.set_match_mode(args.match_mode).await
+}
+
+#[tauri::command]
+async fn redetect_backend(state: State<'_, AppState>) -> AudioResult<SetupStatus> {
+    info!("Re-detecting backend");
+    state.audio_manager.redetect_backend().await
+}
+
+#[tauri::command]
+async fn get_cache_info(state: State<'_, AppState>) -> AudioResult<CacheInfo> {
+    Ok(state.audio_manager.get_cache_info().await)
+}
+
+#[tauri::command]
+async fn get_cached_devices(state: State<'_, AppState>) -> AudioResult<CachedDevices> {
+    Ok(state.audio_manager.get_cached_devices().await)
+}
+
+#[tauri::command]
+async fn clear_device_cache(state: State<'_, AppState>) -> AudioResult<()> {
+    state.audio_manager.clear_device_cache().await;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct BestAvailableArgs {
+    #[serde(alias = "deviceType")]
+    device_type: DeviceType,
+    #[serde(alias = "rankedNames")]
+    ranked_names: Vec<String>,
+}
+
+#[tauri::command]
+async fn switch_to_best_available(
+    args: BestAvailableArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<BestAvailableMatch> {
+    info!(
+        "Switching {:?} to best available of {:?}",
+        args.device_type, args.ranked_names
+    );
+    state
+        .audio_manager
+        .switch_to_best_available(&args.device_type, args.ranked_names)
+        .await
+}
+
+#[tauri::command]
+async fn get_device_icon_data(
+    args: DeviceIdArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<Option<String>> {
+    state
+        .audio_manager
+        .get_device_icon_data(&args.device_id)
+        .await
+}
+
+#[tauri::command]
+async fn get_device_properties(
+    args: DeviceIdArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<HashMap<String, String>> {
+    info!("Reading property store of device: {}", args.device_id);
+    state
+        .audio_manager
+        .get_device_properties(&args.device_id)
+        .await
+}
+
+#[derive(Deserialize)]
+struct DevicePairArgs {
+    #[serde(alias = "deviceIdA")]
+    device_id_a: String,
+    #[serde(alias = "deviceIdB")]
+    device_id_b: String,
+}
+
+#[tauri::command]
+async fn same_physical_device(
+    args: DevicePairArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<bool> {
+    state
+        .audio_manager
+        .same_physical_device(&args.device_id_a, &args.device_id_b)
+        .await
+}
+
+#[derive(Deserialize)]
+struct TemporaryOverrideArgs {
+    #[serde(alias = "deviceId")]
+    device_id: String,
+    minutes: u64,
+}
+
+#[tauri::command]
+async fn temporary_override(
+    args: TemporaryOverrideArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    info!(
+        "Temporarily overriding default with {} for {} min",
+        args.device_id, args.minutes
+    );
+    state
+        .audio_manager
+        .temporary_override(
+            &args.device_id,
+            Duration::from_secs(args.minutes.saturating_mul(60)),
+        )
+        .await
+}
+
+#[tauri::command]
+async fn cancel_override(state: State<'_, AppState>) -> AudioResult<bool> {
+    state.audio_manager.cancel_override().await
+}
+
+#[tauri::command]
+async fn get_active_device_state(
+    state: State<'_, AppState>,
+) -> AudioResult<Option<ActiveDeviceState>> {
+    state.audio_manager.get_active_device_state().await
+}
+
+#[tauri::command]
+async fn validate_priorities(
+    args: DeviceTypeArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<Vec<PriorityValidation>> {
+    state
+        .audio_manager
+        .validate_priorities(&args.device_type)
+        .await
+}
+
+#[tauri::command]
+async fn reset_to_windows_default(
+    args: DeviceTypeArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<AudioDevice> {
+    info!("Resetting {:?} to the Windows default", args.device_type);
+    state
+        .audio_manager
+        .reset_to_windows_default(&args.device_type)
+        .await
+}
+
+#[tauri::command]
+async fn check_format_conflicts(state: State<'_, AppState>) -> AudioResult<Vec<FormatConflict>> {
+    state.audio_manager.check_format_conflicts().await
+}
+
+#[tauri::command]
+async fn clear_manual_override(
+    args: DeviceTypeArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<bool> {
+    info!("Clearing manual override of {:?}", args.device_type);
+    state
+        .audio_manager
+        .clear_manual_override(&args.device_type)
+        .await
+}
+
+#[tauri::command]
+async fn preview_autoswitch_target(
+    args: DeviceTypeArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<Option<AudioDevice>> {
+    state
+        .audio_manager
+        .preview_autoswitch_target(&args.device_type)
+        .await
+}
+
+#[tauri::command]
+async fn list_spatial_providers(state: State<'_, AppState>) -> AudioResult<Vec<SpatialProvider>> {
+    state.audio_manager.list_spatial_providers().await
+}
+
+#[derive(Deserialize)]
+struct AutostartArgs {
+    enabled: bool,
+    #[serde(default, alias = "startMinimized")]
+    start_minimized: bool,
+}
+
+/// Launch the app at login (optionally minimized); returns whether it is now registered
+#[tauri::command]
+async fn set_autostart(
+    args: AutostartArgs,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> AudioResult<bool> {
+    info!(
+        "Setting launch at login: {} (minimized: {})",
+        args.enabled, args.start_minimized
+    );
+    state
+        .audio_manager
+        .ensure_writable("change launch at login")?;
+    let enabled = autostart::set_enabled(&*app.autolaunch(), args.enabled)?;
+    state
+        .audio_manager
+        .set_start_minimized(args.start_minimized)
+        .await?;
+    Ok(enabled)
+}
+
+#[tauri::command]
+async fn get_autostart(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> AudioResult<AutostartStatus> {
+    Ok(AutostartStatus {
+        enabled: autostart::is_enabled(&*app.autolaunch())?,
+        start_minimized: state.audio_manager.start_minimized().await,
+    })
+}
+
+#[derive(Deserialize)]
+struct PauseWatcherArgs {
+    enabled: bool,
+}
+
+#[tauri::command]
+async fn set_pause_watcher_on_battery(
+    args: PauseWatcherArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    state
+        .audio_manager
+        .set_pause_watcher_on_battery(args.enabled)
+        .await
+}
+
+#[tauri::command]
+async fn get_priority_ordered_devices(
+    args: DeviceTypeArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<Vec<PriorityOrderedDevice>> {
+    state
+        .audio_manager
+        .get_priority_ordered_devices(&args.device_type)
+        .await
+}
+
+#[derive(Deserialize)]
+struct FactoryResetArgs {
+    /// Must be `true`; guards against triggering the reset by accident
+    #[serde(default)]
+    confirm: bool,
+}
+
+/// Reset every setting to its default, returning the path of the backup taken first
+#[tauri::command]
+async fn factory_reset(args: FactoryResetArgs, state: State<'_, AppState>) -> AudioResult<String> {
+    if !args.confirm {
+        return Err(AudioError::InvalidArgument(
+            "Factory reset needs confirm: true".to_string(),
+        ));
+    }
+    state.audio_manager.factory_reset().await
+}
+
+#[tauri::command]
+async fn detect_default_anomalies(state: State<'_, AppState>) -> AudioResult<Vec<DefaultAnomaly>> {
+    state.audio_manager.detect_default_anomalies().await
+}
+
+/// Returns the previous communication playback device, to route system sounds back later
+#[tauri::command]
+async fn set_system_sounds_device(
+    args: DeviceIdArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<Option<String>> {
+    state
+        .audio_manager
+        .set_system_sounds_device(&args.device_id)
+        .await
+}
+
+#[derive(Deserialize)]
+struct StateChangeEventsArgs {
+    enabled: bool,
+}
+
+#[tauri::command]
+async fn set_state_change_events(
+    args: StateChangeEventsArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    state
+        .audio_manager
+        .set_state_change_events(args.enabled)
+        .await
+}
+
+#[derive(Deserialize)]
+struct CacheTtlArgs {
+    #[serde(alias = "ttlMs")]
+    ttl_ms: u64,
+}
+
+#[tauri::command]
+async fn set_cache_ttl(args: CacheTtlArgs, state: State<'_, AppState>) -> AudioResult<()> {
+    state.audio_manager.set_cache_ttl(args.ttl_ms).await
+}
+
+#[tauri::command]
+async fn reload_config(state: State<'_, AppState>) -> AudioResult<()> {
+    state.audio_manager.reload_config().await
+}
+
+#[derive(Deserialize)]
+struct DeviceMuteArgs {
+    #[serde(alias = "deviceId")]
+    device_id: String,
+    muted: bool,
+}
+
+#[tauri::command]
+async fn set_device_mute(args: DeviceMuteArgs, state: State<'_, AppState>) -> AudioResult<()> {
+    state
+        .audio_manager
+        .set_device_mute(&args.device_id, args.muted)
+        .await
+}
+
+#[tauri::command]
+async fn toggle_device_mute(args: DeviceIdArgs, state: State<'_, AppState>) -> AudioResult<bool> {
+    state
+        .audio_manager
+        .toggle_device_mute(&args.device_id)
+        .await
+}
+
+#[derive(Deserialize)]
+struct MonitoringArgs {
+    #[serde(alias = "intervalSecs")]
+    interval_secs: u64,
+}
+
+#[tauri::command]
+async fn start_monitoring(
+    args: MonitoringArgs,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    state
+        .audio_manager
+        .start_monitoring(app, Duration::from_secs(args.interval_secs))
+}
+
+#[tauri::command]
+async fn stop_monitoring(state: State<'_, AppState>) -> AudioResult<bool> {
+    Ok(state.audio_manager.stop_monitoring())
+}
+
+#[derive(Deserialize)]
+struct FollowFocusArgs {
+    enabled: bool,
+}
+
+#[tauri::command]
+async fn set_follow_focus(args: FollowFocusArgs, state: State<'_, AppState>) -> AudioResult<()> {
+    state.audio_manager.set_follow_focus(args.enabled).await
+}
+
+#[derive(Deserialize)]
+struct RenameProfileArgs {
+    #[serde(alias = "oldName")]
+    old_name: String,
+    #[serde(alias = "newName")]
+    new_name: String,
+}
+
+#[tauri::command]
+async fn rename_profile(args: RenameProfileArgs, state: State<'_, AppState>) -> AudioResult<()> {
+    state
+        .audio_manager
+        .rename_profile(&args.old_name, &args.new_name)
+        .await
+}
+
+#[tauri::command]
+async fn get_autoswitch_decisions(
+    args: Option<LogPage>,
+    state: State<'_, AppState>,
+) -> AudioResult<Vec<AutoSwitchDecision>> {
+    Ok(state
+        .audio_manager
+        .get_autoswitch_decisions(args.unwrap_or_default())
+        .await)
+}
+
+#[derive(Deserialize)]
+struct AudioSessionsArgs {
+    #[serde(default, alias = "deviceId")]
+    device_id: Option<String>,
+    #[serde(default, alias = "includeSelf")]
+    include_self: bool,
+}
+
+#[tauri::command]
+async fn get_audio_sessions(
+    args: AudioSessionsArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<Vec<AudioSession>> {
+    state
+        .audio_manager
+        .get_audio_sessions(args.device_id.as_deref(), args.include_self)
+        .await
+}
+
+#[derive(Deserialize)]
+struct SupportsChannelsArgs {
+    #[serde(default, alias = "deviceId")]
+    device_id: Option<String>,
+    channels: u16,
+}
+
+#[tauri::command]
+async fn supports_channels(
+    args: SupportsChannelsArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<ChannelSupport> {
+    state
+        .audio_manager
+        .supports_channels(args.device_id.as_deref(), args.channels)
+        .await
+}
+
+#[tauri::command]
+async fn get_loudness_equalization(
+    args: DeviceIdArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<bool> {
+    state
+        .audio_manager
+        .get_loudness_equalization(&args.device_id)
+        .await
+}
+
+#[derive(Deserialize)]
+struct LoudnessEqualizationArgs {
+    #[serde(alias = "deviceId")]
+    device_id: String,
+    enabled: bool,
+}
+
+#[tauri::command]
+async fn set_loudness_equalization(
+    args: LoudnessEqualizationArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    state
+        .audio_manager
+        .set_loudness_equalization(&args.device_id, args.enabled)
+        .await
+}
+
+#[derive(Deserialize)]
+struct SwitchCoalesceWindowArgs {
+    #[serde(default, alias = "windowMs")]
+    window_ms: Option<u64>,
+}
+
+#[tauri::command]
+async fn set_switch_coalesce_window(
+    args: SwitchCoalesceWindowArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    state
+        .audio_manager
+        .set_switch_coalesce_window(args.window_ms)
+        .await
+}
+
+#[tauri::command]
+async fn get_all_defaults(state: State<'_, AppState>) -> AudioResult<RoleDefaults> {
+    state.audio_manager.get_all_defaults().await
+}
+
+/// Endpoint IDs keyed by the PnP container (physical device) they belong to
+#[tauri::command]
+async fn get_device_containers(
+    state: State<'_, AppState>,
+) -> AudioResult<HashMap<String, Vec<String>>> {
+    state.audio_manager.get_device_containers().await
+}
+
+#[tauri::command]
+async fn get_communication_defaults(
+    state: State<'_, AppState>,
+) -> AudioResult<CommunicationDefaults> {
+    state.audio_manager.get_communication_defaults().await
+}
+
+#[tauri::command]
+async fn backup_config(state: State<'_, AppState>) -> AudioResult<String> {
+    state.audio_manager.backup_config().await
+}
+
+#[tauri::command]
+async fn list_config_backups(state: State<'_, AppState>) -> AudioResult<Vec<ConfigBackup>> {
+    state.audio_manager.list_config_backups().await
+}
+
+#[derive(Deserialize)]
+struct RestoreConfigBackupArgs {
+    name: String,
+}
+
+#[tauri::command]
+async fn restore_config_backup(
+    args: RestoreConfigBackupArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    state.audio_manager.restore_config_backup(&args.name).await
+}
+
+#[derive(Deserialize)]
+struct ConfigBackupLimitArgs {
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[tauri::command]
+async fn set_config_backup_limit(
+    args: ConfigBackupLimitArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    state
+        .audio_manager
+        .set_config_backup_limit(args.limit)
+        .await
+}
+
+#[tauri::command]
+fn get_backend_capabilities(state: State<'_, AppState>) -> BackendCapabilities {
+    state.audio_manager.get_backend_capabilities()
+}
+
+#[derive(Deserialize)]
+struct WaitForDeviceArgs {
+    #[serde(alias = "namePattern")]
+    name_pattern: String,
+    #[serde(alias = "timeoutSecs")]
+    timeout_secs: u64,
+}
+
+#[tauri::command]
+async fn wait_for_device(
+    args: WaitForDeviceArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<AudioDevice> {
+    state
+        .audio_manager
+        .wait_for_device(&args.name_pattern, Duration::from_secs(args.timeout_secs))
+        .await
+}
+
+#[tauri::command]
+async fn connect_and_switch(
+    args: WaitForDeviceArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<AudioDevice> {
+    state
+        .audio_manager
+        .connect_and_switch(&args.name_pattern, Duration::from_secs(args.timeout_secs))
+        .await
+}
+
+#[tauri::command]
+async fn check_module_update(state: State<'_, AppState>) -> AudioResult<ModuleUpdateInfo> {
+    state.audio_manager.check_module_update().await
+}
+
+#[tauri::command]
+async fn run_diagnostics(state: State<'_, AppState>) -> AudioResult<Diagnostics> {
+    state.audio_manager.diagnose().await
+}
+
+#[tauri::command]
+async fn set_quiet_hours(args: QuietHours, state: State<'_, AppState>) -> AudioResult<()> {
+    state.audio_manager.set_quiet_hours(args).await
+}
+
+#[tauri::command]
+async fn clear_quiet_hours(state: State<'_, AppState>) -> AudioResult<()> {
+    state.audio_manager.clear_quiet_hours().await
+}
+
+#[tauri::command]
+async fn get_driver_status(
+    args: DeviceIdArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<DriverStatus> {
+    state.audio_manager.get_driver_status(&args.device_id).await
+}
+
+#[derive(Deserialize)]
+struct DefaultPairArgs {
+    #[serde(default, alias = "playbackId")]
+    playback_id: Option<String>,
+    #[serde(default, alias = "recordingId")]
+    recording_id: Option<String>,
+}
+
+#[tauri::command]
+async fn set_default_pair(
+    args: DefaultPairArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<RoleDefaults> {
+    state
+        .audio_manager
+        .set_default_pair(args.playback_id.as_deref(), args.recording_id.as_deref())
+        .await
+}
+
+#[tauri::command]
+async fn get_setup_summary(state: State<'_, AppState>) -> AudioResult<SetupSummary> {
+    state.audio_manager.get_setup_summary().await
+}
+
+#[derive(Deserialize)]
+struct ProfileNameArgs {
+    name: String,
+}
+
+#[tauri::command]
+async fn apply_profile(
+    args: ProfileNameArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<ProfileApplication> {
+    state.audio_manager.apply_profile(&args.name).await
+}
+
+#[derive(Deserialize)]
+struct CycleOutputArgs {
+    #[serde(alias = "deviceType")]
+    device_type: DeviceType,
+    #[serde(default = "default_role")]
+    role: DefaultRole,
+}
+
+#[tauri::command]
+async fn cycle_audio_output(
+    args: CycleOutputArgs,
+    state: State<'_, AppState>,
+) -> AudioResult<Option<AudioDevice>> {
+    state
+        .audio_manager
+        .cycle_to_next_priority(&args.device_type, args.role)
+        .await
+}
+
+#[tauri::command]
+async fn list_profiles(state: State<'_, AppState>) -> AudioResult<Vec<Profile>> {
+    Ok(state.audio_manager.list_profiles().await)
+}
+
+#[tauri::command]
+async fn save_profile(args: ProfileNameArgs, state: State<'_, AppState>) -> AudioResult<Profile> {
+    state.audio_manager.save_profile(&args.name).await
+}
+
+#[tauri::command]
+async fn delete_profile(args: ProfileNameArgs, state: State<'_, AppState>) -> AudioResult<()> {
+    state.audio_manager.delete_profile(&args.name).await
+}
+
+#[derive(Deserialize)]
+struct HotkeyArgs {
+    accelerator: String,
+    action: HotkeyAction,
+}
+
+/// Bind a global hotkey. The binding is validated and saved before the OS registration,
+/// and put back as it was if the OS refuses the shortcut.
+#[tauri::command]
+async fn register_hotkey(
+    args: HotkeyArgs,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> AudioResult<()> {
+    let audio_manager = &state.audio_manager;
+    hotkeys::validate(&args.accelerator)?;
+    let previous = audio_manager.list_hotkeys().await.remove(&args.accelerator);
+    audio_manager
+        .set_hotkey(&args.accelerator, args.action)
+        .await?;
+
+    if let Err(e) = hotkeys::register(&app, audio_manager.clone(), &args.accelerator) {
+        let rollback = match previous {
+            Some(action) => audio_manager.set_hotkey(&args.accelerator, action).await,
+            None => audio_manager
+                .remove_hotkey(&args.accelerator)
+                .await
+                .map(drop),
+        };
+        if let Err(rollback_error) = rollback {
+            error!(
+                "Failed to roll back hotkey {}: {}",
+                args.accelerator, rollback_error
+            );
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct AcceleratorArgs {
+    accelerator: String,
+}
+
+#[tauri::command]
+async fn unregister_hotkey(
+    args: AcceleratorArgs,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> AudioResult<bool> {
+    let removed = state.audio_manager.remove_hotkey(&args.accelerator).await?;
+    hotkeys::unregister(&app, &args.accelerator)?;
+    Ok(removed)
+}
+
+#[tauri::command]
+async fn list_hotkeys(state: State<'_, AppState>) -> AudioResult<BTreeMap<String, HotkeyAction>> {
+    Ok(state.audio_manager.list_hotkeys().await)
+}
+
+#[tauri::command]
+async fn panic_restore(state: State<'_, AppState>) -> AudioResult<AudioDevice> {
+    state.audio_manager.panic_restore().await
+}
+
+#[tauri::command]
+async fn stream_audio_devices(state: State<'_, AppState>) -> AudioResult<usize> {
+    info!("Streaming audio devices...");
+    state.audio_manager.stream_audio_devices().await
+}
+
+#[tauri::command]
+async fn check_module_availability(state: State<'_, AppState>) -> AudioResult<bool> {
+    info!("Checking AudioDeviceCmdlets module availability...");
+    state.audio_manager.check_module_availability().await
+}
+
+#[tauri::command]
+async fn install_audio_module(state: State<'_, AppState>) -> AudioResult<()> {
+    info!("Installing AudioDeviceCmdlets module...");
+    state.audio_manager.install_module().await
+}
+
+#[tauri::command]
+async fn list_profiles_status(state: State<'_, AppState>) -> AudioResult<Vec<ProfileStatus>> {
+    info!("Listing profiles with applicability status...");
+    state.audio_manager.list_profiles_with_status().await
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    // Initialize tracing
+    tracing_subscriber::fmt::init();
+
+    // Create application state
+    let audio_manager = Arc::new(AudioManager::new().expect("Failed to initialize AudioManager"));
+    if std::env::args().any(|arg| arg == "--read-only") {
+        audio_manager.set_read_only(true);
+    }
+    if std::env::args().any(|arg| arg == "--dry-run") {
+        audio_manager.set_dry_run(true);
+    }
+    let launched_at_login = std::env::args().any(|arg| arg == autostart::AUTOSTART_ARG);
+    let app_state = AppState {
+        audio_manager: audio_manager.clone(),
+    };
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
+        .plugin(tauri_plugin_autostart::init(
+            MacosLauncher::LaunchAgent,
+            Some(vec![autostart::AUTOSTART_ARG]),
+        ))
         .manage(app_state)
+        .setup(move |app| {
+            audio_manager.attach_app_handle(app.handle().clone());
+            if launched_at_login && tauri::async_runtime::block_on(audio_manager.start_minimized())
+            {
+                if let Some(window) = app.get_webview_window("main") {
+                    window.minimize()?;
+                }
+            }
+            hotkeys::restore_saved(app.handle(), &audio_manager);
+            let warm_up_manager = audio_manager.clone();
+            tauri::async_runtime::spawn(async move { warm_up_manager.warm_up().await });
+            audio_manager.start_monitoring(app.handle().clone(), WATCH_INTERVAL)?;
+            tauri::async_runtime::spawn(audio_manager.clone().watch_focus());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_audio_devices,
             set_default_device,
             add_device_to_priority_slot,
+            import_priorities_from_frontend,
             check_module_availability,
-            install_audio_module
+            install_audio_module,
+            list_profiles_status,
+            create_group,
+            apply_group,
+            list_groups,
+            suggest_groups,
+            benchmark_enumeration,
+            test_device_format,
+            set_group_volume,
+            diagnose_device,
+            set_default_by_name,
+            set_alias,
+            remove_alias,
+            resolve_alias,
+            measure_switch_latency,
+            debug_run_known_script,
+            set_auto_install_module,
+            get_devices_grouped,
+            set_app_preference,
+            list_app_preferences,
+            set_read_only,
+            is_read_only,
+            get_operation_log,
+            export_diagnostics,
+            evaluate_and_switch,
+            set_autoswitch_unmute,
+            redetect_backend,
+            get_cache_info,
+            switch_to_best_available,
+            get_device_icon_data,
+            get_device_properties,
+            panic_restore,
+            stream_audio_devices,
+            set_match_mode,
+            same_physical_device,
+            temporary_override,
+            cancel_override,
+            get_active_device_state,
+            validate_priorities,
+            reset_to_windows_default,
+            check_format_conflicts,
+            export_devices_csv,
+            clear_manual_override,
+            preview_autoswitch_target,
+            list_spatial_providers,
+            set_pause_watcher_on_battery,
+            rename_profile,
+            get_autoswitch_decisions,
+            get_audio_sessions,
+            get_loudness_equalization,
+            set_loudness_equalization,
+            set_switch_coalesce_window,
+            get_all_defaults,
+            backup_config,
+            list_config_backups,
+            restore_config_backup,
+            set_config_backup_limit,
+            get_backend_capabilities,
+            wait_for_device,
+            connect_and_switch,
+            set_powershell_tracing,
+            check_module_update,
+            set_quiet_hours,
+            clear_quiet_hours,
+            get_driver_status,
+            set_default_pair,
+            get_setup_summary,
+            get_cached_devices,
+            clear_device_cache,
+            apply_profile,
+            register_hotkey,
+            unregister_hotkey,
+            list_hotkeys,
+            get_communication_defaults,
+            get_device_containers,
+            set_state_change_events,
+            set_system_sounds_device,
+            detect_default_anomalies,
+            set_autostart,
+            get_autostart,
+            get_priority_ordered_devices,
+            factory_reset,
+            cross_check_backends,
+            set_follow_focus,
+            supports_channels,
+            load_priorities,
+            save_priorities,
+            clear_priority_slot,
+            start_monitoring,
+            stop_monitoring,
+            set_device_mute,
+            toggle_device_mute,
+            set_cache_ttl,
+            reload_config,
+            refresh_devices,
+            list_profiles,
+            save_profile,
+            delete_profile,
+            cycle_audio_output,
+            get_devices_by_type,
+            set_device_alias,
+            clear_device_alias,
+            set_dry_run,
+            is_dry_run,
+            get_audio_devices_detailed,
+            run_diagnostics,
+            undo_last_switch
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+/// How a user-supplied name is compared with device names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchMode {
+    /// The whole name, case-insensitive
+    Exact,
+    /// The name contains the query, case-insensitive
+    #[default]
+    Substring,
+    /// The normalized query equals the normalized name or its hardware part
+    Normalized,
+    /// Every word of the query appears somewhere in the name, in any order
+    Fuzzy,
+}
+
+impl MatchMode {
+    /// Whether the device name `name` matches `query` under this mode
+    pub fn matches(self, name: &str, query: &str) -> bool {
+        match self {
+            MatchMode::Exact => name.trim().to_lowercase() == query.trim().to_lowercase(),
+            MatchMode::Substring => name.to_lowercase().contains(&query.to_lowercase()),
+            MatchMode::Normalized => {
+                let query = normalize_name(query);
+                !query.is_empty()
+                    && (normalize_name(name) == query
+                        || hardware_name(name).is_some_and(|h| normalize_name(h) == query))
+            }
+            MatchMode::Fuzzy => {
+                let name = name.to_lowercase();
+                let mut words = query.split_whitespace().peekable();
+                words.peek().is_some() && words.all(|word| name.contains(&word.to_lowercase()))
+            }
+        }
+    }
+}
+
+/// Lowercase, drop parenthetical parts and collapse whitespace, so
+/// "Speakers  (Realtek Audio)" becomes "speakers"
+pub fn normalize_name(name: &str) -> String {
+    let mut stripped = String::with_capacity(name.len());
+    let mut depth = 0usize;
+    for c in name.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Hardware part of an endpoint name, e.g. "HyperX Cloud" in "Headphones (HyperX Cloud)"
+pub fn hardware_name(name: &str) -> Option<&str> {
+    let start = name.find('(')?;
+    let end = name.rfind(')')?;
+    let hardware = name.get(start + 1..end)?.trim();
+    (!hardware.is_empty()).then_some(hardware)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEAKERS: &str = "Speakers (Realtek(R) Audio)";
+    const HEADSET: &str = "Headset Earphone (HyperX Cloud  II)";
+    const MONITOR: &str = "DELL U2720Q (NVIDIA High Definition Audio)";
+
+    #[test]
+    fn exact_requires_the_whole_name() {
+        assert!(MatchMode::Exact.matches(SPEAKERS, "speakers (realtek(r) audio)"));
+        assert!(MatchMode::Exact.matches(SPEAKERS, "  Speakers (Realtek(R) Audio) "));
+        assert!(!MatchMode::Exact.matches(SPEAKERS, "Speakers"));
+        assert!(!MatchMode::Exact.matches(SPEAKERS, "Realtek(R) Audio"));
+    }
+
+    #[test]
+    fn substring_matches_any_part_of_the_name() {
+        assert!(MatchMode::Substring.matches(SPEAKERS, "realtek"));
+        assert!(MatchMode::Substring.matches(HEADSET, "HyperX Cloud"));
+        assert!(MatchMode::Substring.matches(MONITOR, "u2720q"));
+        assert!(!MatchMode::Substring.matches(HEADSET, "HyperX Cloud II"));
+        assert!(!MatchMode::Substring.matches(SPEAKERS, "realtek speakers"));
+    }
+
+    #[test]
+    fn normalized_matches_the_endpoint_or_hardware_name() {
+        assert!(MatchMode::Normalized.matches(SPEAKERS, "Speakers"));
+        assert!(MatchMode::Normalized.matches(SPEAKERS, "speakers (Realtek Audio)"));
+        assert!(MatchMode::Normalized.matches(HEADSET, "hyperx cloud ii"));
+        assert!(MatchMode::Normalized.matches(HEADSET, "Headset   Earphone"));
+        assert!(MatchMode::Normalized.matches(MONITOR, "NVIDIA High Definition Audio"));
+        assert!(!MatchMode::Normalized.matches(HEADSET, "HyperX"));
+        assert!(!MatchMode::Normalized.matches(SPEAKERS, "(Realtek)"));
+    }
+
+    #[test]
+    fn fuzzy_matches_words_in_any_order() {
+        assert!(MatchMode::Fuzzy.matches(SPEAKERS, "realtek speakers"));
+        assert!(MatchMode::Fuzzy.matches(HEADSET, "cloud hyperx"));
+        assert!(MatchMode::Fuzzy.matches(MONITOR, "dell nvidia"));
+        assert!(!MatchMode::Fuzzy.matches(MONITOR, "dell realtek"));
+        assert!(!MatchMode::Fuzzy.matches(SPEAKERS, "   "));
+    }
+
+    #[test]
+    fn normalize_name_strips_parentheticals_and_whitespace() {
+        assert_eq!(normalize_name(SPEAKERS), "speakers");
+        assert_eq!(normalize_name("  Line  In (2- USB Audio) "), "line in");
+        assert_eq!(normalize_name("Realtek Audio"), "realtek audio");
+    }
+}
@@ -0,0 +1,11 @@
+use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+/// Whether the machine is running on battery (AC line offline). Desktops and unknown
+/// power states count as AC.
+pub fn on_battery() -> bool {
+    let mut status = SYSTEM_POWER_STATUS::default();
+    match unsafe { GetSystemPowerStatus(&mut status) } {
+        Ok(()) => status.ACLineStatus == 0,
+        Err(_) => false,
+    }
+}
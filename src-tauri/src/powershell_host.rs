@@ -0,0 +1,637 @@
+use serde::Deserialize;
+use std::io;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tracing::{debug, info, warn};
+
+/// Most host processes kept at once; a script arriving while all of them are busy is
+/// refused with `io::ErrorKind::WouldBlock` rather than queued
+const POOL_SIZE: usize = 2;
+
+/// Loop run by the long-lived PowerShell process. Each stdin line is a JSON request
+/// `{"script": ...}`; the script runs in a runspace that imported AudioDeviceCmdlets once,
+/// and the reply is one JSON line with its output, errors and exit code. Scripts run in a
+/// separate runspace so their `exit 1` ends the script rather than the host, and the
+/// runspace's variables are reset after each one so scripts can't see each other's
+/// state. A script may
+/// call `Send-Progress` to report a step as it finishes; each call writes a
+/// `{"progress": ...}` line ahead of the reply.
+const HOST_SCRIPT: &str = r#"
+    $utf8 = New-Object System.Text.UTF8Encoding $false
+    [Console]::InputEncoding = $utf8
+    [Console]::OutputEncoding = $utf8
+
+    $runspace = [runspacefactory]::CreateRunspace()
+    $runspace.Open()
+    $warmup = [powershell]::Create()
+    $warmup.Runspace = $runspace
+    $null = $warmup.AddScript(@'
+Import-Module AudioDeviceCmdlets -ErrorAction SilentlyContinue
+function global:Send-Progress([string]$Json) {
+    [Console]::Out.WriteLine((@{ progress = $Json } | ConvertTo-Json -Compress))
+    [Console]::Out.Flush()
+}
+'@).Invoke()
+    $warmup.Dispose()
+
+    while ($null -ne ($line = [Console]::In.ReadLine())) {
+        $stdout = ''
+        $stderr = ''
+        $exitCode = 0
+        $ps = [powershell]::Create()
+        $ps.Runspace = $runspace
+        try {
+            $request = $line | ConvertFrom-Json
+            $runspace.SessionStateProxy.SetVariable('LASTEXITCODE', 0)
+            $results = $ps.AddScript($request.script).Invoke()
+            $stdout = ($results | ForEach-Object { if ($_ -is [string]) { $_ } else { ($_ | Out-String).TrimEnd() } }) -join "`n"
+            $stderr = ($ps.Streams.Error | ForEach-Object { "$_" }) -join "`n"
+            $exitCode = [int]$runspace.SessionStateProxy.GetVariable('LASTEXITCODE')
+        }
+        catch {
+            $stderr = $_.Exception.Message
+            $exitCode = 1
+        }
+        finally {
+            $ps.Dispose()
+            try { $runspace.ResetRunspaceState() } catch { }
+        }
+        [Console]::Out.WriteLine((@{ stdout = $stdout; stderr = $stderr; exit_code = $exitCode } | ConvertTo-Json -Compress))
+        [Console]::Out.Flush()
+    }
+"#;
+
+/// What a script printed and how it exited, whether it ran on the host or one-shot
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptOutput {
+    /// `None` when a one-shot process was killed by a signal
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl ScriptOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+impl From<std::process::Output> for ScriptOutput {
+    fn from(output: std::process::Output) -> Self {
+        Self {
+            exit_code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+}
+
+/// A line the host writes while a script runs: progress the script reported, or the reply
+/// that ends it
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HostLine {
+    Progress { progress: String },
+    Reply(HostResponse),
+}
+
+#[derive(Deserialize)]
+struct HostResponse {
+    stdout: Option<String>,
+    stderr: Option<String>,
+    exit_code: i32,
+}
+
+/// One running host process and the pipes to talk to it
+pub struct HostSession {
+    executable: String,
+    /// `None` for sessions backed by in-memory streams
+    child: Option<Child>,
+    stdin: Pin<Box<dyn AsyncWrite + Send>>,
+    stdout: Pin<Box<dyn AsyncBufRead + Send>>,
+}
+
+impl HostSession {
+    /// Start a host process; it is killed when the session is dropped
+    pub fn spawn(executable: &str) -> io::Result<Self> {
+        let mut child = Command::new(executable)
+            .args([
+                "-ExecutionPolicy",
+                "Bypass",
+                "-NoProfile",
+                "-NonInteractive",
+                "-Command",
+                HOST_SCRIPT,
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| closed_pipe("stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| closed_pipe("stdout"))?;
+
+        let mut session = Self::from_streams(executable, BufReader::new(stdout), stdin);
+        session.child = Some(child);
+        Ok(session)
+    }
+
+    /// A session talking the host protocol over arbitrary streams
+    pub fn from_streams(
+        executable: &str,
+        reader: impl AsyncBufRead + Send + 'static,
+        writer: impl AsyncWrite + Send + 'static,
+    ) -> Self {
+        Self {
+            executable: executable.to_string(),
+            child: None,
+            stdin: Box::pin(writer),
+            stdout: Box::pin(reader),
+        }
+    }
+
+    /// Health check before reuse: the process hasn't exited
+    fn is_alive(&mut self) -> bool {
+        match &mut self.child {
+            Some(child) => matches!(child.try_wait(), Ok(None)),
+            None => true,
+        }
+    }
+
+    async fn run(
+        &mut self,
+        script: &str,
+        on_progress: &mut (dyn FnMut(&str) + Send),
+    ) -> io::Result<ScriptOutput> {
+        let mut request = serde_json::json!({ "script": script }).to_string();
+        request.push('\n');
+        self.stdin.write_all(request.as_bytes()).await?;
+        self.stdin.flush().await?;
+
+        let response = loop {
+            let mut line = String::new();
+            if self.stdout.read_line(&mut line).await? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "PowerShell host exited",
+                ));
+            }
+            match serde_json::from_str(line.trim_start_matches('\u{feff}'))? {
+                HostLine::Progress { progress } => on_progress(&progress),
+                HostLine::Reply(response) => break response,
+            }
+        };
+        Ok(ScriptOutput {
+            exit_code: Some(response.exit_code),
+            stdout: response.stdout.unwrap_or_default(),
+            stderr: response.stderr.unwrap_or_default(),
+        })
+    }
+}
+
+fn closed_pipe(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        format!("PowerShell host {} unavailable", name),
+    )
+}
+
+type Spawner = Box<dyn Fn(&str) -> io::Result<HostSession> + Send + Sync>;
+
+/// A small pool of warm PowerShell sessions shared by every script, so the process
+/// start-up and module import are paid once rather than per operation. Each session runs
+/// one script at a time; when all `POOL_SIZE` are busy the caller is told to run
+/// one-shot instead of waiting. A session that died, timed out or belongs to another
+/// executable is replaced with a fresh one.
+pub struct PowerShellHost {
+    /// Sessions waiting for a script
+    idle: Mutex<Vec<HostSession>>,
+    /// Sessions alive, idle or running a script
+    live: AtomicUsize,
+    spawn: Spawner,
+}
+
+/// A session taken from the pool for one script. Released sessions go back to the pool;
+/// dropping the lease instead (e.g. on timeout) kills the session.
+struct Lease<'a> {
+    host: &'a PowerShellHost,
+    session: Option<HostSession>,
+}
+
+impl Lease<'_> {
+    fn session(&mut self) -> &mut HostSession {
+        self.session.as_mut().expect("leased session")
+    }
+
+    fn release(mut self) {
+        if let Some(session) = self.session.take() {
+            self.host.idle.lock().unwrap().push(session);
+        }
+    }
+}
+
+impl Drop for Lease<'_> {
+    fn drop(&mut self) {
+        if self.session.take().is_some() {
+            self.host.live.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Default for PowerShellHost {
+    fn default() -> Self {
+        Self::with_spawner(HostSession::spawn)
+    }
+}
+
+impl PowerShellHost {
+    pub fn with_spawner(
+        spawn: impl Fn(&str) -> io::Result<HostSession> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            idle: Mutex::new(Vec::new()),
+            live: AtomicUsize::new(0),
+            spawn: Box::new(spawn),
+        }
+    }
+
+    /// Run `script` on a warm session, restarting the host once if it crashed. `timeout`
+    /// starts once a session is taken and covers the restart; a script that exceeds it
+    /// fails with `io::ErrorKind::TimedOut` and its session is killed.
+    pub async fn run(
+        &self,
+        executable: &str,
+        script: &str,
+        timeout: Duration,
+    ) -> io::Result<ScriptOutput> {
+        self.run_with_progress(executable, script, timeout, &mut |_| {})
+            .await
+    }
+
+    /// Like `run`, handing each `Send-Progress` payload to `on_progress` as it arrives. A
+    /// script rerun after a host crash may report the same steps again.
+    pub async fn run_with_progress(
+        &self,
+        executable: &str,
+        script: &str,
+        timeout: Duration,
+        on_progress: &mut (dyn FnMut(&str) + Send),
+    ) -> io::Result<ScriptOutput> {
+        let mut lease = self.acquire(executable)?;
+        let run = async {
+            match lease.session().run(script, &mut *on_progress).await {
+                Ok(output) => Ok(output),
+                Err(e) => {
+                    warn!("PowerShell host failed ({}), restarting it", e);
+                    *lease.session() = self.start(executable)?;
+                    lease.session().run(script, &mut *on_progress).await
+                }
+            }
+        };
+        let output = tokio::time::timeout(timeout, run).await.map_err(|_| {
+            io::Error::new(io::ErrorKind::TimedOut, "PowerShell script timed out")
+        })??;
+        lease.release();
+        Ok(output)
+    }
+
+    /// Take an idle session for `executable`, or start one if the pool has room
+    fn acquire(&self, executable: &str) -> io::Result<Lease<'_>> {
+        {
+            let mut idle = self.idle.lock().unwrap();
+            while let Some(mut session) = idle.pop() {
+                if session.executable == executable && session.is_alive() {
+                    return Ok(Lease {
+                        host: self,
+                        session: Some(session),
+                    });
+                }
+                debug!("Discarding PowerShell host ({})", session.executable);
+                self.live.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        self.live
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |live| {
+                (live < POOL_SIZE).then_some(live + 1)
+            })
+            .map_err(|_| io::Error::new(io::ErrorKind::WouldBlock, "PowerShell host busy"))?;
+        match self.start(executable) {
+            Ok(session) => Ok(Lease {
+                host: self,
+                session: Some(session),
+            }),
+            Err(e) => {
+                self.live.fetch_sub(1, Ordering::SeqCst);
+                Err(e)
+            }
+        }
+    }
+
+    /// A host whose sessions answer every script with `respond(script)`, for testing code
+    /// that runs scripts without PowerShell
+    #[cfg(test)]
+    pub fn scripted(
+        respond: impl Fn(&str) -> ScriptOutput + Clone + Send + Sync + 'static,
+    ) -> Self {
+        Self::with_spawner(move |executable| {
+            let respond = respond.clone();
+            let (client, server) = tokio::io::duplex(64 * 1024);
+            let (server_read, mut server_write) = tokio::io::split(server);
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(server_read).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    let Ok(request) = serde_json::from_str::<serde_json::Value>(&line) else {
+                        return;
+                    };
+                    let output = respond(request["script"].as_str().unwrap_or_default());
+                    let response = serde_json::json!({
+                        "stdout": output.stdout,
+                        "stderr": output.stderr,
+                        "exit_code": output.exit_code.unwrap_or(1),
+                    });
+                    let reply = format!("{}\n", response);
+                    if server_write.write_all(reply.as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            });
+            let (client_read, client_write) = tokio::io::split(client);
+            Ok(HostSession::from_streams(
+                executable,
+                BufReader::new(client_read),
+                client_write,
+            ))
+        })
+    }
+
+    /// A scripted host answering each script with the first response whose key the
+    /// script contains: `Ok(stdout)` succeeds, `Err(stderr)` fails, and a script matching
+    /// no key fails too
+    #[cfg(test)]
+    pub fn canned(responses: Vec<(&'static str, Result<String, String>)>) -> Self {
+        Self::scripted(move |script| canned_output(&responses, script))
+    }
+
+    fn start(&self, executable: &str) -> io::Result<HostSession> {
+        info!("Starting PowerShell host ({})", executable);
+        (self.spawn)(executable)
+    }
+}
+
+/// The output `PowerShellHost::canned` gives `script`, for scripted hosts that also
+/// need to observe the scripts they answer
+#[cfg(test)]
+pub fn canned_output(
+    responses: &[(&'static str, Result<String, String>)],
+    script: &str,
+) -> ScriptOutput {
+    match responses.iter().find(|(key, _)| script.contains(key)) {
+        Some((_, Ok(stdout))) => ScriptOutput {
+            exit_code: Some(0),
+            stdout: stdout.clone(),
+            stderr: String::new(),
+        },
+        Some((_, Err(stderr))) => ScriptOutput {
+            exit_code: Some(1),
+            stdout: String::new(),
+            stderr: stderr.clone(),
+        },
+        None => ScriptOutput {
+            exit_code: Some(1),
+            stdout: String::new(),
+            stderr: "no canned response".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{duplex, split};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// A host whose sessions read requests but never answer
+    fn silent_host(spawned: Arc<AtomicUsize>) -> PowerShellHost {
+        PowerShellHost::with_spawner(move |executable| {
+            spawned.fetch_add(1, Ordering::SeqCst);
+            let (client, server) = duplex(4096);
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(server).lines();
+                while let Ok(Some(_)) = lines.next_line().await {}
+            });
+            let (client_read, client_write) = split(client);
+            Ok(HostSession::from_streams(
+                executable,
+                BufReader::new(client_read),
+                client_write,
+            ))
+        })
+    }
+
+    /// A host whose sessions answer each request with its sequence number in that session,
+    /// and exit after answering `exit-host`
+    fn fake_host(spawned: Arc<AtomicUsize>) -> PowerShellHost {
+        PowerShellHost::with_spawner(move |executable| {
+            spawned.fetch_add(1, Ordering::SeqCst);
+            let (client, server) = duplex(4096);
+            let (server_read, mut server_write) = split(server);
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(server_read).lines();
+                let mut handled = 0;
+                while let Ok(Some(line)) = lines.next_line().await {
+                    handled += 1;
+                    let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                    let response = serde_json::json!({
+                        "stdout": handled.to_string(),
+                        "stderr": null,
+                        "exit_code": 0,
+                    });
+                    let reply = format!("{}\n", response);
+                    server_write.write_all(reply.as_bytes()).await.unwrap();
+                    if request["script"] == "exit-host" {
+                        return;
+                    }
+                }
+            });
+            let (client_read, client_write) = split(client);
+            Ok(HostSession::from_streams(
+                executable,
+                BufReader::new(client_read),
+                client_write,
+            ))
+        })
+    }
+
+    #[tokio::test]
+    async fn session_is_reused_across_scripts() {
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let host = fake_host(spawned.clone());
+
+        for expected in ["1", "2", "3"] {
+            let output = host
+                .run("powershell", "Get-AudioDevice -List", TIMEOUT)
+                .await
+                .unwrap();
+            assert_eq!(output.stdout, expected);
+            assert!(output.success());
+        }
+        assert_eq!(spawned.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn crashed_session_is_restarted() {
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let host = fake_host(spawned.clone());
+
+        host.run("powershell", "exit-host", TIMEOUT).await.unwrap();
+        let output = host
+            .run("powershell", "Get-AudioDevice -List", TIMEOUT)
+            .await
+            .unwrap();
+
+        // The script ran as the first request of a second session
+        assert_eq!(output.stdout, "1");
+        assert_eq!(output.stderr, "");
+        assert_eq!(spawned.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn unparseable_response_restarts_the_session() {
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let counter = spawned.clone();
+        // The first session answers with something that isn't a response line
+        let host = PowerShellHost::with_spawner(move |executable| {
+            let session = counter.fetch_add(1, Ordering::SeqCst);
+            let (client, server) = duplex(4096);
+            let (server_read, mut server_write) = split(server);
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(server_read).lines();
+                while let Ok(Some(_)) = lines.next_line().await {
+                    let reply = if session == 0 {
+                        "WARNING: profile output\n".to_string()
+                    } else {
+                        "{\"stdout\":\"ok\",\"stderr\":null,\"exit_code\":0}\n".to_string()
+                    };
+                    server_write.write_all(reply.as_bytes()).await.unwrap();
+                }
+            });
+            let (client_read, client_write) = split(client);
+            Ok(HostSession::from_streams(
+                executable,
+                BufReader::new(client_read),
+                client_write,
+            ))
+        });
+
+        let output = host
+            .run("powershell", "Get-AudioDevice -List", TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(output.stdout, "ok");
+        assert_eq!(spawned.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn changing_executable_starts_a_new_session() {
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let host = fake_host(spawned.clone());
+
+        host.run("powershell", "1", TIMEOUT).await.unwrap();
+        host.run("pwsh", "2", TIMEOUT).await.unwrap();
+        assert_eq!(spawned.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn progress_is_handed_over_before_the_reply() {
+        let host = PowerShellHost::with_spawner(|executable| {
+            let (client, server) = duplex(4096);
+            let (server_read, mut server_write) = split(server);
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(server_read).lines();
+                while let Ok(Some(_)) = lines.next_line().await {
+                    let reply = concat!(
+                        "{\"progress\":\"first\"}\n",
+                        "{\"progress\":\"second\"}\n",
+                        "{\"stdout\":\"done\",\"stderr\":null,\"exit_code\":0}\n",
+                    );
+                    server_write.write_all(reply.as_bytes()).await.unwrap();
+                }
+            });
+            let (client_read, client_write) = split(client);
+            Ok(HostSession::from_streams(
+                executable,
+                BufReader::new(client_read),
+                client_write,
+            ))
+        });
+
+        let mut progress = Vec::new();
+        let output = host
+            .run_with_progress(
+                "powershell",
+                "Get-AudioDevice -List",
+                TIMEOUT,
+                &mut |step| progress.push(step.to_string()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(progress, ["first", "second"]);
+        assert_eq!(output.stdout, "done");
+    }
+
+    #[tokio::test]
+    async fn timed_out_session_is_replaced() {
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let host = silent_host(spawned.clone());
+
+        for _ in 0..2 {
+            let error = host
+                .run("powershell", "1", Duration::from_millis(20))
+                .await
+                .unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        }
+        // Each timeout killed its session, so the pool never filled up
+        assert_eq!(spawned.load(Ordering::SeqCst), 2);
+        assert_eq!(host.live.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn busy_pool_refuses_instead_of_waiting() {
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let host = Arc::new(silent_host(spawned.clone()));
+
+        let running: Vec<_> = (0..POOL_SIZE)
+            .map(|_| {
+                let host = host.clone();
+                tokio::spawn(async move { host.run("powershell", "1", TIMEOUT).await })
+            })
+            .collect();
+        while host.live.load(Ordering::SeqCst) < POOL_SIZE {
+            tokio::task::yield_now().await;
+        }
+
+        let error = host.run("powershell", "2", TIMEOUT).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(spawned.load(Ordering::SeqCst), POOL_SIZE);
+        for task in running {
+            task.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn spawn_failure_is_reported() {
+        let host = PowerShellHost::with_spawner(|_| Err(io::ErrorKind::NotFound.into()));
+        let error = host.run("powershell", "1", TIMEOUT).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
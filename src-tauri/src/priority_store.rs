@@ -0,0 +1,524 @@
+use crate::audio_manager::{AudioDevice, DeviceState, DeviceType};
+use crate::config;
+use crate::error::{AudioError, AudioResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Number of fixed priority slots per chain (matches the UI)
+pub const PRIORITY_SLOTS: usize = 5;
+
+/// A device assigned to a priority slot (0 = highest priority). Unknown fields are
+/// rejected so a mangled localStorage import fails instead of half-loading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DevicePriority {
+    pub device_id: String,
+    pub device_name: String,
+    pub device_type: DeviceType,
+    pub priority: usize,
+}
+
+/// Whether the device in one priority slot is currently present
+#[derive(Debug, Clone, Serialize)]
+pub struct PriorityValidation {
+    pub slot: usize,
+    pub device_name: String,
+    pub present: bool,
+    /// State of the device when present
+    pub state: Option<DeviceState>,
+}
+
+/// Check every slot of a chain against the currently enumerated devices
+pub fn check_presence(
+    chain: &[DevicePriority],
+    devices: &[AudioDevice],
+) -> Vec<PriorityValidation> {
+    chain
+        .iter()
+        .map(|entry| {
+            let state = devices
+                .iter()
+                .find(|d| d.id == entry.device_id)
+                .map(|d| d.state.clone());
+            PriorityValidation {
+                slot: entry.priority,
+                device_name: entry.device_name.clone(),
+                present: state.is_some(),
+                state,
+            }
+        })
+        .collect()
+}
+
+/// One row of the priority-ordered device view
+#[derive(Debug, Clone, Serialize)]
+pub struct PriorityOrderedDevice {
+    pub device_id: String,
+    pub device_name: String,
+    /// Priority slot, `None` for devices not in the chain
+    pub priority: Option<usize>,
+    /// Whether the device is currently Active
+    pub present: bool,
+    pub is_default: bool,
+}
+
+/// Active devices of `device_type` in chain order, then the unchained ones by name, then
+/// chained devices that aren't Active, flagged as not present
+pub fn order_by_priority(
+    chain: &[DevicePriority],
+    devices: &[AudioDevice],
+    device_type: &DeviceType,
+) -> Vec<PriorityOrderedDevice> {
+    let mut chain: Vec<&DevicePriority> = chain.iter().collect();
+    chain.sort_by_key(|entry| entry.priority);
+    let active: Vec<&AudioDevice> = devices
+        .iter()
+        .filter(|d| d.device_type == *device_type && d.state == DeviceState::Active)
+        .collect();
+    let row = |device: &AudioDevice, priority| PriorityOrderedDevice {
+        device_id: device.id.clone(),
+        device_name: device.name.clone(),
+        priority,
+        present: true,
+        is_default: device.is_default,
+    };
+
+    let mut ordered: Vec<PriorityOrderedDevice> = chain
+        .iter()
+        .filter_map(|entry| {
+            let device = active.iter().find(|d| d.id == entry.device_id)?;
+            Some(row(device, Some(entry.priority)))
+        })
+        .collect();
+
+    let mut unchained: Vec<&AudioDevice> = active
+        .iter()
+        .copied()
+        .filter(|d| !chain.iter().any(|entry| entry.device_id == d.id))
+        .collect();
+    unchained.sort_by_key(|d| d.name.to_lowercase());
+    ordered.extend(unchained.into_iter().map(|d| row(d, None)));
+
+    ordered.extend(
+        chain
+            .iter()
+            .filter(|entry| !active.iter().any(|d| d.id == entry.device_id))
+            .map(|entry| PriorityOrderedDevice {
+                device_id: entry.device_id.clone(),
+                device_name: entry.device_name.clone(),
+                priority: Some(entry.priority),
+                present: false,
+                is_default: false,
+            }),
+    );
+    ordered
+}
+
+/// On-disk representation of both priority chains
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriorityConfig {
+    pub playback: Vec<DevicePriority>,
+    pub recording: Vec<DevicePriority>,
+    /// Set once the frontend's localStorage priorities have been imported
+    pub migrated_from_frontend: bool,
+}
+
+/// Both chains as returned to the frontend, each highest priority first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PriorityChains {
+    pub playback: Vec<DevicePriority>,
+    pub recording: Vec<DevicePriority>,
+}
+
+/// Backend-owned priority chains persisted to `priorities.json`
+pub struct PriorityStore {
+    path: Option<PathBuf>,
+    priorities: RwLock<PriorityConfig>,
+}
+
+impl PriorityStore {
+    /// Load the store from disk, starting empty if the file is missing or unreadable
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let priorities = match &path {
+            Some(p) => config::load_json(p).unwrap_or_else(|e| {
+                warn!("Failed to load priorities, starting empty: {}", e);
+                PriorityConfig::default()
+            }),
+            None => PriorityConfig::default(),
+        };
+
+        Self {
+            path,
+            priorities: RwLock::new(priorities),
+        }
+    }
+
+    /// The chain for one device type, highest priority first
+    pub async fn chain(&self, device_type: &DeviceType) -> Vec<DevicePriority> {
+        let priorities = self.priorities.read().await;
+        let mut chain = match device_type {
+            DeviceType::Playback => priorities.playback.clone(),
+            DeviceType::Recording => priorities.recording.clone(),
+        };
+        chain.sort_by_key(|entry| entry.priority);
+        chain
+    }
+
+    /// Both chains, highest priority first
+    pub async fn chains(&self) -> PriorityChains {
+        PriorityChains {
+            playback: self.chain(&DeviceType::Playback).await,
+            recording: self.chain(&DeviceType::Recording).await,
+        }
+    }
+
+    /// Replace both chains. Writes hold the lock until the file is saved, so rapid
+    /// successive saves are applied in order.
+    pub async fn save(
+        &self,
+        playback: Vec<DevicePriority>,
+        recording: Vec<DevicePriority>,
+    ) -> AudioResult<()> {
+        Self::validate_chain(&playback, &DeviceType::Playback)?;
+        Self::validate_chain(&recording, &DeviceType::Recording)?;
+
+        let mut priorities = self.priorities.write().await;
+        let updated = PriorityConfig {
+            playback,
+            recording,
+            migrated_from_frontend: true,
+        };
+        self.persist(&updated)?;
+        *priorities = updated;
+        info!(
+            "Saved {} playback and {} recording priorities",
+            priorities.playback.len(),
+            priorities.recording.len()
+        );
+        Ok(())
+    }
+
+    /// Put a device into a slot of its type's chain, replacing whatever held the slot and
+    /// moving the device out of any other slot
+    pub async fn assign_slot(&self, entry: DevicePriority) -> AudioResult<()> {
+        let mut priorities = self.priorities.write().await;
+        let mut updated = priorities.clone();
+        let chain = match entry.device_type {
+            DeviceType::Playback => &mut updated.playback,
+            DeviceType::Recording => &mut updated.recording,
+        };
+        chain.retain(|e| e.device_id != entry.device_id && e.priority != entry.priority);
+        chain.push(entry.clone());
+        Self::validate_chain(chain, &entry.device_type)?;
+
+        self.persist(&updated)?;
+        *priorities = updated;
+        info!(
+            "Assigned {} to {:?} priority slot {}",
+            entry.device_name, entry.device_type, entry.priority
+        );
+        Ok(())
+    }
+
+    /// Empty one slot of a chain, returning whether it held a device
+    pub async fn clear_slot(&self, device_type: &DeviceType, slot: usize) -> AudioResult<bool> {
+        let mut priorities = self.priorities.write().await;
+        let mut updated = priorities.clone();
+        let chain = match device_type {
+            DeviceType::Playback => &mut updated.playback,
+            DeviceType::Recording => &mut updated.recording,
+        };
+        let before = chain.len();
+        chain.retain(|e| e.priority != slot);
+        if chain.len() == before {
+            return Ok(false);
+        }
+
+        self.persist(&updated)?;
+        *priorities = updated;
+        info!("Cleared {:?} priority slot {}", device_type, slot);
+        Ok(true)
+    }
+
+    /// One-time import of the chains the frontend kept in localStorage.
+    /// Returns `false` without touching the store if the migration already ran.
+    pub async fn import_from_frontend(
+        &self,
+        playback: Vec<DevicePriority>,
+        recording: Vec<DevicePriority>,
+    ) -> AudioResult<bool> {
+        let mut priorities = self.priorities.write().await;
+        if priorities.migrated_from_frontend {
+            info!("Frontend priorities already imported, skipping");
+            return Ok(false);
+        }
+
+        Self::validate_chain(&playback, &DeviceType::Playback)?;
+        Self::validate_chain(&recording, &DeviceType::Recording)?;
+
+        let mut updated = priorities.clone();
+        updated.playback = playback;
+        updated.recording = recording;
+        updated.migrated_from_frontend = true;
+
+        self.persist(&updated)?;
+        *priorities = updated;
+
+        info!(
+            "Imported {} playback and {} recording priorities from frontend",
+            priorities.playback.len(),
+            priorities.recording.len()
+        );
+        Ok(true)
+    }
+
+    /// Empty both chains. The frontend import stays marked as done so the old chains
+    /// aren't imported again from localStorage.
+    pub async fn clear(&self) -> AudioResult<()> {
+        let mut priorities = self.priorities.write().await;
+        let cleared = PriorityConfig {
+            migrated_from_frontend: true,
+            ..PriorityConfig::default()
+        };
+        self.persist(&cleared)?;
+        *priorities = cleared;
+        info!("Cleared playback and recording priorities");
+        Ok(())
+    }
+
+    /// Validate slot bounds, slot uniqueness and device uniqueness for one chain
+    fn validate_chain(chain: &[DevicePriority], device_type: &DeviceType) -> AudioResult<()> {
+        let mut slots = HashSet::new();
+        let mut device_ids = HashSet::new();
+
+        for entry in chain {
+            if entry.priority >= PRIORITY_SLOTS {
+                return Err(AudioError::InvalidConfig(format!(
+                    "Priority slot {} for '{}' is out of range (0-{})",
+                    entry.priority,
+                    entry.device_name,
+                    PRIORITY_SLOTS - 1
+                )));
+            }
+            if entry.device_type != *device_type {
+                return Err(AudioError::InvalidConfig(format!(
+                    "Device '{}' is not a {:?} device",
+                    entry.device_name, device_type
+                )));
+            }
+            if !slots.insert(entry.priority) {
+                return Err(AudioError::InvalidConfig(format!(
+                    "Priority slot {} is assigned more than once",
+                    entry.priority
+                )));
+            }
+            if !device_ids.insert(entry.device_id.as_str()) {
+                return Err(AudioError::InvalidConfig(format!(
+                    "Device '{}' appears more than once in the chain",
+                    entry.device_name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the given chains to disk (no-op when no path is configured)
+    fn persist(&self, priorities: &PriorityConfig) -> AudioResult<()> {
+        match &self.path {
+            Some(path) => config::save_json_atomic(path, priorities),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(device_id: &str, priority: usize) -> DevicePriority {
+        DevicePriority {
+            device_id: device_id.to_string(),
+            device_name: format!("Device {}", device_id),
+            device_type: DeviceType::Playback,
+            priority,
+        }
+    }
+
+    fn device(id: &str, state: DeviceState) -> AudioDevice {
+        AudioDevice {
+            id: id.to_string(),
+            name: format!("Device {}", id),
+            device_type: DeviceType::Playback,
+            state,
+            is_default: false,
+            is_communication_default: false,
+            last_seen: None,
+            index: None,
+            icon_path: None,
+            display_name: None,
+        }
+    }
+
+    #[test]
+    fn priority_order_puts_chained_devices_first_and_absent_ones_last() {
+        let chain = vec![entry("dock", 2), entry("headset", 0), entry("speakers", 1)];
+        let mut microphone = device("mic", DeviceState::Active);
+        microphone.device_type = DeviceType::Recording;
+        let devices = vec![
+            device("speakers", DeviceState::Active),
+            device("monitor", DeviceState::Active),
+            device("hdmi", DeviceState::Active),
+            device("headset", DeviceState::Unplugged),
+            device("dock", DeviceState::Active),
+            microphone,
+        ];
+
+        let ordered = order_by_priority(&chain, &devices, &DeviceType::Playback);
+        let rows: Vec<(&str, Option<usize>, bool)> = ordered
+            .iter()
+            .map(|d| (d.device_id.as_str(), d.priority, d.present))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("speakers", Some(1), true),
+                ("dock", Some(2), true),
+                ("hdmi", None, true),
+                ("monitor", None, true),
+                ("headset", Some(0), false),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn slot_changes_are_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("priorities.json");
+        let store = PriorityStore::load(Some(path.clone()));
+
+        store
+            .save(vec![entry("headset", 0), entry("speakers", 1)], Vec::new())
+            .await
+            .unwrap();
+        // Moving speakers to the top slot evicts the headset from it
+        store.assign_slot(entry("speakers", 0)).await.unwrap();
+        store.assign_slot(entry("dock", 3)).await.unwrap();
+        assert!(store.clear_slot(&DeviceType::Playback, 3).await.unwrap());
+        assert!(!store.clear_slot(&DeviceType::Playback, 4).await.unwrap());
+
+        let reloaded = PriorityStore::load(Some(path)).chains().await;
+        let slots: Vec<(&str, usize)> = reloaded
+            .playback
+            .iter()
+            .map(|e| (e.device_id.as_str(), e.priority))
+            .collect();
+        assert_eq!(slots, [("speakers", 0)]);
+        assert!(reloaded.recording.is_empty());
+
+        // Invalid chains are rejected without touching the stored ones
+        let error = store
+            .save(vec![entry("headset", 0), entry("dock", 0)], Vec::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AudioError::InvalidConfig(_)));
+        assert_eq!(store.chains().await.playback.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn invalid_frontend_import_leaves_the_store_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("priorities.json");
+        let store = PriorityStore::load(Some(path.clone()));
+        store.assign_slot(entry("headset", 0)).await.unwrap();
+
+        // Out-of-range slot, duplicate slot, and a recording device in the playback chain
+        let mut microphone = entry("mic", 1);
+        microphone.device_type = DeviceType::Recording;
+        for playback in [
+            vec![entry("dock", PRIORITY_SLOTS)],
+            vec![entry("dock", 0), entry("speakers", 0)],
+            vec![microphone],
+        ] {
+            let error = store
+                .import_from_frontend(playback, Vec::new())
+                .await
+                .unwrap_err();
+            assert!(matches!(error, AudioError::InvalidConfig(_)));
+        }
+
+        // Malformed values and unknown fields don't deserialize at all
+        let parse = |json: &str| serde_json::from_str::<DevicePriority>(json);
+        assert!(parse(
+            r#"{"device_id":"dock","device_name":"Dock","device_type":"Playback","priority":"first"}"#
+        )
+        .is_err());
+        assert!(parse(
+            r#"{"device_id":"dock","device_name":"Dock","device_type":"Playback","priority":0,"volume":50}"#
+        )
+        .is_err());
+
+        let reloaded = PriorityStore::load(Some(path)).chains().await;
+        let slots: Vec<(&str, usize)> = reloaded
+            .playback
+            .iter()
+            .map(|e| (e.device_id.as_str(), e.priority))
+            .collect();
+        assert_eq!(slots, [("headset", 0)]);
+
+        // The rejected imports didn't mark the migration done, so a valid one still runs
+        assert!(store
+            .import_from_frontend(vec![entry("dock", 0)], Vec::new())
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn removing_a_middle_slot_keeps_the_others_in_place() {
+        let store = PriorityStore::load(None);
+        for (id, slot) in [("headset", 0), ("dock", 1), ("speakers", 2)] {
+            store.assign_slot(entry(id, slot)).await.unwrap();
+        }
+
+        assert!(store.clear_slot(&DeviceType::Playback, 1).await.unwrap());
+
+        let playback = store.chains().await.playback;
+        let at = |slot: usize| {
+            playback
+                .iter()
+                .find(|p| p.priority == slot)
+                .map(|p| p.device_id.as_str())
+        };
+        assert_eq!(at(0), Some("headset"));
+        assert_eq!(at(1), None);
+        assert_eq!(at(2), Some("speakers"));
+    }
+
+    #[test]
+    fn check_presence_flags_absent_devices() {
+        let chain = vec![entry("headset", 0), entry("dock", 1), entry("speakers", 2)];
+        let devices = vec![
+            device("speakers", DeviceState::Active),
+            device("headset", DeviceState::Unplugged),
+        ];
+
+        let validation = check_presence(&chain, &devices);
+        assert_eq!(validation.len(), 3);
+
+        assert_eq!(validation[0].slot, 0);
+        assert!(validation[0].present);
+        assert_eq!(validation[0].state, Some(DeviceState::Unplugged));
+
+        assert_eq!(validation[1].slot, 1);
+        assert_eq!(validation[1].device_name, "Device dock");
+        assert!(!validation[1].present);
+        assert_eq!(validation[1].state, None);
+
+        assert!(validation[2].present);
+        assert_eq!(validation[2].state, Some(DeviceState::Active));
+    }
+}
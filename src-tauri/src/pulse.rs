@@ -0,0 +1,242 @@
+use crate::audio_manager::{AudioDevice, DefaultRole, DeviceListResult, DeviceState, DeviceType};
+use crate::backend::AudioBackend;
+use crate::error::{AudioError, AudioResult};
+use std::process::Command;
+
+/// PulseAudio, or PipeWire's Pulse server, driven through `pactl`. Devices are
+/// identified by sink/source name. Pulse keeps a single default per direction, so every
+/// role switches it and it counts as both the multimedia and communications default.
+#[derive(Debug, Default)]
+pub struct PulseBackend;
+
+impl AudioBackend for PulseBackend {
+    fn list_devices(&self) -> AudioResult<DeviceListResult> {
+        let info = pactl(&["info"])?;
+        let mut devices = parse_devices(
+            &pactl(&["list", "sinks"])?,
+            DeviceType::Playback,
+            info_field(&info, "Default Sink"),
+        );
+        devices.extend(parse_devices(
+            &pactl(&["list", "sources"])?,
+            DeviceType::Recording,
+            info_field(&info, "Default Source"),
+        ));
+        Ok(DeviceListResult {
+            devices,
+            skipped: Vec::new(),
+        })
+    }
+
+    fn set_default(&self, device_id: &str, _role: DefaultRole) -> AudioResult<()> {
+        let command = match kind_of(device_id)? {
+            DeviceType::Playback => "set-default-sink",
+            DeviceType::Recording => "set-default-source",
+        };
+        pactl(&[command, device_id]).map(|_| ())
+    }
+
+    fn set_volume(&self, device_id: &str, volume: u8) -> AudioResult<()> {
+        let command = match kind_of(device_id)? {
+            DeviceType::Playback => "set-sink-volume",
+            DeviceType::Recording => "set-source-volume",
+        };
+        pactl(&[command, device_id, &format!("{}%", volume)]).map(|_| ())
+    }
+
+    fn set_mute(&self, device_id: &str, muted: Option<bool>) -> AudioResult<bool> {
+        let (set, get) = match kind_of(device_id)? {
+            DeviceType::Playback => ("set-sink-mute", "get-sink-mute"),
+            DeviceType::Recording => ("set-source-mute", "get-source-mute"),
+        };
+        let value = match muted {
+            Some(true) => "1",
+            Some(false) => "0",
+            None => "toggle",
+        };
+        pactl(&[set, device_id, value])?;
+        // `get-*-mute` answers `Mute: yes` or `Mute: no`
+        Ok(pactl(&[get, device_id])?.trim().ends_with("yes"))
+    }
+}
+
+/// Run `pactl` with `args`, returning its stdout
+fn pactl(args: &[&str]) -> AudioResult<String> {
+    let output = Command::new("pactl")
+        .env("LC_ALL", "C")
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        return Err(AudioError::CommandFailed(format!(
+            "pactl {}: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Whether `device_id` names a sink or a source
+fn kind_of(device_id: &str) -> AudioResult<DeviceType> {
+    let is_listed = |output: &str| {
+        output
+            .lines()
+            .any(|line| line.split('\t').nth(1) == Some(device_id))
+    };
+    if is_listed(&pactl(&["list", "short", "sinks"])?) {
+        Ok(DeviceType::Playback)
+    } else if is_listed(&pactl(&["list", "short", "sources"])?) {
+        Ok(DeviceType::Recording)
+    } else {
+        Err(AudioError::DeviceNotFound(device_id.to_string()))
+    }
+}
+
+/// A `Key: value` line of `pactl info`
+fn info_field<'a>(info: &'a str, key: &str) -> Option<&'a str> {
+    info.lines()
+        .filter_map(|line| line.split_once(": "))
+        .find(|(k, _)| *k == key)
+        .map(|(_, value)| value.trim())
+}
+
+/// Turn `pactl list sinks` or `pactl list sources` output into devices. Monitor
+/// sources, which just tap a sink, are left out.
+fn parse_devices(output: &str, device_type: DeviceType, default: Option<&str>) -> Vec<AudioDevice> {
+    let mut blocks: Vec<Vec<&str>> = Vec::new();
+    for line in output.lines() {
+        if line.starts_with("Sink #") || line.starts_with("Source #") {
+            blocks.push(Vec::new());
+        } else if let Some(block) = blocks.last_mut() {
+            block.push(line);
+        }
+    }
+
+    blocks
+        .iter()
+        .filter_map(|block| {
+            // Top-level properties are indented once, their details (ports, ...) twice
+            let field = |key: &str| {
+                block
+                    .iter()
+                    .filter_map(|line| line.strip_prefix('\t'))
+                    .filter(|line| !line.starts_with('\t'))
+                    .filter_map(|line| line.split_once(": "))
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, value)| value.trim())
+            };
+            if field("Monitor of Sink").is_some_and(|sink| sink != "n/a") {
+                return None;
+            }
+            let id = field("Name")?.to_string();
+
+            let port_unavailable = field("Active Port").is_some_and(|port| {
+                block.iter().any(|line| {
+                    line.trim_start()
+                        .strip_prefix(port)
+                        .is_some_and(|rest| rest.starts_with(':') && rest.contains("not available"))
+                })
+            });
+            let state = match field("State") {
+                Some(_) if port_unavailable => DeviceState::Unplugged,
+                Some("RUNNING" | "IDLE" | "SUSPENDED") => DeviceState::Active,
+                _ => DeviceState::Unknown,
+            };
+            let is_default = default == Some(id.as_str());
+
+            Some(AudioDevice {
+                name: field("Description").unwrap_or(&id).to_string(),
+                is_default,
+                is_communication_default: is_default,
+                id,
+                device_type: device_type.clone(),
+                state,
+                last_seen: None,
+                index: None,
+                icon_path: None,
+                display_name: None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCES: &str = "Source #54
+\tState: SUSPENDED
+\tName: alsa_output.pci-0000_00_1f.3.analog-stereo.monitor
+\tDescription: Monitor of Built-in Audio Analog Stereo
+\tMonitor of Sink: alsa_output.pci-0000_00_1f.3.analog-stereo
+\tMute: no
+
+Source #55
+\tState: RUNNING
+\tName: alsa_input.pci-0000_00_1f.3.analog-stereo
+\tDescription: Built-in Audio Analog Stereo
+\tMonitor of Sink: n/a
+\tProperties:
+\t\tdevice.description = \"Built-in Audio\"
+\tPorts:
+\t\tanalog-input-internal-mic: Internal Microphone (type: Mic, priority: 8900, available)
+\t\tanalog-input-mic: Microphone (type: Mic, priority: 8700, not available)
+\tActive Port: analog-input-internal-mic
+
+Source #61
+\tState: SUSPENDED
+\tName: alsa_input.usb-Logitech_USB_Headset-00.mono-fallback
+\tDescription: Logitech USB Headset Mono
+\tMonitor of Sink: n/a
+\tPorts:
+\t\tanalog-input-mic: Microphone (type: Mic, priority: 8700, not available)
+\tActive Port: analog-input-mic
+";
+
+    #[test]
+    fn sources_skip_monitors_and_map_port_availability() {
+        let devices = parse_devices(
+            SOURCES,
+            DeviceType::Recording,
+            Some("alsa_input.pci-0000_00_1f.3.analog-stereo"),
+        );
+
+        let summary: Vec<(&str, &str, &DeviceState, bool)> = devices
+            .iter()
+            .map(|d| (d.id.as_str(), d.name.as_str(), &d.state, d.is_default))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "alsa_input.pci-0000_00_1f.3.analog-stereo",
+                    "Built-in Audio Analog Stereo",
+                    &DeviceState::Active,
+                    true
+                ),
+                (
+                    "alsa_input.usb-Logitech_USB_Headset-00.mono-fallback",
+                    "Logitech USB Headset Mono",
+                    &DeviceState::Unplugged,
+                    false
+                ),
+            ]
+        );
+        assert!(devices[0].is_communication_default);
+        assert!(devices
+            .iter()
+            .all(|d| d.device_type == DeviceType::Recording));
+    }
+
+    #[test]
+    fn default_devices_come_from_pactl_info() {
+        let info = "Server Name: PulseAudio (on PipeWire 1.0.5)\n\
+                    Default Sink: alsa_output.pci-0000_00_1f.3.analog-stereo\n\
+                    Default Source: alsa_input.pci-0000_00_1f.3.analog-stereo\n";
+        assert_eq!(
+            info_field(info, "Default Sink"),
+            Some("alsa_output.pci-0000_00_1f.3.analog-stereo")
+        );
+        assert_eq!(info_field(info, "Default Card"), None);
+    }
+}
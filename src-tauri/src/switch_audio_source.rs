@@ -0,0 +1,206 @@
+use crate::audio_manager::{AudioDevice, DefaultRole, DeviceListResult, DeviceState, DeviceType};
+use crate::backend::AudioBackend;
+use crate::error::{AudioError, AudioResult};
+use serde::Deserialize;
+use std::io::ErrorKind;
+use std::process::Command;
+use tracing::warn;
+
+const SWITCH_AUDIO_SOURCE: &str = "SwitchAudioSource";
+
+/// macOS Core Audio driven through the `SwitchAudioSource` CLI (switchaudio-osx).
+/// Devices are identified by their Core Audio UID. The tool only lists connected
+/// devices and can't change volume or mute. macOS has one default per direction, so it
+/// counts as both the multimedia and communications default.
+#[derive(Debug, Default)]
+pub struct MacBackend;
+
+/// One line of `SwitchAudioSource -f json` output
+#[derive(Debug, Deserialize)]
+struct ListedDevice {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    id: String,
+    /// Missing from versions before UIDs were printed
+    #[serde(default)]
+    uid: String,
+}
+
+impl ListedDevice {
+    /// The UID, which survives reboots, or the numeric ID without one
+    fn device_id(&self) -> &str {
+        if self.uid.is_empty() {
+            &self.id
+        } else {
+            &self.uid
+        }
+    }
+}
+
+impl AudioBackend for MacBackend {
+    fn check_available(&self) -> AudioResult<()> {
+        switch_audio_source(&["-c"]).map(|_| ())
+    }
+
+    fn list_devices(&self) -> AudioResult<DeviceListResult> {
+        parse_devices(
+            &switch_audio_source(&["-a", "-f", "json"])?,
+            &switch_audio_source(&["-c", "-t", "output", "-f", "json"])?,
+            &switch_audio_source(&["-c", "-t", "input", "-f", "json"])?,
+        )
+    }
+
+    fn set_default(&self, device_id: &str, _role: DefaultRole) -> AudioResult<()> {
+        // A headset lists an input and an output under the same name, so `-s` needs the type
+        let listed = parse_lines(&switch_audio_source(&["-a", "-f", "json"])?)?;
+        let device = listed
+            .iter()
+            .find(|d| d.device_id() == device_id)
+            .ok_or_else(|| AudioError::DeviceNotFound(device_id.to_string()))?;
+        switch_audio_source(&["-t", &device.kind, "-s", &device.name]).map(|_| ())
+    }
+
+    fn set_volume(&self, _device_id: &str, _volume: u8) -> AudioResult<()> {
+        Err(AudioError::Unsupported(
+            "SwitchAudioSource can't change volume".to_string(),
+        ))
+    }
+
+    fn set_mute(&self, _device_id: &str, _muted: Option<bool>) -> AudioResult<bool> {
+        Err(AudioError::Unsupported(
+            "SwitchAudioSource can't change mute".to_string(),
+        ))
+    }
+}
+
+/// Run `SwitchAudioSource` with `args`, returning its stdout
+fn switch_audio_source(args: &[&str]) -> AudioResult<String> {
+    let output = match Command::new(SWITCH_AUDIO_SOURCE).args(args).output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Err(AudioError::ModuleNotInstalled(format!(
+                "{} (install it with `brew install switchaudio-osx`)",
+                SWITCH_AUDIO_SOURCE
+            )))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if !output.status.success() {
+        return Err(AudioError::CommandFailed(format!(
+            "{} {}: {}",
+            SWITCH_AUDIO_SOURCE,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `-f json` prints one object per line rather than an array
+fn parse_lines(output: &str) -> AudioResult<Vec<ListedDevice>> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(AudioError::from))
+        .collect()
+}
+
+/// Turn the `-a` listing and the current output and input (`-c`) into devices. An
+/// entry of a type other than input or output is skipped rather than failing the list.
+fn parse_devices(all: &str, output: &str, input: &str) -> AudioResult<DeviceListResult> {
+    let current: Vec<String> = parse_lines(output)?
+        .into_iter()
+        .chain(parse_lines(input)?)
+        .map(|d| d.device_id().to_string())
+        .collect();
+
+    let mut list = DeviceListResult::default();
+    for listed in parse_lines(all)? {
+        let device_type = match listed.kind.as_str() {
+            "output" => DeviceType::Playback,
+            "input" => DeviceType::Recording,
+            other => {
+                let reason = format!(
+                    "type of {}: unexpected value {:?}",
+                    listed.device_id(),
+                    other
+                );
+                warn!("Skipping device {}: {}", listed.name, reason);
+                list.skipped.push((listed.name, reason));
+                continue;
+            }
+        };
+        let id = listed.device_id().to_string();
+        let is_default = current.contains(&id);
+        list.devices.push(AudioDevice {
+            id,
+            name: listed.name,
+            device_type,
+            state: DeviceState::Active,
+            is_default,
+            is_communication_default: is_default,
+            last_seen: None,
+            index: None,
+            icon_path: None,
+            display_name: None,
+        });
+    }
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: &str = r#"{"name": "MacBook Pro Microphone", "type": "input", "id": "91", "uid": "BuiltInMicrophoneDevice"}
+{"name": "MacBook Pro Speakers", "type": "output", "id": "84", "uid": "BuiltInSpeakerDevice"}
+{"name": "AirPods Pro", "type": "input", "id": "103", "uid": "5C-52-30-A1-B2-C3:input"}
+{"name": "AirPods Pro", "type": "output", "id": "102", "uid": "5C-52-30-A1-B2-C3:output"}
+"#;
+
+    #[test]
+    fn listed_devices_are_active_and_keyed_by_uid() {
+        let devices = parse_devices(
+            ALL,
+            r#"{"name": "AirPods Pro", "type": "output", "id": "102", "uid": "5C-52-30-A1-B2-C3:output"}"#,
+            r#"{"name": "MacBook Pro Microphone", "type": "input", "id": "91", "uid": "BuiltInMicrophoneDevice"}"#,
+        )
+        .unwrap()
+        .devices;
+
+        let summary: Vec<(&str, &DeviceType, bool)> = devices
+            .iter()
+            .map(|d| (d.id.as_str(), &d.device_type, d.is_default))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("BuiltInMicrophoneDevice", &DeviceType::Recording, true),
+                ("BuiltInSpeakerDevice", &DeviceType::Playback, false),
+                ("5C-52-30-A1-B2-C3:input", &DeviceType::Recording, false),
+                ("5C-52-30-A1-B2-C3:output", &DeviceType::Playback, true),
+            ]
+        );
+        assert!(devices.iter().all(|d| d.state == DeviceState::Active));
+    }
+
+    #[test]
+    fn device_of_unexpected_type_is_skipped() {
+        let list = parse_devices(
+            r#"{"name": "Alerts", "type": "system", "id": "84", "uid": "BuiltInSpeakerDevice"}
+{"name": "MacBook Pro Microphone", "type": "input", "id": "91", "uid": "BuiltInMicrophoneDevice"}"#,
+            "",
+            "",
+        )
+        .unwrap();
+
+        let ids: Vec<&str> = list.devices.iter().map(|d| d.id.as_str()).collect();
+        assert_eq!(ids, ["BuiltInMicrophoneDevice"]);
+        assert_eq!(list.skipped.len(), 1);
+        assert_eq!(list.skipped[0].0, "Alerts");
+        assert!(list.skipped[0]
+            .1
+            .starts_with("type of BuiltInSpeakerDevice"));
+    }
+}
@@ -0,0 +1,436 @@
+use crate::audio_manager::{
+    AudioDevice, AudioFormat, AudioSession, DeviceState, DeviceType, FormatSupport,
+};
+use crate::error::{AudioError, AudioResult};
+use windows::core::{Interface, GUID, HSTRING, PWSTR};
+use windows::Win32::Devices::FunctionDiscovery::PKEY_Device_FriendlyName;
+use windows::Win32::Foundation::{S_FALSE, S_OK};
+use windows::Win32::Media::Audio::{
+    eAll, eCapture, eCommunications, eConsole, eRender, AudioSessionStateActive,
+    AudioSessionStateExpired, EDataFlow, ERole, IAudioClient, IAudioSessionControl2,
+    IAudioSessionManager2, IMMDevice, IMMDeviceEnumerator, IMMEndpoint, MMDeviceEnumerator,
+    AUDCLNT_E_UNSUPPORTED_FORMAT, AUDCLNT_SHAREMODE, AUDCLNT_SHAREMODE_EXCLUSIVE,
+    AUDCLNT_SHAREMODE_SHARED, DEVICE_STATEMASK_ALL, DEVICE_STATE_ACTIVE, DEVICE_STATE_DISABLED,
+    DEVICE_STATE_NOTPRESENT, DEVICE_STATE_UNPLUGGED, WAVEFORMATEX, WAVEFORMATEXTENSIBLE,
+    WAVEFORMATEXTENSIBLE_0,
+};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CoTaskMemFree, CLSCTX_ALL, COINIT_MULTITHREADED, STGM_READ,
+};
+
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+const KSDATAFORMAT_SUBTYPE_PCM: GUID = GUID::from_u128(0x00000001_0000_0010_8000_00aa00389b71);
+const COMMON_SAMPLE_RATES: [u32; 6] = [44_100, 48_000, 88_200, 96_000, 176_400, 192_000];
+const COMMON_BIT_DEPTHS: [u16; 3] = [16, 24, 32];
+/// Channel counts with a standard speaker layout: mono, stereo, quad, 5.1 and 7.1
+const STANDARD_CHANNEL_COUNTS: [u16; 5] = [1, 2, 4, 6, 8];
+
+/// Create the endpoint enumerator, initializing COM on the calling thread
+unsafe fn enumerator() -> AudioResult<IMMDeviceEnumerator> {
+    // S_FALSE / RPC_E_CHANGED_MODE only mean COM is already initialized on this thread
+    let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+
+    Ok(CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?)
+}
+
+/// Open an endpoint, initializing COM on the calling thread
+unsafe fn device(device_id: &str) -> AudioResult<IMMDevice> {
+    Ok(enumerator()?.GetDevice(&HSTRING::from(device_id))?)
+}
+
+/// Open the `IAudioClient` of an endpoint
+unsafe fn audio_client(device_id: &str) -> AudioResult<IAudioClient> {
+    Ok(device(device_id)?.Activate(CLSCTX_ALL, None)?)
+}
+
+/// Audio sessions (one per app stream) currently registered on an endpoint
+pub fn list_sessions(device_id: &str) -> AudioResult<Vec<AudioSession>> {
+    unsafe {
+        let manager: IAudioSessionManager2 = device(device_id)?.Activate(CLSCTX_ALL, None)?;
+        let sessions = manager.GetSessionEnumerator()?;
+
+        let mut result = Vec::new();
+        for index in 0..sessions.GetCount()? {
+            let control: IAudioSessionControl2 = sessions.GetSession(index)?.cast()?;
+            let state = control.GetState()?;
+            if state == AudioSessionStateExpired {
+                continue;
+            }
+
+            result.push(AudioSession {
+                session_id: take_string(control.GetSessionInstanceIdentifier()?),
+                process_id: control.GetProcessId().unwrap_or(0),
+                display_name: take_string(control.GetDisplayName()?),
+                active: state == AudioSessionStateActive,
+            });
+        }
+        Ok(result)
+    }
+}
+
+/// Every endpoint in any state, with its default flags and friendly name
+pub fn list_devices() -> AudioResult<Vec<AudioDevice>> {
+    unsafe {
+        let enumerator = enumerator()?;
+        let default_id = |flow: EDataFlow, role: ERole| {
+            enumerator
+                .GetDefaultAudioEndpoint(flow, role)
+                .and_then(|device| device.GetId())
+                .map(|id| take_string(id))
+                .ok()
+        };
+        let defaults = [
+            default_id(eRender, eConsole),
+            default_id(eCapture, eConsole),
+        ];
+        let communication_defaults = [
+            default_id(eRender, eCommunications),
+            default_id(eCapture, eCommunications),
+        ];
+
+        let endpoints = enumerator.EnumAudioEndpoints(eAll, DEVICE_STATEMASK_ALL)?;
+        let mut devices = Vec::new();
+        for index in 0..endpoints.GetCount()? {
+            let endpoint = endpoints.Item(index)?;
+            let id = take_string(endpoint.GetId()?);
+            let device_type = if endpoint.cast::<IMMEndpoint>()?.GetDataFlow()? == eCapture {
+                DeviceType::Recording
+            } else {
+                DeviceType::Playback
+            };
+            let state = endpoint.GetState()?;
+            let state = if state == DEVICE_STATE_ACTIVE {
+                DeviceState::Active
+            } else if state == DEVICE_STATE_DISABLED {
+                DeviceState::Disabled
+            } else if state == DEVICE_STATE_NOTPRESENT {
+                DeviceState::NotPresent
+            } else if state == DEVICE_STATE_UNPLUGGED {
+                DeviceState::Unplugged
+            } else {
+                DeviceState::Unknown
+            };
+
+            devices.push(AudioDevice {
+                is_default: defaults.contains(&Some(id.clone())),
+                is_communication_default: communication_defaults.contains(&Some(id.clone())),
+                name: friendly_name(&endpoint).unwrap_or_default(),
+                id,
+                device_type,
+                state,
+                last_seen: None,
+                index: None,
+                icon_path: None,
+                display_name: None,
+            });
+        }
+        Ok(devices)
+    }
+}
+
+/// `PKEY_Device_FriendlyName` of an endpoint, e.g. `Speakers (Realtek(R) Audio)`
+unsafe fn friendly_name(endpoint: &IMMDevice) -> AudioResult<String> {
+    let store = endpoint.OpenPropertyStore(STGM_READ)?;
+    Ok(store.GetValue(&PKEY_Device_FriendlyName)?.to_string())
+}
+
+/// `PolicyConfigClient`, the coclass behind the undocumented `IPolicyConfig` that the
+/// Sound control panel uses to change default endpoints
+#[cfg(feature = "native-wasapi")]
+const CLSID_POLICY_CONFIG_CLIENT: GUID = GUID::from_u128(0x870af99c_171d_4f9e_af0d_e63df40c2bc9);
+
+/// `IPolicyConfig` as shipped since Windows 7. Only `SetDefaultEndpoint` is called, but
+/// every method is declared to keep the vtable layout.
+#[cfg(feature = "native-wasapi")]
+#[windows_core::interface("f8679f50-850a-41cf-9c72-430f290290c8")]
+unsafe trait IPolicyConfig: windows_core::IUnknown {
+    unsafe fn GetMixFormat(
+        &self,
+        device_id: windows_core::PCWSTR,
+        format: *mut *mut WAVEFORMATEX,
+    ) -> windows_core::HRESULT;
+    unsafe fn GetDeviceFormat(
+        &self,
+        device_id: windows_core::PCWSTR,
+        default: i32,
+        format: *mut *mut WAVEFORMATEX,
+    ) -> windows_core::HRESULT;
+    unsafe fn ResetDeviceFormat(&self, device_id: windows_core::PCWSTR) -> windows_core::HRESULT;
+    unsafe fn SetDeviceFormat(
+        &self,
+        device_id: windows_core::PCWSTR,
+        endpoint_format: *mut WAVEFORMATEX,
+        mix_format: *mut WAVEFORMATEX,
+    ) -> windows_core::HRESULT;
+    unsafe fn GetProcessingPeriod(
+        &self,
+        device_id: windows_core::PCWSTR,
+        default: i32,
+        default_period: *mut i64,
+        minimum_period: *mut i64,
+    ) -> windows_core::HRESULT;
+    unsafe fn SetProcessingPeriod(
+        &self,
+        device_id: windows_core::PCWSTR,
+        period: *mut i64,
+    ) -> windows_core::HRESULT;
+    unsafe fn GetShareMode(
+        &self,
+        device_id: windows_core::PCWSTR,
+        mode: *mut i32,
+    ) -> windows_core::HRESULT;
+    unsafe fn SetShareMode(
+        &self,
+        device_id: windows_core::PCWSTR,
+        mode: *mut i32,
+    ) -> windows_core::HRESULT;
+    unsafe fn GetPropertyValue(
+        &self,
+        device_id: windows_core::PCWSTR,
+        key: *const std::ffi::c_void,
+        value: *mut std::ffi::c_void,
+    ) -> windows_core::HRESULT;
+    unsafe fn SetPropertyValue(
+        &self,
+        device_id: windows_core::PCWSTR,
+        key: *const std::ffi::c_void,
+        value: *mut std::ffi::c_void,
+    ) -> windows_core::HRESULT;
+    unsafe fn SetDefaultEndpoint(
+        &self,
+        device_id: windows_core::PCWSTR,
+        role: ERole,
+    ) -> windows_core::HRESULT;
+    unsafe fn SetEndpointVisibility(
+        &self,
+        device_id: windows_core::PCWSTR,
+        visible: i32,
+    ) -> windows_core::HRESULT;
+}
+
+/// Make `device_id` the default endpoint for `role`. The multimedia role covers both
+/// the console and multimedia Core Audio roles, as the Sound control panel does.
+#[cfg(feature = "native-wasapi")]
+pub fn set_default_endpoint(
+    device_id: &str,
+    role: crate::audio_manager::DefaultRole,
+) -> AudioResult<()> {
+    use crate::audio_manager::DefaultRole;
+    use windows::Win32::Media::Audio::eMultimedia;
+
+    let roles: &[ERole] = match role {
+        DefaultRole::Multimedia => &[eConsole, eMultimedia],
+        DefaultRole::Communications => &[eCommunications],
+        DefaultRole::Both => &[eConsole, eMultimedia, eCommunications],
+    };
+    unsafe {
+        // S_FALSE / RPC_E_CHANGED_MODE only mean COM is already initialized on this thread
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        let policy: IPolicyConfig =
+            CoCreateInstance(&CLSID_POLICY_CONFIG_CLIENT, None, CLSCTX_ALL)?;
+        let id = HSTRING::from(device_id);
+        for role in roles {
+            policy
+                .SetDefaultEndpoint(windows_core::PCWSTR(id.as_ptr()), *role)
+                .ok()?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy a COM-allocated string and free it
+unsafe fn take_string(value: PWSTR) -> String {
+    if value.is_null() {
+        return String::new();
+    }
+    let string = value.to_string().unwrap_or_default();
+    CoTaskMemFree(Some(value.0 as *const _));
+    string
+}
+
+/// Ask Core Audio whether a device accepts `format` in the given share mode.
+///
+/// Shared mode reports the engine's own closest match; exclusive mode can't suggest
+/// one, so common formats with the same channel count are probed instead.
+pub fn is_format_supported(
+    device_id: &str,
+    format: &AudioFormat,
+    exclusive: bool,
+) -> AudioResult<FormatSupport> {
+    unsafe {
+        let client = audio_client(device_id)?;
+
+        if exclusive {
+            if query(&client, AUDCLNT_SHAREMODE_EXCLUSIVE, format, None)? {
+                return Ok(FormatSupport {
+                    supported: true,
+                    closest_match: None,
+                });
+            }
+
+            let closest_match = candidate_formats(format.channels)
+                .filter(|candidate| {
+                    query(&client, AUDCLNT_SHAREMODE_EXCLUSIVE, candidate, None).unwrap_or(false)
+                })
+                .min_by_key(|candidate| format_distance(format, candidate));
+
+            return Ok(FormatSupport {
+                supported: false,
+                closest_match,
+            });
+        }
+
+        let mut closest: *mut WAVEFORMATEX = std::ptr::null_mut();
+        let supported = query(
+            &client,
+            AUDCLNT_SHAREMODE_SHARED,
+            format,
+            Some(&mut closest as *mut _),
+        )?;
+
+        let closest_match = if closest.is_null() {
+            None
+        } else {
+            let matched = from_wave_format(&*closest);
+            CoTaskMemFree(Some(closest as *const _));
+            Some(matched)
+        };
+
+        Ok(FormatSupport {
+            supported,
+            closest_match,
+        })
+    }
+}
+
+/// Channel counts a device can play: the shared-mode mix format's, plus every standard
+/// layout it accepts in exclusive mode at some common sample rate and bit depth
+pub fn supported_channel_counts(device_id: &str) -> AudioResult<Vec<u16>> {
+    unsafe {
+        let client = audio_client(device_id)?;
+        let mix_format = client.GetMixFormat()?;
+        let mix_channels = (*mix_format).nChannels;
+        CoTaskMemFree(Some(mix_format as *const _));
+
+        let mut counts: Vec<u16> = STANDARD_CHANNEL_COUNTS
+            .into_iter()
+            .filter(|&channels| {
+                channels == mix_channels
+                    || candidate_formats(channels).any(|candidate| {
+                        query(&client, AUDCLNT_SHAREMODE_EXCLUSIVE, &candidate, None)
+                            .unwrap_or(false)
+                    })
+            })
+            .collect();
+        if !counts.contains(&mix_channels) {
+            counts.push(mix_channels);
+            counts.sort_unstable();
+        }
+        Ok(counts)
+    }
+}
+
+/// `IsFormatSupported` wrapper mapping S_OK to true and S_FALSE/unsupported to false
+unsafe fn query(
+    client: &IAudioClient,
+    share_mode: AUDCLNT_SHAREMODE,
+    format: &AudioFormat,
+    closest: Option<*mut *mut WAVEFORMATEX>,
+) -> AudioResult<bool> {
+    let wave = to_wave_format(format)?;
+    let hr = client.IsFormatSupported(
+        share_mode,
+        std::ptr::addr_of!(wave).cast::<WAVEFORMATEX>(),
+        closest,
+    );
+
+    if hr == S_OK {
+        Ok(true)
+    } else if hr == S_FALSE || hr == AUDCLNT_E_UNSUPPORTED_FORMAT {
+        Ok(false)
+    } else {
+        hr.ok()?;
+        Ok(false)
+    }
+}
+
+/// Build the PCM wave format for `format`, rejecting one whose block alignment or byte
+/// rate doesn't fit the header fields rather than letting the arithmetic overflow
+fn to_wave_format(format: &AudioFormat) -> AudioResult<WAVEFORMATEXTENSIBLE> {
+    let too_large = || {
+        AudioError::InvalidArgument(format!(
+            "{} Hz, {}-bit, {} channels is too large a format",
+            format.sample_rate, format.bits_per_sample, format.channels
+        ))
+    };
+    let block_align = format
+        .channels
+        .checked_mul(format.bits_per_sample)
+        .ok_or_else(too_large)?
+        / 8;
+    let bytes_per_second = format
+        .sample_rate
+        .checked_mul(u32::from(block_align))
+        .ok_or_else(too_large)?;
+    Ok(WAVEFORMATEXTENSIBLE {
+        Format: WAVEFORMATEX {
+            wFormatTag: WAVE_FORMAT_EXTENSIBLE,
+            nChannels: format.channels,
+            nSamplesPerSec: format.sample_rate,
+            nAvgBytesPerSec: bytes_per_second,
+            nBlockAlign: block_align,
+            wBitsPerSample: format.bits_per_sample,
+            cbSize: (std::mem::size_of::<WAVEFORMATEXTENSIBLE>()
+                - std::mem::size_of::<WAVEFORMATEX>()) as u16,
+        },
+        Samples: WAVEFORMATEXTENSIBLE_0 {
+            wValidBitsPerSample: format.bits_per_sample,
+        },
+        dwChannelMask: channel_mask(format.channels),
+        SubFormat: KSDATAFORMAT_SUBTYPE_PCM,
+    })
+}
+
+fn from_wave_format(wave: &WAVEFORMATEX) -> AudioFormat {
+    AudioFormat {
+        sample_rate: wave.nSamplesPerSec,
+        bits_per_sample: wave.wBitsPerSample,
+        channels: wave.nChannels,
+    }
+}
+
+/// Standard speaker layout for common channel counts (0 lets the driver decide)
+fn channel_mask(channels: u16) -> u32 {
+    match channels {
+        1 => 0x4,
+        2 => 0x3,
+        4 => 0x33,
+        6 => 0x3F,
+        8 => 0x63F,
+        _ => 0,
+    }
+}
+
+fn candidate_formats(channels: u16) -> impl Iterator<Item = AudioFormat> {
+    COMMON_SAMPLE_RATES
+        .into_iter()
+        .flat_map(move |sample_rate| {
+            COMMON_BIT_DEPTHS
+                .into_iter()
+                .map(move |bits_per_sample| AudioFormat {
+                    sample_rate,
+                    bits_per_sample,
+                    channels,
+                })
+        })
+}
+
+/// Ordering key preferring the nearest sample rate, then the nearest bit depth
+fn format_distance(requested: &AudioFormat, candidate: &AudioFormat) -> (u32, u16) {
+    (
+        requested.sample_rate.abs_diff(candidate.sample_rate),
+        requested
+            .bits_per_sample
+            .abs_diff(candidate.bits_per_sample),
+    )
+}
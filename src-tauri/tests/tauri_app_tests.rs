@@ -0,0 +1,78 @@
+use sound_changer_lib::audio_manager::{AudioManager, DeviceState, DeviceType};
+use sound_changer_lib::command_runner::MockRunner;
+use sound_changer_lib::error::AudioError;
+use std::sync::Arc;
+
+/// Representative `Get-AudioDevice` listing: two active playback devices (the speakers
+/// holding both defaults), an unplugged monitor and a microphone
+const DEVICE_LIST: &str = include_str!("fixtures/device_list.json");
+const SPEAKERS: &str = "{0.0.0.00000000}.{5f2a8c1e-3b4d-4e6f-9a7b-1c2d3e4f5a6b}";
+const HEADSET: &str = "{0.0.0.00000000}.{9c1b2d3e-4f5a-4b6c-8d7e-2f3a4b5c6d7e}";
+
+#[tokio::test]
+async fn devices_are_listed_from_the_fixture() {
+    let manager = AudioManager::with_runner(Box::new(MockRunner::new(vec![(
+        "$allAudioDevices",
+        Ok(DEVICE_LIST.to_string()),
+    )])));
+
+    let mut devices = manager.get_audio_devices().await.unwrap();
+    devices.sort_by_key(|d| d.index);
+    let summary: Vec<(&str, &DeviceType, &DeviceState, bool)> = devices
+        .iter()
+        .map(|d| (d.id.as_str(), &d.device_type, &d.state, d.is_default))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (SPEAKERS, &DeviceType::Playback, &DeviceState::Active, true),
+            (HEADSET, &DeviceType::Playback, &DeviceState::Active, false),
+            (
+                "{0.0.0.00000000}.{0d3e4f5a-6b7c-4d8e-9f0a-3b4c5d6e7f8a}",
+                &DeviceType::Playback,
+                &DeviceState::NotPresent,
+                false
+            ),
+            (
+                "{0.0.1.00000000}.{7a4c5d6e-7f8a-4b9c-0d1e-4c5d6e7f8a9b}",
+                &DeviceType::Recording,
+                &DeviceState::Active,
+                true
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn failed_switch_falls_back_to_the_previous_default() {
+    let runner = Arc::new(MockRunner::new(vec![
+        (
+            "Set-AudioDevice -ID \"{0.0.0.00000000}.{9c1b",
+            Err("Set-AudioDevice : The device is in use".to_string()),
+        ),
+        ("Set-AudioDevice", Ok(r#"{"success":true}"#.to_string())),
+        ("$allAudioDevices", Ok(DEVICE_LIST.to_string())),
+    ]));
+    let manager = AudioManager::with_runner(Box::new(runner.clone()));
+    manager.set_switch_coalesce_window(Some(0)).await.unwrap();
+
+    let error = manager.set_default_audio_device(HEADSET).await.unwrap_err();
+    assert!(matches!(&error, AudioError::CommandFailed(message) if message.contains("in use")));
+
+    let switches: Vec<String> = runner
+        .scripts()
+        .into_iter()
+        .filter(|script| script.contains("Set-AudioDevice"))
+        .collect();
+    assert!(switches[0].contains(HEADSET));
+    // The speakers held both roles, so they're restored in one switch
+    let restores: Vec<&String> = switches
+        .iter()
+        .filter(|script| script.contains(SPEAKERS))
+        .collect();
+    assert_eq!(restores.len(), 1);
+    assert!(restores[0].contains(&format!(
+        r#"Set-AudioDevice -ID "{}" -DefaultOnly; Set-AudioDevice -ID "{}" -CommunicationOnly"#,
+        SPEAKERS, SPEAKERS
+    )));
+}
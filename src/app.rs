@@ -10,6 +10,9 @@ extern "C" {
     async fn invoke(cmd: &str, args: JsValue) -> JsValue;
 }
 
+/// Serialized `DeviceState` of a usable endpoint
+const ACTIVE_STATE: &str = "active";
+
 #[derive(Clone, PartialEq, Deserialize, Debug)]
 struct AudioDevice {
     id: String,
@@ -19,6 +22,49 @@ struct AudioDevice {
     is_default: bool,
     is_communication_default: bool,
     last_seen: Option<String>,
+    /// User-set nickname, preferred over the Windows name
+    #[serde(default)]
+    display_name: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ModuleUpdateInfo {
+    latest: String,
+    update_available: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct PriorityChains {
+    playback: Vec<DevicePriority>,
+    recording: Vec<DevicePriority>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct Profile {
+    name: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ProfileApplication {
+    unavailable_devices: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct DeviceListResult {
+    devices: Vec<AudioDevice>,
+    /// (name, reason) of each entry the backend couldn't parse
+    skipped: Vec<(String, String)>,
+}
+
+/// Health check from `run_diagnostics`; `None` where the probe failed
+#[derive(Clone, Debug, Deserialize)]
+struct Diagnostics {
+    powershell_version: Option<String>,
+    module_installed: Option<bool>,
+    module_version: Option<String>,
+    execution_policy: Option<String>,
+    is_admin: Option<bool>,
+    can_list_devices: Option<bool>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -41,57 +87,46 @@ struct AppState {
     last_refresh: Option<f64>,
     error: Option<AppError>,
     module_available: Option<bool>,
+    /// Latest AudioDeviceCmdlets version on PSGallery, when newer than the installed one
+    module_update: Option<String>,
+    /// A user-requested update check is in flight
+    checking_module_update: bool,
+    /// An update check finished, so a missing `module_update` means up to date
+    module_update_checked: bool,
     playback_priorities: Vec<DevicePriority>,
     recording_priorities: Vec<DevicePriority>,
     auto_switch_enabled: bool,
     installing_module: bool,
+    /// A manual refresh is in flight
+    refreshing: bool,
+    /// Names of the saved profiles
+    profiles: Vec<String>,
+    read_only: bool,
+    /// (name, reason) of devices left out of the last enumeration
+    skipped_devices: Vec<(String, String)>,
+    /// Result of the last health check, shown until dismissed
+    diagnostics: Option<Diagnostics>,
+    diagnosing: bool,
 }
 
 use subwayui::MetroProvider;
 
-// Auto-switching logic
-async fn auto_switch_device(priorities: &[DevicePriority], available_devices: &[AudioDevice], device_type: &str) -> Option<String> {
-    // Find the highest priority device that's available
-    for priority in priorities {
-        if let Some(device) = available_devices.iter().find(|d| d.id == priority.device_id && d.state == "Active") {
-            // Check if this device is already the default
-            if !device.is_default {
-                // Try to set this as the default device
-                let args = serde_wasm_bindgen::to_value(&serde_json::json!({
-                    "args": {
-                        "device_id": device.id,
-                        "device_type": device_type
-                    }
-                })).unwrap();
-                
-                let result = invoke("set_default_device", args).await;
-                // Backend returns Result<(), _>; parse unit to confirm success
-                if from_value::<()>(result).is_ok() {
-                    return Some(device.name.clone());
-                }
-            } else {
-                // Already the default, no need to switch
-                return None;
-            }
-        }
-    }
-    None
+// Auto-switching runs in the backend, which picks the highest-priority Active device,
+// leaves an already-default one alone and emits `auto-switched` for the toast
+async fn auto_switch(device_type: &str) -> bool {
+    let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+        "args": { "device_type": device_type }
+    })).unwrap();
+    let result = invoke("evaluate_and_switch", args).await;
+    matches!(from_value::<Option<AudioDevice>>(result), Ok(Some(_)))
 }
 
-// Storage functions for priority persistence
-fn save_priorities_to_storage(playback: &[DevicePriority], recording: &[DevicePriority]) {
-    if let Some(window) = web_sys::window() {
-        if let Ok(Some(storage)) = window.local_storage() {
-            if let Ok(playback_json) = serde_json::to_string(playback) {
-                let _ = storage.set_item("playback_priorities", &playback_json);
-            }
-            if let Ok(recording_json) = serde_json::to_string(recording) {
-                let _ = storage.set_item("recording_priorities", &recording_json);
-            }
-        }
-    }
+// Devices along with the entries the backend had to skip
+async fn load_device_list() -> Option<DeviceListResult> {
+    from_value(invoke("get_audio_devices_detailed", JsValue::NULL).await).ok()
 }
 
+// Priorities kept in localStorage by older versions, read once for the backend migration
 fn load_priorities_from_storage() -> (Vec<DevicePriority>, Vec<DevicePriority>) {
     let mut playback_priorities = Vec::new();
     let mut recording_priorities = Vec::new();
@@ -114,8 +149,46 @@ fn load_priorities_from_storage() -> (Vec<DevicePriority>, Vec<DevicePriority>)
     (playback_priorities, recording_priorities)
 }
 
+const PRIORITIES_MIGRATED_KEY: &str = "priorities_migrated_to_backend";
+
+// One-time migration of localStorage priorities into the backend priority store
+async fn migrate_priorities_to_backend(playback: &[DevicePriority], recording: &[DevicePriority]) {
+    let storage = match web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+        Some(storage) => storage,
+        None => return,
+    };
+
+    if let Ok(Some(_)) = storage.get_item(PRIORITIES_MIGRATED_KEY) {
+        return;
+    }
+
+    let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+        "args": {
+            "playback": playback,
+            "recording": recording
+        }
+    })).unwrap();
+
+    let result = invoke("import_priorities_from_frontend", args).await;
+    // Backend returns whether it imported; either way the migration is done
+    if from_value::<bool>(result).is_ok() {
+        let _ = storage.set_item(PRIORITIES_MIGRATED_KEY, "true");
+    }
+}
+
+async fn load_profile_names() -> Vec<String> {
+    let profiles_val = invoke("list_profiles", JsValue::NULL).await;
+    from_value::<Vec<Profile>>(profiles_val)
+        .map(|profiles| profiles.into_iter().map(|p| p.name).collect())
+        .unwrap_or_default()
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "Yes" } else { "No" }.to_string()
+}
+
 // Helper function to render priority chain like a flow diagram with 5 fixed positions
-fn render_priority_chain(priorities: &[DevicePriority], list_type: String, _app_state: &UseStateHandle<AppState>, available_devices: &[AudioDevice]) -> Html {
+fn render_priority_chain(priorities: &[DevicePriority], list_type: String, app_state: &UseStateHandle<AppState>, available_devices: &[AudioDevice]) -> Html {
     // Create 5 fixed priority positions (0 = highest priority, 4 = lowest priority)
     let priority_slots = (0..5).map(|slot_index| {
         // Find device assigned to this priority slot
@@ -133,7 +206,7 @@ fn render_priority_chain(priorities: &[DevicePriority], list_type: String, _app_
         match assigned_device {
             Some(priority) => {
                 // Slot has a device assigned
-                let is_available = available_devices.iter().any(|d| d.id == priority.device_id && d.state == "Active");
+                let is_available = available_devices.iter().any(|d| d.id == priority.device_id && d.state == ACTIVE_STATE);
                 let is_current = available_devices.iter().any(|d| d.id == priority.device_id && d.is_default);
                 
                 let mut box_class = "priority-box filled".to_string();
@@ -152,6 +225,38 @@ fn render_priority_chain(priorities: &[DevicePriority], list_type: String, _app_
                 } else {
                     "✗ Disconnected"
                 };
+
+                let remove_from_slot = {
+                    let app_state = app_state.clone();
+                    let list_type = list_type.clone();
+                    Callback::from(move |_| {
+                        let app_state = app_state.clone();
+                        let list_type = list_type.clone();
+                        spawn_local(async move {
+                            let device_type = if list_type == "recording" { "Recording" } else { "Playback" };
+                            let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+                                "args": {
+                                    "device_type": device_type,
+                                    "priority_slot": slot_index
+                                }
+                            })).unwrap();
+                            let result = invoke("clear_priority_slot", args).await;
+                            if from_value::<bool>(result).is_err() {
+                                web_sys::console::error_1(&"Failed to remove device from priority slot".into());
+                                return;
+                            }
+
+                            // Reload from the store so the chain matches what was persisted
+                            let chains_val = invoke("load_priorities", JsValue::NULL).await;
+                            if let Ok(chains) = from_value::<PriorityChains>(chains_val) {
+                                let mut state = (*app_state).clone();
+                                state.playback_priorities = chains.playback;
+                                state.recording_priorities = chains.recording;
+                                app_state.set(state);
+                            }
+                        });
+                    })
+                };
                 
                 html! {
                     <div key={format!("slot-{}", slot_index)} class="priority-slot">
@@ -169,7 +274,7 @@ fn render_priority_chain(priorities: &[DevicePriority], list_type: String, _app_
                                 <div class="device-status">{status_text}</div>
                             </div>
                             <div class="priority-actions">
-                                <button class="priority-btn remove" title="Remove">{"✕"}</button>
+                                <button class="priority-btn remove" title="Remove" onclick={remove_from_slot}>{"✕"}</button>
                             </div>
                         </div>
                         if slot_index < 4 {
@@ -221,33 +326,49 @@ fn render_priority_chain(priorities: &[DevicePriority], list_type: String, _app_
 
 #[function_component(App)]
 pub fn app() -> Html {
-    let app_state = use_state(|| {
-        let (playback_priorities, recording_priorities) = load_priorities_from_storage();
-        AppState {
-            devices: Vec::new(),
-            last_refresh: None,
-            error: None,
-            module_available: None,
-            playback_priorities,
-            recording_priorities,
-            auto_switch_enabled: true,
-            installing_module: false,
-        }
+    let app_state = use_state(|| AppState {
+        devices: Vec::new(),
+        last_refresh: None,
+        error: None,
+        module_available: None,
+        module_update: None,
+        checking_module_update: false,
+        module_update_checked: false,
+        playback_priorities: Vec::new(),
+        recording_priorities: Vec::new(),
+        auto_switch_enabled: true,
+        installing_module: false,
+        refreshing: false,
+        profiles: Vec::new(),
+        read_only: false,
+        skipped_devices: Vec::new(),
+        diagnostics: None,
+        diagnosing: false,
     });
 
-    // Load devices on startup
+    // Load priorities and devices on startup. Priorities come from the backend store,
+    // after migrating any that older versions kept in localStorage.
     {
         let app_state = app_state.clone();
         use_effect_with((), move |_| {
             let app_state = app_state.clone();
             spawn_local(async move {
-                let devices_val = invoke("get_audio_devices", JsValue::NULL).await;
-                if let Ok(devices) = from_value::<Vec<AudioDevice>>(devices_val) {
-                    let mut state = (*app_state).clone();
-                    state.devices = devices;
+                let (playback_priorities, recording_priorities) = load_priorities_from_storage();
+                migrate_priorities_to_backend(&playback_priorities, &recording_priorities).await;
+
+                let mut state = (*app_state).clone();
+                let chains_val = invoke("load_priorities", JsValue::NULL).await;
+                if let Ok(chains) = from_value::<PriorityChains>(chains_val) {
+                    state.playback_priorities = chains.playback;
+                    state.recording_priorities = chains.recording;
+                }
+                if let Some(list) = load_device_list().await {
+                    state.devices = list.devices;
+                    state.skipped_devices = list.skipped;
                     state.last_refresh = Some(js_sys::Date::now());
-                    app_state.set(state);
                 }
+                state.profiles = load_profile_names().await;
+                app_state.set(state);
             });
             || ()
         });
@@ -270,53 +391,39 @@ pub fn app() -> Html {
         });
     }
 
+    // Check whether the backend was started in read-only mode
+    {
+        let app_state = app_state.clone();
+        use_effect_with((), move |_| {
+            let app_state = app_state.clone();
+            spawn_local(async move {
+                let read_only = invoke("is_read_only", JsValue::NULL).await;
+                if let Ok(read_only) = from_value::<bool>(read_only) {
+                    let mut state = (*app_state).clone();
+                    state.read_only = read_only;
+                    app_state.set(state);
+                }
+            });
+            || ()
+        });
+    }
+
     // Auto-switching effect - triggers when devices change and auto-switch is enabled
     {
         let app_state = app_state.clone();
         let devices = app_state.devices.clone();
-        let auto_switch_enabled = app_state.auto_switch_enabled;
-        let playback_priorities = app_state.playback_priorities.clone();
-        let recording_priorities = app_state.recording_priorities.clone();
+        let auto_switch_enabled = app_state.auto_switch_enabled && !app_state.read_only;
         
         use_effect_with((devices.len(), auto_switch_enabled), move |_| {
             if auto_switch_enabled && !devices.is_empty() {
                 let app_state = app_state.clone();
-                let devices = devices.clone();
-                let playback_priorities = playback_priorities.clone();
-                let recording_priorities = recording_priorities.clone();
-                
+
                 spawn_local(async move {
-                    let playback_devices: Vec<AudioDevice> = devices
-                        .iter()
-                        .filter(|d| d.device_type == "Playback")
-                        .cloned()
-                        .collect();
-                    
-                    let recording_devices: Vec<AudioDevice> = devices
-                        .iter()
-                        .filter(|d| d.device_type == "Recording")
-                        .cloned()
-                        .collect();
-                    
-                    // Try auto-switching for playback devices
-                    if let Some(switched_device) = auto_switch_device(&playback_priorities, &playback_devices, "Playback").await {
-                        web_sys::console::log_1(&format!("Auto-switched to playback device: {}", switched_device).into());
-                        
-                        // Refresh devices to show the change
-                        let devices_val = invoke("get_audio_devices", JsValue::NULL).await;
-                        if let Ok(devices) = from_value::<Vec<AudioDevice>>(devices_val) {
-                            let mut state = (*app_state).clone();
-                            state.devices = devices;
-                            state.last_refresh = Some(js_sys::Date::now());
-                            app_state.set(state);
-                        }
-                    }
-                    
-                    // Try auto-switching for recording devices  
-                    if let Some(switched_device) = auto_switch_device(&recording_priorities, &recording_devices, "Recording").await {
-                        web_sys::console::log_1(&format!("Auto-switched to recording device: {}", switched_device).into());
-                        
-                        // Refresh devices to show the change
+                    let switched_playback = auto_switch("Playback").await;
+                    let switched_recording = auto_switch("Recording").await;
+
+                    // Refresh devices to show the change
+                    if switched_playback || switched_recording {
                         let devices_val = invoke("get_audio_devices", JsValue::NULL).await;
                         if let Ok(devices) = from_value::<Vec<AudioDevice>>(devices_val) {
                             let mut state = (*app_state).clone();
@@ -377,6 +484,193 @@ pub fn app() -> Html {
         })
     };
 
+    // Check PSGallery for a newer AudioDeviceCmdlets when asked; this reaches out to the
+    // network, so it never runs on its own (failures, e.g. offline, are ignored)
+    let check_module_update = {
+        let app_state = app_state.clone();
+        Callback::from(move |_| {
+            let app_state = app_state.clone();
+            spawn_local(async move {
+                {
+                    let mut s = (*app_state).clone();
+                    s.checking_module_update = true;
+                    app_state.set(s);
+                }
+
+                let update = invoke("check_module_update", JsValue::NULL).await;
+                let mut s = (*app_state).clone();
+                if let Ok(update) = from_value::<ModuleUpdateInfo>(update) {
+                    s.module_update = update.update_available.then_some(update.latest);
+                    s.module_update_checked = true;
+                }
+                s.checking_module_update = false;
+                app_state.set(s);
+            });
+        })
+    };
+
+    // Manual refresh: bypasses the backend's device cache to pick up changes made in
+    // Windows Sound settings straight away
+    let refresh_devices = {
+        let app_state = app_state.clone();
+        Callback::from(move |_| {
+            let app_state = app_state.clone();
+            spawn_local(async move {
+                {
+                    let mut s = (*app_state).clone();
+                    s.refreshing = true;
+                    app_state.set(s);
+                }
+
+                let devices_val = invoke("refresh_devices", JsValue::NULL).await;
+                let mut s = (*app_state).clone();
+                if let Ok(devices) = from_value::<Vec<AudioDevice>>(devices_val) {
+                    s.devices = devices;
+                    s.last_refresh = Some(js_sys::Date::now());
+                    // Served from the cache the refresh just filled
+                    if let Some(list) = load_device_list().await {
+                        s.skipped_devices = list.skipped;
+                    }
+                }
+                s.refreshing = false;
+                app_state.set(s);
+            });
+        })
+    };
+
+    // Apply the profile picked in the dropdown, then reload what it changed
+    let apply_profile = {
+        let app_state = app_state.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            let name = select.value();
+            select.set_value("");
+            if name.is_empty() {
+                return;
+            }
+            let app_state = app_state.clone();
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+                    "args": { "name": name }
+                })).unwrap();
+                let result = invoke("apply_profile", args).await;
+                match from_value::<ProfileApplication>(result) {
+                    Ok(applied) if !applied.unavailable_devices.is_empty() => {
+                        if let Some(window) = web_sys::window() {
+                            let _ = window.alert_with_message(&format!(
+                                "Profile \"{}\" applied, but these devices aren't connected: {}",
+                                name,
+                                applied.unavailable_devices.join(", ")
+                            ));
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(_) => {
+                        web_sys::console::error_1(&format!("Failed to apply profile {}", name).into());
+                        return;
+                    }
+                }
+
+                let mut state = (*app_state).clone();
+                let chains_val = invoke("load_priorities", JsValue::NULL).await;
+                if let Ok(chains) = from_value::<PriorityChains>(chains_val) {
+                    state.playback_priorities = chains.playback;
+                    state.recording_priorities = chains.recording;
+                }
+                if let Some(list) = load_device_list().await {
+                    state.devices = list.devices;
+                    state.skipped_devices = list.skipped;
+                    state.last_refresh = Some(js_sys::Date::now());
+                }
+                app_state.set(state);
+            });
+        })
+    };
+
+    // Save the current defaults and priority chains as a named profile
+    let save_profile = {
+        let app_state = app_state.clone();
+        Callback::from(move |_| {
+            let Some(name) = web_sys::window()
+                .and_then(|window| window.prompt_with_message("Profile name").ok().flatten())
+                .filter(|name| !name.trim().is_empty())
+            else {
+                return;
+            };
+            let app_state = app_state.clone();
+            spawn_local(async move {
+                let args = serde_wasm_bindgen::to_value(&serde_json::json!({
+                    "args": { "name": name }
+                })).unwrap();
+                let result = invoke("save_profile", args).await;
+                if from_value::<Profile>(result).is_err() {
+                    web_sys::console::error_1(&format!("Failed to save profile {}", name).into());
+                    return;
+                }
+
+                let mut state = (*app_state).clone();
+                state.profiles = load_profile_names().await;
+                app_state.set(state);
+            });
+        })
+    };
+
+    // Switch back to the defaults the last switches replaced
+    let undo_switch = {
+        let app_state = app_state.clone();
+        Callback::from(move |_| {
+            let app_state = app_state.clone();
+            spawn_local(async move {
+                let result = invoke("undo_last_switch", JsValue::NULL).await;
+                if from_value::<()>(result).is_err() {
+                    web_sys::console::error_1(&"Failed to undo the last switch".into());
+                    return;
+                }
+
+                if let Some(list) = load_device_list().await {
+                    let mut s = (*app_state).clone();
+                    s.devices = list.devices;
+                    s.skipped_devices = list.skipped;
+                    s.last_refresh = Some(js_sys::Date::now());
+                    app_state.set(s);
+                }
+            });
+        })
+    };
+
+    // Run the PowerShell health check and open the diagnostics panel
+    let run_diagnostics = {
+        let app_state = app_state.clone();
+        Callback::from(move |_| {
+            let app_state = app_state.clone();
+            spawn_local(async move {
+                {
+                    let mut s = (*app_state).clone();
+                    s.diagnosing = true;
+                    app_state.set(s);
+                }
+
+                let result = invoke("run_diagnostics", JsValue::NULL).await;
+                let mut s = (*app_state).clone();
+                match from_value::<Diagnostics>(result) {
+                    Ok(diagnostics) => s.diagnostics = Some(diagnostics),
+                    Err(_) => web_sys::console::error_1(&"Failed to run diagnostics".into()),
+                }
+                s.diagnosing = false;
+                app_state.set(s);
+            });
+        })
+    };
+
+    let close_diagnostics = {
+        let app_state = app_state.clone();
+        Callback::from(move |_| {
+            let mut s = (*app_state).clone();
+            s.diagnostics = None;
+            app_state.set(s);
+        })
+    };
+
     // Native HTML5 DnD handlers are removed to avoid conflicts with InteractJS
 
     // Filter devices by type
@@ -399,11 +693,99 @@ pub fn app() -> Html {
             <div class="app-container">
                 <div class="status-bar">
                     <div class="refresh-time">
-                        {format!("Devices loaded: {}", app_state.devices.len())}
+                        {format!("Devices loaded: {} ", app_state.devices.len())}
+                        <button
+                            type="button"
+                            class="refresh-btn"
+                            onclick={refresh_devices}
+                            disabled={app_state.refreshing}
+                        >
+                            { if app_state.refreshing { "Refreshing..." } else { "Refresh" } }
+                        </button>
                     </div>
+                    <div class="profile-picker">
+                        <select
+                            onchange={apply_profile}
+                            disabled={app_state.read_only || app_state.profiles.is_empty()}
+                        >
+                            <option value="" selected=true>{"Apply profile..."}</option>
+                            {
+                                app_state.profiles.iter().map(|name| html! {
+                                    <option value={name.clone()}>{name.clone()}</option>
+                                }).collect::<Html>()
+                            }
+                        </select>
+                        <button
+                            type="button"
+                            class="refresh-btn"
+                            onclick={save_profile}
+                            disabled={app_state.read_only}
+                        >
+                            {"Save profile"}
+                        </button>
+                    </div>
+                    <button
+                        type="button"
+                        class="refresh-btn"
+                        onclick={undo_switch}
+                        disabled={app_state.read_only}
+                    >
+                        {"Undo switch"}
+                    </button>
+                    <button
+                        type="button"
+                        class="refresh-btn"
+                        onclick={run_diagnostics}
+                        disabled={app_state.diagnosing}
+                    >
+                        { if app_state.diagnosing { "Diagnosing..." } else { "Diagnostics" } }
+                    </button>
+                    if app_state.read_only {
+                        <div class="module-status warn">{"Read-only mode"}</div>
+                    }
+                    if !app_state.skipped_devices.is_empty() {
+                        <div
+                            class="module-status warn"
+                            title={app_state.skipped_devices.iter()
+                                .map(|(name, reason)| format!("{}: {}", name, reason))
+                                .collect::<Vec<_>>()
+                                .join("\n")}
+                        >
+                            {
+                                match app_state.skipped_devices.len() {
+                                    1 => "1 device couldn't be parsed".to_string(),
+                                    n => format!("{} devices couldn't be parsed", n),
+                                }
+                            }
+                        </div>
+                    }
                     {
                         match app_state.module_available {
-                            Some(true) => html! { <div class="module-status ok">{"AudioDeviceCmdlets: Available"}</div> },
+                            Some(true) => html! {
+                                <div class="module-status ok">
+                                    {"AudioDeviceCmdlets: Available "}
+                                    {
+                                        match &app_state.module_update {
+                                            Some(latest) => html! {
+                                                <span class="module-update-badge">{format!("(update {} available)", latest)}</span>
+                                            },
+                                            None if app_state.module_update_checked => html! {
+                                                <span>{"(up to date)"}</span>
+                                            },
+                                            None => html! {
+                                                <button
+                                                    type="button"
+                                                    class="install-module-btn"
+                                                    onclick={check_module_update.clone()}
+                                                    disabled={app_state.checking_module_update}
+                                                >
+                                                    { if app_state.checking_module_update { "Checking..." } else { "Check for updates" } }
+                                                </button>
+                                            },
+                                        }
+                                    }
+                                </div>
+                            },
                             Some(false) => html! {
                                 <div class="module-status warn">
                                     {"AudioDeviceCmdlets: Missing "}
@@ -411,7 +793,7 @@ pub fn app() -> Html {
                                         type="button" 
                                         class="install-module-btn"
                                         onclick={install_module.clone()}
-                                        disabled={app_state.installing_module}
+                                        disabled={app_state.installing_module || app_state.read_only}
                                     >
                                         { if app_state.installing_module { "Installing..." } else { "Install now" } }
                                     </button>
@@ -421,7 +803,31 @@ pub fn app() -> Html {
                         }
                     }
                 </div>
-                
+
+                if let Some(diagnostics) = &app_state.diagnostics {
+                    <div class="diagnostics-panel">
+                        <div class="diagnostics-header">
+                            <h2 class="section-title">{"Diagnostics"}</h2>
+                            <button type="button" class="refresh-btn" onclick={close_diagnostics}>{"Close"}</button>
+                        </div>
+                        {
+                            [
+                                ("PowerShell version", diagnostics.powershell_version.clone()),
+                                ("AudioDeviceCmdlets installed", diagnostics.module_installed.map(yes_no)),
+                                ("AudioDeviceCmdlets version", diagnostics.module_version.clone()),
+                                ("Execution policy", diagnostics.execution_policy.clone()),
+                                ("Running as administrator", diagnostics.is_admin.map(yes_no)),
+                                ("Can list devices", diagnostics.can_list_devices.map(yes_no)),
+                            ].into_iter().map(|(label, value)| html! {
+                                <div class="diagnostics-row">
+                                    <span class="diagnostics-label">{label}</span>
+                                    <span class="diagnostics-value">{value.unwrap_or_else(|| "unknown".to_string())}</span>
+                                </div>
+                            }).collect::<Html>()
+                        }
+                    </div>
+                }
+
                 <div class="devices-container">
                     <div class="device-section">
                         <h2 class="section-title">{format!("Available Playback Devices ({})", playback_devices.len())}</h2>
@@ -449,7 +855,7 @@ pub fn app() -> Html {
                                             onmousedown={Callback::from(|_| {})}
                                         >
                                             <div class="tile-content" style="pointer-events: none;">
-                                                <div class="tile-title">{d.name.clone()}</div>
+                                                <div class="tile-title">{d.display_name.clone().unwrap_or_else(|| d.name.clone())}</div>
                                                 <div class="tile-status">
                                                     {if d.is_default { " (Default)" } else { "" }}
                                                 </div>
@@ -469,6 +875,7 @@ pub fn app() -> Html {
                                     type="button" 
                                     class="auto-switch-button"
                                     onclick={toggle_auto_switch}
+                                    disabled={app_state.read_only}
                                 >
                                     {"🔄 Auto-switch to highest priority available device"}
                                 </button>
@@ -508,7 +915,7 @@ pub fn app() -> Html {
                                             onmousedown={Callback::from(|_| {})}
                                         >
                                             <div class="tile-content" style="pointer-events: none;">
-                                                <div class="tile-title">{d.name.clone()}</div>
+                                                <div class="tile-title">{d.display_name.clone().unwrap_or_else(|| d.name.clone())}</div>
                                                 <div class="tile-status">
                                                     {if d.is_default { " (Default)" } else { "" }}
                                                 </div>